@@ -0,0 +1,78 @@
+/*
+ * C functions of the tushare crate, built with the "ffi" feature.
+ * The results are exported through the Arrow C Data Interface:
+ * https://arrow.apache.org/docs/format/CDataInterface.html
+ *
+ * Every function returns 0 on success and -1 on failure,
+ * call tushare_last_error() for the message of the failure.
+ */
+#ifndef TUSHARE_H
+#define TUSHARE_H
+
+#include <stdint.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+#ifndef ARROW_C_DATA_INTERFACE
+#define ARROW_C_DATA_INTERFACE
+
+#define ARROW_FLAG_DICTIONARY_ORDERED 1
+#define ARROW_FLAG_NULLABLE 2
+#define ARROW_FLAG_MAP_KEYS_SORTED 4
+
+struct ArrowSchema {
+    const char* format;
+    const char* name;
+    const char* metadata;
+    int64_t flags;
+    int64_t n_children;
+    struct ArrowSchema** children;
+    struct ArrowSchema* dictionary;
+    void (*release)(struct ArrowSchema*);
+    void* private_data;
+};
+
+struct ArrowArray {
+    int64_t length;
+    int64_t null_count;
+    int64_t offset;
+    int64_t n_buffers;
+    int64_t n_children;
+    const void** buffers;
+    struct ArrowArray** children;
+    struct ArrowArray* dictionary;
+    void (*release)(struct ArrowArray*);
+    void* private_data;
+};
+
+#endif /* ARROW_C_DATA_INTERFACE */
+
+typedef struct Tushare Tushare;
+
+/* The message of the last failure on the calling thread, NULL if none.
+ * Owned by the library, valid until the next failure on the thread. */
+const char* tushare_last_error(void);
+
+/* Create a client with the token, or read it from TUSHARE_TOKEN if token is NULL.
+ * Free it with tushare_client_free(). */
+int tushare_client_new(const char* token, Tushare** out);
+
+/* Throttle the calls of the client to calls_per_minute on client side. */
+int tushare_client_set_rate_limit(Tushare* client, uint32_t calls_per_minute);
+
+/* Free a client, NULL is ignored. */
+void tushare_client_free(Tushare* client);
+
+/* Query all rows of the api, and export them as a struct array of the columns.
+ * params is a json object like {"ts_code": "000001.SZ"}, fields a comma separated list, both may be NULL.
+ * The caller owns out_array and out_schema, and must call their release callbacks. */
+int tushare_query(const Tushare* client, const char* api_name, const char* params, const char* fields,
+                  struct ArrowArray* out_array, struct ArrowSchema* out_schema);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif /* TUSHARE_H */
@@ -0,0 +1,108 @@
+//! Returns and factors computed from the columns of the daily bars, e.g. Tushare::daily() or Tushare::bars().
+//! Every function returns a polars expression to use in select()/with_columns() of a lazy frame,
+//! wrap it in [per_code] when the frame has several codes, so a code never looks back into another one.
+//! [with_returns] adds the common columns at once.
+//! ```
+//! use polars::prelude::*;
+//! use tushare::analysis::{per_code, simple_return};
+//! let daily = df!(
+//!     "ts_code" => ["000001.SZ", "000001.SZ"],
+//!     "trade_date" => ["20240102", "20240103"],
+//!     "close" => [10.0, 12.5]
+//! ).unwrap();
+//! let df = daily.lazy().with_column(per_code(simple_return("close")).alias("ret")).collect().unwrap();
+//! assert_eq!(df.column("ret").unwrap().f64().unwrap().get(1), Some(0.25));
+//! ```
+use crate::error::TushareError;
+use polars::prelude::*;
+
+/// Trading days of a year on the exchanges of mainland China, used to annualize the volatility
+pub const TRADING_DAYS_PER_YEAR: f64 = 242.0;
+
+/// Evaluate expr within the rows of every ts_code, the rows must be sorted by trade_date
+pub fn per_code(expr: Expr) -> Expr {
+    expr.over([col("ts_code")])
+}
+
+/// The return since the previous row, price / previous price - 1, null on the first row
+pub fn simple_return(price: &str) -> Expr {
+    col(price).cast(DataType::Float64) / col(price).shift(lit(1)).cast(DataType::Float64) - lit(1.0)
+}
+
+/// The log return since the previous row, ln(price / previous price), null on the first row.
+/// Unlike simple returns, log returns of consecutive rows add up to the return of the period.
+pub fn log_return(price: &str) -> Expr {
+    (col(price).cast(DataType::Float64) / col(price).shift(lit(1)).cast(DataType::Float64)).log(std::f64::consts::E)
+}
+
+/// The standard deviation of the log returns over the last window rows, annualized by [TRADING_DAYS_PER_YEAR].
+/// Null until window returns are available.
+pub fn rolling_volatility(price: &str, window: usize) -> Expr {
+    let options = RollingOptionsFixedWindow {
+        window_size: window,
+        min_periods: window,
+        ..Default::default()
+    };
+    log_return(price).rolling_std(options) * lit(TRADING_DAYS_PER_YEAR.sqrt())
+}
+
+/// The turnover rate in percent from vol (手, 100 shares) and float_share (万股, 10,000 shares) of daily_basic,
+/// the same unit as the turnover_rate of daily_basic
+pub fn turnover(vol: &str, float_share: &str) -> Expr {
+    col(vol).cast(DataType::Float64) / col(float_share).cast(DataType::Float64)
+}
+
+/// Sort the bars by ts_code (if any) and trade_date, and add the columns ret, log_ret and volatility of the close price,
+/// the volatility over the last window rows.
+/// turnover is added as well if the bars are joined with the float_share of daily_basic.
+pub fn with_returns(df: DataFrame, window: usize) -> Result<DataFrame, TushareError> {
+    let names = df.get_column_names();
+    let by_code = names.contains(&"ts_code");
+    let has_float_share = names.contains(&"vol") && names.contains(&"float_share");
+    let scope = |e: Expr| match by_code {
+        true => per_code(e),
+        false => e,
+    };
+    let keys: Vec<&str> = match by_code {
+        true => vec!["ts_code", "trade_date"],
+        false => vec!["trade_date"],
+    };
+    let mut columns = vec![
+        scope(simple_return("close")).alias("ret"),
+        scope(log_return("close")).alias("log_ret"),
+        scope(rolling_volatility("close", window)).alias("volatility"),
+    ];
+    if has_float_share {
+        columns.push(turnover("vol", "float_share").alias("turnover"));
+    }
+    Ok(df
+        .lazy()
+        .sort(keys, SortMultipleOptions::default())
+        .with_columns(columns)
+        .collect()?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[test]
+    fn test_with_returns() {
+        let daily = df!(
+            "ts_code" => ["600000.SH", "000001.SZ", "000001.SZ", "000001.SZ", "600000.SH"],
+            "trade_date" => ["20240102", "20240103", "20240102", "20240104", "20240103"],
+            "close" => [7.0, 11.0, 10.0, 12.1, 7.7],
+            "vol" => [1000.0, 200.0, 100.0, 300.0, 2000.0],
+            "float_share" => [100.0, 100.0, 100.0, 100.0, 100.0]
+        )
+        .unwrap();
+        let df = with_returns(daily, 2).unwrap();
+        let ret: Vec<Option<f64>> = df.column("ret").unwrap().f64().unwrap().into_iter().collect();
+        let ret: Vec<Option<f64>> = ret.into_iter().map(|r| r.map(|r| (r * 1e6).round() / 1e6)).collect();
+        assert_eq!(ret, [None, Some(0.1), Some(0.1), None, Some(0.1)]);
+        let volatility = df.column("volatility").unwrap().f64().unwrap();
+        assert_eq!(volatility.get(1), None);
+        // the same returns, up to the float error of the rolling variance
+        assert!(volatility.get(2).unwrap() < 1e-6);
+        assert_eq!(df.column("turnover").unwrap().f64().unwrap().get(0), Some(1.0));
+    }
+}
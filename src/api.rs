@@ -0,0 +1,331 @@
+use crate::date::{TUSHARE_DATETIME_FORMAT, TUSHARE_DATE_FORMAT};
+use std::fmt;
+
+macro_rules! tushare_apis {
+    ($($(#[$doc:meta])* $variant:ident => $name:literal,)*) => {
+        /// Tushare web apis, used as api_name of Tushare::querybuilder().
+        /// Prefer the enum over raw strings to avoid typos, e.g. "stock_basic" vs "stocks_basic".
+        /// Use TushareApi::Custom for the apis not listed here.
+        /// Refer to <https://tushare.pro/document/2> for the api documents.
+        #[derive(Debug, Clone, PartialEq, Eq, Hash)]
+        pub enum TushareApi {
+            $($(#[$doc])* $variant,)*
+            /// Any api not listed, the string is sent as is
+            Custom(String),
+        }
+
+        impl TushareApi {
+            /// The api_name string sent to tushare server
+            pub fn as_str(&self) -> &str {
+                match self {
+                    $(TushareApi::$variant => $name,)*
+                    TushareApi::Custom(name) => name,
+                }
+            }
+
+            /// Map an api_name string to the enum, unknown names become TushareApi::Custom
+            pub fn from_name(name: &str) -> Self {
+                match name {
+                    $($name => TushareApi::$variant,)*
+                    _ => TushareApi::Custom(name.to_string()),
+                }
+            }
+        }
+    };
+}
+
+tushare_apis! {
+    /// Stock list
+    StockBasic => "stock_basic",
+    /// Trade calendar of exchanges
+    TradeCal => "trade_cal",
+    /// Stock name change history
+    NameChange => "namechange",
+    /// Daily bars, unadjusted
+    Daily => "daily",
+    /// Weekly bars, unadjusted
+    Weekly => "weekly",
+    /// Monthly bars, unadjusted
+    Monthly => "monthly",
+    /// Minute bars, unadjusted
+    StkMins => "stk_mins",
+    /// Realtime daily bars of the current trade day
+    RtK => "rt_k",
+    /// Daily indicators like turnover rate, pe, pb
+    DailyBasic => "daily_basic",
+    /// Suspended and resumed stocks of a day
+    SuspendD => "suspend_d",
+    /// Stocks hitting the limit up or down of a day
+    LimitListD => "limit_list_d",
+    /// Price adjust factors
+    AdjFactor => "adj_factor",
+    /// Money flow of a stock by order size
+    Moneyflow => "moneyflow",
+    /// Money flow of the Stock Connect, northbound and southbound
+    MoneyflowHsgt => "moneyflow_hsgt",
+    /// Top 10 stocks traded through the northbound Stock Connect
+    HsgtTop10 => "hsgt_top10",
+    /// Top 10 stocks traded through the southbound Stock Connect
+    GgtTop10 => "ggt_top10",
+    /// Margin trading summary of exchanges
+    Margin => "margin",
+    /// Margin trading of stocks
+    MarginDetail => "margin_detail",
+    /// Top 10 shareholders of the report periods
+    Top10Holders => "top10_holders",
+    /// Top 10 holders of the floating shares of the report periods
+    Top10FloatHolders => "top10_floatholders",
+    /// Share increases and decreases of major holders and executives
+    StkHolderTrade => "stk_holdertrade",
+    /// Share pledge statistics
+    PledgeStat => "pledge_stat",
+    /// Company announcements
+    Anns => "anns",
+    /// Flash news of the financial news sites
+    News => "news",
+    /// Income statement
+    Income => "income",
+    /// Balance sheet
+    BalanceSheet => "balancesheet",
+    /// Cash flow statement
+    CashFlow => "cashflow",
+    /// Financial indicators
+    FinaIndicator => "fina_indicator",
+    /// Dividends and splits
+    Dividend => "dividend",
+    /// Index list
+    IndexBasic => "index_basic",
+    /// Index daily bars
+    IndexDaily => "index_daily",
+    /// Index constituents and weights
+    IndexWeight => "index_weight",
+    /// Shenwan industry index constituents
+    IndexMember => "index_member",
+    /// Shenwan industry classification
+    IndexClassify => "index_classify",
+    /// Concept list of tushare
+    Concept => "concept",
+    /// Stocks of the concepts
+    ConceptDetail => "concept_detail",
+    /// Fund list
+    FundBasic => "fund_basic",
+    /// Fund net asset values
+    FundNav => "fund_nav",
+    /// Fund holdings
+    FundPortfolio => "fund_portfolio",
+    /// Daily bars of exchange traded funds
+    FundDaily => "fund_daily",
+    /// Futures contracts
+    FutBasic => "fut_basic",
+    /// Futures daily bars
+    FutDaily => "fut_daily",
+    /// Options contracts
+    OptBasic => "opt_basic",
+    /// Options daily bars
+    OptDaily => "opt_daily",
+    /// Consumer price index, monthly
+    CnCpi => "cn_cpi",
+    /// Gross domestic product, quarterly
+    CnGdp => "cn_gdp",
+    /// Money supply, monthly
+    CnM => "cn_m",
+    /// Shanghai interbank offered rates, daily
+    Shibor => "shibor",
+    /// Hong Kong stock list
+    HkBasic => "hk_basic",
+    /// Hong Kong stock daily bars
+    HkDaily => "hk_daily",
+    /// US stock list
+    UsBasic => "us_basic",
+    /// US stock daily bars
+    UsDaily => "us_daily",
+}
+
+/// What is known about an api locally, used to validate a query before any network call
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ApiMeta {
+    /// Params the api refuses to run without.
+    /// Apis accepting either of several params, e.g. daily with ts_code or trade_date, list none.
+    pub required_params: &'static [&'static str],
+    /// Max rows returned by a single call, None if not documented
+    pub max_rows: Option<usize>,
+    /// Params holding a date, checked against date_format
+    pub date_params: &'static [&'static str],
+    /// chrono format of the date params, e.g. "%Y%m%d"
+    pub date_format: &'static str,
+}
+
+impl Default for ApiMeta {
+    fn default() -> Self {
+        ApiMeta {
+            required_params: &[],
+            max_rows: None,
+            date_params: &[],
+            date_format: TUSHARE_DATE_FORMAT,
+        }
+    }
+}
+
+/// The date params of the apis queried by trade date or a date range
+const TRADE_DATE_PARAMS: &[&str] = &["trade_date", "start_date", "end_date"];
+/// The date params of the financial statements
+const REPORT_DATE_PARAMS: &[&str] = &["ann_date", "start_date", "end_date", "period"];
+
+impl TushareApi {
+    /// The metadata of a known api, the default metadata (nothing checked) for the others
+    pub fn meta(&self) -> ApiMeta {
+        let meta = ApiMeta::default();
+        match self {
+            TushareApi::TradeCal | TushareApi::NameChange => ApiMeta {
+                date_params: &["start_date", "end_date"],
+                ..meta
+            },
+            TushareApi::Daily => ApiMeta {
+                max_rows: Some(6000),
+                date_params: TRADE_DATE_PARAMS,
+                ..meta
+            },
+            TushareApi::DailyBasic => ApiMeta {
+                max_rows: Some(5000),
+                date_params: TRADE_DATE_PARAMS,
+                ..meta
+            },
+            TushareApi::LimitListD => ApiMeta {
+                max_rows: Some(2500),
+                date_params: TRADE_DATE_PARAMS,
+                ..meta
+            },
+            TushareApi::Moneyflow => ApiMeta {
+                max_rows: Some(6000),
+                date_params: TRADE_DATE_PARAMS,
+                ..meta
+            },
+            TushareApi::MoneyflowHsgt => ApiMeta {
+                max_rows: Some(300),
+                date_params: TRADE_DATE_PARAMS,
+                ..meta
+            },
+            TushareApi::Weekly | TushareApi::Monthly => ApiMeta {
+                max_rows: Some(4500),
+                date_params: TRADE_DATE_PARAMS,
+                ..meta
+            },
+            TushareApi::StkMins => ApiMeta {
+                required_params: &["ts_code", "freq"],
+                max_rows: Some(8000),
+                date_params: &["start_date", "end_date"],
+                date_format: TUSHARE_DATETIME_FORMAT,
+            },
+            TushareApi::RtK => ApiMeta {
+                required_params: &["ts_code"],
+                ..meta
+            },
+            TushareApi::IndexDaily => ApiMeta {
+                required_params: &["ts_code"],
+                max_rows: Some(8000),
+                date_params: TRADE_DATE_PARAMS,
+                ..meta
+            },
+            TushareApi::IndexWeight => ApiMeta {
+                required_params: &["index_code"],
+                date_params: TRADE_DATE_PARAMS,
+                ..meta
+            },
+            TushareApi::Income
+            | TushareApi::BalanceSheet
+            | TushareApi::CashFlow
+            | TushareApi::FinaIndicator
+            | TushareApi::FundPortfolio
+            | TushareApi::Top10Holders
+            | TushareApi::Top10FloatHolders => ApiMeta {
+                required_params: &["ts_code"],
+                date_params: REPORT_DATE_PARAMS,
+                ..meta
+            },
+            TushareApi::Dividend => ApiMeta {
+                date_params: &["ann_date", "record_date", "ex_date", "imp_ann_date"],
+                ..meta
+            },
+            TushareApi::FundNav => ApiMeta {
+                date_params: &["nav_date", "start_date", "end_date"],
+                ..meta
+            },
+            TushareApi::News => ApiMeta {
+                required_params: &["src", "start_date", "end_date"],
+                max_rows: Some(1500),
+                date_params: &["start_date", "end_date"],
+                date_format: TUSHARE_DATETIME_FORMAT,
+            },
+            TushareApi::Anns | TushareApi::StkHolderTrade => ApiMeta {
+                date_params: &["ann_date", "start_date", "end_date"],
+                ..meta
+            },
+            TushareApi::PledgeStat => ApiMeta {
+                date_params: &["end_date"],
+                ..meta
+            },
+            TushareApi::Shibor => ApiMeta {
+                date_params: &["date", "start_date", "end_date"],
+                ..meta
+            },
+            TushareApi::SuspendD
+            | TushareApi::AdjFactor
+            | TushareApi::HsgtTop10
+            | TushareApi::GgtTop10
+            | TushareApi::Margin
+            | TushareApi::MarginDetail
+            | TushareApi::FundDaily
+            | TushareApi::FutDaily
+            | TushareApi::OptDaily
+            | TushareApi::HkDaily
+            | TushareApi::UsDaily => ApiMeta {
+                date_params: TRADE_DATE_PARAMS,
+                ..meta
+            },
+            _ => meta,
+        }
+    }
+
+    /// Params the api refuses to run without, empty for unknown apis, see [ApiMeta]
+    pub fn required_params(&self) -> &'static [&'static str] {
+        self.meta().required_params
+    }
+}
+
+impl fmt::Display for TushareApi {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl From<TushareApi> for String {
+    fn from(api: TushareApi) -> Self {
+        match api {
+            TushareApi::Custom(name) => name,
+            api => api.as_str().to_string(),
+        }
+    }
+}
+
+impl From<&TushareApi> for String {
+    fn from(api: &TushareApi) -> Self {
+        api.as_str().to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[test]
+    fn test_api_name() {
+        assert_eq!(String::from(TushareApi::BalanceSheet), "balancesheet");
+        assert_eq!(TushareApi::from_name("daily"), TushareApi::Daily);
+        assert_eq!(TushareApi::from_name("stk_mins"), TushareApi::StkMins);
+        assert_eq!(TushareApi::from_name("cyq_perf"), TushareApi::Custom("cyq_perf".into()));
+        assert_eq!(TushareApi::Custom("cyq_perf".into()).to_string(), "cyq_perf");
+        assert_eq!(TushareApi::StkMins.required_params(), &["ts_code", "freq"]);
+        assert!(TushareApi::Daily.required_params().is_empty());
+        assert_eq!(TushareApi::Weekly.meta().max_rows, Some(4500));
+        assert_eq!(TushareApi::Custom("cyq_perf".into()).meta(), ApiMeta::default());
+    }
+}
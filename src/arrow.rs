@@ -0,0 +1,99 @@
+//! Arrow output for the consumers without polars, e.g. other Rust arrow stacks or pyarrow reading the IPC stream.
+//! Enabled by the "arrow" feature, the results are converted through an in memory Arrow IPC file,
+//! which is a single copy of the buffers.
+//! ```no_run
+//! # fn main() -> Result<(), tushare::TushareError> {
+//! let tushare = tushare::Tushare::from_env()?;
+//! let batch = tushare.querybuilder("daily").addparam("trade_date", "20240424").query_arrow()?;
+//! println!("{} rows of {:?}", batch.num_rows(), batch.schema());
+//! // or stream every page to stdout, e.g. for `pyarrow.ipc.open_stream(sys.stdin.buffer)`
+//! tushare.querybuilder("trade_cal").query_arrow_stream(std::io::stdout())?;
+//! # Ok(())
+//! # }
+//! ```
+use crate::builder::QueryBuilder;
+use crate::error::TushareError;
+use crate::transform;
+use arrow_array::RecordBatch;
+use arrow_ipc::reader::FileReader;
+use arrow_ipc::writer::StreamWriter;
+use arrow_schema::SchemaRef;
+use polars::prelude::*;
+use std::io::{Cursor, Write};
+use std::ops::ControlFlow;
+
+/// Convert a DataFrame to an arrow RecordBatch
+pub fn to_record_batch(df: &DataFrame) -> Result<RecordBatch, TushareError> {
+    // a single chunk is written as a single batch
+    let mut df = df.clone();
+    df.as_single_chunk();
+    let mut buf = vec![];
+    IpcWriter::new(&mut buf).finish(&mut df)?;
+    let reader = FileReader::try_new(Cursor::new(buf), None)?;
+    let schema: SchemaRef = reader.schema();
+    let batches = reader.collect::<Result<Vec<_>, _>>()?;
+    Ok(match batches.len() {
+        0 => RecordBatch::new_empty(schema),
+        1 => batches.into_iter().next().expect("a batch"),
+        _ => arrow_select::concat::concat_batches(&schema, &batches)?,
+    })
+}
+
+impl QueryBuilder<'_> {
+    /// Same as query(), but returns an arrow RecordBatch
+    pub fn query_arrow(&self) -> Result<RecordBatch, TushareError> {
+        to_record_batch(&self.query()?)
+    }
+
+    /// Query all rows like query_all(), and write every page as a batch of an Arrow IPC stream.
+    /// # return
+    /// The number of rows written
+    pub fn query_arrow_stream(&self, writer: impl Write) -> Result<usize, TushareError> {
+        let mut stream: Option<(Schema, StreamWriter<_>)> = None;
+        let mut writer = Some(writer);
+        let mut rows = 0;
+        let mut write_page = |df: DataFrame| -> Result<(), TushareError> {
+            let df = match &stream {
+                // a later page may infer other dtypes, e.g. a column of all nulls
+                Some((schema, _)) => transform::apply_schema(df, schema)?,
+                None => df,
+            };
+            let batch = to_record_batch(&df)?;
+            let (_, stream) = match stream.as_mut() {
+                Some(stream) => stream,
+                None => {
+                    let out = writer.take().expect("the writer is taken once");
+                    stream.insert((df.schema(), StreamWriter::try_new(out, &batch.schema())?))
+                }
+            };
+            stream.write(&batch)?;
+            rows += batch.num_rows();
+            Ok(())
+        };
+        if let Some(e) = self.query_chunks(|df| match write_page(df) {
+            Ok(()) => ControlFlow::Continue(()),
+            Err(e) => ControlFlow::Break(e),
+        })? {
+            return Err(e);
+        }
+        match stream.as_mut() {
+            Some((_, stream)) => stream.finish()?,
+            None => return Err(TushareError::EmptyError),
+        }
+        Ok(rows)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[test]
+    fn test_to_record_batch() {
+        let df = df!("ts_code" => ["000001.SZ", "600000.SH"], "close" => [Some(10.5), None]).unwrap();
+        let batch = to_record_batch(&df).unwrap();
+        assert_eq!((batch.num_rows(), batch.num_columns()), (2, 2));
+        assert_eq!(batch.column(1).null_count(), 1);
+        let empty = to_record_batch(&df.head(Some(0))).unwrap();
+        assert_eq!((empty.num_rows(), empty.num_columns()), (0, 2));
+    }
+}
@@ -0,0 +1,330 @@
+//! Adjusted price bars, the rust version of pro_bar() in tushare python sdk.
+//! Tushare returns unadjusted prices, which jump at every dividend or split.
+//! The adjusted prices are computed from the adj_factor api:
+//! hfq (backward adjusted) price = price * adj_factor,
+//! qfq (forward adjusted) price = price * adj_factor / the latest adj_factor in the range.
+//! Use [apply_adjustment] to adjust the bars and factors fetched separately.
+use crate::api::TushareApi;
+use crate::date::IntoTushareDate;
+use crate::code::check_codes;
+use crate::error::TushareError;
+use crate::fields;
+use crate::stock::{float_dtypes, DAILY_FIELDS, DAILY_NUMERIC};
+use crate::transform;
+use crate::tushare::Tushare;
+use chrono::NaiveDate;
+use polars::prelude::*;
+use std::ops::RangeInclusive;
+
+/// Price columns multiplied by the adjustment factor, pct_chg and volumes are unchanged
+const ADJUSTED_COLUMNS: &[&str] = &["open", "high", "low", "close", "pre_close", "change"];
+
+/// Fields of stk_mins
+const MINUTE_FIELDS: &str = fields::stk_mins::ALL;
+
+/// Columns returned by Tushare::bars() of every frequency
+pub const BAR_COLUMNS: &[&str] = &["ts_code", "trade_time", "open", "high", "low", "close", "vol", "amount"];
+
+/// Rows returned by a single call of stk_mins
+const MINUTE_PAGE_SIZE: usize = 8000;
+
+/// Price adjustment of bars
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum Adj {
+    /// Unadjusted prices as returned by tushare
+    #[default]
+    None,
+    /// Forward adjusted (前复权), the latest prices are unchanged
+    Qfq,
+    /// Backward adjusted (后复权), the prices at listing are unchanged
+    Hfq,
+}
+
+/// Frequency of bars
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum Freq {
+    /// 1 minute bars
+    Min1,
+    /// 5 minute bars
+    Min5,
+    /// 15 minute bars
+    Min15,
+    /// 30 minute bars
+    Min30,
+    /// 60 minute bars
+    Min60,
+    /// Daily bars
+    #[default]
+    Daily,
+    /// Weekly bars, trade_date is the last trading day of the week
+    Weekly,
+    /// Monthly bars, trade_date is the last trading day of the month
+    Monthly,
+}
+
+impl Freq {
+    /// The tushare api of the bars
+    pub fn api(&self) -> TushareApi {
+        match self {
+            Freq::Daily => TushareApi::Daily,
+            Freq::Weekly => TushareApi::Weekly,
+            Freq::Monthly => TushareApi::Monthly,
+            _ => TushareApi::StkMins,
+        }
+    }
+
+    /// The freq param of stk_mins, None for daily and longer bars
+    pub fn minutes_param(&self) -> Option<&'static str> {
+        match self {
+            Freq::Min1 => Some("1min"),
+            Freq::Min5 => Some("5min"),
+            Freq::Min15 => Some("15min"),
+            Freq::Min30 => Some("30min"),
+            Freq::Min60 => Some("60min"),
+            _ => None,
+        }
+    }
+}
+
+/// Fields returned by Tushare::dividend()
+pub const DIVIDEND_FIELDS: &str = fields::dividend::ALL;
+
+/// Numeric columns of dividend, bonus shares and cash per share
+const DIVIDEND_NUMERIC: &[&str] = &["stk_div", "stk_bo_rate", "stk_co_rate", "cash_div", "cash_div_tax", "base_share"];
+
+/// Multiply the prices of bars by the adj_factor of the same trade_date (and ts_code if both have it).
+/// A missing factor is filled from the previous trading day, e.g. a factor not published yet.
+pub(crate) fn adjust(bars: DataFrame, factors: DataFrame, adj: Adj) -> PolarsResult<DataFrame> {
+    let by_code = bars.get_column_names().contains(&"ts_code") && factors.get_column_names().contains(&"ts_code");
+    let keys: Vec<Expr> = match by_code {
+        true => vec![col("ts_code"), col("trade_date")],
+        false => vec![col("trade_date")],
+    };
+    let per_code = |e: Expr| match by_code {
+        true => e.over([col("ts_code")]),
+        false => e,
+    };
+    let factor = match adj {
+        Adj::None => return Ok(bars),
+        Adj::Hfq => col("adj_factor"),
+        Adj::Qfq => col("adj_factor") / per_code(col("adj_factor").last()),
+    };
+    let prices: Vec<Expr> = ADJUSTED_COLUMNS
+        .iter()
+        .filter(|c| bars.get_column_names().contains(c))
+        .map(|c| col(c) * factor.clone())
+        .collect();
+    let mut factor_columns = keys.clone();
+    factor_columns.push(col("adj_factor"));
+    bars.lazy()
+        .sort_by_exprs(keys.clone(), SortMultipleOptions::default())
+        .join(
+            factors.lazy().select(factor_columns),
+            keys.clone(),
+            keys,
+            JoinArgs::new(JoinType::Left),
+        )
+        .with_column(per_code(col("adj_factor").forward_fill(None).backward_fill(None)))
+        .with_columns(prices)
+        .drop(["adj_factor"])
+        .collect()
+}
+
+/// Adjust the OHLC columns (open, high, low, close, pre_close, change) of raw bars with the adj_factor rows,
+/// for users who fetch daily and adj_factor separately. pct_chg and the volumes are unchanged.
+/// The frames are matched by trade_date, and by ts_code as well if both have it,
+/// so bars of many stocks can be adjusted at once. qfq is relative to the last bar of every stock.
+/// trade_date may be *YYYYMMDD* strings or Date, the result is sorted by ts_code and trade_date.
+pub fn apply_adjustment(prices: DataFrame, factors: DataFrame, adj: Adj) -> Result<DataFrame, TushareError> {
+    let trade_date = ["trade_date".to_string()];
+    let prices = transform::parse_dates(prices, &trade_date)?;
+    let factors = transform::apply_schema(
+        transform::parse_dates(factors, &trade_date)?,
+        &Schema::from_iter([Field::new("adj_factor", DataType::Float64)]),
+    )?;
+    Ok(adjust(prices, factors, adj)?)
+}
+
+impl Tushare {
+    /// Daily/weekly/monthly bars of a stock between start and end, both inclusive, with the price adjustment.
+    /// The result is sorted by trade_date ascending, with trade_date as Date and OHLCV as f64.
+    /// Note qfq prices are relative to the last bar in the range, so they change with the end date.
+    /// ```no_run
+    /// # fn main() -> Result<(), tushare::TushareError> {
+    /// use chrono::NaiveDate;
+    /// use tushare::{Adj, Freq};
+    /// let tushare = tushare::Tushare::from_env()?;
+    /// let start = NaiveDate::from_ymd_opt(2024, 1, 1).unwrap();
+    /// let end = NaiveDate::from_ymd_opt(2024, 4, 30).unwrap();
+    /// let df = tushare.pro_bar("000001.SZ", start, end, Freq::Daily, Adj::Qfq)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn pro_bar(
+        &self,
+        ts_code: impl AsRef<str>,
+        start: impl IntoTushareDate,
+        end: impl IntoTushareDate,
+        freq: Freq,
+        adj: Adj,
+    ) -> Result<DataFrame, TushareError> {
+        check_codes(ts_code.as_ref())?;
+        if freq.minutes_param().is_some() {
+            return Err(TushareError::InvalidParams(format!(
+                "pro_bar() doesn't support {freq:?} bars, use bars() instead"
+            )));
+        }
+        let bars = self
+            .querybuilder(freq.api())
+            .addparam("ts_code", ts_code.as_ref())
+            .start_date(&start)
+            .end_date(&end)
+            .fields(DAILY_FIELDS)
+            .dtypes(&float_dtypes(DAILY_NUMERIC))
+            .parse_dates(true)
+            .query_all()?
+            .sort(["trade_date"], SortMultipleOptions::default())?;
+        if adj == Adj::None {
+            return Ok(bars);
+        }
+        let factors = self.adj_factor(ts_code, &start, &end)?;
+        Ok(adjust(bars, factors, adj)?)
+    }
+
+    /// Adjust factors of a stock between start and end, both inclusive, see [apply_adjustment].
+    /// The result is sorted by trade_date ascending, with trade_date as Date and adj_factor as f64.
+    pub fn adj_factor(
+        &self,
+        ts_code: impl AsRef<str>,
+        start: impl IntoTushareDate,
+        end: impl IntoTushareDate,
+    ) -> Result<DataFrame, TushareError> {
+        check_codes(ts_code.as_ref())?;
+        let df = self
+            .querybuilder(TushareApi::AdjFactor)
+            .addparam("ts_code", ts_code.as_ref())
+            .start_date(start)
+            .end_date(end)
+            .fields("ts_code,trade_date,adj_factor")
+            .dtypes(&[("adj_factor", DataType::Float64)])
+            .parse_dates(true)
+            .query_all()?;
+        Ok(df.sort(["trade_date"], SortMultipleOptions::default())?)
+    }
+
+    /// Dividends, bonus shares and splits of a stock, one row per plan and progress (div_proc),
+    /// sorted by end_date ascending with the dates as Date. Zero rows for a stock never paid any.
+    pub fn dividend(&self, ts_code: impl AsRef<str>) -> Result<DataFrame, TushareError> {
+        check_codes(ts_code.as_ref())?;
+        let df = self
+            .querybuilder(TushareApi::Dividend)
+            .addparam("ts_code", ts_code.as_ref())
+            .fields(DIVIDEND_FIELDS)
+            .dtypes(&float_dtypes(DIVIDEND_NUMERIC))
+            .parse_dates(true)
+            .allow_empty(true)
+            .query_all()?;
+        Ok(df.sort(["end_date"], SortMultipleOptions::default())?)
+    }
+
+    /// Unadjusted bars of a stock in the date range, with the same columns for every frequency:
+    /// ts_code, trade_time (Datetime), open, high, low, close, vol (in shares) and amount (in yuan).
+    /// Daily and longer bars are normalized from trade_date, vol in lots and amount in thousand yuan,
+    /// their trade_time is the midnight of trade_date. The result is sorted by trade_time ascending.
+    pub fn bars(
+        &self,
+        ts_code: impl AsRef<str>,
+        freq: Freq,
+        range: RangeInclusive<NaiveDate>,
+    ) -> Result<DataFrame, TushareError> {
+        check_codes(ts_code.as_ref())?;
+        let time = match freq.minutes_param() {
+            Some(minutes) => self
+                .querybuilder(freq.api())
+                .addparam("ts_code", ts_code.as_ref())
+                .addparam("freq", minutes)
+                .addparam("start_date", format!("{} 00:00:00", range.start().format("%Y-%m-%d")))
+                .addparam("end_date", format!("{} 23:59:59", range.end().format("%Y-%m-%d")))
+                .fields(MINUTE_FIELDS)
+                .dtypes(&float_dtypes(&BAR_COLUMNS[2..]))
+                .page_size(MINUTE_PAGE_SIZE)
+                .query_all()?
+                .lazy()
+                .with_column(col("trade_time").str().to_datetime(
+                    Some(TimeUnit::Milliseconds),
+                    None,
+                    StrptimeOptions {
+                        format: Some("%Y-%m-%d %H:%M:%S".to_string()),
+                        strict: false,
+                        ..Default::default()
+                    },
+                    lit("raise"),
+                )),
+            None => self
+                .pro_bar(ts_code, *range.start(), *range.end(), freq, Adj::None)?
+                .lazy()
+                .with_columns([
+                    col("trade_date")
+                        .cast(DataType::Datetime(TimeUnit::Milliseconds, None))
+                        .alias("trade_time"),
+                    col("vol") * lit(100.0),
+                    col("amount") * lit(1000.0),
+                ]),
+        };
+        let columns: Vec<Expr> = BAR_COLUMNS.iter().map(|c| col(c)).collect();
+        Ok(time
+            .select(columns)
+            .sort(["trade_time"], SortMultipleOptions::default())
+            .collect()?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[test]
+    fn test_adjust() {
+        let bars = df!("trade_date" => ["20240101", "20240102", "20240103"], "close" => [10.0, 5.0, 5.5]).unwrap();
+        let factors = df!("trade_date" => ["20240101", "20240102"], "adj_factor" => [1.0, 2.0]).unwrap();
+        let hfq = adjust(bars.clone(), factors.clone(), Adj::Hfq).unwrap();
+        assert_eq!(hfq.column("close").unwrap().f64().unwrap().to_vec(), [Some(10.0), Some(10.0), Some(11.0)]);
+        assert!(!hfq.get_column_names().contains(&"adj_factor"));
+        let qfq = adjust(bars, factors, Adj::Qfq).unwrap();
+        assert_eq!(qfq.column("close").unwrap().f64().unwrap().to_vec(), [Some(5.0), Some(5.0), Some(5.5)]);
+    }
+
+    #[test]
+    fn test_apply_adjustment() {
+        let prices = df!(
+            "ts_code" => ["600000.SH", "000001.SZ", "000001.SZ"],
+            "trade_date" => ["20240102", "20240102", "20240101"],
+            "close" => [7.0, 5.0, 10.0]
+        )
+        .unwrap();
+        let factors = df!(
+            "ts_code" => ["000001.SZ", "000001.SZ", "600000.SH"],
+            "trade_date" => ["20240101", "20240102", "20240102"],
+            "adj_factor" => ["1.0", "2.0", "3.0"]
+        )
+        .unwrap();
+        let qfq = apply_adjustment(prices, factors, Adj::Qfq).unwrap();
+        assert_eq!(qfq.column("close").unwrap().f64().unwrap().to_vec(), [Some(5.0), Some(5.0), Some(7.0)]);
+        assert_eq!(qfq.column("trade_date").unwrap().dtype(), &DataType::Date);
+    }
+
+    #[test]
+    fn test_bars_daily() {
+        use crate::transport::MockTransport;
+        use serde_json::json;
+        let body = MockTransport::data(
+            &["ts_code", "trade_date", "open", "high", "low", "close", "vol", "amount"],
+            vec![vec![json!("000001.SZ"), json!("20240102"), json!(1), json!(2), json!(1), json!(2), json!(3), json!(4)]],
+        );
+        let tushare = Tushare::new("").with_transport(MockTransport::new().respond("daily", body));
+        let range = NaiveDate::from_ymd_opt(2024, 1, 1).unwrap()..=NaiveDate::from_ymd_opt(2024, 1, 31).unwrap();
+        let df = tushare.bars("000001.SZ", Freq::Daily, range).unwrap();
+        assert_eq!(df.get_column_names(), BAR_COLUMNS);
+        assert_eq!(df.column("vol").unwrap().f64().unwrap().get(0), Some(300.0));
+        assert!(matches!(df.column("trade_time").unwrap().dtype(), DataType::Datetime(_, _)));
+    }
+}
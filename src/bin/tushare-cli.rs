@@ -0,0 +1,86 @@
+//! Query tushare from the command line, the token is read from TUSHARE_TOKEN environment variable.
+//! ```text
+//! tushare-cli --api daily --param ts_code=000001.SZ --param start_date=20240101 --fields ts_code,trade_date,close
+//! tushare-cli --api stock_basic --all --output stocks.parquet
+//! ```
+use clap::Parser;
+use std::path::PathBuf;
+use std::process::ExitCode;
+use tushare::{Tushare, TushareError};
+
+#[derive(Parser, Debug)]
+#[command(name = "tushare-cli", version, about = "Query tushare and print or save the result")]
+struct Args {
+    /// The api name, e.g. daily
+    #[arg(long)]
+    api: String,
+    /// A parameter as key=value, can be repeated
+    #[arg(long = "param", value_parser = parse_param)]
+    params: Vec<(String, String)>,
+    /// The return fields separated with commas
+    #[arg(long)]
+    fields: Option<String>,
+    /// Fetch all pages instead of the first one
+    #[arg(long)]
+    all: bool,
+    /// Convert the known date columns to Date
+    #[arg(long)]
+    parse_dates: bool,
+    /// Load the token and settings from a TOML config file instead of the environment
+    #[arg(long)]
+    config: Option<PathBuf>,
+    /// Write the result to a .csv, .parquet or .ipc file instead of printing it
+    #[arg(long)]
+    output: Option<PathBuf>,
+}
+
+fn parse_param(s: &str) -> Result<(String, String), String> {
+    s.split_once('=')
+        .map(|(k, v)| (k.trim().to_string(), v.trim().to_string()))
+        .ok_or_else(|| format!("expected key=value, got {s}"))
+}
+
+fn run(args: Args) -> Result<(), TushareError> {
+    let tushare = match &args.config {
+        Some(path) => Tushare::from_config(path)?,
+        None => Tushare::from_env()?,
+    };
+    let mut query = tushare.querybuilder(args.api.as_str()).parse_dates(args.parse_dates);
+    for (k, v) in &args.params {
+        query = query.addparam(k.as_str(), v);
+    }
+    if let Some(fields) = &args.fields {
+        query = query.fields(fields);
+    }
+    let Some(output) = &args.output else {
+        let df = if args.all { query.query_all()? } else { query.query()? };
+        println!("{df}");
+        return Ok(());
+    };
+    // a single page is written the same way, by limiting the pages
+    if !args.all {
+        query = query.max_pages(1);
+    }
+    let rows = match output.extension().and_then(|e| e.to_str()) {
+        Some("csv") => query.query_to_csv(output)?,
+        Some("parquet") => query.query_to_parquet(output)?,
+        Some("ipc") | Some("arrow") | Some("feather") => query.query_to_ipc(output)?,
+        _ => {
+            return Err(TushareError::ConfigError(format!(
+                "unknown output format of {output:?}, use .csv, .parquet or .ipc"
+            )))
+        }
+    };
+    eprintln!("{rows} rows written to {output:?}");
+    Ok(())
+}
+
+fn main() -> ExitCode {
+    match run(Args::parse()) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("error: {e}");
+            ExitCode::FAILURE
+        }
+    }
+}
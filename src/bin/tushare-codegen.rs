@@ -0,0 +1,201 @@
+//! Generate the typed surface of new tushare apis from the community Tushare.yaml,
+//! see <https://github.com/ProV1denCEX/Tushare.jl/blob/master/src/Tushare.yaml>.
+//! ```text
+//! tushare-codegen Tushare.yaml > new_apis.rs
+//! tushare-codegen Tushare.yaml --all --api daily --api cyq_perf
+//! ```
+//! For every api not known by the crate yet, it prints the TushareApi variant for src/api.rs,
+//! the `api_fields!` block for src/fields.rs, and a row struct for QueryBuilder::query_as().
+//! Every entry of the yaml is expected to have `api`, an optional `name` as its doc,
+//! and `output` as a list of fields, each a name or a map of `name`, `type` and `desc`.
+use clap::Parser;
+use std::fmt::Write;
+use std::path::PathBuf;
+use std::process::ExitCode;
+use tushare::fields::{self, FieldType};
+use tushare::{TushareApi, TushareError};
+use yaml_rust2::{Yaml, YamlLoader};
+
+#[derive(Parser, Debug)]
+#[command(name = "tushare-codegen", version, about = "Generate the typed apis from Tushare.yaml")]
+struct Args {
+    /// Path of Tushare.yaml
+    yaml: PathBuf,
+    /// Generate the apis already known by the crate as well, to diff them against the yaml
+    #[arg(long)]
+    all: bool,
+    /// Generate only this api, can be repeated
+    #[arg(long = "api")]
+    apis: Vec<String>,
+}
+
+/// An api described by the yaml
+#[derive(Debug, PartialEq)]
+struct ApiSpec {
+    api: String,
+    doc: Option<String>,
+    fields: Vec<FieldSpec>,
+}
+
+#[derive(Debug, PartialEq)]
+struct FieldSpec {
+    name: String,
+    field_type: FieldType,
+    doc: Option<String>,
+}
+
+/// The entries of the yaml, either a list or a map of them
+fn parse_specs(text: &str) -> Result<Vec<ApiSpec>, TushareError> {
+    let docs = YamlLoader::load_from_str(text).map_err(|e| TushareError::ConfigError(format!("invalid yaml: {e}")))?;
+    let entries: Vec<&Yaml> = match docs.first() {
+        Some(Yaml::Array(entries)) => entries.iter().collect(),
+        Some(Yaml::Hash(entries)) => entries.values().collect(),
+        _ => return Err(TushareError::ConfigError("expected a list or a map of apis".to_string())),
+    };
+    Ok(entries.into_iter().filter_map(parse_spec).collect())
+}
+
+fn parse_spec(entry: &Yaml) -> Option<ApiSpec> {
+    let api = entry["api"].as_str()?.trim().to_string();
+    let fields = entry["output"]
+        .as_vec()?
+        .iter()
+        .filter_map(|field| {
+            let (name, declared, doc) = match field {
+                Yaml::String(name) => (name.as_str(), None, None),
+                field => (field["name"].as_str()?, field["type"].as_str(), field["desc"].as_str()),
+            };
+            Some(FieldSpec {
+                name: name.trim().to_string(),
+                field_type: field_type(name, declared),
+                doc: doc.map(|d| d.trim().to_string()),
+            })
+        })
+        .collect();
+    Some(ApiSpec {
+        api,
+        doc: entry["name"].as_str().map(|d| d.trim().to_string()),
+        fields,
+    })
+}
+
+/// The type of a field by its declared type, dates are told by the name since the yaml declares them as str
+fn field_type(name: &str, declared: Option<&str>) -> FieldType {
+    match declared.map(|t| t.trim().to_ascii_lowercase()).as_deref() {
+        Some("float" | "number" | "double") => FieldType::Float,
+        Some("int" | "integer") => FieldType::Int,
+        Some("datetime") => FieldType::Datetime,
+        _ if name.ends_with("_time") => FieldType::Datetime,
+        _ if name.ends_with("date") => FieldType::Date,
+        _ => FieldType::Str,
+    }
+}
+
+/// ts_code to TsCode, with a prefix for the names starting with a digit
+fn camel_case(name: &str) -> String {
+    let camel: String = name
+        .split(|c: char| !c.is_ascii_alphanumeric())
+        .filter(|part| !part.is_empty())
+        .map(|part| {
+            let mut chars = part.chars();
+            chars.next().map_or_else(String::new, |c| c.to_ascii_uppercase().to_string() + chars.as_str())
+        })
+        .collect();
+    match camel.starts_with(|c: char| c.is_ascii_digit()) {
+        true => format!("F{camel}"),
+        false => camel,
+    }
+}
+
+/// The field name as a struct field, keywords like type are raw identifiers
+fn field_ident(name: &str) -> String {
+    const KEYWORDS: &[&str] = &["type", "match", "move", "ref", "use", "where", "loop", "impl", "in", "fn", "mod", "static"];
+    match KEYWORDS.contains(&name) {
+        true => format!("r#{name}"),
+        false => name.to_string(),
+    }
+}
+
+fn rust_type(field_type: FieldType) -> &'static str {
+    match field_type {
+        FieldType::Float => "f64",
+        FieldType::Int => "i64",
+        FieldType::Str | FieldType::Date | FieldType::Datetime => "String",
+    }
+}
+
+/// The generated code of the apis
+fn generate(specs: &[ApiSpec]) -> String {
+    let mut out = String::new();
+    let _ = writeln!(out, "// TushareApi variants, add them to tushare_apis! in src/api.rs");
+    for spec in specs {
+        let _ = writeln!(out, "    /// {}", spec.doc.as_deref().unwrap_or(&spec.api));
+        let _ = writeln!(out, "    {} => \"{}\",", camel_case(&spec.api), spec.api);
+    }
+    for spec in specs {
+        let _ = writeln!(out, "\n// src/fields.rs, and a schema_of() arm of TushareApi::{}", camel_case(&spec.api));
+        let _ = writeln!(out, "api_fields! {{\n    /// Fields of {}\n    {} {{", spec.api, spec.api);
+        for field in &spec.fields {
+            let _ = writeln!(out, "        {} => \"{}\": {:?},", camel_case(&field.name), field.name, field.field_type);
+        }
+        let _ = writeln!(out, "    }}\n}}");
+    }
+    for spec in specs {
+        let _ = writeln!(out, "\n/// A row of {}, for QueryBuilder::query_as()", spec.api);
+        let _ = writeln!(out, "#[derive(Debug, Clone, serde::Deserialize)]\npub struct {}Row {{", camel_case(&spec.api));
+        for field in &spec.fields {
+            if let Some(doc) = &field.doc {
+                let _ = writeln!(out, "    /// {doc}");
+            }
+            let _ = writeln!(out, "    pub {}: Option<{}>,", field_ident(&field.name), rust_type(field.field_type));
+        }
+        let _ = writeln!(out, "}}");
+    }
+    out
+}
+
+fn run(args: Args) -> Result<(), TushareError> {
+    let specs: Vec<ApiSpec> = parse_specs(&std::fs::read_to_string(&args.yaml)?)?
+        .into_iter()
+        .filter(|spec| args.apis.is_empty() || args.apis.contains(&spec.api))
+        .filter(|spec| args.all || fields::schema_of(&TushareApi::from_name(&spec.api)).is_none())
+        .collect();
+    eprintln!("{} apis generated", specs.len());
+    print!("{}", generate(&specs));
+    Ok(())
+}
+
+fn main() -> ExitCode {
+    match run(Args::parse()) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("error: {e}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[test]
+    fn test_generate() {
+        let yaml = "
+- name: 每日筹码分布
+  api: cyq_perf
+  output:
+    - {name: ts_code, type: str, desc: 股票代码}
+    - {name: trade_date, type: str}
+    - {name: cost_5pct, type: float}
+    - his_high
+";
+        let specs = parse_specs(yaml).unwrap();
+        assert_eq!(specs[0].api, "cyq_perf");
+        let types: Vec<FieldType> = specs[0].fields.iter().map(|f| f.field_type).collect();
+        assert_eq!(types, [FieldType::Str, FieldType::Date, FieldType::Float, FieldType::Str]);
+        let code = generate(&specs);
+        assert!(code.contains("    CyqPerf => \"cyq_perf\",\n"));
+        assert!(code.contains("        Cost5pct => \"cost_5pct\": Float,\n"));
+        assert!(code.contains("    /// 股票代码\n    pub ts_code: Option<String>,\n"));
+    }
+}
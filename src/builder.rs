@@ -1,46 +1,37 @@
+use crate::api::TushareApi;
+use crate::cancel::CancellationToken;
+use crate::date::IntoTushareDate;
+pub use crate::error::TushareError;
+use crate::error::TushareServerError;
+use crate::fields::{self, ApiField};
+use crate::middleware::Response;
+use crate::params::IntoParamValue;
+use crate::table::{self, ParseMode, ResponseMeta, Table};
+use crate::telemetry::QuerySpan;
 use crate::tushare::Tushare;
-use log::{error, info};
-use polars::prelude::*;
-use reqwest;
-use reqwest::blocking::Client;
+use chrono::{NaiveDate, NaiveTime};
+use log::{info, warn};
+#[cfg(feature = "dataframe")]
+use crate::progress::ProgressHook;
+#[cfg(feature = "dataframe")]
+use crate::transform::NullPolicy;
+#[cfg(feature = "dataframe")]
+use polars::prelude::Schema;
+use serde::de::DeserializeOwned;
 use serde_json::{json, Value};
 use std::collections::HashMap;
-use std::io::Cursor;
-use thiserror::Error;
-
-/// TushareError enumerates all possible errors returned by this library.
-#[derive(Error, Debug)]
-pub enum TushareError {
-    /// Tushare returns empty rows.
-    /// It might have returned dataframe column names but it's impossible to infer column type without row data
-    /// If this is the intended behavior, the caller should handle this error  
-    #[error("Tushare returned empty data")]
-    EmptyError,
-    /// Tushare returns non-zero error code in response body
-    #[error("Tushare request return error:{code}, msg:{msg}")]
-    RequestError { code: String, msg: String },
-    /// Transform Tushare returned json to polars json error
-    #[error("Expected json node {0} not exist")]
-    DataError(String),
-
-    /// Represents a network failure to read tushare web api.
-    #[error("Request network error, not accessable or possible 500")]
-    NetworkError(#[from] reqwest::Error),
-
-    /// Represents a failure to decode tushare result json
-    #[error("Parse tushare response json error")]
-    JsonError(#[from] serde_json::Error),
-
-    /// Represents a failure to converting json to polars dataframe
-    #[error("Convert json to polars dataframe error")]
-    PolarsError(#[from] polars::error::PolarsError)
-}
+use std::time::{Duration, Instant};
 
 /// Used to specify API parameter pairs
 pub type Dict = HashMap<String, String>;
 
-fn mergedict(map_pre:Dict, map_post:Dict) -> Dict{
-    map_pre.into_iter().chain(map_post).collect()
+/// A copy of the request with the token replaced by "***", for logging and debugging
+pub(crate) fn redact_token(request: &Value) -> Value {
+    let mut request = request.clone();
+    if request.get("token").is_some() {
+        request["token"] = "***".into();
+    }
+    request
 }
 
 
@@ -48,20 +39,102 @@ fn mergedict(map_pre:Dict, map_post:Dict) -> Dict{
 /// The QueryBuilder is immutable, which means a new instance 
 /// of QueryBuilder will be created during params()/addparam()/fields() calling.
 /// So it is safe for multi-threading
+/// param() and set_param() add params to the query itself instead, without cloning the params for every call.
+#[derive(Clone)]
 pub struct QueryBuilder<'a> {
-    tushare: &'a Tushare,
-    api_name: String,
-    params: Option<Dict>,
-    fields: Option<String>,
+    pub(crate) tushare: &'a Tushare,
+    pub(crate) api_name: String,
+    pub(crate) params: Option<Dict>,
+    pub(crate) fields: Option<String>,
+    pub(crate) parse_mode: ParseMode,
+    pub(crate) skip_malformed_rows: bool,
+    pub(crate) timeout: Option<Duration>,
+    pub(crate) cancellation: Option<CancellationToken>,
+    pub(crate) schema_registry: bool,
+    #[cfg(feature = "dataframe")]
+    pub(crate) page_size: usize,
+    #[cfg(feature = "dataframe")]
+    pub(crate) max_pages: usize,
+    #[cfg(feature = "dataframe")]
+    pub(crate) date_columns: Vec<String>,
+    #[cfg(feature = "dataframe")]
+    pub(crate) coerce_numeric: Option<Vec<String>>,
+    #[cfg(feature = "dataframe")]
+    pub(crate) allow_empty: bool,
+    #[cfg(feature = "dataframe")]
+    pub(crate) schema: Option<Schema>,
+    #[cfg(feature = "dataframe")]
+    pub(crate) progress: Option<ProgressHook>,
+    #[cfg(feature = "dataframe")]
+    pub(crate) null_policy: NullPolicy,
+    #[cfg(feature = "dataframe")]
+    pub(crate) column_null_policies: HashMap<String, NullPolicy>,
+    #[cfg(feature = "dataframe")]
+    pub(crate) renames: HashMap<String, String>,
+    #[cfg(feature = "dataframe")]
+    pub(crate) normalize_columns: bool,
+    #[cfg(feature = "dataframe")]
+    pub(crate) sort_columns: Vec<String>,
+    #[cfg(feature = "dataframe")]
+    pub(crate) sort_descending: bool,
+    #[cfg(feature = "dataframe")]
+    pub(crate) dedup_subset: Option<Vec<String>>,
+    #[cfg(feature = "dataframe")]
+    pub(crate) order_key: Option<Vec<String>>,
 }
 
+/// Max rows returned by most tushare apis in a single call
+pub const DEFAULT_PAGE_SIZE: usize = 6000;
+/// Safety limit of query_all() pages, avoids endless loop on a misbehaving api
+pub const DEFAULT_MAX_PAGES: usize = 100;
+/// Key of stable_order(), the columns missing in the result are ignored
+pub const DEFAULT_ORDER_KEY: &[&str] = &["trade_date", "ts_code"];
+
 impl<'a> QueryBuilder<'a> {
-    pub(crate) fn new(tushare: &'a Tushare, api_name: &str) -> Self {
+    pub(crate) fn new(tushare: &'a Tushare, api_name: impl Into<String>) -> Self {
+        let api_name = api_name.into();
+        #[cfg(feature = "dataframe")]
+        let page_size = TushareApi::from_name(&api_name).meta().max_rows.unwrap_or(DEFAULT_PAGE_SIZE);
         QueryBuilder {
             tushare,
-            api_name: api_name.to_string(),
+            api_name,
             params: None,
             fields: None,
+            parse_mode: ParseMode::Strict,
+            skip_malformed_rows: false,
+            timeout: None,
+            cancellation: None,
+            schema_registry: true,
+            #[cfg(feature = "dataframe")]
+            page_size,
+            #[cfg(feature = "dataframe")]
+            max_pages: DEFAULT_MAX_PAGES,
+            #[cfg(feature = "dataframe")]
+            date_columns: vec![],
+            #[cfg(feature = "dataframe")]
+            coerce_numeric: None,
+            #[cfg(feature = "dataframe")]
+            allow_empty: false,
+            #[cfg(feature = "dataframe")]
+            schema: None,
+            #[cfg(feature = "dataframe")]
+            progress: None,
+            #[cfg(feature = "dataframe")]
+            null_policy: NullPolicy::KeepNull,
+            #[cfg(feature = "dataframe")]
+            column_null_policies: HashMap::new(),
+            #[cfg(feature = "dataframe")]
+            renames: HashMap::new(),
+            #[cfg(feature = "dataframe")]
+            normalize_columns: false,
+            #[cfg(feature = "dataframe")]
+            sort_columns: vec![],
+            #[cfg(feature = "dataframe")]
+            sort_descending: false,
+            #[cfg(feature = "dataframe")]
+            dedup_subset: None,
+            #[cfg(feature = "dataframe")]
+            order_key: None,
         }
     }
 
@@ -71,12 +144,10 @@ impl<'a> QueryBuilder<'a> {
     /// The main purpose of parameters is to define your requirements clearly
     /// # param
     /// The predefined request parameters according to each api_name, e.g. 'start_date', 'end_date'
-    pub fn params(self: &Self, params: Dict) -> Self {
+    pub fn params(&self, params: Dict) -> Self {
         QueryBuilder {
-            tushare: self.tushare,
-            api_name: self.api_name.clone(),
             params: Some(params),
-            fields: self.fields.clone(),
+            ..self.clone()
         }
     }
 
@@ -87,36 +158,205 @@ impl<'a> QueryBuilder<'a> {
     /// Note this is optional, you can ignore this during ramp up, and the return will be up to 6,000 rows.
     /// The main purpose of parameters is to define your requirements clearly.
     /// # k/v
-    /// The predefined request key/value pair according to each api_name, e.g. 'start_date', 'end_date'
-    pub fn addparam(self: &Self, k:&str, v:&str) -> Self{
-        let new_paramdict = Dict::from([(k.to_string(), v.to_string())]);
-        let paramdict = match &self.params {
-            Some(dict) => mergedict(dict.clone(),new_paramdict),
-            None => new_paramdict
-        };
-        QueryBuilder{
-            tushare: self.tushare,
-            api_name: self.api_name.clone(),
-            params: Some(paramdict),
-            fields: self.fields.clone(),            
-        }
+    /// The predefined request key/value pair according to each api_name, e.g. 'start_date', 'end_date'.
+    /// The value is a string, number, bool, date or [TsCode](crate::TsCode), see [IntoParamValue]
+    pub fn addparam(&self, k: impl Into<String>, v: impl IntoParamValue) -> Self{
+        self.clone().param(k, v)
+    }
+
+    /// Same as addparam(), but consumes the query instead of cloning it,
+    /// for chains and loops adding many params
+    /// ```
+    /// let tushare = tushare::Tushare::new("<your token>");
+    /// let query = tushare.querybuilder("daily").param("ts_code", "000001.SZ").param("trade_date", 20240424);
+    /// ```
+    pub fn param(mut self, k: impl Into<String>, v: impl IntoParamValue) -> Self {
+        self.set_param(k, v);
+        self
+    }
+
+    /// Set a param in place, like addparam() without creating a new query
+    /// ```
+    /// let tushare = tushare::Tushare::new("<your token>");
+    /// let mut query = tushare.querybuilder("stk_factor");
+    /// for (k, v) in [("ts_code", "000001.SZ"), ("start_date", "20240101")] {
+    ///     query.set_param(k, v);
+    /// }
+    /// assert_eq!(query.preview()["params"]["start_date"], "20240101");
+    /// ```
+    pub fn set_param(&mut self, k: impl Into<String>, v: impl IntoParamValue) -> &mut Self {
+        self.params
+            .get_or_insert_with(Dict::new)
+            .insert(k.into(), v.to_param_value());
+        self
+    }
+    /// Add a date parameter to the query, the date is formatted as *YYYYMMDD* automatically.
+    /// # k/date
+    /// The predefined request key according to each api_name, e.g. 'trade_date', 'ann_date',
+    /// and a date like chrono::NaiveDate
+    pub fn addparam_date(&self, k: impl Into<String>, date: impl IntoTushareDate) -> Self {
+        self.addparam(k, date.to_tushare_date())
+    }
+
+    /// Set the 'start_date' parameter, a shortcut of addparam_date("start_date", date)
+    pub fn start_date(&self, date: impl IntoTushareDate) -> Self {
+        self.addparam_date("start_date", date)
+    }
+
+    /// Set the 'end_date' parameter, a shortcut of addparam_date("end_date", date)
+    pub fn end_date(&self, date: impl IntoTushareDate) -> Self {
+        self.addparam_date("end_date", date)
+    }
+
+    /// Skip the first n rows of the result, the 'offset' paging parameter of tushare.
+    /// query_all()/query_chunks() start paging from it.
+    pub fn offset(&self, n: usize) -> Self {
+        self.addparam("offset", n)
+    }
+
+    /// Return at most n rows, the 'limit' paging parameter of tushare.
+    /// It can't raise the rows cap of a single call, use query_all() to fetch more.
+    pub fn limit(&self, n: usize) -> Self {
+        self.addparam("limit", n)
+    }
+
+    /// The 'offset' parameter if set, 0 otherwise
+    #[cfg(feature = "dataframe")]
+    pub(crate) fn get_offset(&self) -> usize {
+        self.params
+            .as_ref()
+            .and_then(|p| p.get("offset"))
+            .and_then(|offset| offset.parse().ok())
+            .unwrap_or(0)
     }
+
     /// Set the return fields to the query.
     /// For detailed return field explanation, see the tushare api website https://tushare.pro/document/2?doc_id=25 .
     /// Note this is optional, you can ignore this during ramp up, and the return will be up to 10~20 columns.
     /// You may want to use it to reduce network IO and clarify your requirement clearly.
     /// # fields
     /// The predefined fields string separated with commas, e.g. "ts_code,trade_date,open,high,low,close,pre_close"
-    pub fn fields(self: &Self, fields: &str) -> Self {
+    pub fn fields(&self, fields: &str) -> Self {
         QueryBuilder {
-            tushare: self.tushare,
-            api_name: self.api_name.clone(),
-            params: self.params.clone(),
             fields: Some(fields.to_string()),
+            ..self.clone()
+        }
+    }
+
+    /// Set the fields returned by a slice of the field enum of the api, e.g. `&[DailyField::Open, DailyField::Close]`,
+    /// a typed alternative of fields(), see [fields](crate::fields)
+    pub fn select<F: ApiField>(&self, fields: &[F]) -> Self {
+        self.fields(&fields::join(fields))
+    }
+
+    /// Whether the fields of a registered api are checked against [schema_of](fields::schema_of) before sending,
+    /// and the columns cast to the registered types, default true. Apis not registered are never checked.
+    /// Turn it off for a field the registry doesn't know yet.
+    pub fn schema_registry(&self, enable: bool) -> Self {
+        QueryBuilder {
+            schema_registry: enable,
+            ..self.clone()
+        }
+    }
+
+    /// Set how strictly the response is parsed, default [ParseMode::Strict] failing on the first node not as documented.
+    /// [ParseMode::Lenient] fills nulls and logs warnings instead, e.g. for an api returning short rows.
+    pub fn parse_mode(&self, parse_mode: ParseMode) -> Self {
+        QueryBuilder {
+            parse_mode,
+            ..self.clone()
+        }
+    }
+
+    /// Skip the rows whose values don't match the fields with a warning, instead of failing with RowShapeError.
+    /// Default false, since a malformed row usually means the whole response is not as documented.
+    /// The rows are skipped in [ParseMode::Lenient] too, instead of filled with nulls.
+    pub fn skip_malformed_rows(&self, skip: bool) -> Self {
+        QueryBuilder {
+            skip_malformed_rows: skip,
+            ..self.clone()
+        }
+    }
+
+    /// Set the timeout of every request of this query, overriding Tushare::with_timeout().
+    /// A timed out request fails with NetworkError and is retried like other network failures.
+    pub fn timeout(&self, timeout: Duration) -> Self {
+        QueryBuilder {
+            timeout: Some(timeout),
+            ..self.clone()
+        }
+    }
+
+    /// Fail the query with Cancelled before its next request once the token is cancelled,
+    /// e.g. to stop a long query_all() when a pipeline shuts down, see [CancellationToken]
+    pub fn cancellation(&self, token: &CancellationToken) -> Self {
+        QueryBuilder {
+            cancellation: Some(token.clone()),
+            ..self.clone()
+        }
+    }
+
+    /// Validate the query against the [ApiMeta] of the api, and build the request body
+    /// with the token of the [TokenProvider](crate::token::TokenProvider) if it is set
+    pub(crate) fn build(&self) -> Result<Value, TushareError> {
+        self.validate()?;
+        let mut body = self.body();
+        if let Some(provider) = &self.tushare.token_provider {
+            body["token"] = provider.token()?.into();
+        }
+        Ok(body)
+    }
+
+    /// Check the required params are set and the date params are well formed,
+    /// according to the [ApiMeta] of a known api, and the fields are in the registry, see schema_registry().
+    /// Called by every query before sending.
+    /// ```
+    /// let tushare = tushare::Tushare::new("<your token>");
+    /// let query = tushare.querybuilder("stk_mins").addparam("freq", "1min");
+    /// assert!(matches!(query.validate(), Err(tushare::TushareError::InvalidParams(_))));
+    /// ```
+    pub fn validate(&self) -> Result<(), TushareError> {
+        let missing = self.missing_params();
+        if !missing.is_empty() {
+            return Err(TushareError::InvalidParams(format!(
+                "{} requires {}",
+                self.api_name,
+                missing.join(", ")
+            )));
+        }
+        let api = TushareApi::from_name(&self.api_name);
+        if let (true, Some(schema), Some(fields)) = (self.schema_registry, fields::schema_of(&api), &self.fields) {
+            let unknown: Vec<&str> = fields
+                .split(',')
+                .map(str::trim)
+                .filter(|f| !f.is_empty() && !schema.iter().any(|(name, _)| name == f))
+                .collect();
+            if !unknown.is_empty() {
+                return Err(TushareError::InvalidParams(format!(
+                    "unknown fields of {}: {}, turn off schema_registry() if they are new",
+                    self.api_name,
+                    unknown.join(", ")
+                )));
+            }
+        }
+        let meta = api.meta();
+        for param in meta.date_params {
+            let Some(value) = self.params.as_ref().and_then(|p| p.get(*param)) else {
+                continue;
+            };
+            if NaiveDate::parse_from_str(value, meta.date_format).is_err() {
+                let example = NaiveDate::from_ymd_opt(2024, 4, 24).unwrap_or_default().and_time(NaiveTime::MIN);
+                return Err(TushareError::InvalidParams(format!(
+                    "{param} of {} should be like {}, got {value}",
+                    self.api_name,
+                    example.format(meta.date_format)
+                )));
+            }
         }
+        Ok(())
     }
 
-    fn build(self: &Self) -> Value {
+    fn body(&self) -> Value {
         match (&self.params, &self.fields) {
             (Some(p), Some(f)) => json!({
                 "api_name":self.api_name,
@@ -145,72 +385,165 @@ impl<'a> QueryBuilder<'a> {
         }
     }
 
-    fn json_reformat(resp_json:Value) -> Result<Vec<Value>, TushareError>{
-        let mut data_json: Vec<Value> = vec![];
-        let fields_json = resp_json["data"]["fields"]
-            .as_array()
-            .ok_or(TushareError::DataError("data/fields".to_string()))?;
-        let mut fields: Vec<&str> = vec![];
-        for (i, field) in fields_json.iter().enumerate() {
-            let _field = field
-                .as_str()
-                .ok_or(TushareError::DataError(format!("data/fields at {i}")))?;
-            fields.push(_field);
-        }
-        let data= resp_json["data"]["items"]
-            .as_array()
-            .ok_or(TushareError::DataError("data/items".to_string()))?;
-        for (i, item) in data.iter().enumerate() {
-            let item_data = item.as_array().ok_or(TushareError::DataError(format!(
-                "data/items/{i} is expected to be an array"
-            )))?;
-            let mut item_json: serde_json::Map<String, Value> = serde_json::Map::new();
-            for (k, v) in fields.iter().zip(item_data.iter()) {
-                item_json.insert(k.to_string(), v.clone());
+    /// The json body that would be posted to tushare, with the token redacted, without sending it.
+    /// Useful to debug an unexpected empty result, together with validate().
+    /// Note the middlewares may still modify the request before sending, e.g. to rotate the token.
+    /// ```
+    /// let tushare = tushare::Tushare::new("<your token>");
+    /// let query = tushare.querybuilder("stk_mins").addparam("freq", "1min");
+    /// let body = query.preview();
+    /// assert_eq!(body["token"], "***");
+    /// assert_eq!(body["params"]["freq"], "1min");
+    /// assert_eq!(query.missing_params(), vec!["ts_code"]);
+    /// ```
+    pub fn preview(&self) -> Value {
+        redact_token(&self.body())
+    }
+
+    /// The required params of a known api not set yet, see TushareApi::required_params()
+    pub fn missing_params(&self) -> Vec<&'static str> {
+        TushareApi::from_name(&self.api_name)
+            .required_params()
+            .iter()
+            .copied()
+            .filter(|p| !self.params.as_ref().is_some_and(|params| params.contains_key(*p)))
+            .collect()
+    }
+
+    /// Post the request to tushare, check the return code in response body and extract the table.
+    /// Returns the response metadata together with the table.
+    fn send(&self, tushare_request: &Value) -> Result<(ResponseMeta, Table), TushareError> {
+        if self.cancellation.as_ref().is_some_and(|c| c.is_cancelled()) {
+            return Err(TushareError::Cancelled);
+        }
+        if let Some(limiter) = &self.tushare.rate_limiter {
+            limiter.acquire_cancellable(self.cancellation.as_ref())?;
+        }
+        let mut tushare_request = tushare_request.clone();
+        for middleware in &self.tushare.middlewares {
+            middleware.before(&mut tushare_request);
+        }
+        let start = Instant::now();
+        let result = self.post(&tushare_request);
+        let response = Response {
+            request: &tushare_request,
+            result: &result,
+            elapsed: start.elapsed(),
+        };
+        match &result {
+            Ok((meta, _)) => info!(
+                "api_name={} status={} elapsed_ms={}",
+                self.api_name,
+                meta.http_status.unwrap_or_default(),
+                response.elapsed.as_millis()
+            ),
+            Err(e) => info!("api_name={} elapsed_ms={} error={e}", self.api_name, response.elapsed.as_millis()),
+        }
+        for middleware in &self.tushare.middlewares {
+            middleware.after(&response);
+        }
+        if let (Some(limiter), Err(e)) = (&self.tushare.rate_limiter, &result) {
+            match e.retry_after() {
+                Some(wait) if self.tushare.retry_policy.wait_on_rate_limit => limiter.pause(wait),
+                _ => {}
             }
-            data_json.push(Value::Object(item_json))
-        }
-        Ok(data_json)
-
-    }
-
-
-    /// Query API predefined request type & parameters and return a Data Frame as output
-    /// Fundamental entry for every tushare data access.
-    pub fn query(self: &Self) -> Result<DataFrame, TushareError> {
-        let tushare_request = self.build();
-        info!(
-            "Request text:\n {}\n",
-            serde_json::to_string(&tushare_request).unwrap_or("to str error".to_string())
-        );
-        let client = Client::new();
-        let resp_text = client
-            .post(self.tushare.api_endpoint.clone())
-            .body(tushare_request.to_string())
-            .send()? // sending network error
-            .error_for_status()? // 400 or other http error
-            .text()?;
-        info!("Network return:\n {}\n", resp_text);
-        let resp_json: Value = serde_json::from_str(&resp_text)?;
-        if let Some(ret_code) = resp_json["code"].as_i64() {
-            info!("resp code: {:?}", ret_code);    
+        }
+        result
+    }
+
+    fn post(&self, tushare_request: &Value) -> Result<(ResponseMeta, Table), TushareError> {
+        if self.tushare.log_bodies {
+            info!("Request body: {}", redact_token(tushare_request));
+        }
+        let (status, resp_text) = self.tushare.transport.post_with_status(
+            &self.tushare.api_endpoint,
+            &tushare_request.to_string(),
+            self.timeout.or(self.tushare.client_config.timeout),
+        )?;
+        if self.tushare.log_bodies {
+            info!("Response body: {resp_text}");
+        }
+        // a gateway or a proxy may answer with an html page, which is not worth a json parse error
+        if !resp_text.trim_start().starts_with(['{', '[']) {
+            return Err(TushareError::invalid_response(status, &resp_text));
+        }
+        // the rows are read straight into the columns, a large body is not held twice in memory
+        let response = table::parse_response(&resp_text, self.parse_mode, self.skip_malformed_rows)?;
+        if let Some(ret_code) = response.code {
             if ret_code != 0 {
-                let code = resp_json["code"].as_str().unwrap_or("unknown");
-                let msg = resp_json["msg"].as_str().unwrap_or("unknown");
-                return Err(TushareError::RequestError {
-                    code: code.to_string(),
-                    msg: msg.to_string(),
-                });
+                let msg = response.msg.as_deref().unwrap_or("unknown");
+                return Err(TushareServerError::from_code(ret_code, msg).into());
             }
         }
-        let data_json = Self::json_reformat(resp_json)?;
-        let data_str = serde_json::to_string(&data_json)?;
-        info!("data_str: {}", data_str);
-        if data_str == "" || data_str == "[]"{
-            return Err(TushareError::EmptyError)
+        let table = response.table?;
+        let meta = ResponseMeta {
+            request_id: response.request_id,
+            has_more: table.has_more,
+            rows: table.len(),
+            http_status: Some(status),
+        };
+        Ok((meta, table))
+    }
+
+    /// Send the request with the retry policy, and extract the table from the response
+    pub(crate) fn fetch(&self, tushare_request: &Value) -> Result<Table, TushareError> {
+        Ok(self.fetch_with_meta(tushare_request)?.0)
+    }
+
+    /// Same as fetch(), together with the response metadata
+    pub(crate) fn fetch_with_meta(
+        &self,
+        tushare_request: &Value,
+    ) -> Result<(Table, ResponseMeta), TushareError> {
+        let span = QuerySpan::enter(&self.api_name, self.params.as_ref());
+        let result = self.fetch_in_span(tushare_request, &span);
+        span.finish(&result, |(table, _)| table.len());
+        result
+    }
+
+    fn fetch_in_span(&self, tushare_request: &Value, span: &QuerySpan) -> Result<(Table, ResponseMeta), TushareError> {
+        let (meta, table) = self.tushare.retry_policy.run(self.cancellation.as_ref(), || {
+            let result = self.send(tushare_request);
+            span.attempt(&result);
+            result
+        })?;
+        if table.has_more {
+            warn!("{} returned {} rows but has more, use query_all() to fetch all of them", self.api_name, table.len());
         }
-        let cursor = Cursor::new(data_str);
-        let df = JsonReader::new(cursor).finish()?;
-        Ok(df)
+        Ok((table, meta))
+    }
+
+    /// Query the raw fields/items returned by tushare, without converting to a DataFrame.
+    /// This is available without the "dataframe" feature, an empty table is not an error.
+    pub fn query_raw(&self) -> Result<Table, TushareError> {
+        self.fetch(&self.build()?)
+    }
+
+    /// Query and deserialize every row into T, for users who don't want a polars DataFrame.
+    /// The fields of T are matched by the tushare field names, use serde attributes to rename them.
+    /// Unlike query(), an empty Vec is returned for zero rows, and the cache is not used.
+    /// ```no_run
+    /// # fn main() -> Result<(), tushare::TushareError> {
+    /// #[derive(serde::Deserialize)]
+    /// struct DailyBar {
+    ///     ts_code: String,
+    ///     trade_date: String,
+    ///     close: f64,
+    /// }
+    /// let tushare = tushare::Tushare::new("<your token>");
+    /// let bars: Vec<DailyBar> = tushare
+    ///     .querybuilder("daily")
+    ///     .addparam("ts_code", "000001.SZ")
+    ///     .fields("ts_code,trade_date,close")
+    ///     .query_as()?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn query_as<T: DeserializeOwned>(&self) -> Result<Vec<T>, TushareError> {
+        self.query_raw()?
+            .rows()
+            .into_iter()
+            .map(|row| Ok(serde_json::from_value(row)?))
+            .collect()
     }
 }
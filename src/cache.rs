@@ -0,0 +1,491 @@
+use log::{info, warn};
+use polars::prelude::*;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::fs::{self, File};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{Duration, Instant, SystemTime};
+
+/// The version of the cache key layout, bumped when the cached DataFrame changes,
+/// so the entries written by an older release are missed instead of misread
+pub const CACHE_KEY_VERSION: u32 = 3;
+
+/// A cache of query results, set by Tushare::with_cache().
+/// Identical queries within the ttl are served from the cache and don't consume api points.
+/// The cached DataFrame is the raw result before any post processing like parse_dates(),
+/// a result with the numeric strings coerced, parsed leniently or with the malformed rows skipped
+/// is cached under its own key.
+pub trait Cache: Send + Sync {
+    /// Get the cached result of the key if it exists and is not expired
+    fn get(&self, key: &str) -> Option<DataFrame>;
+    /// Store the result of the key, failures are logged and ignored
+    fn put(&self, key: &str, df: &DataFrame);
+    /// Remove the cached results of an api, e.g. after tushare corrected its data.
+    /// Does nothing by default.
+    fn invalidate(&self, _api_name: &str) {}
+    /// Remove every cached result. Does nothing by default.
+    fn clear(&self) {}
+}
+
+/// Build the cache key of a tushare request, `v{CACHE_KEY_VERSION}-{api_name}-{params hash}-{fields hash}`.
+/// The token is excluded, so the key is the same across accounts.
+/// The key is a stable string which can be safely used as a file name.
+pub fn cache_key(request: &Value) -> String {
+    let mut request = request.clone();
+    let (api_name, fields) = match request.as_object_mut() {
+        Some(obj) => {
+            obj.remove("token");
+            (obj.remove("api_name"), obj.remove("fields"))
+        }
+        None => (None, None),
+    };
+    let api_name: String = api_name
+        .as_ref()
+        .and_then(Value::as_str)
+        .unwrap_or_default()
+        .chars()
+        .filter(|c| c.is_ascii_alphanumeric() || *c == '_')
+        .collect();
+    let fields = fields.as_ref().and_then(Value::as_str).unwrap_or_default();
+    // serde_json sorts the object keys, so the string is canonical
+    format!(
+        "v{CACHE_KEY_VERSION}-{api_name}-{:016x}-{:016x}",
+        fnv1a(request.to_string().as_bytes()),
+        fnv1a(fields.as_bytes())
+    )
+}
+
+/// The prefix of the cache keys of an api
+fn api_prefix(api_name: &str) -> String {
+    format!("v{CACHE_KEY_VERSION}-{api_name}-")
+}
+
+/// FNV-1a hash, unlike std DefaultHasher it is stable across rust versions
+fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf29ce484222325, |hash, b| {
+        (hash ^ *b as u64).wrapping_mul(0x100000001b3)
+    })
+}
+
+/// Cache results in memory, the results are lost after the process exits
+pub struct MemoryCache {
+    ttl: Duration,
+    entries: Mutex<HashMap<String, (Instant, DataFrame)>>,
+}
+
+impl MemoryCache {
+    /// Create an in-memory cache, entries expire after ttl
+    pub fn new(ttl: Duration) -> Self {
+        MemoryCache {
+            ttl,
+            entries: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Remove all entries
+    pub fn clear(&self) {
+        self.entries.lock().unwrap_or_else(|e| e.into_inner()).clear();
+    }
+}
+
+impl Cache for MemoryCache {
+    fn get(&self, key: &str) -> Option<DataFrame> {
+        let mut entries = self.entries.lock().unwrap_or_else(|e| e.into_inner());
+        match entries.get(key) {
+            Some((stored, df)) if stored.elapsed() < self.ttl => Some(df.clone()),
+            Some(_) => {
+                entries.remove(key);
+                None
+            }
+            None => None,
+        }
+    }
+
+    fn put(&self, key: &str, df: &DataFrame) {
+        self.entries
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .insert(key.to_string(), (Instant::now(), df.clone()));
+    }
+
+    fn invalidate(&self, api_name: &str) {
+        let prefix = api_prefix(api_name);
+        self.entries
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .retain(|key, _| !key.starts_with(&prefix));
+    }
+
+    fn clear(&self) {
+        MemoryCache::clear(self)
+    }
+}
+
+/// The file format of DiskCache
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum CacheFormat {
+    /// Parquet files, the smallest on disk
+    #[default]
+    Parquet,
+    /// Arrow IPC files, the fastest to read back
+    Ipc,
+    /// bincode of the column values, without polars file readers in the way.
+    /// Date columns are stored as strings and the compression is not applied.
+    #[cfg(feature = "bincode")]
+    Bincode,
+}
+
+impl CacheFormat {
+    /// The extension of the cache files
+    fn extension(self) -> &'static str {
+        match self {
+            CacheFormat::Parquet => "parquet",
+            CacheFormat::Ipc => "arrow",
+            #[cfg(feature = "bincode")]
+            CacheFormat::Bincode => "bin",
+        }
+    }
+}
+
+/// The compression of the DiskCache files
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum CacheCompression {
+    /// Store the files uncompressed
+    None,
+    /// zstd, a good ratio at a fast speed
+    #[default]
+    Zstd,
+    /// lz4, faster but larger than zstd
+    Lz4,
+}
+
+/// Cache results as files named by the cache key under a directory, parquet compressed by zstd by default.
+/// The file modified time is used to check the ttl, so the cache survives process restarts.
+/// ```no_run
+/// # #[cfg(feature = "dataframe")]
+/// # fn main() -> Result<(), tushare::TushareError> {
+/// use std::time::Duration;
+/// use tushare::cache::{CacheCompression, CacheFormat, DiskCache};
+/// let cache = DiskCache::new("cache", Duration::from_secs(3600))
+///     .with_format(CacheFormat::Ipc)
+///     .with_compression(CacheCompression::Lz4);
+/// let tushare = tushare::Tushare::from_env()?.with_cache(cache);
+/// tushare.invalidate_cache("daily");
+/// # Ok(())
+/// # }
+/// # #[cfg(not(feature = "dataframe"))] fn main() {}
+/// ```
+pub struct DiskCache {
+    dir: PathBuf,
+    ttl: Duration,
+    format: CacheFormat,
+    compression: CacheCompression,
+}
+
+impl DiskCache {
+    /// Create an on-disk cache under dir, the directory is created if not exists
+    pub fn new(dir: impl Into<PathBuf>, ttl: Duration) -> Self {
+        DiskCache {
+            dir: dir.into(),
+            ttl,
+            format: CacheFormat::default(),
+            compression: CacheCompression::default(),
+        }
+    }
+
+    /// Store the results in this format, the files of other formats are ignored
+    pub fn with_format(self, format: CacheFormat) -> Self {
+        DiskCache { format, ..self }
+    }
+
+    /// Compress the parquet and ipc files with this codec
+    pub fn with_compression(self, compression: CacheCompression) -> Self {
+        DiskCache { compression, ..self }
+    }
+
+    fn path(&self, key: &str) -> PathBuf {
+        self.dir.join(format!("{key}.{}", self.format.extension()))
+    }
+
+    fn read(&self, key: &str) -> PolarsResult<Option<DataFrame>> {
+        let path = self.path(key);
+        let expired = match fs::metadata(&path).and_then(|m| m.modified()) {
+            Ok(modified) => SystemTime::now()
+                .duration_since(modified)
+                .map(|age| age >= self.ttl)
+                .unwrap_or(false),
+            Err(_) => return Ok(None),
+        };
+        if expired {
+            let _ = fs::remove_file(&path);
+            return Ok(None);
+        }
+        let file = File::open(&path)?;
+        let df = match self.format {
+            CacheFormat::Parquet => ParquetReader::new(file).finish()?,
+            CacheFormat::Ipc => IpcReader::new(file).finish()?,
+            #[cfg(feature = "bincode")]
+            CacheFormat::Bincode => rows::read(file)?,
+        };
+        Ok(Some(df))
+    }
+
+    fn write(&self, key: &str, df: &DataFrame) -> PolarsResult<()> {
+        fs::create_dir_all(&self.dir)?;
+        // write to a temp file first, a concurrent reader never sees a partial file
+        let path = self.path(key);
+        let tmp = path.with_extension(format!("{}.tmp", self.format.extension()));
+        let file = File::create(&tmp)?;
+        match self.format {
+            CacheFormat::Parquet => {
+                let compression = match self.compression {
+                    CacheCompression::None => ParquetCompression::Uncompressed,
+                    CacheCompression::Zstd => ParquetCompression::Zstd(None),
+                    CacheCompression::Lz4 => ParquetCompression::Lz4Raw,
+                };
+                ParquetWriter::new(file).with_compression(compression).finish(&mut df.clone())?;
+            }
+            CacheFormat::Ipc => {
+                let compression = match self.compression {
+                    CacheCompression::None => None,
+                    CacheCompression::Zstd => Some(IpcCompression::ZSTD),
+                    CacheCompression::Lz4 => Some(IpcCompression::LZ4),
+                };
+                IpcWriter::new(file).with_compression(compression).finish(&mut df.clone())?;
+            }
+            #[cfg(feature = "bincode")]
+            CacheFormat::Bincode => rows::write(file, df)?,
+        }
+        fs::rename(&tmp, path)?;
+        Ok(())
+    }
+
+    /// Remove the cache files of any format whose name passes remove, returns the number of files removed
+    fn remove_files(&self, remove: impl Fn(&str) -> bool) -> std::io::Result<usize> {
+        let mut removed = 0;
+        for entry in fs::read_dir(&self.dir)? {
+            let path = entry?.path();
+            if is_cache_file(&path) && path.file_name().and_then(|n| n.to_str()).is_some_and(&remove) {
+                fs::remove_file(&path)?;
+                removed += 1;
+            }
+        }
+        Ok(removed)
+    }
+
+    /// Remove the expired files and those written with another CACHE_KEY_VERSION, returns the number of files removed
+    pub fn purge(&self) -> std::io::Result<usize> {
+        let version = format!("v{CACHE_KEY_VERSION}-");
+        let now = SystemTime::now();
+        self.remove_files(|name| {
+            let expired = fs::metadata(self.dir.join(name))
+                .and_then(|m| m.modified())
+                .map(|modified| now.duration_since(modified).is_ok_and(|age| age >= self.ttl))
+                .unwrap_or(false);
+            expired || !name.starts_with(&version)
+        })
+    }
+}
+
+/// Whether the file is written by a DiskCache, the other files of its directory are never removed
+fn is_cache_file(path: &Path) -> bool {
+    let name = path.file_name().and_then(|n| n.to_str()).unwrap_or_default();
+    let name = name.strip_suffix(".tmp").unwrap_or(name);
+    [".parquet", ".arrow", ".bin"].iter().any(|ext| name.ends_with(ext))
+}
+
+impl Cache for DiskCache {
+    fn get(&self, key: &str) -> Option<DataFrame> {
+        self.read(key).unwrap_or_else(|e| {
+            warn!("Read cache {key} error: {e}");
+            None
+        })
+    }
+
+    fn put(&self, key: &str, df: &DataFrame) {
+        match self.write(key, df) {
+            Ok(()) => info!("Cache {key} stored in {:?}", self.dir),
+            Err(e) => warn!("Write cache {key} error: {e}"),
+        }
+    }
+
+    fn invalidate(&self, api_name: &str) {
+        let prefix = api_prefix(api_name);
+        match self.remove_files(|name| name.starts_with(&prefix)) {
+            Ok(removed) => info!("Cache of {api_name} invalidated, {removed} files removed from {:?}", self.dir),
+            Err(e) => warn!("Invalidate cache of {api_name} error: {e}"),
+        }
+    }
+
+    fn clear(&self) {
+        if let Err(e) = self.remove_files(|_| true) {
+            warn!("Clear cache {:?} error: {e}", self.dir);
+        }
+    }
+}
+
+/// The bincode format of the cached DataFrames
+#[cfg(feature = "bincode")]
+mod rows {
+    use polars::prelude::*;
+    use serde::{Deserialize, Serialize};
+    use std::fs::File;
+    use std::io::{BufReader, BufWriter};
+
+    #[derive(Serialize, Deserialize)]
+    enum Values {
+        Bool(Vec<Option<bool>>),
+        Int(Vec<Option<i64>>),
+        Float(Vec<Option<f64>>),
+        Str(Vec<Option<String>>),
+        Null(usize),
+    }
+
+    fn bincode_error(e: bincode::Error) -> PolarsError {
+        PolarsError::ComputeError(format!("bincode cache: {e}").into())
+    }
+
+    pub(super) fn write(file: File, df: &DataFrame) -> PolarsResult<()> {
+        let mut columns = Vec::with_capacity(df.width());
+        for series in df.get_columns() {
+            let values = match series.dtype() {
+                DataType::Boolean => Values::Bool(series.bool()?.into_iter().collect()),
+                DataType::Null => Values::Null(series.len()),
+                dtype if dtype.is_integer() => Values::Int(series.cast(&DataType::Int64)?.i64()?.into_iter().collect()),
+                dtype if dtype.is_float() => Values::Float(series.cast(&DataType::Float64)?.f64()?.into_iter().collect()),
+                _ => {
+                    let strings = series.cast(&DataType::String)?;
+                    Values::Str(strings.str()?.into_iter().map(|v| v.map(str::to_string)).collect())
+                }
+            };
+            columns.push((series.name().to_string(), values));
+        }
+        bincode::serialize_into(BufWriter::new(file), &columns).map_err(bincode_error)
+    }
+
+    pub(super) fn read(file: File) -> PolarsResult<DataFrame> {
+        let columns: Vec<(String, Values)> = bincode::deserialize_from(BufReader::new(file)).map_err(bincode_error)?;
+        let columns = columns
+            .into_iter()
+            .map(|(name, values)| match values {
+                Values::Bool(v) => Series::new(&name, v),
+                Values::Int(v) => Series::new(&name, v),
+                Values::Float(v) => Series::new(&name, v),
+                Values::Str(v) => Series::new(&name, v),
+                Values::Null(len) => Series::new_null(&name, len),
+            })
+            .collect();
+        DataFrame::new(columns)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    #[test]
+    fn test_cache_key() {
+        let a = json!({"api_name": "daily", "token": "a", "params": {"x": "1", "y": "2"}});
+        let b = json!({"params": {"y": "2", "x": "1"}, "api_name": "daily", "token": "b"});
+        assert_eq!(cache_key(&a), cache_key(&b));
+        assert_ne!(cache_key(&a), cache_key(&json!({"api_name": "weekly"})));
+        let key = cache_key(&json!({"api_name": "daily", "fields": "close", "params": {}}));
+        assert!(key.starts_with("v3-daily-"));
+        assert_ne!(key, cache_key(&json!({"api_name": "daily", "fields": "open", "params": {}})));
+    }
+
+    #[test]
+    fn test_memory_cache() {
+        let cache = MemoryCache::new(Duration::from_secs(60));
+        let df = df!("a" => [1, 2]).unwrap();
+        cache.put("k", &df);
+        assert!(cache.get("k").unwrap().equals(&df));
+        assert!(cache.get("other").is_none());
+        let expired = MemoryCache::new(Duration::ZERO);
+        expired.put("k", &df);
+        assert!(expired.get("k").is_none());
+    }
+
+    #[test]
+    fn test_disk_cache_formats() {
+        let dir = std::env::temp_dir().join(format!("tushare_cache_{}", std::process::id()));
+        let df = df!("ts_code" => ["000001.SZ", "600000.SH"], "close" => [Some(10.5), None], "vol" => [1i64, 2]).unwrap();
+        let daily = cache_key(&json!({"api_name": "daily", "params": {}}));
+        let weekly = cache_key(&json!({"api_name": "weekly", "params": {}}));
+        let formats = [
+            CacheFormat::Parquet,
+            CacheFormat::Ipc,
+            #[cfg(feature = "bincode")]
+            CacheFormat::Bincode,
+        ];
+        for format in formats {
+            let cache = DiskCache::new(&dir, Duration::from_secs(60))
+                .with_format(format)
+                .with_compression(CacheCompression::Lz4);
+            cache.put(&daily, &df);
+            cache.put(&weekly, &df);
+            assert!(cache.get(&daily).unwrap().equals_missing(&df), "{format:?}");
+            cache.invalidate("daily");
+            assert!(cache.get(&daily).is_none());
+            assert!(cache.get(&weekly).is_some());
+            cache.clear();
+            assert!(cache.get(&weekly).is_none());
+        }
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_cache_coerce_numeric() {
+        use crate::transport::MockTransport;
+        use crate::Tushare;
+        let mock = std::sync::Arc::new(MockTransport::new().respond("daily", MockTransport::data(&["close"], vec![vec!["10.5".into()]])));
+        let tushare = Tushare::new("<token here>")
+            .with_transport(mock.clone())
+            .with_cache(MemoryCache::new(Duration::from_secs(60)));
+        // the registered schema would cast close to Float64 anyway
+        let query = tushare.querybuilder("daily").addparam("ts_code", "000001.SZ").schema_registry(false);
+        let coerced = query.coerce_numeric_columns(&["close"]).query().unwrap();
+        assert_eq!(coerced.column("close").unwrap().dtype(), &DataType::Float64);
+        let raw = query.query().unwrap();
+        assert_eq!(raw.column("close").unwrap().dtype(), &DataType::String);
+        assert_eq!(mock.requests().len(), 2);
+        // both results are cached
+        query.query().unwrap();
+        query.coerce_numeric_columns(&["close"]).query().unwrap();
+        assert_eq!(mock.requests().len(), 2);
+    }
+
+    #[test]
+    fn test_cache_parse_options() {
+        use crate::table::ParseMode;
+        use crate::transport::MockTransport;
+        use crate::{Tushare, TushareError};
+        let body = MockTransport::data(&["ts_code", "close"], vec![vec!["000001.SZ".into(), 10.5.into()], vec!["600000.SH".into()]]);
+        let tushare = Tushare::new("<token here>")
+            .with_transport(MockTransport::new().respond("daily", body))
+            .with_cache(MemoryCache::new(Duration::from_secs(60)));
+        let query = tushare.querybuilder("daily");
+        assert_eq!(query.skip_malformed_rows(true).query().unwrap().height(), 1);
+        assert_eq!(query.parse_mode(ParseMode::Lenient).query().unwrap().height(), 2);
+        assert!(matches!(query.query(), Err(TushareError::RowShapeError { row: 1, .. })));
+    }
+
+    #[test]
+    fn test_offline() {
+        use crate::transport::MockTransport;
+        use crate::{Tushare, TushareError};
+        let dir = std::env::temp_dir().join(format!("tushare_offline_{}", std::process::id()));
+        let mock = MockTransport::new().respond("daily", MockTransport::data(&["close"], vec![vec![10.5.into()]]));
+        let online = Tushare::new("<token here>")
+            .with_transport(mock)
+            .with_cache(DiskCache::new(&dir, Duration::MAX));
+        online.querybuilder("daily").addparam("ts_code", "000001.SZ").query().unwrap();
+        let offline = Tushare::offline(DiskCache::new(&dir, Duration::MAX));
+        let df = offline.querybuilder("daily").addparam("ts_code", "000001.SZ").query().unwrap();
+        assert_eq!(df.height(), 1);
+        let missed = offline.querybuilder("daily").addparam("ts_code", "600000.SH").query();
+        assert!(matches!(missed, Err(TushareError::CacheMiss(_))));
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
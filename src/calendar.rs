@@ -0,0 +1,106 @@
+use crate::api::TushareApi;
+use crate::error::TushareError;
+use crate::date::parse_tushare_date;
+use crate::params::Exchange;
+use crate::tushare::Tushare;
+use chrono::NaiveDate;
+use polars::prelude::*;
+use std::collections::BTreeSet;
+use std::ops::RangeInclusive;
+
+/// Trade calendar of an exchange within a date range, fetched once by Tushare::trade_calendar().
+/// All lookups are local, no more api points are consumed.
+#[derive(Debug, Clone)]
+pub struct TradeCalendar {
+    range: RangeInclusive<NaiveDate>,
+    open_days: BTreeSet<NaiveDate>,
+}
+
+impl TradeCalendar {
+    /// Create a calendar from the trading days within range, days out of range are ignored
+    pub fn new(range: RangeInclusive<NaiveDate>, open_days: impl IntoIterator<Item = NaiveDate>) -> Self {
+        let open_days = open_days.into_iter().filter(|d| range.contains(d)).collect();
+        TradeCalendar { range, open_days }
+    }
+
+    /// The date range covered by the calendar
+    pub fn range(&self) -> &RangeInclusive<NaiveDate> {
+        &self.range
+    }
+
+    /// Whether the exchange is open on the date, None if the date is out of range
+    pub fn is_open(&self, date: NaiveDate) -> Option<bool> {
+        self.range
+            .contains(&date)
+            .then(|| self.open_days.contains(&date))
+    }
+
+    /// The first trading day after the date, None if it is beyond the range
+    pub fn next_trading_day(&self, date: NaiveDate) -> Option<NaiveDate> {
+        date.succ_opt()
+            .and_then(|next| self.open_days.range(next..).next().copied())
+    }
+
+    /// The last trading day before the date, None if it is before the range
+    pub fn prev_trading_day(&self, date: NaiveDate) -> Option<NaiveDate> {
+        self.open_days.range(..date).next_back().copied()
+    }
+
+    /// Trading days between a and b, both inclusive, in ascending order
+    pub fn trading_days_between(&self, a: NaiveDate, b: NaiveDate) -> Vec<NaiveDate> {
+        if a > b {
+            return vec![];
+        }
+        self.open_days.range(a..=b).copied().collect()
+    }
+
+    /// All trading days within the range in ascending order
+    pub fn trading_days(&self) -> impl Iterator<Item = NaiveDate> + '_ {
+        self.open_days.iter().copied()
+    }
+}
+
+impl Tushare {
+    /// Fetch the trade calendar of the exchange within range
+    pub fn trade_calendar(
+        &self,
+        exchange: Exchange,
+        range: RangeInclusive<NaiveDate>,
+    ) -> Result<TradeCalendar, TushareError> {
+        let df = self
+            .querybuilder(TushareApi::TradeCal)
+            .addparam("exchange", exchange)
+            .start_date(range.start())
+            .end_date(range.end())
+            .fields("cal_date,is_open")
+            .dtypes(&[("is_open", DataType::Int64)])
+            .query_all()?;
+        let dates = df.column("cal_date")?.str()?;
+        let is_open = df.column("is_open")?.i64()?;
+        let open_days = dates
+            .into_iter()
+            .zip(is_open)
+            .filter(|(_, open)| *open == Some(1))
+            .filter_map(|(date, _)| date.and_then(parse_tushare_date));
+        Ok(TradeCalendar::new(range, open_days))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[test]
+    fn test_trade_calendar() {
+        let day = |d| NaiveDate::from_ymd_opt(2024, 5, d).unwrap();
+        // 2024-05-01 ~ 05-05 is the labor day holiday
+        let calendar = TradeCalendar::new(day(1)..=day(10), [6, 7, 8, 9, 10].map(day));
+        assert_eq!(calendar.is_open(day(1)), Some(false));
+        assert_eq!(calendar.is_open(day(6)), Some(true));
+        assert_eq!(calendar.is_open(day(11)), None);
+        assert_eq!(calendar.next_trading_day(day(1)), Some(day(6)));
+        assert_eq!(calendar.next_trading_day(day(10)), None);
+        assert_eq!(calendar.prev_trading_day(day(7)), Some(day(6)));
+        assert_eq!(calendar.prev_trading_day(day(6)), None);
+        assert_eq!(calendar.trading_days_between(day(2), day(7)), vec![day(6), day(7)]);
+    }
+}
@@ -0,0 +1,122 @@
+//! Cancel running queries from another thread, set by QueryBuilder::cancellation()
+use crate::error::TushareError;
+use std::sync::{Arc, Condvar, Mutex};
+use std::time::{Duration, Instant};
+
+/// A flag shared by the clones, cancelling one cancels the queries of all of them.
+/// A cancelled query fails with TushareError::Cancelled before its next request, i.e. the next page or retry,
+/// and a query waiting for a retry or the rate limiter is woken up at once.
+/// The request in flight is not interrupted, bound it with QueryBuilder::timeout().
+/// ```no_run
+/// # #[cfg(feature = "dataframe")]
+/// # fn main() -> Result<(), tushare::TushareError> {
+/// use std::time::Duration;
+/// use tushare::CancellationToken;
+/// let tushare = tushare::Tushare::from_env()?;
+/// let cancel = CancellationToken::new();
+/// let stop = cancel.clone();
+/// std::thread::spawn(move || {
+///     std::thread::sleep(Duration::from_secs(60));
+///     stop.cancel();
+/// });
+/// let df = tushare
+///     .querybuilder("trade_cal")
+///     .timeout(Duration::from_secs(10))
+///     .cancellation(&cancel)
+///     .query_all();
+/// # Ok(())
+/// # }
+/// # #[cfg(not(feature = "dataframe"))] fn main() {}
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct CancellationToken {
+    cancelled: Arc<(Mutex<bool>, Condvar)>,
+}
+
+impl CancellationToken {
+    /// Create a token not cancelled yet
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Cancel the queries of the token and its clones, it can't be undone
+    pub fn cancel(&self) {
+        let (cancelled, condvar) = &*self.cancelled;
+        *cancelled.lock().unwrap_or_else(|e| e.into_inner()) = true;
+        condvar.notify_all();
+    }
+
+    /// Whether cancel() is called on the token or a clone
+    pub fn is_cancelled(&self) -> bool {
+        *self.cancelled.0.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Block the current thread for the duration, or until the token is cancelled
+    fn wait(&self, duration: Duration) -> bool {
+        let (cancelled, condvar) = &*self.cancelled;
+        let deadline = Instant::now() + duration;
+        let mut guard = cancelled.lock().unwrap_or_else(|e| e.into_inner());
+        while !*guard {
+            let now = Instant::now();
+            if now >= deadline {
+                break;
+            }
+            guard = condvar.wait_timeout(guard, deadline - now).unwrap_or_else(|e| e.into_inner()).0;
+        }
+        *guard
+    }
+}
+
+/// Sleep for the duration, Cancelled as soon as the token of the query is cancelled
+pub(crate) fn sleep(cancellation: Option<&CancellationToken>, duration: Duration) -> Result<(), TushareError> {
+    match cancellation {
+        Some(token) if token.wait(duration) => Err(TushareError::Cancelled),
+        Some(_) => Ok(()),
+        None => {
+            std::thread::sleep(duration);
+            Ok(())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::transport::MockTransport;
+    use crate::{Tushare, TushareError};
+    use std::sync::Arc;
+
+    #[test]
+    fn test_cancel() {
+        let mock = Arc::new(MockTransport::new().respond("daily", MockTransport::data(&["close"], vec![vec![1.0.into()]])));
+        let tushare = Tushare::new("<token here>").with_transport(mock.clone());
+        let cancel = CancellationToken::new();
+        let query = tushare.querybuilder("daily").cancellation(&cancel);
+        assert_eq!(query.query_raw().unwrap().len(), 1);
+        cancel.clone().cancel();
+        assert!(matches!(query.query_raw(), Err(TushareError::Cancelled)));
+        assert_eq!(mock.requests().len(), 1);
+    }
+
+    #[test]
+    fn test_cancel_retry_backoff() {
+        use crate::RetryPolicy;
+        use std::time::{Duration, Instant};
+        let mock = Arc::new(MockTransport::new().respond("daily", MockTransport::error(40203, "访问频率过高，请30秒后重试")));
+        let tushare = Tushare::new("<token here>")
+            .with_transport(mock.clone())
+            .with_retry(RetryPolicy::default().wait_on_rate_limit(true));
+        let cancel = CancellationToken::new();
+        let stop = cancel.clone();
+        std::thread::spawn(move || {
+            std::thread::sleep(Duration::from_millis(100));
+            stop.cancel();
+        });
+        let start = Instant::now();
+        let result = tushare.querybuilder("daily").cancellation(&cancel).query_raw();
+        assert!(matches!(result, Err(TushareError::Cancelled)));
+        // woken up during the 30 seconds backoff
+        assert!(start.elapsed() < Duration::from_secs(5));
+        assert_eq!(mock.requests().len(), 1);
+    }
+}
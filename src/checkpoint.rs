@@ -0,0 +1,110 @@
+//! Progress of a long running bulk job persisted to a JSON file, e.g. by Tushare::download() and Tushare::sync(),
+//! so an interrupted job resumes from the units done instead of restarting.
+//! A unit is any string key of the job, e.g. a ts_code, or a ts_code with a date window.
+use crate::error::TushareError;
+use log::info;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct State {
+    job: String,
+    done: BTreeMap<String, usize>,
+}
+
+/// The units done of a job with their rows, saved after every unit.
+/// It is shared by the worker threads of the job.
+#[derive(Debug)]
+pub struct Checkpoint {
+    path: PathBuf,
+    state: Mutex<State>,
+}
+
+impl Checkpoint {
+    /// Open the checkpoint file of the job.
+    /// The units done are loaded if the file was saved by the same job, otherwise the job starts over.
+    pub fn open(path: impl Into<PathBuf>, job: &str) -> Result<Self, TushareError> {
+        let path = path.into();
+        let state = if path.exists() {
+            serde_json::from_str::<State>(&fs::read_to_string(&path)?)?
+        } else {
+            State::default()
+        };
+        let state = if state.job == job {
+            info!("Resuming {job} with {} units done from {path:?}", state.done.len());
+            state
+        } else {
+            State {
+                job: job.to_string(),
+                done: BTreeMap::new(),
+            }
+        };
+        Ok(Checkpoint {
+            path,
+            state: Mutex::new(state),
+        })
+    }
+
+    /// The file of the checkpoint
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Rows of the unit if it is done
+    pub fn done(&self, unit: &str) -> Option<usize> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner()).done.get(unit).copied()
+    }
+
+    /// Count of the units done
+    pub fn len(&self) -> usize {
+        self.state.lock().unwrap_or_else(|e| e.into_inner()).done.len()
+    }
+
+    /// Whether no unit is done
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Record the unit done with its rows, and save the file through a temp file
+    pub fn mark_done(&self, unit: &str, rows: usize) -> Result<(), TushareError> {
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        state.done.insert(unit.to_string(), rows);
+        // saved under the lock, so the threads never write the temp file at the same time
+        if let Some(parent) = self.path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let tmp = self.path.with_extension("json.tmp");
+        fs::write(&tmp, serde_json::to_string(&*state)?)?;
+        fs::rename(&tmp, &self.path)?;
+        Ok(())
+    }
+
+    /// Remove the file once the job is complete, so the next run starts over
+    pub fn finish(self) -> Result<(), TushareError> {
+        if self.path.exists() {
+            fs::remove_file(&self.path)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[test]
+    fn test_checkpoint() {
+        let path = std::env::temp_dir().join(format!("tushare_checkpoint_{}.json", std::process::id()));
+        let checkpoint = Checkpoint::open(&path, "daily 20240101-20240424").unwrap();
+        checkpoint.mark_done("000001.SZ", 100).unwrap();
+        let resumed = Checkpoint::open(&path, "daily 20240101-20240424").unwrap();
+        assert_eq!(resumed.done("000001.SZ"), Some(100));
+        assert_eq!(resumed.done("600000.SH"), None);
+        let other = Checkpoint::open(&path, "daily 20230101-20240424").unwrap();
+        assert!(other.is_empty());
+        resumed.finish().unwrap();
+        assert!(!path.exists());
+    }
+}
@@ -0,0 +1,189 @@
+//! Helpers of the concept and Shenwan industry classification apis,
+//! and attach_labels() to add the classification of every stock to a price DataFrame.
+use crate::api::TushareApi;
+use crate::code::check_codes;
+use crate::error::TushareError;
+use crate::params::SwLevel;
+use crate::tushare::Tushare;
+use polars::prelude::*;
+use std::collections::{BTreeSet, HashMap};
+
+/// Fields returned by Tushare::concept()
+pub const CONCEPT_FIELDS: &str = "code,name,src";
+
+/// Fields returned by Tushare::concept_detail() and concepts_of()
+pub const CONCEPT_DETAIL_FIELDS: &str = "id,concept_name,ts_code,name,in_date,out_date";
+
+/// Fields returned by Tushare::index_classify()
+pub const INDEX_CLASSIFY_FIELDS: &str = "index_code,industry_name,level,industry_code,is_pub,parent_code,src";
+
+/// Version of the Shenwan classification returned by index_classify()
+const SW_VERSION: &str = "SW2021";
+
+/// Date columns of concept_detail, in_date/out_date are not in the known date columns
+const CONCEPT_DETAIL_DATES: &[&str] = &["in_date", "out_date"];
+
+/// Add a label column to df from labels, matched by the key column (e.g. ts_code) of both.
+/// A key with several labels, e.g. a stock of several concepts, gets them joined by commas in sorted order,
+/// so the rows of df are kept as is. The label is null for a key not in labels.
+/// ```
+/// use polars::prelude::*;
+/// let prices = df!("ts_code" => ["000001.SZ", "600000.SH"], "close" => [10.5, 7.2]).unwrap();
+/// let industries = df!("ts_code" => ["000001.SZ", "600000.SH"], "industry" => ["银行", "银行"]).unwrap();
+/// let df = tushare::classify::attach_labels(prices, &industries, "ts_code", "industry").unwrap();
+/// assert_eq!(df.column("industry").unwrap().str().unwrap().get(1), Some("银行"));
+/// ```
+pub fn attach_labels(df: DataFrame, labels: &DataFrame, key: &str, label: &str) -> Result<DataFrame, TushareError> {
+    let mut by_key: HashMap<&str, BTreeSet<&str>> = HashMap::new();
+    let label_values = labels.column(label)?.str()?;
+    for (k, v) in labels.column(key)?.str()?.into_iter().zip(label_values) {
+        if let (Some(k), Some(v)) = (k, v) {
+            by_key.entry(k).or_default().insert(v);
+        }
+    }
+    let column: StringChunked = df
+        .column(key)?
+        .str()?
+        .into_iter()
+        .map(|k| {
+            k.and_then(|k| by_key.get(k))
+                .map(|values| values.iter().copied().collect::<Vec<_>>().join(","))
+        })
+        .collect();
+    let mut df = df;
+    df.with_column(column.into_series().with_name(label))?;
+    Ok(df)
+}
+
+impl Tushare {
+    /// The concept list of tushare, one row per concept with its code and name
+    pub fn concept(&self) -> Result<DataFrame, TushareError> {
+        self.querybuilder(TushareApi::Concept)
+            .addparam("src", "ts")
+            .fields(CONCEPT_FIELDS)
+            .query_all()
+    }
+
+    /// The stocks of a concept by its code in concept(), e.g. "TS2"
+    pub fn concept_detail(&self, id: &str) -> Result<DataFrame, TushareError> {
+        self.querybuilder(TushareApi::ConceptDetail)
+            .addparam("id", id)
+            .fields(CONCEPT_DETAIL_FIELDS)
+            .parse_date_columns(CONCEPT_DETAIL_DATES)
+            .allow_empty(true)
+            .query_all()
+    }
+
+    /// The concepts of a stock, same columns as concept_detail().
+    /// Pass the result to attach_labels() with the concept_name label to tag the prices with the concepts.
+    pub fn concepts_of(&self, ts_code: impl AsRef<str>) -> Result<DataFrame, TushareError> {
+        check_codes(ts_code.as_ref())?;
+        self.querybuilder(TushareApi::ConceptDetail)
+            .addparam("ts_code", ts_code.as_ref())
+            .fields(CONCEPT_DETAIL_FIELDS)
+            .parse_date_columns(CONCEPT_DETAIL_DATES)
+            .allow_empty(true)
+            .query_all()
+    }
+
+    /// The Shenwan industries (2021 version) of the level, with the index_code to query the members by index_member()
+    /// ```no_run
+    /// # fn main() -> Result<(), tushare::TushareError> {
+    /// use tushare::classify::attach_labels;
+    /// let tushare = tushare::Tushare::from_env()?;
+    /// let industries = tushare.index_classify(tushare::SwLevel::L1)?;
+    /// let mut banks = tushare.index_member("801780.SI", false)?;
+    /// banks.rename("con_code", "ts_code")?;
+    /// let prices = tushare.querybuilder("daily").addparam("trade_date", "20240424").query()?;
+    /// let tagged = attach_labels(prices, &banks, "ts_code", "index_name")?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn index_classify(&self, level: SwLevel) -> Result<DataFrame, TushareError> {
+        self.querybuilder(TushareApi::IndexClassify)
+            .addparam("level", level)
+            .addparam("src", SW_VERSION)
+            .fields(INDEX_CLASSIFY_FIELDS)
+            .query_all()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::transport::MockTransport;
+    use chrono::NaiveDate;
+    use serde_json::json;
+    use std::sync::Arc;
+
+    #[test]
+    fn test_attach_labels() {
+        let prices = df!(
+            "ts_code" => [Some("000001.SZ"), Some("600000.SH"), Some("000001.SZ"), Some("300750.SZ"), None],
+            "close" => [10.5, 7.2, 10.6, 180.0, 1.0]
+        )
+        .unwrap();
+        let concepts = df!(
+            "ts_code" => ["000001.SZ", "000001.SZ", "600000.SH", "000001.SZ", "600000.SH"],
+            "concept_name" => [Some("数字货币"), Some("破净股"), Some("破净股"), Some("数字货币"), None]
+        )
+        .unwrap();
+        let df = attach_labels(prices, &concepts, "ts_code", "concept_name").unwrap();
+        assert_eq!(df.height(), 5);
+        let labels: Vec<_> = df.column("concept_name").unwrap().str().unwrap().into_iter().collect();
+        assert_eq!(labels, [Some("数字货币,破净股"), Some("破净股"), Some("数字货币,破净股"), None, None]);
+        assert!(attach_labels(df, &concepts, "ts_code", "industry").is_err());
+    }
+
+    #[test]
+    fn test_concept() {
+        let fields: Vec<&str> = CONCEPT_FIELDS.split(',').collect();
+        let body = MockTransport::data(&fields, vec![vec![json!("TS2"), json!("5G"), json!("ts")]]);
+        let mock = Arc::new(MockTransport::new().respond("concept", body));
+        let tushare = Tushare::new("<token here>").with_transport(mock.clone());
+        let df = tushare.concept().unwrap();
+        let request = &mock.requests()[0];
+        assert_eq!(request["params"]["src"], "ts");
+        assert_eq!(request["fields"], CONCEPT_FIELDS);
+        assert_eq!(df.column("name").unwrap().str().unwrap().get(0), Some("5G"));
+    }
+
+    #[test]
+    fn test_concept_detail() {
+        let fields: Vec<&str> = CONCEPT_DETAIL_FIELDS.split(',').collect();
+        let row = |code: &str, out_date| vec![json!("TS2"), json!("5G"), json!(code), json!("name"), json!("20190101"), out_date];
+        let rows = vec![row("600000.SH", json!(null)), row("000001.SZ", json!("20240424"))];
+        let mock = Arc::new(MockTransport::new().respond("concept_detail", MockTransport::data(&fields, rows)));
+        let tushare = Tushare::new("<token here>").with_transport(mock.clone());
+        let df = tushare.concept_detail("TS2").unwrap();
+        assert_eq!(mock.requests()[0]["params"]["id"], "TS2");
+        for column in CONCEPT_DETAIL_DATES {
+            assert_eq!(df.column(column).unwrap().dtype(), &DataType::Date, "{column}");
+        }
+        let out_dates: Vec<_> = df.column("out_date").unwrap().date().unwrap().as_date_iter().collect();
+        assert_eq!(out_dates, [None, NaiveDate::from_ymd_opt(2024, 4, 24)]);
+
+        let df = tushare.concepts_of("600000.SH").unwrap();
+        assert_eq!(mock.requests()[1]["params"]["ts_code"], "600000.SH");
+        assert_eq!(df.column("in_date").unwrap().dtype(), &DataType::Date);
+        assert!(tushare.concepts_of("600000").is_err());
+        assert_eq!(mock.requests().len(), 2);
+
+        let mock = MockTransport::new().respond("concept_detail", MockTransport::data(&fields, vec![]));
+        let df = Tushare::new("<token here>").with_transport(mock).concept_detail("TS0").unwrap();
+        assert_eq!(df.height(), 0);
+    }
+
+    #[test]
+    fn test_index_classify() {
+        let fields: Vec<&str> = INDEX_CLASSIFY_FIELDS.split(',').collect();
+        let row = vec![json!("801780.SI"), json!("银行"), json!("L1"), json!("480000"), json!("1"), json!("0"), json!("SW2021")];
+        let mock = Arc::new(MockTransport::new().respond("index_classify", MockTransport::data(&fields, vec![row])));
+        let tushare = Tushare::new("<token here>").with_transport(mock.clone());
+        let df = tushare.index_classify(SwLevel::L2).unwrap();
+        let params = &mock.requests()[0]["params"];
+        assert_eq!(params["level"], "L2");
+        assert_eq!(params["src"], SW_VERSION);
+        assert_eq!(df.column("index_code").unwrap().str().unwrap().get(0), Some("801780.SI"));
+    }
+}
@@ -0,0 +1,65 @@
+use crate::error::TushareError;
+use reqwest::blocking::Client;
+use reqwest::Proxy;
+use std::time::Duration;
+
+/// Settings of the underlying http client, set by TushareBuilder
+#[derive(Debug, Clone, Default)]
+pub struct ClientConfig {
+    /// Timeout of establishing the connection
+    pub connect_timeout: Option<Duration>,
+    /// Timeout of a whole request, from connecting until the body is read.
+    /// reqwest blocking client defaults to 30 seconds.
+    pub timeout: Option<Duration>,
+    /// Proxy of all requests, e.g. "http://127.0.0.1:8080"
+    pub proxy: Option<String>,
+    /// User-Agent header of all requests
+    pub user_agent: Option<String>,
+    /// Whether gzip/deflate compressed responses are accepted and decompressed, on if not set.
+    /// The json of a whole market day compresses well, turn it off only for a proxy mangling the encoding.
+    pub compression: Option<bool>,
+    /// Max idle connections kept per host, raise it for a downloader with many threads
+    pub pool_max_idle_per_host: Option<usize>,
+    /// How long an idle connection is kept for reuse, reqwest defaults to 90 seconds
+    pub pool_idle_timeout: Option<Duration>,
+    /// Interval of the TCP keep-alive probes, which keep a connection through NATs and firewalls
+    pub tcp_keepalive: Option<Duration>,
+    /// Talk HTTP/2 without negotiating it, only for an endpoint or gateway known to support it
+    pub http2_prior_knowledge: bool,
+}
+
+impl ClientConfig {
+    /// Build a reqwest client with the settings, an invalid proxy url returns NetworkError
+    pub fn build_client(&self) -> Result<Client, TushareError> {
+        let mut builder = Client::builder();
+        // keep reqwest defaults if not set, e.g. 30 seconds timeout of the blocking client
+        if let Some(connect_timeout) = self.connect_timeout {
+            builder = builder.connect_timeout(connect_timeout);
+        }
+        if let Some(timeout) = self.timeout {
+            builder = builder.timeout(timeout);
+        }
+        if let Some(proxy) = &self.proxy {
+            builder = builder.proxy(Proxy::all(proxy)?);
+        }
+        if let Some(user_agent) = &self.user_agent {
+            builder = builder.user_agent(user_agent);
+        }
+        if let Some(compression) = self.compression {
+            builder = builder.gzip(compression).deflate(compression);
+        }
+        if let Some(max_idle) = self.pool_max_idle_per_host {
+            builder = builder.pool_max_idle_per_host(max_idle);
+        }
+        if let Some(idle_timeout) = self.pool_idle_timeout {
+            builder = builder.pool_idle_timeout(idle_timeout);
+        }
+        if let Some(keepalive) = self.tcp_keepalive {
+            builder = builder.tcp_keepalive(keepalive);
+        }
+        if self.http2_prior_knowledge {
+            builder = builder.http2_prior_knowledge();
+        }
+        Ok(builder.build()?)
+    }
+}
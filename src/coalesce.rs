@@ -0,0 +1,125 @@
+//! Coalescing of identical queries in flight, set by Tushare::with_coalescing().
+//! A fan-out pipeline asking for the same data from many threads makes one http call,
+//! the other callers wait for it and share its DataFrame, whose columns are reference counted.
+use crate::error::TushareError;
+use polars::prelude::DataFrame;
+use std::collections::HashMap;
+use std::sync::{Arc, Condvar, Mutex};
+
+/// The outcome of a call shared with the waiting callers, None if it failed
+type Shared = Option<DataFrame>;
+
+/// A call in flight, waited for by the identical queries
+#[derive(Default)]
+struct Call {
+    result: Mutex<Option<Shared>>,
+    done: Condvar,
+}
+
+/// The calls in flight by the cache key of their requests
+#[derive(Default)]
+pub(crate) struct Coalescer {
+    inflight: Mutex<HashMap<String, Arc<Call>>>,
+}
+
+/// Publishes the outcome of the leading call when dropped, so the waiting callers never hang even if it panics
+struct Leader<'a> {
+    coalescer: &'a Coalescer,
+    key: &'a str,
+    call: Arc<Call>,
+    result: Shared,
+}
+
+impl Drop for Leader<'_> {
+    fn drop(&mut self) {
+        self.coalescer
+            .inflight
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .remove(self.key);
+        *self.call.result.lock().unwrap_or_else(|e| e.into_inner()) = Some(self.result.take());
+        self.call.done.notify_all();
+    }
+}
+
+impl Coalescer {
+    /// Run f unless an identical call is in flight, in which case its DataFrame is returned.
+    /// Errors are not shared, the waiting callers run f themselves if the leading call failed.
+    pub(crate) fn run(
+        &self,
+        key: &str,
+        f: impl FnOnce() -> Result<DataFrame, TushareError>,
+    ) -> Result<DataFrame, TushareError> {
+        let (call, leading) = {
+            let mut inflight = self.inflight.lock().unwrap_or_else(|e| e.into_inner());
+            match inflight.get(key) {
+                Some(call) => (call.clone(), false),
+                None => {
+                    let call = Arc::new(Call::default());
+                    inflight.insert(key.to_string(), call.clone());
+                    (call, true)
+                }
+            }
+        };
+        if leading {
+            let mut leader = Leader {
+                coalescer: self,
+                key,
+                call,
+                result: None,
+            };
+            let result = f();
+            leader.result = result.as_ref().ok().cloned();
+            return result;
+        }
+        let mut result = call.result.lock().unwrap_or_else(|e| e.into_inner());
+        while result.is_none() {
+            result = call.done.wait(result).unwrap_or_else(|e| e.into_inner());
+        }
+        match result.clone().flatten() {
+            Some(df) => Ok(df),
+            None => {
+                drop(result);
+                f()
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use polars::prelude::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Barrier;
+    use std::thread;
+    use std::time::Duration;
+
+    #[test]
+    fn test_coalesce() {
+        let coalescer = Coalescer::default();
+        let calls = AtomicUsize::new(0);
+        let start = Barrier::new(4);
+        let heights: Vec<usize> = thread::scope(|s| {
+            let handles: Vec<_> = (0..4)
+                .map(|_| {
+                    s.spawn(|| {
+                        start.wait();
+                        coalescer
+                            .run("daily", || {
+                                calls.fetch_add(1, Ordering::Relaxed);
+                                thread::sleep(Duration::from_millis(200));
+                                Ok(df!("close" => [10.5, 10.6])?)
+                            })
+                            .unwrap()
+                            .height()
+                    })
+                })
+                .collect();
+            handles.into_iter().map(|h| h.join().unwrap()).collect()
+        });
+        assert_eq!(heights, [2; 4]);
+        assert_eq!(calls.load(Ordering::Relaxed), 1);
+        assert!(coalescer.inflight.lock().unwrap().is_empty());
+    }
+}
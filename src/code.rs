@@ -0,0 +1,97 @@
+//! Stock codes in tushare format, the symbol and the exchange suffix like "000001.SZ"
+use crate::error::TushareError;
+use crate::params::Exchange;
+use std::fmt;
+use std::str::FromStr;
+
+/// A validated tushare code of a stock, index or exchange traded fund,
+/// 6 digits with .SH/.SZ/.BJ suffix, or 5 digits with .HK suffix.
+/// Tushare returns empty data for a malformed code, parse it to catch the typo locally.
+/// ```
+/// use tushare::{Exchange, TsCode};
+/// let code: TsCode = "600000.SH".parse().unwrap();
+/// assert_eq!(code.symbol(), "600000");
+/// assert_eq!(code.exchange(), Exchange::SSE);
+/// assert!("600000.SS".parse::<TsCode>().is_err());
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct TsCode {
+    code: String,
+    exchange: Exchange,
+}
+
+impl TsCode {
+    /// Create a code from the symbol and exchange, e.g. ("000001", Exchange::SZSE)
+    pub fn new(symbol: &str, exchange: Exchange) -> Result<Self, TushareError> {
+        format!("{symbol}.{}", exchange.code_suffix()).parse()
+    }
+
+    /// The code sent to tushare, e.g. "000001.SZ"
+    pub fn as_str(&self) -> &str {
+        &self.code
+    }
+
+    /// The code without the exchange suffix, e.g. "000001"
+    pub fn symbol(&self) -> &str {
+        &self.code[..self.code.len() - 3]
+    }
+
+    /// The exchange of the suffix
+    pub fn exchange(&self) -> Exchange {
+        self.exchange
+    }
+}
+
+impl FromStr for TsCode {
+    type Err = TushareError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || TushareError::InvalidCode(format!("{s}, expected a code like 000001.SZ"));
+        let (symbol, suffix) = s.split_once('.').ok_or_else(invalid)?;
+        let (exchange, digits) = match suffix {
+            "SH" => (Exchange::SSE, 6),
+            "SZ" => (Exchange::SZSE, 6),
+            "BJ" => (Exchange::BSE, 6),
+            "HK" => (Exchange::HKEX, 5),
+            _ => return Err(invalid()),
+        };
+        if symbol.len() != digits || !symbol.bytes().all(|b| b.is_ascii_digit()) {
+            return Err(invalid());
+        }
+        Ok(TsCode {
+            code: s.to_string(),
+            exchange,
+        })
+    }
+}
+
+impl fmt::Display for TsCode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.code)
+    }
+}
+
+impl AsRef<str> for TsCode {
+    fn as_ref(&self) -> &str {
+        &self.code
+    }
+}
+
+/// Check every code of a comma separated list like "000001.SZ,600000.SH"
+#[cfg(feature = "dataframe")]
+pub(crate) fn check_codes(codes: &str) -> Result<(), TushareError> {
+    codes.split(',').try_for_each(|code| code.trim().parse::<TsCode>().map(|_| ()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[test]
+    fn test_ts_code() {
+        let code = TsCode::new("00700", Exchange::HKEX).unwrap();
+        assert_eq!(code.to_string(), "00700.HK");
+        assert!(TsCode::new("0700", Exchange::HKEX).is_err());
+        assert!("000001".parse::<TsCode>().is_err());
+        assert!("00001a.SZ".parse::<TsCode>().is_err());
+    }
+}
@@ -0,0 +1,66 @@
+use crate::error::TushareError;
+use serde::Deserialize;
+use std::env;
+use std::fs;
+use std::path::Path;
+
+/// Environment variable holding the tushare token, read by Tushare::from_env()
+pub const TOKEN_ENV: &str = "TUSHARE_TOKEN";
+
+/// Settings of a Tushare object loaded from a TOML file by Tushare::from_config().
+/// Every key is optional, e.g.
+/// ```toml
+/// token = "<your token>"   # falls back to TUSHARE_TOKEN if missing
+/// endpoint = "https://api.tushare.pro"
+/// timeout_secs = 30
+/// rate_limit = 200         # calls per minute
+/// ```
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct TushareConfig {
+    /// Tushare access token
+    pub token: Option<String>,
+    /// Tushare api endpoint
+    pub endpoint: Option<String>,
+    /// Timeout of a single http request in seconds
+    pub timeout_secs: Option<u64>,
+    /// Max calls per minute, see Tushare::with_rate_limit()
+    pub rate_limit: Option<u32>,
+}
+
+impl TushareConfig {
+    /// Load the config from a TOML file
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, TushareError> {
+        let path = path.as_ref();
+        let text = fs::read_to_string(path)
+            .map_err(|e| TushareError::ConfigError(format!("read {path:?} error: {e}")))?;
+        Self::parse(&text)
+    }
+
+    /// Parse the config from a TOML string
+    pub fn parse(text: &str) -> Result<Self, TushareError> {
+        toml::from_str(text).map_err(|e| TushareError::ConfigError(e.to_string()))
+    }
+}
+
+/// Read the token from TUSHARE_TOKEN environment variable
+pub fn token_from_env() -> Result<String, TushareError> {
+    match env::var(TOKEN_ENV) {
+        Ok(token) if !token.trim().is_empty() => Ok(token.trim().to_string()),
+        _ => Err(TushareError::ConfigError(format!(
+            "environment variable {TOKEN_ENV} is not set"
+        ))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[test]
+    fn test_parse() {
+        let config = TushareConfig::parse("token = \"abc\"\nrate_limit = 200\n").unwrap();
+        assert_eq!(config.token.as_deref(), Some("abc"));
+        assert_eq!(config.rate_limit, Some(200));
+        assert!(config.endpoint.is_none());
+        assert!(TushareConfig::parse("rate_limit = \"x\"").is_err());
+    }
+}
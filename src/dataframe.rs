@@ -0,0 +1,824 @@
+use crate::api::TushareApi;
+use crate::builder::{QueryBuilder, DEFAULT_ORDER_KEY};
+use crate::error::TushareError;
+use crate::fields;
+use crate::cache;
+use crate::date::{parse_tushare_date, IntoTushareDate};
+use crate::params::Exchange;
+use crate::pool;
+use crate::progress::{Progress, ProgressHook, ProgressTracker};
+use crate::table::{PageAnomaly, ParseMode, QueryReport, ResponseMeta, Table};
+use crate::transform::{self, NullPolicy};
+use chrono::{NaiveDate, NaiveTime};
+use log::info;
+use polars::prelude::*;
+use serde_json::Value;
+use std::collections::{HashMap, HashSet};
+use std::ops::ControlFlow;
+use std::time::Instant;
+
+impl Table {
+    /// Convert the table to a polars DataFrame with the columns in the order of the fields,
+    /// the column types are inferred from the json values: Boolean, Int64, Float64 or String,
+    /// and Null for a column of nulls only.
+    /// Zero rows table becomes a zero rows DataFrame with every field as a String column.
+    pub fn to_dataframe(&self) -> Result<DataFrame, TushareError> {
+        if self.is_empty() {
+            let columns = self
+                .fields
+                .iter()
+                .map(|field| Series::new_empty(field, &DataType::String))
+                .collect();
+            return Ok(DataFrame::new(columns)?);
+        }
+        let mut seen = HashSet::new();
+        // the columns are built straight from the buffers, without a json round trip through JsonReader
+        let columns = self
+            .fields
+            .iter()
+            .enumerate()
+            .filter(|(_, field)| seen.insert(field.as_str()))
+            .map(|(i, field)| json_column(field, self.columns.get(i).map_or(&[][..], Vec::as_slice).iter()))
+            .collect();
+        Ok(DataFrame::new(columns)?)
+    }
+}
+
+/// The polars type of json values
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum JsonKind {
+    Null,
+    Bool,
+    Int,
+    Float,
+    String,
+}
+
+impl JsonKind {
+    fn of(value: &Value) -> Self {
+        match value {
+            Value::Null => JsonKind::Null,
+            Value::Bool(_) => JsonKind::Bool,
+            Value::Number(n) if n.is_i64() => JsonKind::Int,
+            Value::Number(_) => JsonKind::Float,
+            _ => JsonKind::String,
+        }
+    }
+
+    /// The type holding the values of both kinds
+    fn merge(self, other: Self) -> Self {
+        match (self, other) {
+            (a, b) if a == b => a,
+            (JsonKind::Null, kind) | (kind, JsonKind::Null) => kind,
+            (JsonKind::Int, JsonKind::Float) | (JsonKind::Float, JsonKind::Int) => JsonKind::Float,
+            _ => JsonKind::String,
+        }
+    }
+}
+
+/// A column of json values typed like the polars json reader: Boolean, Int64, Float64 if integers are mixed with floats,
+/// or String. A column of nulls only has the Null type, and the values of mixed types become their json text.
+fn json_column<'v>(name: &str, values: impl Iterator<Item = &'v Value> + Clone) -> Series {
+    match values.clone().map(JsonKind::of).fold(JsonKind::Null, JsonKind::merge) {
+        JsonKind::Null => Series::full_null(name, values.count(), &DataType::Null),
+        JsonKind::Bool => values.map(Value::as_bool).collect::<BooleanChunked>().into_series().with_name(name),
+        JsonKind::Int => values.map(Value::as_i64).collect::<Int64Chunked>().into_series().with_name(name),
+        JsonKind::Float => values.map(Value::as_f64).collect::<Float64Chunked>().into_series().with_name(name),
+        JsonKind::String => values
+            .map(|value| match value {
+                Value::Null => None,
+                Value::String(s) => Some(s.clone()),
+                value => Some(value.to_string()),
+            })
+            .collect::<StringChunked>()
+            .into_series()
+            .with_name(name),
+    }
+}
+
+/// QueryBuilder methods returning polars DataFrame, enabled by the "dataframe" feature
+impl<'a> QueryBuilder<'a> {
+    /// Set the rows fetched per page by query_all(), default the max rows of the api
+    /// in [ApiMeta](crate::api::ApiMeta), or 6,000 if unknown.
+    /// Some apis have a lower limit per call (e.g. 5,000 for daily_basic), set it accordingly.
+    pub fn page_size(&self, page_size: usize) -> Self {
+        QueryBuilder {
+            page_size,
+            ..self.clone()
+        }
+    }
+
+    /// Set the max pages fetched by query_all(), default 100.
+    /// This is a safety limit, query_all() stops silently once it is reached.
+    pub fn max_pages(&self, max_pages: usize) -> Self {
+        QueryBuilder {
+            max_pages,
+            ..self.clone()
+        }
+    }
+
+    /// Convert the known date columns (see [transform::KNOWN_DATE_COLUMNS]) from String to polars Date.
+    /// By default the date columns are kept as String returned by tushare server.
+    pub fn parse_dates(&self, enable: bool) -> Self {
+        let date_columns = if enable {
+            transform::KNOWN_DATE_COLUMNS.iter().map(|c| c.to_string()).collect()
+        } else {
+            vec![]
+        };
+        QueryBuilder {
+            date_columns,
+            ..self.clone()
+        }
+    }
+
+    /// Convert the given columns in *YYYYMMDD* format from String to polars Date.
+    /// Use this instead of parse_dates(true) if a date column is not in the known list.
+    pub fn parse_date_columns(&self, columns: &[&str]) -> Self {
+        QueryBuilder {
+            date_columns: columns.iter().map(|c| c.to_string()).collect(),
+            ..self.clone()
+        }
+    }
+
+    /// Convert the numeric strings to numbers before the DataFrame is built, for the apis returning
+    /// some numbers as strings (e.g. "10.23"), which makes the column a String or fails the type inference.
+    /// Only the columns mixing numbers and numeric strings are coerced, see [Table::coerce_numeric].
+    pub fn coerce_numeric(&self, enable: bool) -> Self {
+        QueryBuilder {
+            coerce_numeric: enable.then(Vec::new),
+            ..self.clone()
+        }
+    }
+
+    /// Convert the given columns to numbers before the DataFrame is built, a string not a number becomes null.
+    /// Use this instead of coerce_numeric(true) if a column is returned all as strings.
+    pub fn coerce_numeric_columns(&self, columns: &[&str]) -> Self {
+        QueryBuilder {
+            coerce_numeric: Some(columns.iter().map(|c| c.to_string()).collect()),
+            ..self.clone()
+        }
+    }
+
+    /// Return a zero rows DataFrame instead of EmptyError when tushare returns no data,
+    /// e.g. query daily bars on a holiday.
+    /// The columns are built from the fields returned by tushare, all in String type unless schema() is set.
+    pub fn allow_empty(&self, allow_empty: bool) -> Self {
+        QueryBuilder {
+            allow_empty,
+            ..self.clone()
+        }
+    }
+
+    /// Set the column types of the returned DataFrame.
+    /// Without a schema the types are inferred from the json values, which may vary between calls,
+    /// e.g. a numeric column returned as strings, or all nulls in a page.
+    /// Columns not in the schema are still inferred, and schema columns not returned are ignored.
+    pub fn schema(&self, schema: Schema) -> Self {
+        QueryBuilder {
+            schema: Some(schema),
+            ..self.clone()
+        }
+    }
+
+    /// A helper of schema() with name/type pairs, e.g. &[("close", DataType::Float64)]
+    pub fn dtypes(&self, dtypes: &[(&str, DataType)]) -> Self {
+        self.schema(Schema::from_iter(
+            dtypes.iter().map(|(name, dtype)| Field::new(name, dtype.clone())),
+        ))
+    }
+
+    /// Call f with the progress after every page of query_all()/query_chunks(),
+    /// or every code of query_batch(), e.g. to draw a progress bar
+    pub fn on_progress(&self, f: impl Fn(&Progress) + Send + Sync + 'static) -> Self {
+        QueryBuilder {
+            progress: Some(ProgressHook::new(f)),
+            ..self.clone()
+        }
+    }
+
+    /// Handle the nulls of numeric columns after the DataFrame is built, default NullPolicy::KeepNull.
+    /// The policy applies after schema(), so columns cast to a numeric type are covered as well.
+    pub fn null_policy(&self, null_policy: NullPolicy) -> Self {
+        QueryBuilder {
+            null_policy,
+            ..self.clone()
+        }
+    }
+
+    /// Override null_policy() for a single column, e.g. drop the rows without close
+    /// but fill the missing vol with 0
+    pub fn null_policy_for(&self, column: &str, null_policy: NullPolicy) -> Self {
+        let mut column_null_policies = self.column_null_policies.clone();
+        column_null_policies.insert(column.to_string(), null_policy);
+        QueryBuilder {
+            column_null_policies,
+            ..self.clone()
+        }
+    }
+
+    /// Rename the returned columns from tushare field names to your own, e.g. "q_ops_gr" to "op_income_growth".
+    /// The renames are merged with the ones already set.
+    /// Other options like schema() and null_policy_for() still refer to the tushare field names,
+    /// since the columns are renamed last.
+    pub fn rename_map(&self, renames: HashMap<&str, &str>) -> Self {
+        let mut merged = self.renames.clone();
+        merged.extend(renames.into_iter().map(|(k, v)| (k.to_string(), v.to_string())));
+        QueryBuilder {
+            renames: merged,
+            ..self.clone()
+        }
+    }
+
+    /// Convert the column names not in rename_map() to snake_case, see [transform::snake_case]
+    pub fn normalize_columns(&self, normalize_columns: bool) -> Self {
+        QueryBuilder {
+            normalize_columns,
+            ..self.clone()
+        }
+    }
+
+    /// Sort the result by the columns, e.g. sort_by(&["trade_date"], false).
+    /// Tushare returns most apis in descending date order, and pages of query_all() may not be in order.
+    /// The columns are the returned names, after rename_map().
+    /// query_all() sorts the stacked pages once, query_chunks() can only sort within every page.
+    pub fn sort_by(&self, columns: &[&str], descending: bool) -> Self {
+        QueryBuilder {
+            sort_columns: columns.iter().map(|c| c.to_string()).collect(),
+            sort_descending: descending,
+            ..self.clone()
+        }
+    }
+
+    /// Drop the duplicated rows of the result by the subset columns, keeping the first one.
+    /// An empty subset compares all columns.
+    /// Useful when the pages of query_all() overlap, e.g. paging by date returns the boundary day twice.
+    /// query_all() dedups the stacked pages once, query_chunks() can only dedup within every page.
+    pub fn dedup(&self, subset: &[&str]) -> Self {
+        QueryBuilder {
+            dedup_subset: Some(subset.iter().map(|c| c.to_string()).collect()),
+            ..self.clone()
+        }
+    }
+
+    /// Build the DataFrame of a fetched table, with the numeric strings coerced if set
+    fn to_dataframe(&self, mut table: Table) -> Result<DataFrame, TushareError> {
+        if let Some(columns) = &self.coerce_numeric {
+            let columns: Vec<&str> = columns.iter().map(|c| c.as_str()).collect();
+            table.coerce_numeric(&columns);
+        }
+        table.to_dataframe()
+    }
+
+    /// Sort the result of query_all() by [DEFAULT_ORDER_KEY] (trade_date, ts_code) in ascending order,
+    /// and check the page boundaries for overlaps and order breaks, reported by query_all_detailed().
+    /// Tushare pages by offset, so rows inserted or deleted on tushare during the paging shift the pages.
+    /// The key columns missing in the result are ignored, sort_by() still applies after it.
+    pub fn stable_order(&self, enable: bool) -> Self {
+        QueryBuilder {
+            order_key: enable.then(|| DEFAULT_ORDER_KEY.iter().map(|c| c.to_string()).collect()),
+            ..self.clone()
+        }
+    }
+
+    /// Same as stable_order(true) with another key, e.g. &["cal_date"] of trade_cal
+    pub fn stable_order_by(&self, key: &[&str]) -> Self {
+        QueryBuilder {
+            order_key: Some(key.iter().map(|c| c.to_string()).collect()),
+            ..self.clone()
+        }
+    }
+
+    /// Apply dedup() and sort_by() to the result, again after the pages or codes are stacked
+    fn dedup_and_sort(&self, df: DataFrame) -> Result<DataFrame, TushareError> {
+        let df = match &self.dedup_subset {
+            Some(subset) if subset.is_empty() => df.unique_stable(None, UniqueKeepStrategy::First, None)?,
+            Some(subset) => df.unique_stable(Some(subset), UniqueKeepStrategy::First, None)?,
+            None => df,
+        };
+        if self.sort_columns.is_empty() {
+            return Ok(df);
+        }
+        let options = SortMultipleOptions::default()
+            .with_order_descending(self.sort_descending)
+            .with_maintain_order(true);
+        Ok(df.sort(self.sort_columns.clone(), options)?)
+    }
+
+    /// Apply the post processing options to the raw DataFrame
+    fn postprocess(&self, df: DataFrame) -> Result<DataFrame, TushareError> {
+        if df.height() == 0 && !self.allow_empty {
+            return Err(TushareError::EmptyError);
+        }
+        let df = self.apply_policies(self.prepare(df)?)?;
+        self.dedup_and_sort(df)
+    }
+
+    /// Apply the schemas and parse_dates() to a raw DataFrame, the rows are kept as is
+    /// so the pages of query_all() are counted and stacked before any row is dropped
+    fn prepare(&self, df: DataFrame) -> Result<DataFrame, TushareError> {
+        let registered = fields::schema_of(&TushareApi::from_name(&self.api_name)).filter(|_| self.schema_registry);
+        let df = match registered {
+            Some(fields) => {
+                let schema = Schema::from_iter(fields.iter().map(|(name, field_type)| Field::new(name, field_type.dtype())));
+                transform::apply_schema(df, &schema)?
+            }
+            None => df,
+        };
+        let df = match &self.schema {
+            Some(schema) => transform::apply_schema(df, schema)?,
+            None => df,
+        };
+        Ok(transform::parse_dates(df, &self.date_columns)?)
+    }
+
+    /// Apply the null policies and the renames to a prepared DataFrame
+    fn apply_policies(&self, df: DataFrame) -> Result<DataFrame, TushareError> {
+        let df = transform::apply_null_policy(df, self.null_policy, &self.column_null_policies)?;
+        Ok(transform::rename_columns(df, &self.renames, self.normalize_columns)?)
+    }
+
+    /// The request identifying the cached and coalesced result, with the options changing the raw DataFrame,
+    /// so a query without coerce_numeric() is never served a coerced result,
+    /// and a strict query never a result parsed leniently or with the malformed rows skipped
+    fn cache_request(&self, request: &Value) -> Value {
+        let mut request = request.clone();
+        if let Some(columns) = &self.coerce_numeric {
+            request["coerce_numeric"] = columns.clone().into();
+        }
+        if self.parse_mode != ParseMode::Strict {
+            request["parse_mode"] = format!("{:?}", self.parse_mode).into();
+        }
+        if self.skip_malformed_rows {
+            request["skip_malformed_rows"] = true.into();
+        }
+        request
+    }
+
+    /// Query API predefined request type & parameters and return a Data Frame as output
+    /// Fundamental entry for every tushare data access.
+    pub fn query(&self) -> Result<DataFrame, TushareError> {
+        self.postprocess(self.fetch_dataframe()?)
+    }
+
+    /// The raw DataFrame of a single call, from the cache if set
+    fn fetch_dataframe(&self) -> Result<DataFrame, TushareError> {
+        let tushare_request = self.build()?;
+        let cache = self.tushare.cache.as_deref();
+        let key = cache::cache_key(&self.cache_request(&tushare_request));
+        if let Some(df) = cache.and_then(|c| c.get(&key)) {
+            info!("Cache hit: {key}");
+            return Ok(df);
+        }
+        let fetch = || self.to_dataframe(self.fetch(&tushare_request)?);
+        let df = match &self.tushare.coalescer {
+            Some(coalescer) => coalescer.run(&key, fetch)?,
+            None => fetch()?,
+        };
+        if let Some(cache) = cache {
+            cache.put(&key, &df);
+        }
+        Ok(df)
+    }
+
+    /// Same as query(), together with the response metadata like has_more.
+    /// Check has_more to detect a result truncated at the rows cap of a single call.
+    /// The cache is not used, since the metadata is not cached.
+    pub fn query_with_meta(&self) -> Result<(DataFrame, ResponseMeta), TushareError> {
+        let (table, meta) = self.fetch_with_meta(&self.build()?)?;
+        let df = self.postprocess(self.to_dataframe(table)?)?;
+        Ok((df, meta))
+    }
+
+    /// Same as query_with_meta(), with a [QueryReport] of the request, http status, elapsed time and the result shape.
+    /// ```no_run
+    /// # fn main() -> Result<(), tushare::TushareError> {
+    /// let tushare = tushare::Tushare::from_env()?;
+    /// let (df, report) = tushare.querybuilder("daily").addparam("trade_date", "20240424").query_detailed()?;
+    /// log::info!("{} returned {}x{} in {:?}", report.request["api_name"], report.rows, report.columns, report.elapsed);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn query_detailed(&self) -> Result<(DataFrame, QueryReport), TushareError> {
+        let start = Instant::now();
+        let request = self.build()?;
+        let (table, meta) = self.fetch_with_meta(&request)?;
+        let df = self.postprocess(self.to_dataframe(table)?)?;
+        let report = QueryReport::new(&request, meta, df.width(), start.elapsed());
+        Ok((df, report))
+    }
+
+    /// Same as query(), but returns a polars LazyFrame to chain filters/aggregations lazily.
+    /// The http request is still sent immediately, only the downstream processing is lazy.
+    pub fn query_lazy(&self) -> Result<LazyFrame, TushareError> {
+        Ok(self.query()?.lazy())
+    }
+
+    /// Same as query_all(), but returns a polars LazyFrame
+    pub fn query_all_lazy(&self) -> Result<LazyFrame, TushareError> {
+        Ok(self.query_all()?.lazy())
+    }
+
+    /// Query all rows page by page with the offset/limit parameters, and call f with every page.
+    /// Unlike query_all(), the pages are not kept in memory, f decides what to do with them,
+    /// e.g. append them to a file. Return ControlFlow::Break from f to stop early.
+    /// The loop starts from offset() if set, and stops when a page returns less rows than page_size, or max_pages is reached.
+    /// The rows are counted as returned by tushare, a page emptied by the null policy is not passed to f.
+    /// dedup() and sort_by() apply within every page, since the pages are not kept.
+    /// # return
+    /// The value passed to ControlFlow::Break, or None if all pages are consumed.
+    pub fn query_chunks<B>(
+        &self,
+        mut f: impl FnMut(DataFrame) -> ControlFlow<B>,
+    ) -> Result<Option<B>, TushareError> {
+        self.query_pages(|df| {
+            let df = self.dedup_and_sort(self.apply_policies(df)?)?;
+            if df.height() == 0 && !self.allow_empty {
+                return Ok(ControlFlow::Continue(()));
+            }
+            Ok(f(df))
+        })
+    }
+
+    /// Call f with every prepared page, see query_chunks().
+    /// The paging stops by the rows returned by tushare, before any row is dropped by the post processing.
+    fn query_pages<B>(
+        &self,
+        mut f: impl FnMut(DataFrame) -> Result<ControlFlow<B>, TushareError>,
+    ) -> Result<Option<B>, TushareError> {
+        let tracker = ProgressTracker::new(self.progress.clone(), None);
+        let start = self.get_offset();
+        for page in 0..self.max_pages {
+            let page_query = self.offset(start + page * self.page_size).limit(self.page_size);
+            let df = page_query.fetch_dataframe()?;
+            let rows = df.height();
+            if rows == 0 && page > 0 {
+                break;
+            }
+            if rows == 0 && !self.allow_empty {
+                return Err(TushareError::EmptyError);
+            }
+            tracker.advance(1, rows);
+            if let ControlFlow::Break(b) = f(self.prepare(df)?)? {
+                return Ok(Some(b));
+            }
+            if rows < self.page_size {
+                break;
+            }
+        }
+        Ok(None)
+    }
+
+    /// Query all rows by looping the offset/limit parameters, and vstack every page into one Data Frame.
+    /// Tushare caps the rows of a single call (6,000 for most apis), use this instead of query() when more data is expected.
+    /// The loop stops when a page returns less rows than page_size, or max_pages is reached.
+    /// EmptyError is only returned if the first page is empty and allow_empty is not set.
+    pub fn query_all(&self) -> Result<DataFrame, TushareError> {
+        Ok(self.query_all_detailed()?.0)
+    }
+
+    /// Same as query_all(), with a [QueryReport] of the pages, and the anomalies at the page boundaries if stable_order() is set.
+    /// request_id and http_status of the report are not set, since every page has its own.
+    /// ```no_run
+    /// # fn main() -> Result<(), tushare::TushareError> {
+    /// let tushare = tushare::Tushare::from_env()?;
+    /// let query = tushare.querybuilder("daily").addparam("ts_code", "000001.SZ").stable_order(true);
+    /// let (df, report) = query.query_all_detailed()?;
+    /// if !report.anomalies.is_empty() {
+    ///     log::warn!("data changed during the paging: {:?}", report.anomalies);
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn query_all_detailed(&self) -> Result<(DataFrame, QueryReport), TushareError> {
+        let start = Instant::now();
+        let request = self.build()?;
+        let mut result: Option<DataFrame> = None;
+        let mut pages = 0;
+        let mut anomalies = vec![];
+        let mut previous: Option<Vec<String>> = None;
+        self.query_pages(|df| {
+            if let Some(key) = &self.order_key {
+                let keys = row_keys(&df, key)?;
+                if let (Some(previous), Some(keys)) = (&previous, &keys) {
+                    anomalies.extend(page_anomaly(pages, previous, keys));
+                }
+                previous = keys;
+            }
+            pages += 1;
+            match result.as_mut() {
+                Some(acc) => {
+                    acc.vstack_mut(&df)?;
+                }
+                None => result = Some(df),
+            }
+            Ok(ControlFlow::<()>::Continue(()))
+        })?;
+        // the rows are dropped once all the pages are stacked
+        let df = self.apply_policies(result.ok_or(TushareError::EmptyError)?)?;
+        let df = match &self.order_key {
+            Some(key) => sort_by_key(df, key)?,
+            None => df,
+        };
+        let df = self.dedup_and_sort(df)?;
+        let meta = ResponseMeta {
+            rows: df.height(),
+            ..ResponseMeta::default()
+        };
+        let mut report = QueryReport::new(&request, meta, df.width(), start.elapsed());
+        report.pages = pages;
+        report.anomalies = anomalies;
+        Ok((df, report))
+    }
+
+    /// Query the date range window by window, every window sized to fit in a single call of the api.
+    /// The window is counted in trading days of SSE, and the rows of a day are estimated
+    /// from the codes in the ts_code param (the whole market if unset) and the freq param of minute bars.
+    /// Every window is fetched by query_all(), so an underestimated window is still complete.
+    /// The windows cover every calendar day of the range, windows without data are skipped.
+    /// ```no_run
+    /// # fn main() -> Result<(), tushare::TushareError> {
+    /// let tushare = tushare::Tushare::from_env()?;
+    /// let start = chrono::NaiveDate::from_ymd_opt(2010, 1, 1).unwrap();
+    /// let end = chrono::NaiveDate::from_ymd_opt(2024, 4, 24).unwrap();
+    /// let df = tushare
+    ///     .querybuilder("stk_mins")
+    ///     .addparam("ts_code", "000001.SZ")
+    ///     .addparam("freq", "5min")
+    ///     .query_range(start, end)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn query_range(
+        &self,
+        start: impl IntoTushareDate,
+        end: impl IntoTushareDate,
+    ) -> Result<DataFrame, TushareError> {
+        let date = |d: String| {
+            parse_tushare_date(&d).ok_or_else(|| TushareError::InvalidParams(format!("invalid date {d}")))
+        };
+        let (start, end) = (date(start.to_tushare_date())?, date(end.to_tushare_date())?);
+        let calendar = self.tushare.trade_calendar(Exchange::SSE, start..=end)?;
+        let days = calendar.trading_days().collect::<Vec<_>>();
+        let windows = range_windows(start, end, &days, (self.page_size / self.rows_per_day()).max(1));
+        info!("Querying {} from {start} to {end} in {} windows", self.api_name, windows.len());
+
+        let date_format = TushareApi::from_name(&self.api_name).meta().date_format;
+        let day_end = NaiveTime::from_hms_opt(23, 59, 59).unwrap_or(NaiveTime::MIN);
+        let tracker = ProgressTracker::new(self.progress.clone(), Some(windows.len()));
+        // the progress is reported per window, not per page of every window
+        let query = QueryBuilder {
+            progress: None,
+            ..self.clone()
+        };
+        let mut result: Option<DataFrame> = None;
+        for (first, last) in windows {
+            let df = query
+                .addparam("start_date", first.and_time(NaiveTime::MIN).format(date_format).to_string())
+                .addparam("end_date", last.and_time(day_end).format(date_format).to_string())
+                .query_all();
+            tracker.advance(0, df.as_ref().map_or(0, |df| df.height()));
+            let df = match df {
+                Ok(df) => df,
+                Err(TushareError::EmptyError) => continue,
+                Err(e) => return Err(e),
+            };
+            match result.as_mut() {
+                Some(acc) => {
+                    acc.vstack_mut(&df)?;
+                }
+                None => result = Some(df),
+            }
+        }
+        self.dedup_and_sort(result.ok_or(TushareError::EmptyError)?)
+    }
+
+    /// Rows of a trading day estimated from the ts_code and freq params, see query_range()
+    fn rows_per_day(&self) -> usize {
+        let param = |k: &str| self.params.as_ref().and_then(|p| p.get(k));
+        let codes = param("ts_code").map_or(MARKET_SIZE, |c| c.split(',').count());
+        // 240 minutes a day for the minute bars, e.g. 48 bars of freq 5min
+        let bars = param("freq")
+            .and_then(|f| f.strip_suffix("min")?.parse::<usize>().ok())
+            .map_or(1, |m| (240 / m.max(1)).max(1));
+        codes * bars
+    }
+
+    /// Query the same api for every code in codes, by setting the 'ts_code' parameter one by one.
+    /// Every result is tagged with a 'ts_code' column if the api does not return it,
+    /// then all of them are vstacked into one Data Frame in the order of codes.
+    /// Codes with empty results are skipped, EmptyError is returned only if all of them are empty.
+    /// The rate limit of the Tushare object is respected, see Tushare::with_rate_limit().
+    pub fn query_batch(&self, codes: &[&str]) -> Result<DataFrame, TushareError> {
+        self.query_batch_parallel(codes, 1)
+    }
+
+    /// Same as query_batch(), but up to workers codes are queried in parallel threads
+    pub fn query_batch_parallel(
+        &self,
+        codes: &[&str],
+        workers: usize,
+    ) -> Result<DataFrame, TushareError> {
+        let tracker = ProgressTracker::new(self.progress.clone(), Some(codes.len()));
+        // the progress is reported per code, not per page of every code
+        let query = QueryBuilder {
+            progress: None,
+            ..self.clone()
+        };
+        let results = pool::parallel_map(codes, workers, |code| {
+            let result = query.addparam("ts_code", code).query_all();
+            tracker.advance(0, result.as_ref().map_or(0, |df| df.height()));
+            result
+        });
+        let mut batch: Option<DataFrame> = None;
+        for (code, result) in codes.iter().zip(results) {
+            let df = match result {
+                Ok(df) if df.height() > 0 => df,
+                Ok(_) | Err(TushareError::EmptyError) => continue,
+                Err(e) => return Err(e),
+            };
+            let df = self.tag_code(df, code)?;
+            match batch.as_mut() {
+                Some(acc) => {
+                    acc.vstack_mut(&df)?;
+                }
+                None => batch = Some(df),
+            }
+        }
+        self.dedup_and_sort(batch.ok_or(TushareError::EmptyError)?)
+    }
+
+    fn tag_code(&self, mut df: DataFrame, code: &str) -> Result<DataFrame, TushareError> {
+        // the column may be renamed by rename_map()
+        let column = self.renames.get("ts_code").map_or("ts_code", |c| c.as_str());
+        if df.column(column).is_err() {
+            let tag = Series::new(column, vec![code; df.height()]);
+            df.insert_column(0, tag)?;
+        }
+        Ok(df)
+    }
+}
+
+/// The key of every row joined by "|", None if no key column is in the DataFrame
+fn row_keys(df: &DataFrame, key: &[String]) -> Result<Option<Vec<String>>, TushareError> {
+    let mut columns = vec![];
+    for name in key.iter().filter(|name| df.get_column_index(name).is_some()) {
+        columns.push(df.column(name)?.cast(&DataType::String)?);
+    }
+    if columns.is_empty() {
+        return Ok(None);
+    }
+    let columns = columns.iter().map(|c| c.str()).collect::<Result<Vec<_>, _>>()?;
+    Ok(Some(
+        (0..df.height())
+            .map(|i| columns.iter().map(|c| c.get(i).unwrap_or("")).collect::<Vec<_>>().join("|"))
+            .collect(),
+    ))
+}
+
+/// Compare the keys of a page with the previous page, in the order of the previous page
+fn page_anomaly(page: usize, previous: &[String], keys: &[String]) -> Option<PageAnomaly> {
+    let seen: HashSet<&String> = previous.iter().collect();
+    let rows = keys.iter().filter(|k| seen.contains(k)).count();
+    if rows > 0 {
+        return Some(PageAnomaly::Overlap { page, rows });
+    }
+    let (first, last, next) = (previous.first()?, previous.last()?, keys.first()?);
+    let broken = match first.cmp(last) {
+        std::cmp::Ordering::Greater => next > last,
+        std::cmp::Ordering::Less => next < last,
+        std::cmp::Ordering::Equal => false,
+    };
+    broken.then_some(PageAnomaly::OrderBreak { page })
+}
+
+/// Sort by the key columns in the DataFrame, keeping the order of equal rows
+fn sort_by_key(df: DataFrame, key: &[String]) -> Result<DataFrame, TushareError> {
+    let columns: Vec<String> = key.iter().filter(|name| df.get_column_index(name).is_some()).cloned().collect();
+    if columns.is_empty() {
+        return Ok(df);
+    }
+    Ok(df.sort(columns, SortMultipleOptions::default().with_maintain_order(true))?)
+}
+
+/// Rough count of the listed stocks, the rows of a day when ts_code is not set
+const MARKET_SIZE: usize = 5500;
+
+/// Split start..=end into windows of up to window_days trading days.
+/// The windows are contiguous, so the non trading days are covered as well.
+fn range_windows(
+    start: NaiveDate,
+    end: NaiveDate,
+    trading_days: &[NaiveDate],
+    window_days: usize,
+) -> Vec<(NaiveDate, NaiveDate)> {
+    let mut windows = vec![];
+    let mut first = start;
+    for chunk in trading_days.chunks(window_days) {
+        let last = chunk[chunk.len() - 1];
+        windows.push((first, last));
+        match last.succ_opt() {
+            Some(next) => first = next,
+            None => return windows,
+        }
+    }
+    match windows.last_mut() {
+        Some(window) => window.1 = end,
+        None => windows.push((start, end)),
+    }
+    windows
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[test]
+    fn test_page_anomaly() {
+        let keys = |k: &[&str]| k.iter().map(|k| k.to_string()).collect::<Vec<_>>();
+        let previous = keys(&["20240424", "20240423"]);
+        assert_eq!(page_anomaly(1, &previous, &keys(&["20240422"])), None);
+        assert_eq!(page_anomaly(1, &previous, &keys(&["20240423", "20240422"])), Some(PageAnomaly::Overlap { page: 1, rows: 1 }));
+        assert_eq!(page_anomaly(1, &previous, &keys(&["20240425"])), Some(PageAnomaly::OrderBreak { page: 1 }));
+    }
+
+    #[test]
+    fn test_to_dataframe() {
+        let table = Table {
+            fields: ["ts_code", "vol", "close", "is_open", "note", "flag"].map(String::from).to_vec(),
+            columns: vec![
+                vec!["000001.SZ".into(), "600000.SH".into()],
+                vec![100.into(), 200.into()],
+                vec![10.5.into(), 7.into()],
+                vec![true.into(), Value::Null],
+                vec![Value::Null, Value::Null],
+                vec![1.into(), "x".into()],
+            ],
+            has_more: false,
+        };
+        let df = table.to_dataframe().unwrap();
+        assert_eq!(df.get_column_names(), ["ts_code", "vol", "close", "is_open", "note", "flag"]);
+        let dtypes = [DataType::String, DataType::Int64, DataType::Float64, DataType::Boolean, DataType::Null, DataType::String];
+        assert_eq!(df.dtypes(), dtypes);
+        assert_eq!(df.column("close").unwrap().f64().unwrap().get(1), Some(7.0));
+        assert_eq!(df.column("flag").unwrap().str().unwrap().get(0), Some("1"));
+    }
+
+    #[test]
+    fn test_query_all_drop_rows() {
+        use crate::transport::MockTransport;
+        use crate::Tushare;
+        let page = |rows: Vec<(&str, Option<f64>)>| {
+            let items = rows.into_iter().map(|(code, close)| vec![code.into(), close.into()]).collect();
+            MockTransport::data(&["ts_code", "close"], items)
+        };
+        let mock = MockTransport::new()
+            .respond("daily", page(vec![("000001.SZ", None), ("000002.SZ", Some(10.5))]))
+            .respond("daily", page(vec![("600000.SH", Some(7.2))]));
+        let tushare = Tushare::new("<token here>").with_transport(mock);
+        let query = tushare.querybuilder("daily").page_size(2).null_policy(NullPolicy::DropRows);
+        // the page shrunk by the null policy is not taken as the last one
+        let df = query.query_all().unwrap();
+        let codes: Vec<Option<&str>> = df.column("ts_code").unwrap().str().unwrap().into_iter().collect();
+        assert_eq!(codes, [Some("000002.SZ"), Some("600000.SH")]);
+
+        // every row of the first page dropped is not an empty result
+        let mock = MockTransport::new()
+            .respond("daily", page(vec![("000001.SZ", None), ("000002.SZ", None)]))
+            .respond("daily", page(vec![("600000.SH", Some(7.2))]));
+        let tushare = Tushare::new("<token here>").with_transport(mock);
+        let df = tushare.querybuilder("daily").page_size(2).null_policy(NullPolicy::DropRows).query_all().unwrap();
+        assert_eq!(df.height(), 1);
+    }
+
+    #[test]
+    fn test_query_all_dedup_and_sort() {
+        use crate::transport::MockTransport;
+        use crate::Tushare;
+        let page = |dates: &[&str]| MockTransport::data(&["trade_date"], dates.iter().map(|d| vec![(*d).into()]).collect());
+        // the boundary day 20240103 is returned by both pages, and the first page loses a row to dedup()
+        let mock = MockTransport::new()
+            .respond("daily", page(&["20240104", "20240104"]))
+            .respond("daily", page(&["20240103", "20240105"]))
+            .respond("daily", page(&["20240103"]));
+        let tushare = Tushare::new("<token here>").with_transport(mock);
+        let df = tushare
+            .querybuilder("daily")
+            .page_size(2)
+            .dedup(&["trade_date"])
+            .sort_by(&["trade_date"], false)
+            .query_all()
+            .unwrap();
+        let dates: Vec<Option<&str>> = df.column("trade_date").unwrap().str().unwrap().into_iter().collect();
+        assert_eq!(dates, [Some("20240103"), Some("20240104"), Some("20240105")]);
+    }
+
+    #[test]
+    fn test_range_windows() {
+        let day = |d| NaiveDate::from_ymd_opt(2024, 4, d).unwrap();
+        let days = [day(1), day(2), day(3), day(8), day(9)];
+        assert_eq!(
+            range_windows(day(1), day(10), &days, 2),
+            vec![(day(1), day(2)), (day(3), day(8)), (day(9), day(10))]
+        );
+        assert_eq!(range_windows(day(6), day(7), &[], 2), vec![(day(6), day(7))]);
+    }
+}
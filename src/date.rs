@@ -0,0 +1,56 @@
+use chrono::{DateTime, NaiveDate, NaiveDateTime, TimeZone};
+
+/// Tushare date format, e.g. "20240424"
+pub const TUSHARE_DATE_FORMAT: &str = "%Y%m%d";
+
+/// Tushare datetime format of minute bars and news, e.g. "2024-04-24 09:30:00"
+pub const TUSHARE_DATETIME_FORMAT: &str = "%Y-%m-%d %H:%M:%S";
+
+/// Types that can be formatted as a tushare date parameter.
+/// Tushare silently returns empty data if the date is not in *YYYYMMDD* format,
+/// use this trait with QueryBuilder::addparam_date() to avoid the pitfall.
+pub trait IntoTushareDate {
+    /// Format self as *YYYYMMDD*
+    fn to_tushare_date(&self) -> String;
+}
+
+impl IntoTushareDate for NaiveDate {
+    fn to_tushare_date(&self) -> String {
+        self.format(TUSHARE_DATE_FORMAT).to_string()
+    }
+}
+
+impl IntoTushareDate for NaiveDateTime {
+    fn to_tushare_date(&self) -> String {
+        self.date().to_tushare_date()
+    }
+}
+
+impl<Tz: TimeZone> IntoTushareDate for DateTime<Tz> {
+    fn to_tushare_date(&self) -> String {
+        self.date_naive().to_tushare_date()
+    }
+}
+
+impl<T: IntoTushareDate> IntoTushareDate for &T {
+    fn to_tushare_date(&self) -> String {
+        (*self).to_tushare_date()
+    }
+}
+
+/// Parse a *YYYYMMDD* string returned by tushare
+pub fn parse_tushare_date(s: &str) -> Option<NaiveDate> {
+    NaiveDate::parse_from_str(s, TUSHARE_DATE_FORMAT).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[test]
+    fn test_tushare_date() {
+        let date = NaiveDate::from_ymd_opt(2024, 4, 4).unwrap();
+        assert_eq!(date.to_tushare_date(), "20240404");
+        assert_eq!(parse_tushare_date("20240404"), Some(date));
+        assert_eq!(parse_tushare_date("2024-04-04"), None);
+    }
+}
@@ -0,0 +1,191 @@
+//! Helpers of the futures and options apis
+use crate::api::TushareApi;
+use crate::error::TushareError;
+use crate::params::DerivativeExchange;
+use crate::stock::float_dtypes;
+use crate::tushare::Tushare;
+use chrono::NaiveDate;
+use polars::prelude::*;
+use std::ops::RangeInclusive;
+
+/// Fields returned by Tushare::fut_basic()
+pub const FUT_BASIC_FIELDS: &str = "ts_code,symbol,exchange,name,fut_code,multiplier,trade_unit,\
+per_unit,quote_unit,quote_unit_desc,d_mode_desc,list_date,delist_date,d_month,last_ddate,trade_time_desc";
+
+/// Fields returned by Tushare::fut_daily()
+pub const FUT_DAILY_FIELDS: &str = "ts_code,trade_date,pre_close,pre_settle,open,high,low,close,\
+settle,change1,change2,vol,amount,oi,oi_chg";
+
+/// Fields returned by Tushare::opt_basic()
+pub const OPT_BASIC_FIELDS: &str = "ts_code,exchange,name,per_unit,opt_code,opt_type,call_put,\
+exercise_type,exercise_price,s_month,maturity_date,list_price,list_date,delist_date,last_edate,\
+last_ddate,quote_unit,min_price_chg";
+
+/// Fields returned by Tushare::opt_daily()
+pub const OPT_DAILY_FIELDS: &str = "ts_code,trade_date,exchange,pre_settle,pre_close,open,high,low,close,settle,vol,amount,oi";
+
+/// Date columns of the contracts, the expiry and delivery dates are not in the known date columns
+const CONTRACT_DATES: &[&str] = &["list_date", "delist_date", "last_ddate", "maturity_date", "last_edate"];
+
+/// Numeric columns of fut_basic, multiplier is the contract size used to compute the notional value
+const FUT_BASIC_NUMERIC: &[&str] = &["multiplier", "per_unit"];
+
+/// Numeric columns of opt_basic
+const OPT_BASIC_NUMERIC: &[&str] = &["per_unit", "exercise_price", "list_price", "min_price_chg"];
+
+/// Numeric columns of the daily bars, the union of fut_daily and opt_daily
+const DERIVATIVE_DAILY_NUMERIC: &[&str] = &[
+    "pre_close", "pre_settle", "open", "high", "low", "close", "settle", "change1", "change2", "vol",
+    "amount", "oi", "oi_chg",
+];
+
+impl Tushare {
+    /// Futures contracts of an exchange, with the dates as Date and multiplier as f64.
+    /// Continuous and main contracts like "RB.SHF" are included only if continuous is true.
+    pub fn fut_basic(
+        &self,
+        exchange: DerivativeExchange,
+        continuous: bool,
+    ) -> Result<DataFrame, TushareError> {
+        self.querybuilder(TushareApi::FutBasic)
+            .addparam("exchange", exchange)
+            .addparam("fut_type", if continuous { "2" } else { "1" })
+            .fields(FUT_BASIC_FIELDS)
+            .dtypes(&float_dtypes(FUT_BASIC_NUMERIC))
+            .parse_date_columns(CONTRACT_DATES)
+            .query_all()
+    }
+
+    /// Daily bars of a futures contract within range, sorted by trade_date ascending
+    pub fn fut_daily(
+        &self,
+        ts_code: impl AsRef<str>,
+        range: RangeInclusive<NaiveDate>,
+    ) -> Result<DataFrame, TushareError> {
+        self.derivative_daily(TushareApi::FutDaily, FUT_DAILY_FIELDS, ts_code, range)
+    }
+
+    /// Options contracts of an exchange, with the expiry dates as Date and exercise_price as f64
+    pub fn opt_basic(&self, exchange: DerivativeExchange) -> Result<DataFrame, TushareError> {
+        self.querybuilder(TushareApi::OptBasic)
+            .addparam("exchange", exchange)
+            .fields(OPT_BASIC_FIELDS)
+            .dtypes(&float_dtypes(OPT_BASIC_NUMERIC))
+            .parse_date_columns(CONTRACT_DATES)
+            .query_all()
+    }
+
+    /// Daily bars of an options contract within range, sorted by trade_date ascending
+    pub fn opt_daily(
+        &self,
+        ts_code: impl AsRef<str>,
+        range: RangeInclusive<NaiveDate>,
+    ) -> Result<DataFrame, TushareError> {
+        self.derivative_daily(TushareApi::OptDaily, OPT_DAILY_FIELDS, ts_code, range)
+    }
+
+    fn derivative_daily(
+        &self,
+        api: TushareApi,
+        fields: &str,
+        ts_code: impl AsRef<str>,
+        range: RangeInclusive<NaiveDate>,
+    ) -> Result<DataFrame, TushareError> {
+        let df = self
+            .querybuilder(api)
+            .addparam("ts_code", ts_code.as_ref())
+            .start_date(range.start())
+            .end_date(range.end())
+            .fields(fields)
+            .dtypes(&float_dtypes(DERIVATIVE_DAILY_NUMERIC))
+            .parse_dates(true)
+            .query_all()?;
+        Ok(df.sort(["trade_date"], SortMultipleOptions::default())?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::transport::MockTransport;
+    use serde_json::{json, Value};
+    use std::sync::Arc;
+
+    fn day(d: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(2024, 1, d).unwrap()
+    }
+
+    /// A row of the fields with integer numbers, so the cast to Float64 is checked
+    fn row(fields: &str, numeric: &[&str], values: &[(&str, Value)]) -> Vec<Value> {
+        fields
+            .split(',')
+            .map(|f| match values.iter().find(|(name, _)| *name == f) {
+                Some((_, value)) => value.clone(),
+                None if numeric.contains(&f) => json!(10),
+                None if CONTRACT_DATES.contains(&f) => json!("20240102"),
+                None => json!("x"),
+            })
+            .collect()
+    }
+
+    fn transport(api: &str, fields: &str, rows: Vec<Vec<Value>>) -> Arc<MockTransport> {
+        let fields: Vec<&str> = fields.split(',').collect();
+        Arc::new(MockTransport::new().respond(api, MockTransport::data(&fields, rows)))
+    }
+
+    fn assert_columns(df: &DataFrame, numeric: &[&str], dates: &[&str]) {
+        for column in numeric {
+            assert_eq!(df.column(column).unwrap().dtype(), &DataType::Float64, "{column}");
+        }
+        for column in dates {
+            assert_eq!(df.column(column).unwrap().dtype(), &DataType::Date, "{column}");
+        }
+    }
+
+    #[test]
+    fn test_fut_basic() {
+        let mock = transport("fut_basic", FUT_BASIC_FIELDS, vec![row(FUT_BASIC_FIELDS, FUT_BASIC_NUMERIC, &[])]);
+        let tushare = Tushare::new("<token here>").with_transport(mock.clone());
+        let df = tushare.fut_basic(DerivativeExchange::SHFE, false).unwrap();
+        tushare.fut_basic(DerivativeExchange::DCE, true).unwrap();
+        let requests = mock.requests();
+        assert_eq!(requests[0]["params"]["exchange"], "SHFE");
+        assert_eq!(requests[0]["params"]["fut_type"], "1");
+        assert_eq!(requests[1]["params"]["exchange"], "DCE");
+        assert_eq!(requests[1]["params"]["fut_type"], "2");
+        assert_columns(&df, FUT_BASIC_NUMERIC, &["list_date", "delist_date", "last_ddate"]);
+    }
+
+    #[test]
+    fn test_opt_basic() {
+        let mock = transport("opt_basic", OPT_BASIC_FIELDS, vec![row(OPT_BASIC_FIELDS, OPT_BASIC_NUMERIC, &[])]);
+        let tushare = Tushare::new("<token here>").with_transport(mock.clone());
+        let df = tushare.opt_basic(DerivativeExchange::SSE).unwrap();
+        assert_eq!(mock.requests()[0]["params"]["exchange"], "SSE");
+        assert_columns(&df, OPT_BASIC_NUMERIC, CONTRACT_DATES);
+    }
+
+    #[test]
+    fn test_derivative_daily() {
+        for (api, fields) in [("fut_daily", FUT_DAILY_FIELDS), ("opt_daily", OPT_DAILY_FIELDS)] {
+            let bar = |trade_date: &str| row(fields, DERIVATIVE_DAILY_NUMERIC, &[("trade_date", json!(trade_date))]);
+            let mock = transport(api, fields, vec![bar("20240103"), bar("20240102")]);
+            let tushare = Tushare::new("<token here>").with_transport(mock.clone());
+            let df = match api {
+                "fut_daily" => tushare.fut_daily("RB2405.SHF", day(2)..=day(3)),
+                _ => tushare.opt_daily("RB2405.SHF", day(2)..=day(3)),
+            }
+            .unwrap();
+            let request = &mock.requests()[0];
+            assert_eq!(request["api_name"], api);
+            assert_eq!(request["fields"], fields);
+            assert_eq!(request["params"]["ts_code"], "RB2405.SHF");
+            assert_eq!(request["params"]["start_date"], "20240102");
+            assert_eq!(request["params"]["end_date"], "20240103");
+            let numeric: Vec<&str> = fields.split(',').filter(|f| DERIVATIVE_DAILY_NUMERIC.contains(f)).collect();
+            assert_columns(&df, &numeric, &["trade_date"]);
+            let dates: Vec<_> = df.column("trade_date").unwrap().date().unwrap().as_date_iter().flatten().collect();
+            assert_eq!(dates, [day(2), day(3)]);
+        }
+    }
+}
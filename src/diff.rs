@@ -0,0 +1,142 @@
+//! Compare two pulls of the same query, e.g. to verify restated financials or corrected bars,
+//! or to find the rows a sync has to rewrite.
+use crate::error::TushareError;
+use polars::prelude::*;
+use std::collections::HashMap;
+
+/// The rows differing between an old and a new DataFrame, returned by [diff]
+#[derive(Debug, Clone)]
+pub struct DataDiff {
+    /// The rows of the new DataFrame whose key is not in the old one
+    pub added: DataFrame,
+    /// The rows of the old DataFrame whose key is not in the new one
+    pub removed: DataFrame,
+    /// The rows of the new DataFrame whose key is in the old one with other values
+    pub changed: DataFrame,
+    /// The old rows of changed, in the same order
+    pub changed_before: DataFrame,
+}
+
+impl DataDiff {
+    /// Whether the two DataFrames have the same rows
+    pub fn is_empty(&self) -> bool {
+        self.added.height() == 0 && self.removed.height() == 0 && self.changed.height() == 0
+    }
+}
+
+/// The values of the columns of every row as a string, a null is told apart from an empty string
+fn row_values(df: &DataFrame, columns: &[&str]) -> Result<Vec<String>, TushareError> {
+    let columns = columns
+        .iter()
+        .map(|name| df.column(name)?.cast(&DataType::String))
+        .collect::<PolarsResult<Vec<Series>>>()?;
+    let columns = columns.iter().map(|c| c.str()).collect::<PolarsResult<Vec<_>>>()?;
+    Ok((0..df.height())
+        .map(|i| {
+            columns
+                .iter()
+                .map(|c| c.get(i).map_or("\u{0}".to_string(), |v| format!("={v}")))
+                .collect::<Vec<_>>()
+                .join("\u{1f}")
+        })
+        .collect())
+}
+
+/// The row of every key, an error for a duplicated key since its rows can't be matched
+fn index_by_key<'a>(keys: &'a [String], which: &str) -> Result<HashMap<&'a str, usize>, TushareError> {
+    let mut index = HashMap::with_capacity(keys.len());
+    for (i, key) in keys.iter().enumerate() {
+        if index.insert(key.as_str(), i).is_some() {
+            let key = key.replace('\u{1f}', ",").replace('=', "");
+            return Err(TushareError::InvalidParams(format!(
+                "duplicated key {key} in the {which} DataFrame, add key columns to make the rows unique"
+            )));
+        }
+    }
+    Ok(index)
+}
+
+fn take(df: &DataFrame, rows: Vec<usize>) -> Result<DataFrame, TushareError> {
+    let rows = IdxCa::from_vec("", rows.into_iter().map(|i| i as IdxSize).collect());
+    Ok(df.take(&rows)?)
+}
+
+/// The rows added, removed and changed from df_old to df_new, matched by the key columns, e.g. ts_code and trade_date.
+/// The other columns of df_new also in df_old are compared, so a column added by the new pull is not a change.
+/// The rows keep the order of their DataFrame, and a key must be unique in both DataFrames.
+/// ```
+/// use polars::prelude::*;
+/// let old = df!("trade_date" => ["20240102", "20240103"], "close" => [10.5, 10.6]).unwrap();
+/// let new = df!("trade_date" => ["20240103", "20240104"], "close" => [10.7, 10.8]).unwrap();
+/// let diff = tushare::diff(&old, &new, &["trade_date"]).unwrap();
+/// assert_eq!(diff.added.column("trade_date").unwrap().str().unwrap().get(0), Some("20240104"));
+/// assert_eq!(diff.removed.column("trade_date").unwrap().str().unwrap().get(0), Some("20240102"));
+/// assert_eq!(diff.changed.column("close").unwrap().f64().unwrap().get(0), Some(10.7));
+/// assert_eq!(diff.changed_before.column("close").unwrap().f64().unwrap().get(0), Some(10.6));
+/// ```
+pub fn diff(df_old: &DataFrame, df_new: &DataFrame, key_cols: &[&str]) -> Result<DataDiff, TushareError> {
+    if key_cols.is_empty() {
+        return Err(TushareError::InvalidParams("diff needs at least one key column".to_string()));
+    }
+    let old_keys = row_values(df_old, key_cols)?;
+    let new_keys = row_values(df_new, key_cols)?;
+    let old_index = index_by_key(&old_keys, "old")?;
+    let new_index = index_by_key(&new_keys, "new")?;
+
+    let old_names = df_old.get_column_names();
+    let compared: Vec<&str> = df_new
+        .get_column_names()
+        .into_iter()
+        .filter(|name| !key_cols.contains(name) && old_names.contains(name))
+        .collect();
+    let old_values = row_values(df_old, &compared)?;
+    let new_values = row_values(df_new, &compared)?;
+
+    let (mut added, mut changed, mut changed_before) = (vec![], vec![], vec![]);
+    for (i, key) in new_keys.iter().enumerate() {
+        match old_index.get(key.as_str()) {
+            None => added.push(i),
+            Some(&j) if old_values[j] != new_values[i] => {
+                changed.push(i);
+                changed_before.push(j);
+            }
+            Some(_) => {}
+        }
+    }
+    let removed: Vec<usize> = (0..old_keys.len())
+        .filter(|&j| !new_index.contains_key(old_keys[j].as_str()))
+        .collect();
+    Ok(DataDiff {
+        added: take(df_new, added)?,
+        removed: take(df_old, removed)?,
+        changed: take(df_new, changed)?,
+        changed_before: take(df_old, changed_before)?,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[test]
+    fn test_diff() {
+        let old = df!(
+            "ts_code" => ["000001.SZ", "000001.SZ", "600000.SH"],
+            "end_date" => ["20231231", "20240331", "20231231"],
+            "n_income" => [Some(4.6e10), None, Some(3.7e10)]
+        )
+        .unwrap();
+        let new = df!(
+            "ts_code" => ["000001.SZ", "000001.SZ", "600000.SH"],
+            "end_date" => ["20231231", "20240331", "20231231"],
+            "n_income" => [Some(4.6e10), Some(1.5e10), Some(3.7e10)],
+            "update_flag" => ["1", "1", "1"]
+        )
+        .unwrap();
+        let keys = ["ts_code", "end_date"];
+        let diff = diff(&old, &new, &keys).unwrap();
+        assert_eq!((diff.added.height(), diff.removed.height(), diff.changed.height()), (0, 0, 1));
+        assert_eq!(diff.changed.column("end_date").unwrap().str().unwrap().get(0), Some("20240331"));
+        assert!(super::diff(&old, &old, &keys).unwrap().is_empty());
+        assert!(matches!(super::diff(&old, &new, &["ts_code"]), Err(TushareError::InvalidParams(_))));
+    }
+}
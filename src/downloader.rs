@@ -0,0 +1,294 @@
+//! Download the history of many codes in parallel into a parquet dataset partitioned by code,
+//! e.g. the daily bars of the full market.
+//! The calls respect the rate limit and retry policy of the Tushare object, see Tushare::with_rate_limit().
+//! A code failed is recorded in the manifest instead of stopping the others,
+//! and download again with the same directory resumes from the [Checkpoint] of the codes done.
+//! ```no_run
+//! # fn main() -> Result<(), tushare::TushareError> {
+//! use chrono::NaiveDate;
+//! use tushare::downloader::Downloader;
+//! let tushare = tushare::Tushare::from_env()?.with_rate_limit(500);
+//! let start = NaiveDate::from_ymd_opt(2000, 1, 1).unwrap();
+//! let end = NaiveDate::from_ymd_opt(2024, 4, 24).unwrap();
+//! let job = Downloader::new(&["000001.SZ", "600000.SH"], start, end).workers(8);
+//! let manifest = tushare.download(&job, "data/daily")?;
+//! println!("{} rows, failed {:?}", manifest.total_rows(), manifest.failed);
+//! # Ok(())
+//! # }
+//! ```
+use crate::api::TushareApi;
+use crate::checkpoint::Checkpoint;
+use crate::date::IntoTushareDate;
+use crate::error::TushareError;
+use crate::pool;
+use crate::progress::{Progress, ProgressHook, ProgressTracker};
+use crate::sync::{read_parquet, write_parquet};
+use crate::tushare::Tushare;
+use chrono::NaiveDate;
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// File name of the manifest in the dataset directory
+pub const MANIFEST_FILE: &str = "manifest.json";
+/// File name of the checkpoint in the dataset directory, removed once every code is downloaded
+pub const CHECKPOINT_FILE: &str = "checkpoint.json";
+
+/// Describe what Tushare::download() fetches into a dataset directory.
+/// Every code is stored in `<dir>/ts_code=<ts_code>/data.parquet`, readable as a hive partitioned dataset.
+#[derive(Debug, Clone)]
+pub struct Downloader {
+    api_name: String,
+    codes: Vec<String>,
+    start: NaiveDate,
+    end: NaiveDate,
+    fields: Option<String>,
+    workers: usize,
+    progress: Option<ProgressHook>,
+}
+
+impl Downloader {
+    /// Download the daily bars of the codes from start to end, both inclusive, in 4 threads
+    pub fn new(codes: &[&str], start: NaiveDate, end: NaiveDate) -> Self {
+        Downloader {
+            api_name: TushareApi::Daily.to_string(),
+            codes: codes.iter().map(|c| c.to_string()).collect(),
+            start,
+            end,
+            fields: None,
+            workers: 4,
+            progress: None,
+        }
+    }
+
+    /// The api queried with ts_code/start_date/end_date, default daily
+    pub fn api_name(self, api_name: impl Into<String>) -> Self {
+        Downloader {
+            api_name: api_name.into(),
+            ..self
+        }
+    }
+
+    /// Return fields of every query
+    pub fn fields(self, fields: &str) -> Self {
+        Downloader {
+            fields: Some(fields.to_string()),
+            ..self
+        }
+    }
+
+    /// Codes downloaded at the same time, default 4
+    pub fn workers(self, workers: usize) -> Self {
+        Downloader {
+            workers: workers.max(1),
+            ..self
+        }
+    }
+
+    /// Call f with the progress after every code is downloaded
+    pub fn on_progress(self, f: impl Fn(&Progress) + Send + Sync + 'static) -> Self {
+        Downloader {
+            progress: Some(ProgressHook::new(f)),
+            ..self
+        }
+    }
+
+    /// The codes of the job
+    pub fn codes(&self) -> &[String] {
+        &self.codes
+    }
+
+    /// The partition file of the code, relative to the dataset directory
+    pub fn partition(&self, code: &str) -> PathBuf {
+        Path::new(&format!("ts_code={code}")).join("data.parquet")
+    }
+}
+
+/// Describe a dataset written by Tushare::download(), saved as manifest.json in the dataset directory
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Manifest {
+    /// The api downloaded
+    pub api_name: String,
+    /// First date of the range, *YYYYMMDD*
+    pub start: String,
+    /// Last date of the range, *YYYYMMDD*
+    pub end: String,
+    /// Rows of every code written
+    pub partitions: BTreeMap<String, Partition>,
+    /// Codes returning no rows in the range, e.g. listed after the end
+    pub empty: BTreeSet<String>,
+    /// Codes failed with the error message, downloaded again by the next run
+    pub failed: BTreeMap<String, String>,
+}
+
+/// A parquet file of the dataset
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Partition {
+    /// The file relative to the dataset directory
+    pub path: PathBuf,
+    /// Rows in the file
+    pub rows: usize,
+}
+
+impl Manifest {
+    /// Load the manifest of a dataset directory, None if it is not downloaded yet
+    pub fn load(dir: impl AsRef<Path>) -> Result<Option<Self>, TushareError> {
+        let path = dir.as_ref().join(MANIFEST_FILE);
+        if !path.exists() {
+            return Ok(None);
+        }
+        Ok(Some(serde_json::from_str(&fs::read_to_string(path)?)?))
+    }
+
+    /// Save the manifest through a temp file, so a crash never leaves a partial file
+    fn save(&self, dir: &Path) -> Result<(), TushareError> {
+        fs::create_dir_all(dir)?;
+        let tmp = dir.join(format!("{MANIFEST_FILE}.tmp"));
+        fs::write(&tmp, serde_json::to_string_pretty(self)?)?;
+        fs::rename(&tmp, dir.join(MANIFEST_FILE))?;
+        Ok(())
+    }
+
+    /// Rows of all partitions
+    pub fn total_rows(&self) -> usize {
+        self.partitions.values().map(|p| p.rows).sum()
+    }
+
+    /// Whether every code is downloaded
+    pub fn is_complete(&self) -> bool {
+        self.failed.is_empty()
+    }
+}
+
+/// Result of a single code
+enum Downloaded {
+    Rows(usize),
+    Empty,
+}
+
+impl Tushare {
+    /// Download every code of the job into the dataset directory, and write the manifest.
+    /// Codes done are saved to checkpoint.json in the directory as soon as they are written,
+    /// and skipped by the next run of the same range together with the codes known empty by the manifest,
+    /// so run it again to resume an interrupted or partially failed download.
+    /// Errors of a single code are recorded in Manifest::failed, only local io errors are returned.
+    pub fn download(&self, job: &Downloader, dir: impl AsRef<Path>) -> Result<Manifest, TushareError> {
+        let dir = dir.as_ref();
+        let mut manifest = Manifest {
+            api_name: job.api_name.clone(),
+            start: job.start.to_tushare_date(),
+            end: job.end.to_tushare_date(),
+            ..Default::default()
+        };
+        let known_empty = Manifest::load(dir)?
+            .filter(|last| last.api_name == manifest.api_name && last.start == manifest.start && last.end == manifest.end)
+            .map(|last| last.empty)
+            .unwrap_or_default();
+        let checkpoint = Checkpoint::open(
+            dir.join(CHECKPOINT_FILE),
+            &format!("{} {}-{}", manifest.api_name, manifest.start, manifest.end),
+        )?;
+        let tracker = ProgressTracker::new(job.progress.clone(), Some(job.codes.len()));
+        let results = pool::parallel_map(&job.codes, job.workers, |code| {
+            let result = match checkpoint.done(code) {
+                // no partition is written for an empty code
+                Some(0) => Ok(Downloaded::Empty),
+                Some(rows) => Ok(Downloaded::Rows(rows)),
+                None if known_empty.contains(code) => Ok(Downloaded::Empty),
+                None => self.download_code(job, dir, code).and_then(|downloaded| {
+                    let rows = match downloaded {
+                        Downloaded::Rows(rows) => rows,
+                        Downloaded::Empty => 0,
+                    };
+                    checkpoint.mark_done(code, rows)?;
+                    Ok(downloaded)
+                }),
+            };
+            match &result {
+                Ok(Downloaded::Rows(rows)) => tracker.advance(0, *rows),
+                _ => tracker.advance(0, 0),
+            }
+            result
+        });
+        for (code, result) in job.codes.iter().zip(results) {
+            match result {
+                Ok(Downloaded::Rows(rows)) => {
+                    let path = job.partition(code);
+                    manifest.partitions.insert(code.clone(), Partition { path, rows });
+                }
+                Ok(Downloaded::Empty) => {
+                    manifest.empty.insert(code.clone());
+                }
+                Err(e @ TushareError::IoError(_)) => return Err(e),
+                Err(e) => {
+                    warn!("Download {} {code} failed: {e}", job.api_name);
+                    manifest.failed.insert(code.clone(), e.to_string());
+                }
+            }
+        }
+        manifest.save(dir)?;
+        if manifest.is_complete() {
+            checkpoint.finish()?;
+        }
+        info!(
+            "Downloaded {} rows of {} codes into {dir:?}, {} failed",
+            manifest.total_rows(),
+            manifest.partitions.len(),
+            manifest.failed.len()
+        );
+        Ok(manifest)
+    }
+
+    /// Download a single code unless its partition is written
+    fn download_code(&self, job: &Downloader, dir: &Path, code: &str) -> Result<Downloaded, TushareError> {
+        let path = dir.join(job.partition(code));
+        if let Some(df) = read_parquet(&path)? {
+            info!("{} {code} is downloaded to {path:?}", job.api_name);
+            return Ok(Downloaded::Rows(df.height()));
+        }
+        let mut query = self
+            .querybuilder(job.api_name.as_str())
+            .addparam("ts_code", code)
+            .start_date(job.start)
+            .end_date(job.end);
+        if let Some(fields) = &job.fields {
+            query = query.fields(fields);
+        }
+        let mut df = match query.query_all() {
+            Ok(df) => df,
+            Err(TushareError::EmptyError) => return Ok(Downloaded::Empty),
+            Err(e) => return Err(e),
+        };
+        write_parquet(&path, &mut df)?;
+        Ok(Downloaded::Rows(df.height()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::transport::MockTransport;
+    use serde_json::json;
+    use std::sync::Arc;
+
+    #[test]
+    fn test_download() {
+        let dir = std::env::temp_dir().join(format!("tushare_download_{}", std::process::id()));
+        let bars = MockTransport::data(&["ts_code", "trade_date", "close"], vec![vec![json!("000001.SZ"), json!("20240424"), json!(10.5)]]);
+        let mock = Arc::new(MockTransport::new().respond("daily", bars));
+        let tushare = Tushare::new("<token here>").with_transport(mock.clone());
+        let day = NaiveDate::from_ymd_opt(2024, 4, 24).unwrap();
+        let job = Downloader::new(&["000001.SZ"], day, day);
+        let manifest = tushare.download(&job, &dir).unwrap();
+        assert_eq!(manifest.total_rows(), 1);
+        assert!(dir.join("ts_code=000001.SZ/data.parquet").exists());
+        assert_eq!(Manifest::load(&dir).unwrap(), Some(manifest));
+        assert!(!dir.join(CHECKPOINT_FILE).exists());
+        // resumed without any request
+        tushare.download(&job, &dir).unwrap();
+        assert_eq!(mock.requests().len(), 1);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,103 @@
+//! SQL analytics over the local data with DuckDB, enabled by the "duckdb" feature.
+//! This module only generates SQL: the statements are run by a DuckDB connection of your choice
+//! (the duckdb crate, the CLI or Python), DuckDB itself is not linked since it builds from a large C++ source.
+//! DuckDB reads the parquet files in place, so the views are always up to date with the last sync.
+//! There is no writer taking a duckdb::Connection: appending a query result means running the statements
+//! of QueryBuilder::to_duckdb_sql() with your connection, e.g. `conn.execute_batch(&sql)` of the duckdb crate.
+//! ```no_run
+//! # fn main() -> Result<(), tushare::TushareError> {
+//! // e.g. the daily and stk_mins views of the files written by Tushare::sync() under data/
+//! let sql = tushare::duckdb::views_sql("data")?;
+//! std::fs::write("views.sql", sql)?;
+//! // duckdb analytics.db < views.sql
+//! # Ok(())
+//! # }
+//! ```
+use crate::builder::QueryBuilder;
+use crate::downloader::{Manifest, MANIFEST_FILE};
+use crate::error::TushareError;
+use std::fs;
+use std::path::Path;
+
+/// Quote an identifier
+fn identifier(name: &str) -> String {
+    format!("\"{}\"", name.replace('"', "\"\""))
+}
+
+/// Quote a string literal, e.g. a path
+fn literal(value: &str) -> String {
+    format!("'{}'", value.replace('\'', "''"))
+}
+
+/// The statement of a view reading the parquet files matching the glob
+fn view(name: &str, glob: &Path, hive: bool) -> String {
+    let glob = literal(&glob.to_string_lossy());
+    let options = if hive { ", hive_partitioning = true" } else { "" };
+    format!("CREATE OR REPLACE VIEW {} AS SELECT * FROM read_parquet({glob}{options});\n", identifier(name))
+}
+
+/// The statements creating a view of every dataset under dir:
+/// a `<dir>/<api_name>/` directory of Tushare::sync() becomes a view named api_name,
+/// and a dataset of Tushare::download() becomes a view named after the api of its manifest.
+/// Paths are written as given, pass an absolute dir if the database is opened elsewhere.
+pub fn views_sql(dir: impl AsRef<Path>) -> Result<String, TushareError> {
+    let dir = dir.as_ref();
+    let mut sql = String::new();
+    if let Some(manifest) = Manifest::load(dir)? {
+        sql.push_str(&view(&manifest.api_name, &dir.join("**").join("*.parquet"), true));
+        return Ok(sql);
+    }
+    let mut entries = fs::read_dir(dir)?.collect::<Result<Vec<_>, _>>()?;
+    entries.sort_by_key(|e| e.file_name());
+    for entry in entries {
+        let path = entry.path();
+        if !path.is_dir() {
+            continue;
+        }
+        let name = entry.file_name().to_string_lossy().to_string();
+        if path.join(MANIFEST_FILE).exists() {
+            let manifest = Manifest::load(&path)?.unwrap_or_default();
+            sql.push_str(&view(&manifest.api_name, &path.join("**").join("*.parquet"), true));
+        } else if fs::read_dir(&path)?.flatten().any(|f| f.path().extension().is_some_and(|e| e == "parquet")) {
+            sql.push_str(&view(&name, &path.join("*.parquet"), false));
+        }
+    }
+    Ok(sql)
+}
+
+/// The statements appending a parquet file into a DuckDB table, created with the columns of the file if not exists.
+/// Nothing is appended until the statements are run by a DuckDB connection.
+pub fn append_sql(table: &str, parquet: impl AsRef<Path>) -> String {
+    let source = format!("read_parquet({})", literal(&parquet.as_ref().to_string_lossy()));
+    format!(
+        "CREATE TABLE IF NOT EXISTS {table} AS SELECT * FROM {source} LIMIT 0;\nINSERT INTO {table} SELECT * FROM {source};\n",
+        table = identifier(table)
+    )
+}
+
+impl QueryBuilder<'_> {
+    /// Query all rows into a parquet staging file like query_to_parquet(),
+    /// and return the statements appending it into the DuckDB table, see [append_sql].
+    /// The table is not touched, run the statements with a DuckDB connection and keep the staging file until then.
+    pub fn to_duckdb_sql(&self, table: &str, staging: impl AsRef<Path>) -> Result<String, TushareError> {
+        self.query_to_parquet(staging.as_ref())?;
+        Ok(append_sql(table, staging))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[test]
+    fn test_views_sql() {
+        let dir = std::env::temp_dir().join(format!("tushare_duckdb_{}", std::process::id()));
+        fs::create_dir_all(dir.join("daily")).unwrap();
+        fs::write(dir.join("daily").join("000001.SZ.parquet"), b"").unwrap();
+        fs::create_dir_all(dir.join("empty")).unwrap();
+        let sql = views_sql(&dir).unwrap();
+        let glob = dir.join("daily").join("*.parquet");
+        assert_eq!(sql, format!("CREATE OR REPLACE VIEW \"daily\" AS SELECT * FROM read_parquet('{}');\n", glob.display()));
+        assert!(append_sql("daily", "it's.parquet").contains("read_parquet('it''s.parquet')"));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,131 @@
+//! Helpers of the macro economy apis.
+//! Monthly and quarterly data is keyed by strings like "202401" and "2023Q4" in tushare,
+//! the helpers convert them to the first day of the period as Date, so the series can be joined and resampled.
+use crate::api::TushareApi;
+use crate::error::TushareError;
+use crate::tushare::Tushare;
+use chrono::{Datelike, NaiveDate};
+use polars::prelude::*;
+use std::ops::RangeInclusive;
+
+/// Fields returned by Tushare::cn_cpi(), nt for national, town for urban and cnt for rural
+pub const CN_CPI_FIELDS: &str = "month,nt_val,nt_yoy,nt_mom,nt_accu,town_val,town_yoy,town_mom,\
+town_accu,cnt_val,cnt_yoy,cnt_mom,cnt_accu";
+
+/// Fields returned by Tushare::cn_gdp(), pi/si/ti for the primary/secondary/tertiary industry
+pub const CN_GDP_FIELDS: &str = "quarter,gdp,gdp_yoy,pi,pi_yoy,si,si_yoy,ti,ti_yoy";
+
+/// Fields returned by Tushare::cn_m()
+pub const CN_M_FIELDS: &str = "month,m0,m0_yoy,m0_mom,m1,m1_yoy,m1_mom,m2,m2_yoy,m2_mom";
+
+/// Fields returned by Tushare::shibor(), rates in percent of the terms
+pub const SHIBOR_FIELDS: &str = "date,on,1w,2w,1m,3m,6m,9m,1y";
+
+/// Parse a month like "202401" or a quarter like "2023Q4" to the first day of the period
+fn parse_period(period: &str) -> Option<NaiveDate> {
+    let year = period.get(..4)?.parse().ok()?;
+    let month = match period.get(4..)? {
+        q if q.starts_with('Q') => q[1..].parse::<u32>().ok()?.checked_sub(1)? * 3 + 1,
+        m => m.parse().ok()?,
+    };
+    NaiveDate::from_ymd_opt(year, month, 1)
+}
+
+/// Replace the period column with Date, and sort by it ascending
+fn parse_period_column(df: DataFrame, column: &str) -> PolarsResult<DataFrame> {
+    let dates: Vec<Option<NaiveDate>> = df
+        .column(column)?
+        .str()?
+        .into_iter()
+        .map(|p| p.and_then(parse_period))
+        .collect();
+    let mut df = df;
+    df.with_column(Series::new(column, dates))?;
+    df.sort([column], SortMultipleOptions::default())
+}
+
+fn quarter(date: &NaiveDate) -> String {
+    format!("{}Q{}", date.year(), date.month0() / 3 + 1)
+}
+
+impl Tushare {
+    /// Query a macro api with all columns but the period as f64
+    fn macro_query(
+        &self,
+        api: TushareApi,
+        fields: &str,
+        params: [(&str, String); 2],
+    ) -> Result<DataFrame, TushareError> {
+        let dtypes: Vec<(&str, DataType)> = fields
+            .split(',')
+            .skip(1)
+            .map(|f| (f, DataType::Float64))
+            .collect();
+        let mut query = self.querybuilder(api).fields(fields).dtypes(&dtypes);
+        for (key, value) in params {
+            query = query.addparam(key, &value);
+        }
+        query.query_all()
+    }
+
+    /// Monthly consumer price index within range, with month as Date
+    /// ```no_run
+    /// # fn main() -> Result<(), tushare::TushareError> {
+    /// use chrono::NaiveDate;
+    /// let tushare = tushare::Tushare::from_env()?;
+    /// let range = NaiveDate::from_ymd_opt(2020, 1, 1).unwrap()..=NaiveDate::from_ymd_opt(2023, 12, 31).unwrap();
+    /// let cpi = tushare.cn_cpi(range)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn cn_cpi(&self, range: RangeInclusive<NaiveDate>) -> Result<DataFrame, TushareError> {
+        let params = [
+            ("start_m", range.start().format("%Y%m").to_string()),
+            ("end_m", range.end().format("%Y%m").to_string()),
+        ];
+        let df = self.macro_query(TushareApi::CnCpi, CN_CPI_FIELDS, params)?;
+        Ok(parse_period_column(df, "month")?)
+    }
+
+    /// Quarterly GDP within range, with quarter as the Date of the first day of the quarter
+    pub fn cn_gdp(&self, range: RangeInclusive<NaiveDate>) -> Result<DataFrame, TushareError> {
+        let params = [("start_q", quarter(range.start())), ("end_q", quarter(range.end()))];
+        let df = self.macro_query(TushareApi::CnGdp, CN_GDP_FIELDS, params)?;
+        Ok(parse_period_column(df, "quarter")?)
+    }
+
+    /// Monthly money supply M0/M1/M2 within range, with month as Date
+    pub fn cn_m(&self, range: RangeInclusive<NaiveDate>) -> Result<DataFrame, TushareError> {
+        let params = [
+            ("start_m", range.start().format("%Y%m").to_string()),
+            ("end_m", range.end().format("%Y%m").to_string()),
+        ];
+        let df = self.macro_query(TushareApi::CnM, CN_M_FIELDS, params)?;
+        Ok(parse_period_column(df, "month")?)
+    }
+
+    /// Daily shibor rates within range, with date as Date
+    pub fn shibor(&self, range: RangeInclusive<NaiveDate>) -> Result<DataFrame, TushareError> {
+        let params = [
+            ("start_date", range.start().format("%Y%m%d").to_string()),
+            ("end_date", range.end().format("%Y%m%d").to_string()),
+        ];
+        let df = self.macro_query(TushareApi::Shibor, SHIBOR_FIELDS, params)?;
+        let df = crate::transform::parse_dates(df, &["date".to_string()])?;
+        Ok(df.sort(["date"], SortMultipleOptions::default())?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[test]
+    fn test_parse_period() {
+        let day = |y, m| NaiveDate::from_ymd_opt(y, m, 1);
+        assert_eq!(parse_period("202402"), day(2024, 2));
+        assert_eq!(parse_period("2023Q4"), day(2023, 10));
+        assert_eq!(parse_period("2023Q0"), None);
+        assert_eq!(parse_period("2023"), None);
+        assert_eq!(quarter(&NaiveDate::from_ymd_opt(2023, 9, 30).unwrap()), "2023Q3");
+    }
+}
@@ -0,0 +1,360 @@
+use std::time::Duration;
+use thiserror::Error;
+
+/// TushareError enumerates all possible errors returned by this library.
+#[derive(Error, Debug)]
+pub enum TushareError {
+    /// Tushare returns empty rows.
+    /// It might have returned dataframe column names but it's impossible to infer column type without row data
+    /// If this is the intended behavior, the caller should handle this error  
+    #[error("Tushare returned empty data")]
+    EmptyError,
+    /// Tushare returns non-zero error code in response body, see [TushareServerError]
+    #[error("Tushare request return error:{}, msg:{}", .0.code(), .0.msg())]
+    RequestError(#[from] TushareServerError),
+    /// Transform Tushare returned json to polars json error
+    #[error("Expected json node {0} not exist")]
+    DataError(String),
+    /// A row of the tushare response has more or less values than the fields.
+    /// Skip such rows with QueryBuilder::skip_malformed_rows(true) instead
+    #[error("Row {row} has {got} values, expected {expected} fields")]
+    RowShapeError {
+        /// Index of the row in the response
+        row: usize,
+        /// Number of the fields
+        expected: usize,
+        /// Number of the values in the row
+        got: usize,
+    },
+
+    /// The http status of the response is 4xx or 5xx, checked before the body is parsed, see [HttpStatusError]
+    #[error("Http error: {0}")]
+    HttpError(#[from] HttpStatusError),
+
+    /// The response body is not json, e.g. the html page of a gateway error or a captive portal
+    #[error("Invalid response with http status {status}: {snippet}")]
+    InvalidResponse {
+        /// The http status of the response
+        status: u16,
+        /// The first chars of the body
+        snippet: String,
+    },
+
+    /// Represents a network failure to read tushare web api.
+    #[error("Request network error, not accessable or possible 500")]
+    NetworkError(#[from] reqwest::Error),
+
+    /// Represents a failure to decode tushare result json
+    #[error("Parse tushare response json error")]
+    JsonError(#[from] serde_json::Error),
+
+    /// The result is neither cached nor recorded while the network is off, see Tushare::offline() and Tushare::replaying()
+    #[error("Cache miss: {0}")]
+    CacheMiss(String),
+
+    /// The query is cancelled by its [CancellationToken](crate::CancellationToken)
+    #[error("Query cancelled")]
+    Cancelled,
+
+    /// Represents a failure to converting json to polars dataframe
+    #[cfg(feature = "dataframe")]
+    #[error("Convert json to polars dataframe error")]
+    PolarsError(#[from] polars::error::PolarsError),
+
+    /// Represents a failure to read or write local files, e.g. synced parquet files
+    #[error("Local file io error")]
+    IoError(#[from] std::io::Error),
+
+    /// Represents a missing or malformed token/config file
+    #[error("Load config error: {0}")]
+    ConfigError(String),
+
+    /// Represents a malformed stock code, checked locally since tushare returns empty data for it
+    #[error("Invalid ts_code: {0}")]
+    InvalidCode(String),
+
+    /// Represents a missing required param or a malformed date param of a known api, see [ApiMeta](crate::api::ApiMeta).
+    /// Checked locally before any request, since tushare returns empty data for most of them
+    #[error("Invalid params: {0}")]
+    InvalidParams(String),
+
+    /// Represents a failure to convert the results to arrow
+    #[cfg(feature = "arrow")]
+    #[error("Arrow conversion error")]
+    ArrowError(#[from] arrow_schema::ArrowError),
+
+    /// Represents a failure to read or write a SQLite database
+    #[cfg(feature = "sqlite")]
+    #[error("SQLite error")]
+    SqliteError(#[from] rusqlite::Error),
+
+    /// Represents a failure of the realtime websocket connection
+    #[cfg(feature = "realtime")]
+    #[error("Realtime websocket error")]
+    WebSocketError(#[source] Box<tungstenite::Error>),
+}
+
+#[cfg(feature = "realtime")]
+impl From<tungstenite::Error> for TushareError {
+    // boxed since the websocket error is much larger than the other variants
+    fn from(e: tungstenite::Error) -> Self {
+        TushareError::WebSocketError(Box::new(e))
+    }
+}
+
+/// The number of chars of the body kept by InvalidResponse
+const SNIPPET_CHARS: usize = 200;
+
+impl TushareError {
+    /// An InvalidResponse with the first chars of the body
+    pub(crate) fn invalid_response(status: u16, body: &str) -> Self {
+        TushareError::InvalidResponse {
+            status,
+            snippet: body.trim().chars().take(SNIPPET_CHARS).collect(),
+        }
+    }
+
+    /// Whether the error is transient and the same request may succeed later.
+    /// Network failures, HTTP 429 and 5xx responses are retryable, while other 4xx responses are not.
+    /// Server errors are retryable if the server is just busy, see [TushareServerError::is_retryable].
+    pub fn is_retryable(&self) -> bool {
+        match self {
+            TushareError::RequestError(e) => e.is_retryable(),
+            TushareError::HttpError(e) => e.is_retryable(),
+            TushareError::NetworkError(e) => match e.status() {
+                Some(status) => status.is_server_error(),
+                None => true,
+            },
+            TushareError::InvalidResponse { status, .. } => *status >= 500,
+            _ => false,
+        }
+    }
+
+    /// The wait suggested by a rate limit error, see [TushareServerError::retry_after] and [HttpStatusError::retry_after]
+    pub fn retry_after(&self) -> Option<Duration> {
+        match self {
+            TushareError::RequestError(e) => e.retry_after(),
+            TushareError::HttpError(e) => e.retry_after(),
+            _ => None,
+        }
+    }
+}
+
+/// The http error statuses of the response, mapped before the body is parsed.
+/// A gateway in front of tushare answers with them, while tushare itself reports the errors by the code in the body.
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum HttpStatusError {
+    /// HTTP 401, e.g. a corporate proxy requiring authentication
+    #[error("401 unauthorized")]
+    Unauthorized,
+    /// HTTP 403, e.g. the ip is blocked by the gateway
+    #[error("403 forbidden")]
+    Forbidden,
+    /// HTTP 429, the gateway throttles the calls
+    #[error("429 too many requests")]
+    TooManyRequests {
+        /// The wait of the Retry-After header
+        retry_after: Option<Duration>,
+    },
+    /// HTTP 5xx, e.g. 502 bad gateway during a deployment of tushare
+    #[error("{status} server error")]
+    ServerError {
+        status: u16,
+        /// The wait of the Retry-After header, usually sent with 503
+        retry_after: Option<Duration>,
+    },
+    /// Any other 4xx status
+    #[error("{status} client error")]
+    Other { status: u16 },
+}
+
+impl HttpStatusError {
+    /// Classify an error status, with the value of the Retry-After header if any.
+    /// Only the delay in seconds of Retry-After is supported, not the http date.
+    pub fn from_status(status: u16, retry_after: Option<&str>) -> Self {
+        let retry_after = retry_after
+            .and_then(|secs| secs.trim().parse().ok())
+            .map(Duration::from_secs);
+        match status {
+            401 => HttpStatusError::Unauthorized,
+            403 => HttpStatusError::Forbidden,
+            429 => HttpStatusError::TooManyRequests { retry_after },
+            500.. => HttpStatusError::ServerError { status, retry_after },
+            status => HttpStatusError::Other { status },
+        }
+    }
+
+    /// The http status of the response
+    pub fn status(&self) -> u16 {
+        match self {
+            HttpStatusError::Unauthorized => 401,
+            HttpStatusError::Forbidden => 403,
+            HttpStatusError::TooManyRequests { .. } => 429,
+            HttpStatusError::ServerError { status, .. } | HttpStatusError::Other { status } => *status,
+        }
+    }
+
+    /// Whether the same request may succeed later, i.e. 429 and 5xx
+    pub fn is_retryable(&self) -> bool {
+        matches!(self, HttpStatusError::TooManyRequests { .. } | HttpStatusError::ServerError { .. })
+    }
+
+    /// The wait of the Retry-After header of a retryable status
+    pub fn retry_after(&self) -> Option<Duration> {
+        match self {
+            HttpStatusError::TooManyRequests { retry_after } | HttpStatusError::ServerError { retry_after, .. } => {
+                *retry_after
+            }
+            _ => None,
+        }
+    }
+}
+
+/// Phrases of the 40203 msg when the call frequency is exceeded
+const RATE_LIMIT_HINTS: &[&str] = &["最多访问", "频率", "frequency"];
+
+/// Phrases of the 40203 msg when the limit is the calls of a day, e.g. "每天最多访问该接口10000次"
+const DAILY_QUOTA_HINTS: &[&str] = &["每天", "每日", "per day"];
+
+/// Error codes returned by tushare server in the response body.
+/// Tushare reuses some codes for different reasons, so the msg is checked as well.
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum TushareServerError {
+    /// Wrong or expired token, code 40101
+    #[error("invalid token: {msg}")]
+    InvalidToken { msg: String },
+    /// The account points are not enough for the api, code 40203
+    #[error("insufficient points: {msg}")]
+    InsufficientPoints { msg: String },
+    /// The account has no permission of the api, code 40203
+    #[error("permission denied: {msg}")]
+    PermissionDenied { msg: String },
+    /// Calls per minute/hour of the api exceeded, code 40203
+    #[error("rate limited: {msg}")]
+    RateLimited { msg: String },
+    /// Calls per day of the api exceeded, code 40203, the api can't be called again before tomorrow
+    #[error("daily quota exceeded: {msg}")]
+    DailyQuotaExceeded { msg: String },
+    /// Missing or malformed parameters, code -2001
+    #[error("invalid params: {msg}")]
+    InvalidParams { msg: String },
+    /// Any other code
+    #[error("error {code}: {msg}")]
+    Other { code: i64, msg: String },
+}
+
+impl TushareServerError {
+    /// Classify the code and msg returned by tushare
+    pub fn from_code(code: i64, msg: &str) -> Self {
+        let msg = msg.to_string();
+        match code {
+            40101 => TushareServerError::InvalidToken { msg },
+            -2001 => TushareServerError::InvalidParams { msg },
+            // the rate limit msg also links to the permission doc, so it is checked first
+            40203 if RATE_LIMIT_HINTS.iter().any(|h| msg.contains(h)) => {
+                match DAILY_QUOTA_HINTS.iter().any(|h| msg.contains(h)) {
+                    true => TushareServerError::DailyQuotaExceeded { msg },
+                    false => TushareServerError::RateLimited { msg },
+                }
+            }
+            40203 if msg.contains("积分") => TushareServerError::InsufficientPoints { msg },
+            40203 if msg.contains("权限") => TushareServerError::PermissionDenied { msg },
+            code => TushareServerError::Other { code, msg },
+        }
+    }
+
+    /// The code returned by tushare
+    pub fn code(&self) -> i64 {
+        match self {
+            TushareServerError::InvalidToken { .. } => 40101,
+            TushareServerError::InsufficientPoints { .. }
+            | TushareServerError::PermissionDenied { .. }
+            | TushareServerError::RateLimited { .. }
+            | TushareServerError::DailyQuotaExceeded { .. } => 40203,
+            TushareServerError::InvalidParams { .. } => -2001,
+            TushareServerError::Other { code, .. } => *code,
+        }
+    }
+
+    /// The msg returned by tushare
+    pub fn msg(&self) -> &str {
+        match self {
+            TushareServerError::InvalidToken { msg }
+            | TushareServerError::InsufficientPoints { msg }
+            | TushareServerError::PermissionDenied { msg }
+            | TushareServerError::RateLimited { msg }
+            | TushareServerError::DailyQuotaExceeded { msg }
+            | TushareServerError::InvalidParams { msg }
+            | TushareServerError::Other { msg, .. } => msg,
+        }
+    }
+
+    /// Whether the same request may succeed later without any change, i.e. rate limited.
+    /// The daily quota is not, waiting until tomorrow is not a retry.
+    pub fn is_retryable(&self) -> bool {
+        matches!(self, TushareServerError::RateLimited { .. })
+    }
+
+    /// How long to wait before the next call of a rate limited api, parsed from the msg.
+    /// An explicit wait like "请30秒后重试" is used as is, otherwise the window of the limit,
+    /// e.g. 60 seconds for "每分钟最多访问该接口200次". None for the other errors.
+    pub fn retry_after(&self) -> Option<Duration> {
+        let TushareServerError::RateLimited { msg } = self else {
+            return None;
+        };
+        if let Some(secs) = number_before(msg, "秒") {
+            return Some(Duration::from_secs(secs));
+        }
+        if msg.contains("每分钟") || msg.contains("per minute") {
+            Some(Duration::from_secs(60))
+        } else if msg.contains("每小时") || msg.contains("per hour") {
+            Some(Duration::from_secs(3600))
+        } else {
+            None
+        }
+    }
+}
+
+/// Parse the digits right before the first occurrence of unit, e.g. 30 of "请30秒后重试"
+fn number_before(msg: &str, unit: &str) -> Option<u64> {
+    let head = &msg[..msg.find(unit)?];
+    let digits = head.len() - head.trim_end_matches(|c: char| c.is_ascii_digit()).len();
+    head[head.len() - digits..].parse().ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[test]
+    fn test_from_code() {
+        let e = TushareServerError::from_code(40203, "抱歉，您每分钟最多访问该接口500次");
+        assert!(matches!(e, TushareServerError::RateLimited { .. }));
+        assert!(e.is_retryable());
+        assert_eq!(e.retry_after(), Some(Duration::from_secs(60)));
+        let e = TushareServerError::from_code(40203, "抱歉，您每分钟最多访问该接口200次，权限的具体详情访问：https://tushare.pro/document/1?doc_id=108。");
+        assert!(matches!(e, TushareServerError::RateLimited { .. }));
+        assert_eq!(TushareServerError::from_code(40203, "访问频率过高，请30秒后重试").retry_after(), Some(Duration::from_secs(30)));
+        let e = TushareServerError::from_code(40203, "抱歉，您每天最多访问该接口10000次");
+        assert!(matches!(e, TushareServerError::DailyQuotaExceeded { .. }));
+        assert_eq!((e.code(), e.is_retryable(), e.retry_after()), (40203, false, None));
+        let e = TushareServerError::from_code(40203, "抱歉，您没有访问该接口的权限");
+        assert!(matches!(e, TushareServerError::PermissionDenied { .. }));
+        assert!(!e.is_retryable());
+        assert_eq!(TushareServerError::from_code(40101, "token不对").code(), 40101);
+        assert_eq!(TushareServerError::from_code(-1, "x"), TushareServerError::Other { code: -1, msg: "x".into() });
+        assert_eq!(TushareServerError::from_code(40203, "x"), TushareServerError::Other { code: 40203, msg: "x".into() });
+    }
+
+    #[test]
+    fn test_from_status() {
+        let e = HttpStatusError::from_status(429, Some("30"));
+        assert_eq!(e, HttpStatusError::TooManyRequests { retry_after: Some(Duration::from_secs(30)) });
+        assert!(TushareError::from(e).is_retryable());
+        let e = HttpStatusError::from_status(503, Some("Wed, 21 Oct 2026 07:28:00 GMT"));
+        assert_eq!((e.status(), e.retry_after()), (503, None));
+        assert!(e.is_retryable());
+        assert_eq!(HttpStatusError::from_status(401, None), HttpStatusError::Unauthorized);
+        assert!(!HttpStatusError::from_status(403, None).is_retryable());
+        assert_eq!(HttpStatusError::from_status(404, None).status(), 404);
+    }
+}
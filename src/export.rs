@@ -0,0 +1,135 @@
+//! Write query results to files page by page, without holding all rows in memory
+use crate::builder::QueryBuilder;
+use crate::error::TushareError;
+use crate::transform;
+use polars::prelude::*;
+use std::fs::{self, File};
+use std::ops::ControlFlow;
+use std::path::Path;
+
+/// Write a page with Some, or finish the file with None
+type PageWriter = Box<dyn FnMut(Option<&DataFrame>) -> PolarsResult<()>>;
+
+#[derive(Debug, Clone, Copy)]
+enum Format {
+    Parquet,
+    Csv,
+    Ipc,
+}
+
+impl Format {
+    fn writer(&self, file: File, schema: &Schema) -> PolarsResult<PageWriter> {
+        Ok(match self {
+            Format::Parquet => {
+                let mut writer = ParquetWriter::new(file).batched(schema)?;
+                Box::new(move |df| match df {
+                    Some(df) => writer.write_batch(df),
+                    None => writer.finish().map(|_| ()),
+                })
+            }
+            Format::Csv => {
+                let mut writer = CsvWriter::new(file).batched(schema)?;
+                Box::new(move |df| match df {
+                    Some(df) => writer.write_batch(df),
+                    None => writer.finish(),
+                })
+            }
+            Format::Ipc => {
+                let mut writer = IpcWriter::new(file).batched(schema)?;
+                Box::new(move |df| match df {
+                    Some(df) => writer.write_batch(df),
+                    None => writer.finish(),
+                })
+            }
+        })
+    }
+}
+
+impl QueryBuilder<'_> {
+    /// Query all rows like query_all(), and write them to a parquet file page by page.
+    /// Every page is written once fetched, so the memory usage is bounded by page_size.
+    /// The file is written to a temp file and renamed at the end, so a failed query never leaves a partial file.
+    /// # return
+    /// The number of rows written
+    pub fn query_to_parquet(&self, path: impl AsRef<Path>) -> Result<usize, TushareError> {
+        self.query_to_file(path.as_ref(), Format::Parquet)
+    }
+
+    /// Same as query_to_parquet(), in csv format with a header line
+    pub fn query_to_csv(&self, path: impl AsRef<Path>) -> Result<usize, TushareError> {
+        self.query_to_file(path.as_ref(), Format::Csv)
+    }
+
+    /// Same as query_to_parquet(), in arrow ipc (feather v2) format
+    pub fn query_to_ipc(&self, path: impl AsRef<Path>) -> Result<usize, TushareError> {
+        self.query_to_file(path.as_ref(), Format::Ipc)
+    }
+
+    fn query_to_file(&self, path: &Path, format: Format) -> Result<usize, TushareError> {
+        let mut tmp = path.as_os_str().to_owned();
+        tmp.push(".tmp");
+        let tmp = Path::new(&tmp);
+        let mut writer: Option<(Schema, PageWriter)> = None;
+        let mut rows = 0;
+        let mut write_page = |df: DataFrame| -> Result<(), TushareError> {
+            let (schema, write) = match writer.as_mut() {
+                Some(writer) => writer,
+                None => {
+                    let schema = df.schema();
+                    let write = format.writer(File::create(tmp)?, &schema)?;
+                    writer.insert((schema, write))
+                }
+            };
+            // a later page may infer other dtypes, e.g. a column of all nulls
+            let df = transform::apply_schema(df, schema)?;
+            write(Some(&df))?;
+            rows += df.height();
+            Ok(())
+        };
+        let stopped = self.query_chunks(|df| match write_page(df) {
+            Ok(()) => ControlFlow::Continue(()),
+            Err(e) => ControlFlow::Break(e),
+        });
+        let result = match stopped {
+            Ok(Some(e)) | Err(e) => Err(e),
+            Ok(None) => match writer.as_mut() {
+                Some((_, write)) => write(None).map_err(TushareError::from),
+                None => Err(TushareError::EmptyError),
+            },
+        };
+        drop(writer);
+        match result {
+            Ok(()) => {
+                fs::rename(tmp, path)?;
+                Ok(rows)
+            }
+            Err(e) => {
+                let _ = fs::remove_file(tmp);
+                Err(e)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::transport::MockTransport;
+    use crate::Tushare;
+    use serde_json::json;
+    #[test]
+    fn test_query_to_csv() {
+        let page = |rows: Vec<serde_json::Value>| {
+            MockTransport::data(&["ts_code", "close"], rows.into_iter().map(|c| vec![json!("000001.SZ"), c]).collect())
+        };
+        let mock = MockTransport::new()
+            .respond("daily", page(vec![json!(1.5), json!(2.5)]))
+            .respond("daily", page(vec![json!(null)]));
+        let tushare = Tushare::new("").with_transport(mock);
+        let path = std::env::temp_dir().join(format!("tushare_export_{}.csv", std::process::id()));
+        let rows = tushare.querybuilder("daily").page_size(2).query_to_csv(&path).unwrap();
+        assert_eq!(rows, 3);
+        let csv = std::fs::read_to_string(&path).unwrap();
+        assert_eq!(csv.lines().count(), 4);
+        let _ = std::fs::remove_file(&path);
+    }
+}
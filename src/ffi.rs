@@ -0,0 +1,176 @@
+//! C functions to embed the client in other languages or C++ trading systems, enabled by the "ffi" feature.
+//! The results are exported through the [Arrow C Data Interface](https://arrow.apache.org/docs/format/CDataInterface.html)
+//! as a struct array of the columns, imported zero-copy by any arrow implementation, e.g. arrow::ImportRecordBatch() in C++.
+//! See include/tushare.h for the declarations, link a cdylib or staticlib crate depending on this crate with the feature.
+//!
+//! Every function returns 0 on success and -1 on failure, call tushare_last_error() for the message.
+use crate::arrow::to_record_batch;
+use crate::builder::Dict;
+use crate::error::TushareError;
+use crate::ratelimit::RateLimiter;
+use crate::tushare::Tushare;
+use arrow_array::ffi::{to_ffi, FFI_ArrowArray, FFI_ArrowSchema};
+use arrow_array::{Array, StructArray};
+use serde_json::Value;
+use std::cell::RefCell;
+use std::ffi::{c_char, c_int, CStr, CString};
+use std::panic::{self, AssertUnwindSafe};
+use std::ptr;
+
+thread_local! {
+    static LAST_ERROR: RefCell<Option<CString>> = const { RefCell::new(None) };
+}
+
+fn set_last_error(msg: String) {
+    let msg = CString::new(msg.replace('\0', " ")).unwrap_or_default();
+    LAST_ERROR.with(|e| *e.borrow_mut() = Some(msg));
+}
+
+/// Run f, and turn its error or panic into -1 with the last error set
+fn guard(f: impl FnOnce() -> Result<(), TushareError>) -> c_int {
+    match panic::catch_unwind(AssertUnwindSafe(f)) {
+        Ok(Ok(())) => 0,
+        Ok(Err(e)) => {
+            set_last_error(e.to_string());
+            -1
+        }
+        Err(_) => {
+            set_last_error("panicked".to_string());
+            -1
+        }
+    }
+}
+
+/// A borrowed utf-8 string, None for a null pointer
+unsafe fn optional_str<'a>(s: *const c_char) -> Result<Option<&'a str>, TushareError> {
+    if s.is_null() {
+        return Ok(None);
+    }
+    CStr::from_ptr(s)
+        .to_str()
+        .map(Some)
+        .map_err(|e| TushareError::InvalidParams(format!("not utf-8: {e}")))
+}
+
+/// Params from a json object, numbers and bools are sent as their text
+fn params_from_json(json: &str) -> Result<Dict, TushareError> {
+    let params: serde_json::Map<String, Value> = serde_json::from_str(json)?;
+    Ok(params
+        .into_iter()
+        .map(|(k, v)| match v {
+            Value::String(s) => (k, s),
+            v => (k, v.to_string()),
+        })
+        .collect())
+}
+
+/// The message of the last failure on the calling thread, null if none.
+/// The string is owned by the library and valid until the next failure on the thread.
+#[no_mangle]
+pub extern "C" fn tushare_last_error() -> *const c_char {
+    LAST_ERROR.with(|e| e.borrow().as_ref().map_or(ptr::null(), |s| s.as_ptr()))
+}
+
+/// Create a client with the token, or read it from TUSHARE_TOKEN if the token is null.
+/// The client is written to out, free it with tushare_client_free().
+/// # Safety
+/// token must be null or a nul terminated string, out must be a valid pointer.
+#[no_mangle]
+pub unsafe extern "C" fn tushare_client_new(token: *const c_char, out: *mut *mut Tushare) -> c_int {
+    guard(|| {
+        let tushare = match optional_str(token)? {
+            Some(token) => Tushare::new(token),
+            None => Tushare::from_env()?,
+        };
+        *out = Box::into_raw(Box::new(tushare));
+        Ok(())
+    })
+}
+
+/// Throttle the calls of the client on client side, see Tushare::with_rate_limit()
+/// # Safety
+/// client must be created by tushare_client_new() and not freed, and not used by other threads during the call.
+#[no_mangle]
+pub unsafe extern "C" fn tushare_client_set_rate_limit(client: *mut Tushare, calls_per_minute: u32) -> c_int {
+    guard(|| {
+        let tushare = client
+            .as_mut()
+            .ok_or_else(|| TushareError::InvalidParams("null client".to_string()))?;
+        tushare.rate_limiter = Some(RateLimiter::per_minute(calls_per_minute));
+        Ok(())
+    })
+}
+
+/// Free a client created by tushare_client_new(), null is ignored
+/// # Safety
+/// client must be null or created by tushare_client_new() and not freed yet.
+#[no_mangle]
+pub unsafe extern "C" fn tushare_client_free(client: *mut Tushare) {
+    if !client.is_null() {
+        drop(Box::from_raw(client));
+    }
+}
+
+/// Query all rows of the api, and export them as a struct array of the columns into out_array and out_schema.
+/// params is a json object like `{"ts_code": "000001.SZ"}` and fields a comma separated list, both may be null.
+/// The caller owns the exported structs and must call their release callbacks.
+/// # Safety
+/// client must be created by tushare_client_new() and not freed, the strings must be null or nul terminated,
+/// out_array and out_schema must be valid pointers to uninitialized structs.
+#[no_mangle]
+pub unsafe extern "C" fn tushare_query(
+    client: *const Tushare,
+    api_name: *const c_char,
+    params: *const c_char,
+    fields: *const c_char,
+    out_array: *mut FFI_ArrowArray,
+    out_schema: *mut FFI_ArrowSchema,
+) -> c_int {
+    guard(|| {
+        let tushare = client
+            .as_ref()
+            .ok_or_else(|| TushareError::InvalidParams("null client".to_string()))?;
+        let api_name = optional_str(api_name)?.ok_or_else(|| TushareError::InvalidParams("null api_name".to_string()))?;
+        let mut query = tushare.querybuilder(api_name);
+        if let Some(params) = optional_str(params)? {
+            query = query.params(params_from_json(params)?);
+        }
+        if let Some(fields) = optional_str(fields)? {
+            query = query.fields(fields);
+        }
+        let batch = to_record_batch(&query.query_all()?)?;
+        let (array, schema) = to_ffi(&StructArray::from(batch).to_data())?;
+        ptr::write(out_array, array);
+        ptr::write(out_schema, schema);
+        Ok(())
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::transport::MockTransport;
+    use arrow_array::ffi::from_ffi;
+    use serde_json::json;
+    use std::mem::MaybeUninit;
+
+    #[test]
+    fn test_query() {
+        let mock = MockTransport::new().respond("daily", MockTransport::data(&["ts_code", "close"], vec![vec![json!("000001.SZ"), json!(10.5)]]));
+        let client = Box::into_raw(Box::new(Tushare::new("<token here>").with_transport(mock)));
+        let (mut array, mut schema) = (MaybeUninit::uninit(), MaybeUninit::uninit());
+        let api_name = CString::new("daily").unwrap();
+        let params = CString::new(r#"{"trade_date": "20240424", "limit": 10}"#).unwrap();
+        unsafe {
+            let ret = tushare_query(client, api_name.as_ptr(), params.as_ptr(), ptr::null(), array.as_mut_ptr(), schema.as_mut_ptr());
+            assert_eq!(ret, 0);
+            let data = from_ffi(array.assume_init(), &schema.assume_init()).unwrap();
+            let columns = StructArray::from(data);
+            assert_eq!((columns.len(), columns.num_columns()), (1, 2));
+            let (mut array, mut schema) = (MaybeUninit::uninit(), MaybeUninit::uninit());
+            assert_eq!(tushare_query(client, ptr::null(), ptr::null(), ptr::null(), array.as_mut_ptr(), schema.as_mut_ptr()), -1);
+            assert_eq!(CStr::from_ptr(tushare_last_error()).to_str().unwrap(), "Invalid params: null api_name");
+            tushare_client_free(client);
+        }
+    }
+}
@@ -0,0 +1,315 @@
+//! Field lists of the common apis, to pass to QueryBuilder::fields() instead of hand typed strings.
+//! Every api module has `ALL` with every field, some shortcuts like `daily::OHLCV`,
+//! and a `Field` enum to pick the fields with typos caught at compile time,
+//! re-exported as `DailyField`, `StockBasicField` and so on for QueryBuilder::select().
+//! ```
+//! use tushare::fields::{self, daily, DailyField};
+//! let tushare = tushare::Tushare::new("<your token>");
+//! let query = tushare.querybuilder("daily").fields(daily::OHLCV);
+//! let query = tushare.querybuilder("daily").select(&[DailyField::TsCode, DailyField::Close]);
+//! assert_eq!(fields::join(&[daily::Field::TsCode, daily::Field::Close]), "ts_code,close");
+//! ```
+
+use crate::api::TushareApi;
+
+/// Join the fields with commas, e.g. for QueryBuilder::fields()
+pub fn join<F: AsRef<str>>(fields: &[F]) -> String {
+    fields.iter().map(|f| f.as_ref()).collect::<Vec<_>>().join(",")
+}
+
+/// The `Field` enums of the api modules, accepted by QueryBuilder::select() and the `_select` helpers.
+/// Unlike a comma separated string, a misspelled field doesn't compile.
+pub trait ApiField: Copy + AsRef<str> {}
+
+/// The type of a field, as documented by tushare
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum FieldType {
+    /// Text like a code or a name
+    Str,
+    /// Any number, e.g. prices and volumes
+    Float,
+    /// An integer, e.g. a flag like is_open
+    Int,
+    /// A *YYYYMMDD* date, kept as a string until QueryBuilder::parse_dates()
+    Date,
+    /// A *YYYY-MM-DD HH:MM:SS* date time, kept as a string like the dates
+    Datetime,
+}
+
+impl FieldType {
+    /// The polars type the column is cast to
+    #[cfg(feature = "dataframe")]
+    pub fn dtype(&self) -> polars::prelude::DataType {
+        use polars::prelude::DataType;
+        match self {
+            FieldType::Float => DataType::Float64,
+            FieldType::Int => DataType::Int64,
+            FieldType::Str | FieldType::Date | FieldType::Datetime => DataType::String,
+        }
+    }
+}
+
+/// The registry of the known fields of an api, None for the apis without a module here.
+/// QueryBuilder checks the fields() of a registered api against it, and casts the columns to the field types,
+/// turn both off with QueryBuilder::schema_registry(false), e.g. for a field added by tushare recently.
+/// ```
+/// use tushare::fields::{schema_of, FieldType};
+/// use tushare::TushareApi;
+/// let schema = schema_of(&TushareApi::Weekly).unwrap();
+/// assert!(schema.contains(&("close", FieldType::Float)));
+/// assert!(schema_of(&TushareApi::Custom("cyq_perf".into())).is_none());
+/// ```
+pub fn schema_of(api: &TushareApi) -> Option<&'static [(&'static str, FieldType)]> {
+    Some(match api {
+        TushareApi::Daily | TushareApi::Weekly | TushareApi::Monthly => daily::SCHEMA,
+        TushareApi::StkMins => stk_mins::SCHEMA,
+        TushareApi::DailyBasic => daily_basic::SCHEMA,
+        TushareApi::AdjFactor => adj_factor::SCHEMA,
+        TushareApi::StockBasic => stock_basic::SCHEMA,
+        TushareApi::TradeCal => trade_cal::SCHEMA,
+        TushareApi::NameChange => namechange::SCHEMA,
+        TushareApi::SuspendD => suspend_d::SCHEMA,
+        TushareApi::Dividend => dividend::SCHEMA,
+        TushareApi::IndexDaily => index_daily::SCHEMA,
+        _ => return None,
+    })
+}
+
+pub use adj_factor::Field as AdjFactorField;
+pub use daily::Field as DailyField;
+pub use daily_basic::Field as DailyBasicField;
+pub use dividend::Field as DividendField;
+pub use index_daily::Field as IndexDailyField;
+pub use namechange::Field as NamechangeField;
+pub use stk_mins::Field as StkMinsField;
+pub use stock_basic::Field as StockBasicField;
+pub use suspend_d::Field as SuspendDField;
+pub use trade_cal::Field as TradeCalField;
+
+macro_rules! api_fields {
+    ($(#[$doc:meta])* $module:ident {
+        $first_variant:ident => $first:literal: $first_type:ident $(, $variant:ident => $name:literal: $type:ident)* $(,)?
+    } $($extra:item)*) => {
+        $(#[$doc])*
+        pub mod $module {
+            /// Fields of the api
+            #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+            pub enum Field {
+                #[allow(missing_docs)]
+                $first_variant,
+                $(#[allow(missing_docs)] $variant,)*
+            }
+
+            impl Field {
+                /// The field name returned by tushare
+                pub const fn as_str(&self) -> &'static str {
+                    match self {
+                        Field::$first_variant => $first,
+                        $(Field::$variant => $name,)*
+                    }
+                }
+            }
+
+            impl AsRef<str> for Field {
+                fn as_ref(&self) -> &str {
+                    self.as_str()
+                }
+            }
+
+            impl super::ApiField for Field {}
+
+            /// All fields of the api, comma separated
+            pub const ALL: &str = concat!($first $(, ",", $name)*);
+
+            /// Every field of the api with its type, see [schema_of](super::schema_of)
+            pub const SCHEMA: &[(&str, super::FieldType)] = &[
+                ($first, super::FieldType::$first_type),
+                $(($name, super::FieldType::$type),)*
+            ];
+
+            $($extra)*
+        }
+    };
+}
+
+api_fields! {
+    /// Fields of daily, weekly and monthly bars
+    daily {
+        TsCode => "ts_code": Str,
+        TradeDate => "trade_date": Date,
+        Open => "open": Float,
+        High => "high": Float,
+        Low => "low": Float,
+        Close => "close": Float,
+        PreClose => "pre_close": Float,
+        Change => "change": Float,
+        PctChg => "pct_chg": Float,
+        Vol => "vol": Float,
+        Amount => "amount": Float,
+    }
+    /// Code, date and the prices
+    pub const OHLC: &str = "ts_code,trade_date,open,high,low,close";
+    /// Code, date, the prices and volume
+    pub const OHLCV: &str = "ts_code,trade_date,open,high,low,close,vol";
+}
+
+api_fields! {
+    /// Fields of stk_mins
+    stk_mins {
+        TsCode => "ts_code": Str,
+        TradeTime => "trade_time": Datetime,
+        Open => "open": Float,
+        Close => "close": Float,
+        High => "high": Float,
+        Low => "low": Float,
+        Vol => "vol": Float,
+        Amount => "amount": Float,
+    }
+}
+
+api_fields! {
+    /// Fields of daily_basic
+    daily_basic {
+        TsCode => "ts_code": Str,
+        TradeDate => "trade_date": Date,
+        Close => "close": Float,
+        TurnoverRate => "turnover_rate": Float,
+        TurnoverRateF => "turnover_rate_f": Float,
+        VolumeRatio => "volume_ratio": Float,
+        Pe => "pe": Float,
+        PeTtm => "pe_ttm": Float,
+        Pb => "pb": Float,
+        Ps => "ps": Float,
+        PsTtm => "ps_ttm": Float,
+        DvRatio => "dv_ratio": Float,
+        DvTtm => "dv_ttm": Float,
+        TotalShare => "total_share": Float,
+        FloatShare => "float_share": Float,
+        FreeShare => "free_share": Float,
+        TotalMv => "total_mv": Float,
+        CircMv => "circ_mv": Float,
+    }
+    /// Code, date and the valuation ratios
+    pub const VALUATION: &str = "ts_code,trade_date,pe,pe_ttm,pb,ps,ps_ttm,dv_ratio,dv_ttm";
+    /// Code, date and the market values
+    pub const MARKET_VALUE: &str = "ts_code,trade_date,total_mv,circ_mv";
+}
+
+api_fields! {
+    /// Fields of adj_factor
+    adj_factor {
+        TsCode => "ts_code": Str,
+        TradeDate => "trade_date": Date,
+        AdjFactor => "adj_factor": Float,
+    }
+}
+
+api_fields! {
+    /// Fields of stock_basic
+    stock_basic {
+        TsCode => "ts_code": Str,
+        Symbol => "symbol": Str,
+        Name => "name": Str,
+        Area => "area": Str,
+        Industry => "industry": Str,
+        Fullname => "fullname": Str,
+        Enname => "enname": Str,
+        Cnspell => "cnspell": Str,
+        Market => "market": Str,
+        Exchange => "exchange": Str,
+        CurrType => "curr_type": Str,
+        ListStatus => "list_status": Str,
+        ListDate => "list_date": Date,
+        DelistDate => "delist_date": Date,
+        IsHs => "is_hs": Str,
+    }
+}
+
+api_fields! {
+    /// Fields of trade_cal
+    trade_cal {
+        Exchange => "exchange": Str,
+        CalDate => "cal_date": Date,
+        IsOpen => "is_open": Int,
+        PretradeDate => "pretrade_date": Date,
+    }
+}
+
+api_fields! {
+    /// Fields of namechange
+    namechange {
+        TsCode => "ts_code": Str,
+        Name => "name": Str,
+        StartDate => "start_date": Date,
+        EndDate => "end_date": Date,
+        AnnDate => "ann_date": Date,
+        ChangeReason => "change_reason": Str,
+    }
+}
+
+api_fields! {
+    /// Fields of suspend_d
+    suspend_d {
+        TsCode => "ts_code": Str,
+        TradeDate => "trade_date": Date,
+        SuspendTiming => "suspend_timing": Str,
+        SuspendType => "suspend_type": Str,
+    }
+}
+
+api_fields! {
+    /// Fields of dividend
+    dividend {
+        TsCode => "ts_code": Str,
+        EndDate => "end_date": Date,
+        AnnDate => "ann_date": Date,
+        DivProc => "div_proc": Str,
+        StkDiv => "stk_div": Float,
+        StkBoRate => "stk_bo_rate": Float,
+        StkCoRate => "stk_co_rate": Float,
+        CashDiv => "cash_div": Float,
+        CashDivTax => "cash_div_tax": Float,
+        RecordDate => "record_date": Date,
+        ExDate => "ex_date": Date,
+        PayDate => "pay_date": Date,
+        DivListdate => "div_listdate": Date,
+        ImpAnnDate => "imp_ann_date": Date,
+        BaseDate => "base_date": Date,
+        BaseShare => "base_share": Float,
+    }
+}
+
+api_fields! {
+    /// Fields of index_daily
+    index_daily {
+        TsCode => "ts_code": Str,
+        TradeDate => "trade_date": Date,
+        Close => "close": Float,
+        Open => "open": Float,
+        High => "high": Float,
+        Low => "low": Float,
+        PreClose => "pre_close": Float,
+        Change => "change": Float,
+        PctChg => "pct_chg": Float,
+        Vol => "vol": Float,
+        Amount => "amount": Float,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[test]
+    fn test_fields() {
+        assert_eq!(daily::ALL, "ts_code,trade_date,open,high,low,close,pre_close,change,pct_chg,vol,amount");
+        assert_eq!(adj_factor::Field::AdjFactor.as_str(), "adj_factor");
+        assert_eq!(join(&[trade_cal::Field::CalDate, trade_cal::Field::IsOpen]), "cal_date,is_open");
+        let tushare = crate::Tushare::new("<token here>");
+        let query = tushare.querybuilder("daily").select(&[DailyField::Open, DailyField::Close]);
+        assert_eq!(query.preview()["fields"], "open,close");
+        let query = tushare.querybuilder("daily").fields("ts_code,clsoe");
+        assert!(matches!(query.validate(), Err(crate::TushareError::InvalidParams(msg)) if msg.ends_with("clsoe, turn off schema_registry() if they are new")));
+        assert!(query.schema_registry(false).validate().is_ok());
+        assert!(tushare.querybuilder("cyq_perf").fields("anything").validate().is_ok());
+    }
+}
@@ -0,0 +1,271 @@
+//! Helpers of the financial statement apis: income, balancesheet, cashflow and fina_indicator.
+//! Tushare returns every revision of a report as a separate row (update_flag 0/1),
+//! use [one_row_per_period] to keep the latest revision of each report period,
+//! or [point_in_time] to align them to daily prices as announced.
+use crate::api::TushareApi;
+use crate::code::check_codes;
+use crate::error::TushareError;
+use crate::params::ReportType;
+use crate::transform;
+use crate::tushare::Tushare;
+use chrono::NaiveDate;
+use polars::prelude::*;
+use std::ops::RangeInclusive;
+
+/// Fields returned by Tushare::income()
+pub const INCOME_FIELDS: &str = "ts_code,ann_date,f_ann_date,end_date,report_type,comp_type,\
+basic_eps,diluted_eps,total_revenue,revenue,total_cogs,oper_cost,sell_exp,admin_exp,fin_exp,\
+operate_profit,total_profit,income_tax,n_income,n_income_attr_p,ebit,ebitda,update_flag";
+
+/// Fields returned by Tushare::balancesheet()
+pub const BALANCESHEET_FIELDS: &str = "ts_code,ann_date,f_ann_date,end_date,report_type,comp_type,\
+total_share,cap_rese,undistr_porfit,surplus_rese,money_cap,accounts_receiv,inventories,\
+total_cur_assets,fix_assets,total_nca,total_assets,st_borr,total_cur_liab,lt_borr,total_ncl,\
+total_liab,total_hldr_eqy_exc_min_int,total_liab_hldr_eqy,update_flag";
+
+/// Fields returned by Tushare::cashflow()
+pub const CASHFLOW_FIELDS: &str = "ts_code,ann_date,f_ann_date,end_date,report_type,comp_type,\
+net_profit,c_fr_sale_sg,n_cashflow_act,c_pay_acq_const_fiolta,n_cashflow_inv_act,\
+n_cash_flows_fnc_act,n_incr_cash_cash_equ,c_cash_equ_beg_period,c_cash_equ_end_period,\
+free_cashflow,update_flag";
+
+/// Fields returned by Tushare::fina_indicator()
+pub const FINA_INDICATOR_FIELDS: &str = "ts_code,ann_date,end_date,eps,dt_eps,bps,ocfps,cfps,\
+roe,roe_dt,roa,grossprofit_margin,netprofit_margin,debt_to_assets,current_ratio,quick_ratio,\
+assets_turn,netprofit_yoy,or_yoy,update_flag";
+
+/// Columns of the statements which are not numeric
+const NON_NUMERIC: &[&str] = &[
+    "ts_code",
+    "ann_date",
+    "f_ann_date",
+    "end_date",
+    "report_type",
+    "comp_type",
+    "update_flag",
+];
+
+/// Keep the latest revision of every report period of every ts_code (if any), sorted by ts_code and end_date ascending.
+/// The latest revision is the one with update_flag 1, and then the latest ann_date.
+pub fn one_row_per_period(df: DataFrame) -> PolarsResult<DataFrame> {
+    let names = df.get_column_names();
+    let keys: Vec<String> = ["ts_code", "end_date"]
+        .into_iter()
+        .filter(|c| *c == "end_date" || names.contains(c))
+        .map(String::from)
+        .collect();
+    let by: Vec<&str> = ["ts_code", "end_date", "update_flag", "f_ann_date", "ann_date"]
+        .into_iter()
+        .filter(|c| names.contains(c))
+        .collect();
+    df.sort(by, SortMultipleOptions::default())?
+        .unique_stable(Some(&keys), UniqueKeepStrategy::Last, None)
+}
+
+/// Temporary column of point_in_time(), the first day a statement can be used
+const AVAILABLE_DATE: &str = "__available_date";
+
+/// Align the statements to the daily prices as announced, producing a point-in-time panel for backtests.
+/// Every price row gets the statement of the latest report period announced before its trade_date,
+/// i.e. a report is used from the day after f_ann_date (or ann_date if missing), never by its end_date.
+/// A late revision of an older period is ignored once a newer period is announced,
+/// and price rows before the first announcement get nulls.
+/// Both frames need ts_code, prices need trade_date, and statements need end_date and the announcement date,
+/// either as *YYYYMMDD* strings or Date. Statement columns also in prices get a "_right" suffix.
+/// ```no_run
+/// # fn main() -> Result<(), tushare::TushareError> {
+/// use chrono::NaiveDate;
+/// use tushare::{finance::point_in_time, ReportType};
+/// let tushare = tushare::Tushare::from_env()?;
+/// let periods = NaiveDate::from_ymd_opt(2022, 1, 1).unwrap()..=NaiveDate::from_ymd_opt(2023, 12, 31).unwrap();
+/// let income = tushare.income("000001.SZ", periods, ReportType::Consolidated)?;
+/// let prices = tushare.querybuilder("daily").addparam("ts_code", "000001.SZ").addparam("start_date", "20230101").query_all()?;
+/// let panel = point_in_time(prices, income)?;
+/// # Ok(())
+/// # }
+/// ```
+pub fn point_in_time(prices: DataFrame, statements: DataFrame) -> Result<DataFrame, TushareError> {
+    let ann_date = match statements.column("f_ann_date") {
+        Ok(_) => "f_ann_date",
+        Err(_) => "ann_date",
+    };
+    let prices = transform::parse_dates(prices, &["trade_date".to_string()])?
+        .sort(["trade_date"], SortMultipleOptions::default())?;
+    let statements = transform::parse_dates(statements, &[ann_date.to_string(), "end_date".to_string()])?
+        .lazy()
+        .with_column(
+            (col(ann_date).cast(DataType::Int32) + lit(1))
+                .cast(DataType::Date)
+                .alias(AVAILABLE_DATE),
+        )
+        .filter(col(AVAILABLE_DATE).is_not_null())
+        .sort(["ts_code", AVAILABLE_DATE, "end_date"], SortMultipleOptions::default())
+        // drop the revisions of older periods announced after a newer period
+        .filter(col("end_date").eq(col("end_date").cum_max(false).over([col("ts_code")])))
+        .collect()?
+        .unique_stable(
+            Some(&["ts_code".to_string(), AVAILABLE_DATE.to_string()]),
+            UniqueKeepStrategy::Last,
+            None,
+        )?
+        .sort([AVAILABLE_DATE], SortMultipleOptions::default())?;
+    let panel = prices
+        .join_asof_by(
+            &statements,
+            "trade_date",
+            AVAILABLE_DATE,
+            ["ts_code"],
+            ["ts_code"],
+            AsofStrategy::Backward,
+            None,
+        )?
+        .drop(AVAILABLE_DATE)?
+        .sort(["ts_code", "trade_date"], SortMultipleOptions::default())?;
+    Ok(panel)
+}
+
+impl Tushare {
+    /// Fetch a statement api of a stock, filtered by the report period end_date within periods
+    fn statement(
+        &self,
+        api: TushareApi,
+        fields: &str,
+        ts_code: impl AsRef<str>,
+        periods: RangeInclusive<impl Into<NaiveDate>>,
+        report_type: Option<ReportType>,
+    ) -> Result<DataFrame, TushareError> {
+        check_codes(ts_code.as_ref())?;
+        let (start, end) = periods.into_inner();
+        let (start, end): (NaiveDate, NaiveDate) = (start.into(), end.into());
+        let dtypes: Vec<(&str, DataType)> = fields
+            .split(',')
+            .filter(|f| !NON_NUMERIC.contains(f))
+            .map(|f| (f, DataType::Float64))
+            .collect();
+        let mut query = self
+            .querybuilder(api)
+            .addparam("ts_code", ts_code.as_ref())
+            .fields(fields)
+            .dtypes(&dtypes)
+            .parse_dates(true)
+            .allow_empty(true);
+        if let Some(report_type) = report_type {
+            query = query.addparam("report_type", report_type);
+        }
+        // start_date/end_date of tushare filter the announcement date, not the report period,
+        // so the periods are filtered here. The whole history of a stock is only a few pages.
+        let df = query
+            .query_all()?
+            .lazy()
+            .filter(
+                col("end_date")
+                    .gt_eq(lit(start))
+                    .and(col("end_date").lt_eq(lit(end))),
+            )
+            .sort(["end_date"], SortMultipleOptions::default())
+            .collect()?;
+        Ok(df)
+    }
+
+    /// Income statements of a stock with the report period end_date within periods,
+    /// a range of dates or of [ReportPeriod](crate::ReportPeriod), e.g. `ReportPeriod::Q1(2020)..=ReportPeriod::Annual(2023)`.
+    /// Every revision of a report is returned, see [one_row_per_period].
+    /// ```no_run
+    /// # fn main() -> Result<(), tushare::TushareError> {
+    /// use chrono::NaiveDate;
+    /// use tushare::{finance::one_row_per_period, ReportType};
+    /// let tushare = tushare::Tushare::from_env()?;
+    /// let periods = NaiveDate::from_ymd_opt(2020, 1, 1).unwrap()..=NaiveDate::from_ymd_opt(2023, 12, 31).unwrap();
+    /// let df = one_row_per_period(tushare.income("000001.SZ", periods, ReportType::Consolidated)?)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn income(
+        &self,
+        ts_code: impl AsRef<str>,
+        periods: RangeInclusive<impl Into<NaiveDate>>,
+        report_type: ReportType,
+    ) -> Result<DataFrame, TushareError> {
+        self.statement(TushareApi::Income, INCOME_FIELDS, ts_code, periods, Some(report_type))
+    }
+
+    /// Balance sheets of a stock with the report period end_date within periods, see income()
+    pub fn balancesheet(
+        &self,
+        ts_code: impl AsRef<str>,
+        periods: RangeInclusive<impl Into<NaiveDate>>,
+        report_type: ReportType,
+    ) -> Result<DataFrame, TushareError> {
+        self.statement(TushareApi::BalanceSheet, BALANCESHEET_FIELDS, ts_code, periods, Some(report_type))
+    }
+
+    /// Cash flow statements of a stock with the report period end_date within periods, see income()
+    pub fn cashflow(
+        &self,
+        ts_code: impl AsRef<str>,
+        periods: RangeInclusive<impl Into<NaiveDate>>,
+        report_type: ReportType,
+    ) -> Result<DataFrame, TushareError> {
+        self.statement(TushareApi::CashFlow, CASHFLOW_FIELDS, ts_code, periods, Some(report_type))
+    }
+
+    /// Financial indicators of a stock with the report period end_date within periods.
+    /// They are computed from the consolidated statements, so there is no report type.
+    pub fn fina_indicator(
+        &self,
+        ts_code: impl AsRef<str>,
+        periods: RangeInclusive<impl Into<NaiveDate>>,
+    ) -> Result<DataFrame, TushareError> {
+        self.statement(TushareApi::FinaIndicator, FINA_INDICATOR_FIELDS, ts_code, periods, None)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[test]
+    fn test_one_row_per_period() {
+        let df = df!(
+            "end_date" => ["20231231", "20230930", "20231231"],
+            "update_flag" => ["1", "0", "0"],
+            "n_income" => [3.0, 1.0, 2.0]
+        )
+        .unwrap();
+        let df = one_row_per_period(df).unwrap();
+        assert_eq!(df.column("n_income").unwrap().f64().unwrap().to_vec(), [Some(1.0), Some(3.0)]);
+    }
+
+    #[test]
+    fn test_one_row_per_period_of_codes() {
+        let df = df!(
+            "ts_code" => ["600000.SH", "000001.SZ", "600000.SH", "000001.SZ"],
+            "end_date" => ["20231231", "20231231", "20231231", "20230930"],
+            "update_flag" => ["1", "1", "0", "1"],
+            "n_income" => [3.0, 4.0, 2.0, 1.0]
+        )
+        .unwrap();
+        let df = one_row_per_period(df).unwrap();
+        assert_eq!(df.column("n_income").unwrap().f64().unwrap().to_vec(), [Some(1.0), Some(4.0), Some(3.0)]);
+    }
+
+    #[test]
+    fn test_point_in_time() {
+        let prices = df!(
+            "ts_code" => ["000001.SZ"; 4],
+            "trade_date" => ["20231025", "20231026", "20231102", "20240321"],
+            "close" => [10.0, 10.1, 10.2, 10.3]
+        )
+        .unwrap();
+        let statements = df!(
+            "ts_code" => ["000001.SZ"; 3],
+            "ann_date" => ["20231025", "20231101", "20240320"],
+            "end_date" => ["20230930", "20221231", "20231231"],
+            "n_income" => [3.0, 9.0, 4.0]
+        )
+        .unwrap();
+        let panel = point_in_time(prices, statements).unwrap();
+        let n_income = panel.column("n_income").unwrap().f64().unwrap().to_vec();
+        // not known on the announcement day, the late 2022 revision is ignored
+        assert_eq!(n_income, [None, Some(3.0), Some(3.0), Some(4.0)]);
+    }
+}
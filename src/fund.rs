@@ -0,0 +1,225 @@
+//! Helpers of the fund apis, both exchange traded funds and mutual funds
+use crate::api::TushareApi;
+use crate::error::TushareError;
+use crate::params::{FundMarket, FundStatus};
+use crate::stock::{float_dtypes, DAILY_FIELDS, DAILY_NUMERIC};
+use crate::tushare::Tushare;
+use chrono::NaiveDate;
+use polars::prelude::*;
+use std::ops::RangeInclusive;
+
+/// Fields returned by Tushare::fund_basic()
+pub const FUND_BASIC_FIELDS: &str = "ts_code,name,management,custodian,fund_type,found_date,due_date,\
+list_date,issue_date,delist_date,issue_amount,m_fee,c_fee,duration_year,p_value,min_amount,\
+benchmark,status,invest_type,type,market";
+
+/// Date columns of fund_basic, most of them are not in the known date columns
+const FUND_BASIC_DATES: &[&str] = &["found_date", "due_date", "list_date", "issue_date", "delist_date"];
+
+/// Numeric columns of fund_basic
+const FUND_BASIC_NUMERIC: &[&str] = &["issue_amount", "m_fee", "c_fee", "duration_year", "p_value", "min_amount"];
+
+/// Fields returned by Tushare::fund_nav()
+pub const FUND_NAV_FIELDS: &str = "ts_code,ann_date,nav_date,unit_nav,accum_nav,accum_div,net_asset,total_netasset,adj_nav";
+
+/// Numeric columns of fund_nav
+const FUND_NAV_NUMERIC: &[&str] = &["unit_nav", "accum_nav", "accum_div", "net_asset", "total_netasset", "adj_nav"];
+
+/// Fields returned by Tushare::fund_portfolio()
+pub const FUND_PORTFOLIO_FIELDS: &str = "ts_code,ann_date,end_date,symbol,mkv,amount,stk_mkv_ratio,stk_float_ratio";
+
+/// Numeric columns of fund_portfolio
+const FUND_PORTFOLIO_NUMERIC: &[&str] = &["mkv", "amount", "stk_mkv_ratio", "stk_float_ratio"];
+
+impl Tushare {
+    /// The fund list, one row per fund, with the dates as Date and the fees/amounts as f64.
+    /// Every filter is optional.
+    pub fn fund_basic(
+        &self,
+        market: Option<FundMarket>,
+        status: Option<FundStatus>,
+    ) -> Result<DataFrame, TushareError> {
+        let mut query = self
+            .querybuilder(TushareApi::FundBasic)
+            .fields(FUND_BASIC_FIELDS)
+            .dtypes(&float_dtypes(FUND_BASIC_NUMERIC))
+            .parse_date_columns(FUND_BASIC_DATES);
+        if let Some(market) = market {
+            query = query.addparam("market", market);
+        }
+        if let Some(status) = status {
+            query = query.addparam("status", status);
+        }
+        query.query_all()
+    }
+
+    /// Net asset values of a fund with nav_date within range, sorted by nav_date ascending
+    /// ```no_run
+    /// # fn main() -> Result<(), tushare::TushareError> {
+    /// use chrono::NaiveDate;
+    /// let tushare = tushare::Tushare::from_env()?;
+    /// let range = NaiveDate::from_ymd_opt(2024, 1, 1).unwrap()..=NaiveDate::from_ymd_opt(2024, 4, 30).unwrap();
+    /// let nav = tushare.fund_nav("510300.SH", range)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn fund_nav(
+        &self,
+        ts_code: impl AsRef<str>,
+        range: RangeInclusive<NaiveDate>,
+    ) -> Result<DataFrame, TushareError> {
+        let df = self
+            .querybuilder(TushareApi::FundNav)
+            .addparam("ts_code", ts_code.as_ref())
+            .start_date(range.start())
+            .end_date(range.end())
+            .fields(FUND_NAV_FIELDS)
+            .dtypes(&float_dtypes(FUND_NAV_NUMERIC))
+            .parse_dates(true)
+            .query_all()?;
+        Ok(df.sort(["nav_date"], SortMultipleOptions::default())?)
+    }
+
+    /// Holdings of a fund announced within range, one row per stock and report period end_date.
+    /// mkv is the market value in yuan, amount the number of shares.
+    pub fn fund_portfolio(
+        &self,
+        ts_code: impl AsRef<str>,
+        range: RangeInclusive<NaiveDate>,
+    ) -> Result<DataFrame, TushareError> {
+        let df = self
+            .querybuilder(TushareApi::FundPortfolio)
+            .addparam("ts_code", ts_code.as_ref())
+            .start_date(range.start())
+            .end_date(range.end())
+            .fields(FUND_PORTFOLIO_FIELDS)
+            .dtypes(&float_dtypes(FUND_PORTFOLIO_NUMERIC))
+            .parse_dates(true)
+            .query_all()?;
+        Ok(df.sort(["end_date", "symbol"], SortMultipleOptions::default())?)
+    }
+
+    /// Daily bars of an exchange traded fund within range, same columns as Tushare::daily()
+    pub fn fund_daily(
+        &self,
+        ts_code: impl AsRef<str>,
+        range: RangeInclusive<NaiveDate>,
+    ) -> Result<DataFrame, TushareError> {
+        let df = self
+            .querybuilder(TushareApi::FundDaily)
+            .addparam("ts_code", ts_code.as_ref())
+            .start_date(range.start())
+            .end_date(range.end())
+            .fields(DAILY_FIELDS)
+            .dtypes(&float_dtypes(DAILY_NUMERIC))
+            .parse_dates(true)
+            .query_all()?;
+        Ok(df.sort(["trade_date"], SortMultipleOptions::default())?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::transport::MockTransport;
+    use serde_json::{json, Value};
+    use std::sync::Arc;
+
+    fn day(d: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(2024, 1, d).unwrap()
+    }
+
+    /// A row of the fields with integer numbers, so the cast to Float64 is checked, and 20240102 as every date
+    fn row(fields: &str, numeric: &[&str], values: &[(&str, Value)]) -> Vec<Value> {
+        fields
+            .split(',')
+            .map(|f| match values.iter().find(|(name, _)| *name == f) {
+                Some((_, value)) => value.clone(),
+                None if numeric.contains(&f) => json!(1),
+                None if f.ends_with("_date") => json!("20240102"),
+                None => json!("x"),
+            })
+            .collect()
+    }
+
+    fn transport(api: &str, fields: &str, rows: Vec<Vec<Value>>) -> Arc<MockTransport> {
+        let fields: Vec<&str> = fields.split(',').collect();
+        Arc::new(MockTransport::new().respond(api, MockTransport::data(&fields, rows)))
+    }
+
+    fn assert_columns(df: &DataFrame, numeric: &[&str], dates: &[&str]) {
+        for column in numeric {
+            assert_eq!(df.column(column).unwrap().dtype(), &DataType::Float64, "{column}");
+        }
+        for column in dates {
+            assert_eq!(df.column(column).unwrap().dtype(), &DataType::Date, "{column}");
+        }
+    }
+
+    fn dates(df: &DataFrame, column: &str) -> Vec<NaiveDate> {
+        df.column(column).unwrap().date().unwrap().as_date_iter().flatten().collect()
+    }
+
+    #[test]
+    fn test_fund_basic() {
+        let mock = transport("fund_basic", FUND_BASIC_FIELDS, vec![row(FUND_BASIC_FIELDS, FUND_BASIC_NUMERIC, &[])]);
+        let tushare = Tushare::new("<token here>").with_transport(mock.clone());
+        let df = tushare.fund_basic(Some(FundMarket::Exchange), Some(FundStatus::Listed)).unwrap();
+        tushare.fund_basic(Some(FundMarket::Otc), None).unwrap();
+        tushare.fund_basic(None, Some(FundStatus::Delisted)).unwrap();
+        let requests = mock.requests();
+        assert_eq!(requests[0]["params"]["market"], "E");
+        assert_eq!(requests[0]["params"]["status"], "L");
+        assert_eq!(requests[1]["params"]["market"], "O");
+        assert!(requests[1]["params"].get("status").is_none());
+        assert!(requests[2]["params"].get("market").is_none());
+        assert_eq!(requests[2]["params"]["status"], "D");
+        assert_columns(&df, FUND_BASIC_NUMERIC, FUND_BASIC_DATES);
+    }
+
+    #[test]
+    fn test_fund_nav() {
+        let nav = |nav_date: &str| row(FUND_NAV_FIELDS, FUND_NAV_NUMERIC, &[("nav_date", json!(nav_date))]);
+        let mock = transport("fund_nav", FUND_NAV_FIELDS, vec![nav("20240103"), nav("20240102")]);
+        let tushare = Tushare::new("<token here>").with_transport(mock.clone());
+        let df = tushare.fund_nav("510300.SH", day(2)..=day(3)).unwrap();
+        let params = &mock.requests()[0]["params"];
+        assert_eq!(params["ts_code"], "510300.SH");
+        assert_eq!(params["start_date"], "20240102");
+        assert_eq!(params["end_date"], "20240103");
+        assert_columns(&df, FUND_NAV_NUMERIC, &["ann_date", "nav_date"]);
+        assert_eq!(dates(&df, "nav_date"), [day(2), day(3)]);
+    }
+
+    #[test]
+    fn test_fund_portfolio() {
+        let holding = |end_date: &str, symbol: &str| {
+            row(FUND_PORTFOLIO_FIELDS, FUND_PORTFOLIO_NUMERIC, &[("end_date", json!(end_date)), ("symbol", json!(symbol))])
+        };
+        let rows = vec![holding("20231231", "600519.SH"), holding("20230930", "600519.SH"), holding("20231231", "000858.SZ")];
+        let mock = transport("fund_portfolio", FUND_PORTFOLIO_FIELDS, rows);
+        let tushare = Tushare::new("<token here>").with_transport(mock.clone());
+        let df = tushare.fund_portfolio("510300.SH", day(1)..=day(31)).unwrap();
+        let params = &mock.requests()[0]["params"];
+        assert_eq!(params["ts_code"], "510300.SH");
+        assert_eq!(params["start_date"], "20240101");
+        assert_eq!(params["end_date"], "20240131");
+        assert_columns(&df, FUND_PORTFOLIO_NUMERIC, &["ann_date", "end_date"]);
+        let symbols: Vec<_> = df.column("symbol").unwrap().str().unwrap().into_iter().flatten().collect();
+        assert_eq!(symbols, ["600519.SH", "000858.SZ", "600519.SH"]);
+    }
+
+    #[test]
+    fn test_fund_daily() {
+        let bar = |trade_date: &str| row(DAILY_FIELDS, DAILY_NUMERIC, &[("trade_date", json!(trade_date))]);
+        let mock = transport("fund_daily", DAILY_FIELDS, vec![bar("20240103"), bar("20240102")]);
+        let tushare = Tushare::new("<token here>").with_transport(mock.clone());
+        let df = tushare.fund_daily("510300.SH", day(2)..=day(3)).unwrap();
+        let request = &mock.requests()[0];
+        assert_eq!(request["params"]["ts_code"], "510300.SH");
+        assert_eq!(request["params"]["start_date"], "20240102");
+        assert_eq!(request["fields"], DAILY_FIELDS);
+        assert_columns(&df, DAILY_NUMERIC, &["trade_date"]);
+        assert_eq!(dates(&df, "trade_date"), [day(2), day(3)]);
+    }
+}
@@ -0,0 +1,232 @@
+//! Helpers of the shareholder apis, for ownership and insider trading analysis.
+//! The share amounts are in shares and the ratios in percent.
+use crate::api::TushareApi;
+use crate::code::check_codes;
+use crate::error::TushareError;
+use crate::params::HolderTradeType;
+use crate::stock::float_dtypes;
+use crate::tushare::Tushare;
+use chrono::NaiveDate;
+use polars::prelude::*;
+use std::ops::RangeInclusive;
+
+/// Fields returned by Tushare::top10_holders() and top10_floatholders()
+pub const TOP10_HOLDERS_FIELDS: &str =
+    "ts_code,ann_date,end_date,holder_name,hold_amount,hold_ratio,hold_float_ratio,hold_change,holder_type";
+
+/// Numeric columns of top10_holders and top10_floatholders
+const TOP10_HOLDERS_NUMERIC: &[&str] = &["hold_amount", "hold_ratio", "hold_float_ratio", "hold_change"];
+
+/// Fields returned by Tushare::stk_holdertrade()
+pub const HOLDER_TRADE_FIELDS: &str = "ts_code,ann_date,holder_name,holder_type,in_de,change_vol,change_ratio,\
+after_share,after_ratio,avg_price,total_share,begin_date,close_date";
+
+/// Numeric columns of stk_holdertrade
+const HOLDER_TRADE_NUMERIC: &[&str] =
+    &["change_vol", "change_ratio", "after_share", "after_ratio", "avg_price", "total_share"];
+
+/// Date columns of stk_holdertrade, begin_date and close_date are not in the known date columns
+const HOLDER_TRADE_DATES: &[&str] = &["ann_date", "begin_date", "close_date"];
+
+/// Fields returned by Tushare::pledge_stat()
+pub const PLEDGE_STAT_FIELDS: &str = "ts_code,end_date,pledge_count,unrest_pledge,rest_pledge,total_share,pledge_ratio";
+
+/// Numeric columns of pledge_stat, the pledged shares are in 10 thousand shares
+const PLEDGE_STAT_NUMERIC: &[&str] = &["pledge_count", "unrest_pledge", "rest_pledge", "total_share", "pledge_ratio"];
+
+impl Tushare {
+    /// Top 10 shareholders of a stock announced within range, one row per holder and report period end_date.
+    /// Sorted by end_date ascending and hold_amount descending.
+    pub fn top10_holders(
+        &self,
+        ts_code: impl AsRef<str>,
+        range: RangeInclusive<NaiveDate>,
+    ) -> Result<DataFrame, TushareError> {
+        self.holders(TushareApi::Top10Holders, ts_code.as_ref(), range)
+    }
+
+    /// Top 10 holders of the floating shares of a stock announced within range, same columns as top10_holders()
+    pub fn top10_floatholders(
+        &self,
+        ts_code: impl AsRef<str>,
+        range: RangeInclusive<NaiveDate>,
+    ) -> Result<DataFrame, TushareError> {
+        self.holders(TushareApi::Top10FloatHolders, ts_code.as_ref(), range)
+    }
+
+    fn holders(
+        &self,
+        api: TushareApi,
+        ts_code: &str,
+        range: RangeInclusive<NaiveDate>,
+    ) -> Result<DataFrame, TushareError> {
+        check_codes(ts_code)?;
+        let df = self
+            .querybuilder(api)
+            .addparam("ts_code", ts_code)
+            .start_date(range.start())
+            .end_date(range.end())
+            .fields(TOP10_HOLDERS_FIELDS)
+            .dtypes(&float_dtypes(TOP10_HOLDERS_NUMERIC))
+            .parse_dates(true)
+            .query_all()?;
+        let options = SortMultipleOptions::default().with_order_descendings([false, true]);
+        Ok(df.sort(["end_date", "hold_amount"], options)?)
+    }
+
+    /// Share increases and decreases of the major holders and executives announced within range,
+    /// of every stock if ts_code is None, sorted by ann_date ascending.
+    /// in_de is "IN" for an increase and "DE" for a decrease, filter them with trade_type.
+    /// ```no_run
+    /// # fn main() -> Result<(), tushare::TushareError> {
+    /// use chrono::NaiveDate;
+    /// let tushare = tushare::Tushare::from_env()?;
+    /// let april = NaiveDate::from_ymd_opt(2024, 4, 1).unwrap()..=NaiveDate::from_ymd_opt(2024, 4, 30).unwrap();
+    /// let sells = tushare.stk_holdertrade(None, april, Some(tushare::HolderTradeType::Decrease))?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn stk_holdertrade(
+        &self,
+        ts_code: Option<&str>,
+        range: RangeInclusive<NaiveDate>,
+        trade_type: Option<HolderTradeType>,
+    ) -> Result<DataFrame, TushareError> {
+        let mut query = self
+            .querybuilder(TushareApi::StkHolderTrade)
+            .start_date(range.start())
+            .end_date(range.end())
+            .fields(HOLDER_TRADE_FIELDS)
+            .dtypes(&float_dtypes(HOLDER_TRADE_NUMERIC))
+            .parse_date_columns(HOLDER_TRADE_DATES)
+            .allow_empty(true);
+        if let Some(ts_code) = ts_code {
+            check_codes(ts_code)?;
+            query = query.addparam("ts_code", ts_code);
+        }
+        if let Some(trade_type) = trade_type {
+            query = query.addparam("trade_type", trade_type);
+        }
+        Ok(query.query_all()?.sort(["ann_date"], SortMultipleOptions::default())?)
+    }
+
+    /// Share pledge statistics of a stock by end_date, sorted by end_date ascending.
+    /// pledge_ratio is the pledged shares in percent of the total shares.
+    pub fn pledge_stat(&self, ts_code: impl AsRef<str>) -> Result<DataFrame, TushareError> {
+        check_codes(ts_code.as_ref())?;
+        let df = self
+            .querybuilder(TushareApi::PledgeStat)
+            .addparam("ts_code", ts_code.as_ref())
+            .fields(PLEDGE_STAT_FIELDS)
+            .dtypes(&float_dtypes(PLEDGE_STAT_NUMERIC))
+            .parse_dates(true)
+            .allow_empty(true)
+            .query_all()?;
+        Ok(df.sort(["end_date"], SortMultipleOptions::default())?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::transport::MockTransport;
+    use serde_json::{json, Value};
+    use std::sync::Arc;
+
+    fn day(d: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(2024, 4, d).unwrap()
+    }
+
+    /// A row of the fields with integer numbers, so the cast to Float64 is checked
+    fn row(fields: &str, numeric: &[&str], values: &[(&str, Value)]) -> Vec<Value> {
+        fields
+            .split(',')
+            .map(|f| match values.iter().find(|(name, _)| *name == f) {
+                Some((_, value)) => value.clone(),
+                None if numeric.contains(&f) => json!(1),
+                None => json!("x"),
+            })
+            .collect()
+    }
+
+    fn transport(api: &str, fields: &str, rows: Vec<Vec<Value>>) -> Arc<MockTransport> {
+        let fields: Vec<&str> = fields.split(',').collect();
+        Arc::new(MockTransport::new().respond(api, MockTransport::data(&fields, rows)))
+    }
+
+    fn assert_columns(df: &DataFrame, numeric: &[&str], dates: &[&str]) {
+        for column in numeric {
+            assert_eq!(df.column(column).unwrap().dtype(), &DataType::Float64, "{column}");
+        }
+        for column in dates {
+            assert_eq!(df.column(column).unwrap().dtype(), &DataType::Date, "{column}");
+        }
+    }
+
+    #[test]
+    fn test_top10_holders() {
+        let holder = |end_date: &str, amount: i64| {
+            let values = [("ts_code", json!("600000.SH")), ("ann_date", json!("20240420")), ("end_date", json!(end_date)), ("hold_amount", json!(amount))];
+            row(TOP10_HOLDERS_FIELDS, TOP10_HOLDERS_NUMERIC, &values)
+        };
+        let rows = vec![holder("20240331", 100), holder("20231231", 200), holder("20240331", 300)];
+        for api in ["top10_holders", "top10_floatholders"] {
+            let mock = transport(api, TOP10_HOLDERS_FIELDS, rows.clone());
+            let tushare = Tushare::new("<token here>").with_transport(mock.clone());
+            let df = match api {
+                "top10_holders" => tushare.top10_holders("600000.SH", day(1)..=day(30)),
+                _ => tushare.top10_floatholders("600000.SH", day(1)..=day(30)),
+            }
+            .unwrap();
+            let request = &mock.requests()[0];
+            assert_eq!(request["api_name"], api);
+            assert_eq!(request["params"]["ts_code"], "600000.SH");
+            assert_eq!(request["params"]["start_date"], "20240401");
+            assert_eq!(request["params"]["end_date"], "20240430");
+            assert_columns(&df, TOP10_HOLDERS_NUMERIC, &["ann_date", "end_date"]);
+            let amounts: Vec<_> = df.column("hold_amount").unwrap().f64().unwrap().into_iter().flatten().collect();
+            assert_eq!(amounts, [200.0, 300.0, 100.0]);
+        }
+        let tushare = Tushare::new("<token here>").with_transport(transport("top10_holders", TOP10_HOLDERS_FIELDS, rows));
+        assert!(tushare.top10_holders("600000", day(1)..=day(30)).is_err());
+    }
+
+    #[test]
+    fn test_stk_holdertrade() {
+        let trade = |ann_date: &str, close_date: Value| {
+            let values = [("ts_code", json!("600000.SH")), ("ann_date", json!(ann_date)), ("in_de", json!("DE")), ("begin_date", json!("20240401")), ("close_date", close_date)];
+            row(HOLDER_TRADE_FIELDS, HOLDER_TRADE_NUMERIC, &values)
+        };
+        let mock = transport("stk_holdertrade", HOLDER_TRADE_FIELDS, vec![trade("20240420", json!(null)), trade("20240410", json!("20240409"))]);
+        let tushare = Tushare::new("<token here>").with_transport(mock.clone());
+        let df = tushare.stk_holdertrade(Some("600000.SH"), day(1)..=day(30), Some(HolderTradeType::Decrease)).unwrap();
+        tushare.stk_holdertrade(None, day(1)..=day(30), Some(HolderTradeType::Increase)).unwrap();
+        tushare.stk_holdertrade(None, day(1)..=day(30), None).unwrap();
+        let requests = mock.requests();
+        assert_eq!(requests[0]["params"]["ts_code"], "600000.SH");
+        assert_eq!(requests[0]["params"]["trade_type"], "DE");
+        assert_eq!(requests[0]["params"]["start_date"], "20240401");
+        assert_eq!(requests[1]["params"]["trade_type"], "IN");
+        assert!(requests[1]["params"].get("ts_code").is_none());
+        assert!(requests[2]["params"].get("trade_type").is_none());
+        assert_columns(&df, HOLDER_TRADE_NUMERIC, HOLDER_TRADE_DATES);
+        let dates: Vec<_> = df.column("ann_date").unwrap().date().unwrap().as_date_iter().flatten().collect();
+        assert_eq!(dates, [day(10), day(20)]);
+
+        let empty = transport("stk_holdertrade", HOLDER_TRADE_FIELDS, vec![]);
+        let df = Tushare::new("<token here>").with_transport(empty).stk_holdertrade(None, day(1)..=day(30), None).unwrap();
+        assert_eq!(df.height(), 0);
+    }
+
+    #[test]
+    fn test_pledge_stat() {
+        let stat = |end_date: &str| row(PLEDGE_STAT_FIELDS, PLEDGE_STAT_NUMERIC, &[("ts_code", json!("600000.SH")), ("end_date", json!(end_date))]);
+        let mock = transport("pledge_stat", PLEDGE_STAT_FIELDS, vec![stat("20240419"), stat("20240412")]);
+        let tushare = Tushare::new("<token here>").with_transport(mock.clone());
+        let df = tushare.pledge_stat("600000.SH").unwrap();
+        assert_eq!(mock.requests()[0]["params"]["ts_code"], "600000.SH");
+        assert_columns(&df, PLEDGE_STAT_NUMERIC, &["end_date"]);
+        let dates: Vec<_> = df.column("end_date").unwrap().date().unwrap().as_date_iter().flatten().collect();
+        assert_eq!(dates, [day(12), day(19)]);
+    }
+}
@@ -0,0 +1,235 @@
+//! Helpers of the index constituent apis
+use crate::analysis::{per_code, simple_return};
+use crate::api::TushareApi;
+use crate::date::IntoTushareDate;
+use crate::error::TushareError;
+use crate::fields::IndexDailyField;
+use crate::params::Index;
+use crate::tushare::Tushare;
+use chrono::{Duration, Local, NaiveDate};
+use polars::prelude::*;
+use std::ops::RangeInclusive;
+
+/// Fields returned by Tushare::index_weight()
+pub const INDEX_WEIGHT_FIELDS: &str = "index_code,con_code,trade_date,weight";
+
+/// Fields returned by Tushare::index_member()
+pub const INDEX_MEMBER_FIELDS: &str = "index_code,index_name,con_code,con_name,in_date,out_date,is_new";
+
+/// Index weights are published monthly, so the latest ones are within this many days
+const WEIGHT_LOOKBACK_DAYS: i64 = 62;
+
+impl Tushare {
+    /// Constituents and weights (in percent) of an index published on trade_date,
+    /// sorted by weight descending. Weights are published monthly,
+    /// so the result is empty on the other days. Use index_constituents() for the latest ones.
+    /// # index_code
+    /// Index code like "000300.SH", or Index::CSI300.as_str()
+    pub fn index_weight(
+        &self,
+        index_code: &str,
+        trade_date: impl IntoTushareDate,
+    ) -> Result<DataFrame, TushareError> {
+        let df = self
+            .querybuilder(TushareApi::IndexWeight)
+            .addparam("index_code", index_code)
+            .addparam_date("trade_date", trade_date)
+            .fields(INDEX_WEIGHT_FIELDS)
+            .dtypes(&[("weight", DataType::Float64)])
+            .parse_dates(true)
+            .allow_empty(true)
+            .query_all()?;
+        Ok(df.sort(["weight"], SortMultipleOptions::default().with_order_descending(true))?)
+    }
+
+    /// Constituents of a Shenwan industry index, with in_date/out_date as Date.
+    /// Only the current constituents are returned unless history is true.
+    pub fn index_member(&self, index_code: &str, history: bool) -> Result<DataFrame, TushareError> {
+        let mut query = self
+            .querybuilder(TushareApi::IndexMember)
+            .addparam("index_code", index_code)
+            .fields(INDEX_MEMBER_FIELDS)
+            .parse_dates(true);
+        if !history {
+            query = query.addparam("is_new", "Y");
+        }
+        query.query_all()
+    }
+
+    /// The latest published constituents and weights of an index, see index_weight()
+    /// ```no_run
+    /// # fn main() -> Result<(), tushare::TushareError> {
+    /// let tushare = tushare::Tushare::from_env()?;
+    /// let csi300 = tushare.index_constituents(tushare::Index::CSI300)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn index_constituents(&self, index: Index) -> Result<DataFrame, TushareError> {
+        let today = Local::now().date_naive();
+        let df = self
+            .querybuilder(TushareApi::IndexWeight)
+            .addparam("index_code", index)
+            .start_date(today - Duration::days(WEIGHT_LOOKBACK_DAYS))
+            .end_date(today)
+            .fields(INDEX_WEIGHT_FIELDS)
+            .dtypes(&[("weight", DataType::Float64)])
+            .parse_dates(true)
+            .query_all()?;
+        let df = df
+            .lazy()
+            .filter(col("trade_date").eq(col("trade_date").max()))
+            .sort(["weight"], SortMultipleOptions::default().with_order_descending(true))
+            .collect()?;
+        Ok(df)
+    }
+
+    /// Compare the daily bars of stocks with an index, e.g. the result of daily() against 000300.SH.
+    /// index_daily in the range is fetched and joined by trade_date (a string or a Date column),
+    /// adding the columns index_close, ret, index_ret, excess_ret (ret - index_ret)
+    /// and relative_ret, the return relative to the index since the first row of every stock.
+    /// The rows of df_stock are kept, sorted by ts_code and trade_date.
+    /// ```no_run
+    /// # fn main() -> Result<(), tushare::TushareError> {
+    /// use chrono::NaiveDate;
+    /// let tushare = tushare::Tushare::from_env()?;
+    /// let (start, end) = (NaiveDate::from_ymd_opt(2024, 1, 1).unwrap(), NaiveDate::from_ymd_opt(2024, 6, 30).unwrap());
+    /// let daily = tushare.daily("000001.SZ", start, end)?;
+    /// let df = tushare.compare_with_index(&daily, tushare::Index::CSI300.as_str(), start..=end)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn compare_with_index(
+        &self,
+        df_stock: &DataFrame,
+        index_code: &str,
+        range: RangeInclusive<NaiveDate>,
+    ) -> Result<DataFrame, TushareError> {
+        let is_date = df_stock.column("trade_date")?.dtype() == &DataType::Date;
+        let index = self
+            .querybuilder(TushareApi::IndexDaily)
+            .addparam("ts_code", index_code)
+            .start_date(*range.start())
+            .end_date(*range.end())
+            .select(&[IndexDailyField::TradeDate, IndexDailyField::Close])
+            .parse_dates(is_date)
+            .query_all()?
+            .lazy()
+            .select([col("trade_date"), col("close").alias("index_close")]);
+        let by_code = df_stock.get_column_names().contains(&"ts_code");
+        let scope = |e: Expr| match by_code {
+            true => per_code(e),
+            false => e,
+        };
+        let keys: Vec<&str> = match by_code {
+            true => vec!["ts_code", "trade_date"],
+            false => vec!["trade_date"],
+        };
+        let relative = (col("close") / col("close").first()) / (col("index_close") / col("index_close").first()) - lit(1.0);
+        let df = df_stock
+            .clone()
+            .lazy()
+            .join(index, [col("trade_date")], [col("trade_date")], JoinArgs::new(JoinType::Left))
+            .sort(keys, SortMultipleOptions::default())
+            .with_columns([
+                scope(simple_return("close")).alias("ret"),
+                scope(simple_return("index_close")).alias("index_ret"),
+                scope(relative).alias("relative_ret"),
+            ])
+            .with_column((col("ret") - col("index_ret")).alias("excess_ret"))
+            .collect()?;
+        Ok(df)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::transport::MockTransport;
+    use serde_json::json;
+    use std::sync::Arc;
+    #[test]
+    fn test_compare_with_index() {
+        let index = MockTransport::data(
+            &["trade_date", "close"],
+            vec![vec!["20240102".into(), 3000.0.into()], vec!["20240103".into(), 3300.0.into()]],
+        );
+        let tushare = Tushare::new("<token here>").with_transport(MockTransport::new().respond("index_daily", index));
+        let daily = df!("ts_code" => ["000001.SZ", "000001.SZ"], "trade_date" => ["20240103", "20240102"], "close" => [12.5, 10.0]).unwrap();
+        let day = |d| NaiveDate::from_ymd_opt(2024, 1, d).unwrap();
+        let df = tushare.compare_with_index(&daily, "000300.SH", day(2)..=day(3)).unwrap();
+        let excess = df.column("excess_ret").unwrap().f64().unwrap().get(1).unwrap();
+        assert!((excess - 0.15).abs() < 1e-9);
+        let relative = df.column("relative_ret").unwrap().f64().unwrap().get(1).unwrap();
+        assert!((relative - (1.25 / 1.1 - 1.0)).abs() < 1e-9);
+    }
+
+    fn weights(rows: &[(&str, &str, i64)]) -> String {
+        let fields: Vec<&str> = INDEX_WEIGHT_FIELDS.split(',').collect();
+        let rows = rows
+            .iter()
+            .map(|(code, date, weight)| vec![json!("000300.SH"), json!(code), json!(date), json!(weight)])
+            .collect();
+        MockTransport::data(&fields, rows)
+    }
+
+    fn codes(df: &DataFrame, column: &str) -> Vec<String> {
+        df.column(column).unwrap().str().unwrap().into_iter().flatten().map(String::from).collect()
+    }
+
+    #[test]
+    fn test_index_constituents() {
+        // the weights of the previous month are in the lookback window too
+        let body = weights(&[
+            ("600519.SH", "20240329", 6),
+            ("600519.SH", "20240430", 5),
+            ("300750.SZ", "20240430", 3),
+            ("601318.SH", "20240430", 4),
+        ]);
+        let mock = Arc::new(MockTransport::new().respond("index_weight", body));
+        let tushare = Tushare::new("<token here>").with_transport(mock.clone());
+        let df = tushare.index_constituents(Index::CSI300).unwrap();
+        let params = &mock.requests()[0]["params"];
+        let today = Local::now().date_naive();
+        assert_eq!(params["index_code"], "000300.SH");
+        assert_eq!(params["start_date"], (today - Duration::days(WEIGHT_LOOKBACK_DAYS)).format("%Y%m%d").to_string());
+        assert_eq!(params["end_date"], today.format("%Y%m%d").to_string());
+        assert_eq!(codes(&df, "con_code"), ["600519.SH", "601318.SH", "300750.SZ"]);
+        assert_eq!(df.column("weight").unwrap().dtype(), &DataType::Float64);
+        let latest = NaiveDate::from_ymd_opt(2024, 4, 30);
+        assert!(df.column("trade_date").unwrap().date().unwrap().as_date_iter().all(|d| d == latest));
+    }
+
+    #[test]
+    fn test_index_weight() {
+        let body = weights(&[("300750.SZ", "20240430", 3), ("600519.SH", "20240430", 5)]);
+        let mock = Arc::new(MockTransport::new().respond("index_weight", body));
+        let tushare = Tushare::new("<token here>").with_transport(mock.clone());
+        let df = tushare.index_weight(Index::CSI300.as_str(), NaiveDate::from_ymd_opt(2024, 4, 30).unwrap()).unwrap();
+        let params = &mock.requests()[0]["params"];
+        assert_eq!(params["index_code"], "000300.SH");
+        assert_eq!(params["trade_date"], "20240430");
+        assert_eq!(codes(&df, "con_code"), ["600519.SH", "300750.SZ"]);
+        assert_eq!(df.column("trade_date").unwrap().dtype(), &DataType::Date);
+
+        let fields: Vec<&str> = INDEX_WEIGHT_FIELDS.split(',').collect();
+        let mock = MockTransport::new().respond("index_weight", MockTransport::data(&fields, vec![]));
+        let df = Tushare::new("<token here>").with_transport(mock).index_weight("000300.SH", NaiveDate::from_ymd_opt(2024, 4, 29).unwrap());
+        assert_eq!(df.unwrap().height(), 0);
+    }
+
+    #[test]
+    fn test_index_member() {
+        let fields: Vec<&str> = INDEX_MEMBER_FIELDS.split(',').collect();
+        let row = vec![json!("801780.SI"), json!("银行"), json!("600000.SH"), json!("浦发银行"), json!("19991110"), json!(null), json!("Y")];
+        let mock = Arc::new(MockTransport::new().respond("index_member", MockTransport::data(&fields, vec![row])));
+        let tushare = Tushare::new("<token here>").with_transport(mock.clone());
+        let df = tushare.index_member("801780.SI", false).unwrap();
+        tushare.index_member("801780.SI", true).unwrap();
+        let requests = mock.requests();
+        assert_eq!(requests[0]["params"]["index_code"], "801780.SI");
+        assert_eq!(requests[0]["params"]["is_new"], "Y");
+        assert!(requests[1]["params"].get("is_new").is_none());
+        assert_eq!(codes(&df, "con_code"), ["600000.SH"]);
+        assert_eq!(df.column("in_date").unwrap().dtype(), &DataType::Date);
+    }
+}
@@ -0,0 +1,138 @@
+//! Enrich a query with the results of other apis, e.g. daily + daily_basic + adj_factor on ts_code/trade_date,
+//! in one call instead of several queries and joins.
+use crate::builder::QueryBuilder;
+use crate::error::TushareError;
+use crate::pool::parallel_map;
+use polars::prelude::*;
+
+/// A query joined to the base query of a [JoinPlan]
+#[derive(Clone)]
+struct Step<'a> {
+    query: QueryBuilder<'a>,
+    on: Vec<String>,
+}
+
+/// A base query and the queries left joined to it by key columns.
+/// All the queries run with query_all() at the same time, then every result is joined to the base in order,
+/// so the rows of the base are kept as is. A column already in the result is dropped from a joined query,
+/// e.g. the close of daily_basic is the close of daily.
+/// ```no_run
+/// # fn main() -> Result<(), tushare::TushareError> {
+/// use tushare::{JoinPlan, TushareApi};
+/// let tushare = tushare::Tushare::from_env()?;
+/// let day = |api| tushare.querybuilder(api).addparam("trade_date", "20240102");
+/// let df = JoinPlan::new(day(TushareApi::Daily), &["ts_code", "trade_date"])
+///     .join(day(TushareApi::DailyBasic))
+///     .join(day(TushareApi::AdjFactor))
+///     .join_on(tushare.querybuilder(TushareApi::StockBasic).fields("ts_code,name,industry"), &["ts_code"])
+///     .query()?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone)]
+pub struct JoinPlan<'a> {
+    base: QueryBuilder<'a>,
+    on: Vec<String>,
+    steps: Vec<Step<'a>>,
+    workers: usize,
+}
+
+impl<'a> JoinPlan<'a> {
+    /// Start a plan from the base query, the other queries are joined on the key columns unless join_on() is used
+    pub fn new(base: QueryBuilder<'a>, on: &[&str]) -> Self {
+        JoinPlan {
+            base,
+            on: on.iter().map(|c| c.to_string()).collect(),
+            steps: vec![],
+            workers: 4,
+        }
+    }
+
+    /// Left join the result of query on the key columns of the plan
+    pub fn join(self, query: QueryBuilder<'a>) -> Self {
+        let on = self.on.clone();
+        self.push(query, on)
+    }
+
+    /// Left join the result of query on other key columns, e.g. stock_basic on ts_code only
+    pub fn join_on(self, query: QueryBuilder<'a>, on: &[&str]) -> Self {
+        self.push(query, on.iter().map(|c| c.to_string()).collect())
+    }
+
+    /// Queries running at the same time, default 4
+    pub fn workers(self, workers: usize) -> Self {
+        JoinPlan {
+            workers: workers.max(1),
+            ..self
+        }
+    }
+
+    fn push(mut self, query: QueryBuilder<'a>, on: Vec<String>) -> Self {
+        self.steps.push(Step { query, on });
+        self
+    }
+
+    /// Run the queries and join their results, the first failed query fails the plan
+    pub fn query(&self) -> Result<DataFrame, TushareError> {
+        let queries: Vec<&QueryBuilder> = std::iter::once(&self.base).chain(self.steps.iter().map(|s| &s.query)).collect();
+        let mut results = parallel_map(&queries, self.workers, |query| query.query_all()).into_iter();
+        let mut df = results.next().expect("the base query is always run")?;
+        for (step, result) in self.steps.iter().zip(results) {
+            let other = result?;
+            let names = df.get_column_names();
+            let columns: Vec<Expr> = other
+                .get_column_names()
+                .into_iter()
+                .filter(|c| step.on.iter().any(|k| k == c) || !names.contains(c))
+                .map(col)
+                .collect();
+            let keys: Vec<Expr> = step.on.iter().map(|c| col(c)).collect();
+            df = df
+                .lazy()
+                .join(other.lazy().select(columns), keys.clone(), keys, JoinArgs::new(JoinType::Left))
+                .collect()?;
+        }
+        Ok(df)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::transport::MockTransport;
+    use crate::Tushare;
+    #[test]
+    fn test_join_plan() {
+        let mock = MockTransport::new()
+            .respond(
+                "daily",
+                MockTransport::data(
+                    &["ts_code", "trade_date", "close"],
+                    vec![
+                        vec!["000001.SZ".into(), "20240102".into(), 10.5.into()],
+                        vec!["600000.SH".into(), "20240102".into(), 7.2.into()],
+                    ],
+                ),
+            )
+            .respond(
+                "daily_basic",
+                MockTransport::data(
+                    &["ts_code", "trade_date", "close", "pe"],
+                    vec![vec!["000001.SZ".into(), "20240102".into(), 10.5.into(), 5.1.into()]],
+                ),
+            )
+            .respond(
+                "stock_basic",
+                MockTransport::data(&["ts_code", "name"], vec![vec!["600000.SH".into(), "浦发银行".into()]]),
+            );
+        let tushare = Tushare::new("<token here>").with_transport(mock);
+        let df = JoinPlan::new(tushare.querybuilder("daily"), &["ts_code", "trade_date"])
+            .join(tushare.querybuilder("daily_basic"))
+            .join_on(tushare.querybuilder("stock_basic"), &["ts_code"])
+            .query()
+            .unwrap();
+        assert_eq!(df.get_column_names(), ["ts_code", "trade_date", "close", "pe", "name"]);
+        assert_eq!(df.column("pe").unwrap().f64().unwrap().get(0), Some(5.1));
+        assert_eq!(df.column("name").unwrap().str().unwrap().get(1), Some("浦发银行"));
+    }
+}
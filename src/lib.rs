@@ -1,46 +1,228 @@
 //! # Tushare
-//! A rust library to read the data from http://api.tushare.pro and tranform it into polars dataframe object.
+//! A rust library to read the data from https://api.tushare.pro and tranform it into polars dataframe object.
 //! ## Example
 //! Typical approach to get 1 row stock info of 000001.SZ(Pingan Bank) with a builder pattern:
-//! ```rust
-//! use tushare::*;
+//! ```no_run
+//! # #[cfg(not(feature = "dataframe"))] fn main() {}
+//! # #[cfg(feature = "dataframe")]
+//! # fn main() -> Result<(), tushare::TushareError> {
+//! use tushare::{Tushare, TushareApi};
 //! let tushare = Tushare::new("<your token>");
-//! let df = tushare.querybuilder("daily")
+//! let df = tushare.querybuilder(TushareApi::Daily)
 //!             .addparam("trade_date", "20240424") //opiontal step
 //!             .addparam("ts_code","000001.SZ") //optional step
 //!             .fields("ts_code,trade_date,open,high,low,close,pre_close,change,pct_chg,vol") //optional step
 //!             .query()?;
 //! print!("here are the results\n");
 //! print!("{df:?}");
+//! # Ok(())
+//! # }
 //! ```
 //! ## Note
-//! 1. Get a token from tushare.pro site before you start.
+//! 1. Get a token from tushare.pro site before you start. Tushare::from_env() reads it from TUSHARE_TOKEN environment variable,
+//!    and Tushare::from_config() loads it with other settings from a TOML file.
+//!    To fetch it from a vault or rotate it at runtime, set a [TokenProvider](token::TokenProvider) with Tushare::with_token_provider().
 //! 2. Param api_name for function tushare.querybuilder() is predefined by Tushare webapi, refer to <https://tushare.pro/document/1?doc_id=130>.
-//! If you are still confusing what string should be used here (like I do), refer to the "api" field of [this doc](https://github.com/ProV1denCEX/Tushare.jl/blob/master/src/Tushare.yaml)
-//! from ProV1denCEX. I personally found it very useful, together with other optional fields.
+//!    Common apis are listed in the TushareApi enum, use TushareApi::Custom or a raw string for the others.
+//!    If you are still confusing what string should be used here (like I do), refer to the "api" field of [this doc](https://github.com/ProV1denCEX/Tushare.jl/blob/master/src/Tushare.yaml)
+//!    from ProV1denCEX. I personally found it very useful, together with other optional fields.
 //! 3. Param k/v, fields are defined clearly on Tushare website, see example <https://tushare.pro/document/2?doc_id=25>
+//!    The [fields] module has the field lists of the common apis, e.g. fields::daily::OHLCV,
+//!    and the field enums for select(), e.g. `select(&[DailyField::Open, DailyField::Close])` checked at compile time.
+//!    The fields of the registered apis are checked before sending and their columns cast to the documented types, see fields::schema_of().
+//!    Pass the enums of the [params] module to addparam(), e.g. Exchange::SZSE or Market::ChiNext, since a misspelled value silently returns empty data.
 //! 4. Be aware of date string. They must be in *YYYYMMDD* format. Otherwise empty data will be returned.
-//! 5. The date column of dataframe are *String* type by tushare server. You could tranform the whole column to datetime using Polars by yourself. 
+//!    Prefer addparam_date()/start_date()/end_date() with a chrono::NaiveDate, which format the date for you.
+//!    Use ReportPeriod, e.g. ReportPeriod::H1(2024), for the period params and the periods of the financial statements instead of "20240630".
+//! 5. The date column of dataframe are *String* type by tushare server. Call parse_dates(true) on the QueryBuilder to convert them to Polars Date.
+//!    Numeric columns of financial apis are often sparse, use null_policy() to fill the nulls with 0 or drop the rows.
+//! 6. Polars is enabled by the default "dataframe" feature. Without it, use query_raw()/query_as() to get the rows.
+//! 7. Tushare caps the rows returned by a single call (6,000 for most apis). Use query_all() instead of query() to fetch every page with offset()/limit().
+//! 8. Tushare limits the call frequency by account points. Use Tushare::with_rate_limit() to throttle the calls on client side.
+//!    Tushare::with_coalescing() makes one call for identical queries running at the same time from many threads and shares the DataFrame.
+//!    Tushare::with_cache() serves repeated queries from a MemoryCache or a DiskCache of parquet, Arrow IPC or bincode files, drop stale results with Tushare::invalidate_cache().
+//! 9. Use Tushare::with_transport() with a MockTransport to test your code with canned responses, without token and network.
+//!    With the "replay" feature, Tushare::recording() saves real responses to a file and Tushare::replaying() serves them back offline.
+//! 10. Install the tushare-cli binary with `cargo install tushare --features cli` for ad-hoc queries,
+//!     e.g. `tushare-cli --api daily --param ts_code=000001.SZ --output daily.csv`.
+//!     The tushare-codegen binary of the "codegen" feature generates the TushareApi variants, field enums and row structs of new apis from Tushare.yaml.
+//! 11. With the "realtime" feature, Tushare::subscribe() listens to the realtime ticks pushed over websocket.
+//!     Accounts without the websocket access can poll the realtime apis with a [Poller](realtime::Poller) instead.
+//! 12. Tushare::download() fetches the history of many codes in parallel into a parquet dataset described by [Downloader](downloader::Downloader), resumable after a crash.
+//! 13. With the "sqlite" feature, QueryBuilder::to_sqlite() and Tushare::sync_sqlite() upsert the rows into SQLite tables keyed by ts_code and trade_date.
+//!     With the "duckdb" feature, duckdb::views_sql() generates the DuckDB views of the synced parquet files for SQL analytics,
+//!     and QueryBuilder::to_duckdb_sql() the statements appending a query result. Only the SQL is generated, DuckDB is not linked:
+//!     run the statements with a connection of your own, e.g. the duckdb crate or the CLI.
+//! 14. With the "arrow" feature, query_arrow() returns an arrow RecordBatch and query_arrow_stream() writes an Arrow IPC stream, for the consumers without polars.
+//! 15. With the "python" feature, the python module exposes Tushare and QueryBuilder to Python through PyO3, returning py-polars DataFrames.
+//!     With the "ffi" feature, the C functions declared in include/tushare.h export the results through the Arrow C Data Interface.
+//! 16. Only a line of the api, status and elapsed time is logged per request, Tushare::with_log_bodies(true) logs the bodies with the token redacted.
+//!     With the "tracing" feature, every query runs in a tracing span with the api, params, attempts, elapsed time and rows.
 //! 
 //! ## Recommended error handling flow
 //! See [TushareError] for error definition details.
 //! The only place that will produce an error is the query() method of QueryBuilder. The recommended error handling flow is:
-//! 1. NetworkError occurs during http request. You may want to retry if your network is not stable.
-//! 2. RequestError occurs if Tushare server explicity return a nonzero code in its body. See error message for more details. Possible reason: wrong token
+//! 1. NetworkError occurs during http request. Transient ones are already retried according to [RetryPolicy], configure it with Tushare::with_retry().
+//!    HttpError occurs if the http status is 4xx or 5xx, match the [HttpStatusError] inside for the reason. 429 and 5xx are retried, after the Retry-After header with RetryPolicy::wait_on_rate_limit(true).
+//!    Bound the requests of a query with QueryBuilder::timeout(), and stop it from another thread with cancellation(), which fails it with Cancelled.
+//! 2. RequestError occurs if Tushare server explicity return a nonzero code in its body. Match the [TushareServerError] inside for the reason, e.g. wrong token or rate limited.
+//!    Rate limited calls are only retried with RetryPolicy::wait_on_rate_limit(true), after the wait tushare asks for, otherwise they fail at once.
 //! 3. JsonError/DataError occur if body returned by Tushare server is not the same as document. Normally this won't happen. 
-//! You could set the log level to "Info" and check the log for the request and response body.
+//!    InvalidResponse occurs if the body is not json at all, e.g. the html page of a gateway or a captive portal, with the first chars of the body.
+//!    RowShapeError occurs if a row has more or less values than the fields, skip such rows with skip_malformed_rows(true),
+//!    or fill their missing values with nulls with parse_mode(ParseMode::Lenient).
+//!    You could set Tushare::with_log_bodies(true) and the log level to "Info" to check the request (token redacted) and response body.
 //! 4. EmptyError occurs if Tushare return zero rows of data. Because this makes it impossible to infer the data type of each columns, it was marked as error.
-//! Usually you can check if wrong date format is used. The correct format is "20240404".
+//!    Set allow_empty(true) on the QueryBuilder to get a zero rows DataFrame with String columns instead.
+//!    Usually you can check if wrong date format is used. The correct format is "20240404".
 //! 5. PolarsError occurs during the json -> dataframe transforming. Again, it should not happen. Check the info log for more details.
+//! 6. InvalidCode occurs before any request if a stock code is malformed, e.g. "700.HK" instead of "00700.HK".
+//!    InvalidParams occurs before any request if a known api misses a required param or a date param is malformed, e.g. stk_mins without ts_code.
+//! 7. CacheMiss occurs if the result is not in the cache of Tushare::offline() or the recording of Tushare::replaying(), since no request is sent.
 
+#[cfg(feature = "dataframe")]
+pub mod analysis;
+pub mod api;
+#[cfg(feature = "arrow")]
+pub mod arrow;
+#[cfg(feature = "dataframe")]
+pub mod bar;
 pub mod builder;
+pub mod checkpoint;
+#[cfg(feature = "dataframe")]
+pub mod cache;
+#[cfg(feature = "dataframe")]
+pub mod calendar;
+pub mod cancel;
+#[cfg(feature = "dataframe")]
+pub mod classify;
+pub mod client;
+pub mod code;
+#[cfg(feature = "dataframe")]
+mod coalesce;
+pub mod config;
+#[cfg(feature = "dataframe")]
+mod dataframe;
+#[cfg(feature = "dataframe")]
+pub mod downloader;
+#[cfg(feature = "duckdb")]
+pub mod duckdb;
+pub mod date;
+#[cfg(feature = "dataframe")]
+pub mod derivatives;
+#[cfg(feature = "dataframe")]
+pub mod diff;
+#[cfg(feature = "dataframe")]
+pub mod economy;
+pub mod error;
+#[cfg(feature = "dataframe")]
+mod export;
+#[cfg(feature = "ffi")]
+pub mod ffi;
+pub mod fields;
+#[cfg(feature = "dataframe")]
+pub mod finance;
+#[cfg(feature = "dataframe")]
+pub mod fund;
+#[cfg(feature = "dataframe")]
+pub mod holders;
+#[cfg(feature = "dataframe")]
+pub mod index;
+#[cfg(feature = "dataframe")]
+pub mod join;
+#[cfg(feature = "dataframe")]
+pub mod margin;
+pub mod metrics;
+pub mod middleware;
+#[cfg(feature = "dataframe")]
+pub mod moneyflow;
+#[cfg(feature = "dataframe")]
+pub mod news;
+#[cfg(feature = "dataframe")]
+pub mod overseas;
+#[cfg(feature = "dataframe")]
+pub mod panel;
+pub mod params;
+#[cfg(feature = "dataframe")]
+pub mod pool;
+#[cfg(feature = "dataframe")]
+pub mod progress;
+#[cfg(feature = "python")]
+pub mod python;
+pub mod ratelimit;
+pub mod realtime;
+#[cfg(feature = "replay")]
+pub mod replay;
+pub mod retry;
+pub mod rotation;
+#[cfg(feature = "sqlite")]
+pub mod sqlite;
+#[cfg(feature = "dataframe")]
+pub mod status;
+#[cfg(feature = "dataframe")]
+pub mod stock;
+#[cfg(feature = "dataframe")]
+pub mod sync;
+pub mod table;
+mod telemetry;
+pub mod token;
+#[cfg(feature = "dataframe")]
+pub mod transform;
+pub mod transport;
 pub mod tushare;
-pub use tushare::Tushare;
-pub use builder::{Dict, QueryBuilder, TushareError};
+#[cfg(feature = "realtime")]
+pub mod websocket;
+pub use tushare::{Tushare, TushareBuilder, TUSHARE_ENDPOINT, WADITU_ENDPOINT};
+pub use api::TushareApi;
+#[cfg(feature = "dataframe")]
+pub use bar::{apply_adjustment, Adj, Freq};
+pub use builder::{Dict, QueryBuilder};
+pub use error::{HttpStatusError, TushareError, TushareServerError};
+#[cfg(feature = "dataframe")]
+pub use cache::{Cache, CacheCompression, CacheFormat, DiskCache, MemoryCache};
+#[cfg(feature = "dataframe")]
+pub use calendar::TradeCalendar;
+#[cfg(feature = "dataframe")]
+pub use diff::{diff, DataDiff};
+#[cfg(feature = "dataframe")]
+pub use panel::PanelBuilder;
+pub use cancel::CancellationToken;
+pub use checkpoint::Checkpoint;
+pub use client::ClientConfig;
+pub use middleware::Middleware;
+pub use code::TsCode;
+pub use config::TushareConfig;
+pub use params::{
+    DerivativeExchange, Exchange, FundMarket, FundStatus, HolderTradeType, HolderType, Index, IntoParamValue,
+    LimitType, ListStatus, Market, NewsSource, Northbound, ReportPeriod, ReportType, Southbound, SwLevel,
+};
+#[cfg(feature = "dataframe")]
+pub use join::JoinPlan;
+#[cfg(feature = "dataframe")]
+pub use pool::QueryPool;
+#[cfg(feature = "dataframe")]
+pub use progress::{Progress, ProgressHook};
+pub use date::IntoTushareDate;
+#[cfg(feature = "dataframe")]
+pub use downloader::{Downloader, Manifest};
+pub use ratelimit::RateLimiter;
+pub use realtime::{Poller, Tick};
+pub use retry::RetryPolicy;
+#[cfg(feature = "replay")]
+pub use replay::{RecordingTransport, ReplayTransport};
+#[cfg(feature = "dataframe")]
+pub use sync::{SyncJob, SyncReport};
+pub use token::TokenProvider;
+pub use table::{PageAnomaly, ParseMode, QueryReport, ResponseMeta, Table};
+#[cfg(feature = "dataframe")]
+pub use transform::NullPolicy;
+pub use transport::{MockTransport, OfflineTransport, ReqwestTransport, Transport};
+#[cfg(feature = "realtime")]
+pub use websocket::Subscription;
 
 
 
-#[cfg(test)]
+#[cfg(all(test, feature = "dataframe"))]
 mod tests {
     use super::*;
     #[test]
@@ -54,4 +236,93 @@ mod tests {
         print!("here are the results");
         print!("{df:?}");
     }
+
+    fn mock_page(rows: std::ops::Range<i64>) -> String {
+        MockTransport::data(
+            &["cal_date", "is_open"],
+            rows.map(|i| vec![serde_json::json!(format!("{}", 20240101 + i)), serde_json::json!(1)]).collect(),
+        )
+    }
+
+    #[test]
+    fn test_query_all() {
+        // every page is full, so only max_pages stops the loop
+        let mock = std::sync::Arc::new(MockTransport::new().respond("trade_cal", mock_page(0..2)));
+        let tushare = Tushare::new("<token here>").with_transport(mock.clone());
+        let df = tushare
+            .querybuilder("trade_cal")
+            .addparam("start_date", "20000101")
+            .page_size(2)
+            .max_pages(3)
+            .query_all()
+            .unwrap();
+        assert_eq!(df.height(), 6);
+        let offsets: Vec<serde_json::Value> = mock.requests().iter().map(|r| r["params"]["offset"].clone()).collect();
+        assert_eq!(offsets, ["0", "2", "4"]);
+    }
+
+    #[test]
+    fn test_mock_query_all() {
+        let mock = std::sync::Arc::new(
+            MockTransport::new()
+                .respond("trade_cal", mock_page(0..2))
+                .respond("trade_cal", mock_page(2..3)),
+        );
+        let tushare = Tushare::new("<token here>").with_transport(mock.clone());
+        let df = tushare.querybuilder("trade_cal").page_size(2).query_all().unwrap();
+        assert_eq!(df.height(), 3);
+        let requests = mock.requests();
+        assert_eq!(requests.len(), 2);
+        assert_eq!(requests[1]["params"]["offset"], "2");
+        let _ = tushare.querybuilder("trade_cal").offset(10).page_size(2).query_all();
+        assert_eq!(mock.requests().last().unwrap()["params"]["offset"], "10");
+    }
+
+    #[test]
+    fn test_mock_server_error() {
+        let mock = MockTransport::new().respond("daily", MockTransport::error(40101, "token不对"));
+        let tushare = Tushare::new("<token here>").with_transport(mock);
+        match tushare.querybuilder("daily").query() {
+            Err(TushareError::RequestError(TushareServerError::InvalidToken { .. })) => {}
+            other => panic!("unexpected result {other:?}"),
+        }
+    }
+
+    #[test]
+    fn test_mock_html_response() {
+        let mock = MockTransport::new().respond("daily", "<html><body>502 Bad Gateway</body></html>");
+        let tushare = Tushare::new("<token here>").with_transport(mock);
+        match tushare.querybuilder("daily").query() {
+            Err(TushareError::InvalidResponse { status: 200, snippet }) => assert!(snippet.starts_with("<html>")),
+            other => panic!("unexpected result {other:?}"),
+        }
+    }
+
+    #[test]
+    fn test_mock_query_detailed() {
+        let tushare = Tushare::new("<token here>").with_transport(MockTransport::new().respond("trade_cal", mock_page(0..2)));
+        let (df, report) = tushare.querybuilder("trade_cal").query_detailed().unwrap();
+        assert_eq!(df.height(), 2);
+        assert_eq!((report.rows, report.columns), (2, 2));
+        assert_eq!(report.http_status, Some(200));
+        assert_eq!(report.request_id.as_deref(), Some("mock"));
+        assert!(report.request.get("token").is_none());
+    }
+
+    #[test]
+    fn test_mock_dedup_and_sort() {
+        let mock = MockTransport::new()
+            .respond("trade_cal", mock_page(0..2))
+            .respond("trade_cal", mock_page(1..2));
+        let tushare = Tushare::new("<token here>").with_transport(mock);
+        let df = tushare
+            .querybuilder("trade_cal")
+            .page_size(2)
+            .dedup(&["cal_date"])
+            .sort_by(&["cal_date"], true)
+            .query_all()
+            .unwrap();
+        assert_eq!(df.height(), 2);
+        assert_eq!(df.column("cal_date").unwrap().str().unwrap().get(0), Some("20240102"));
+    }
 }
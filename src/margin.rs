@@ -0,0 +1,166 @@
+//! Helpers of the margin trading and short selling apis (融资融券).
+//! The amounts are in yuan and the volumes in shares, rz* columns are margin buying (融资)
+//! and rq* columns short selling (融券), rzrqye is the total balance of both.
+use crate::api::TushareApi;
+use crate::code::check_codes;
+use crate::error::TushareError;
+use crate::params::Exchange;
+use crate::stock::float_dtypes;
+use crate::tushare::Tushare;
+use chrono::NaiveDate;
+use polars::prelude::*;
+use std::ops::RangeInclusive;
+
+/// Fields returned by Tushare::margin()
+pub const MARGIN_FIELDS: &str = "trade_date,exchange_id,rzye,rzmre,rzche,rqye,rqmcl,rzrqye,rqyl";
+
+/// Numeric columns of margin
+const MARGIN_NUMERIC: &[&str] = &["rzye", "rzmre", "rzche", "rqye", "rqmcl", "rzrqye", "rqyl"];
+
+/// Fields returned by Tushare::margin_detail()
+pub const MARGIN_DETAIL_FIELDS: &str = "trade_date,ts_code,name,rzye,rqye,rzmre,rqyl,rzche,rqchl,rqmcl,rzrqye";
+
+/// Numeric columns of margin_detail
+const MARGIN_DETAIL_NUMERIC: &[&str] = &["rzye", "rqye", "rzmre", "rqyl", "rzche", "rqchl", "rqmcl", "rzrqye"];
+
+impl Tushare {
+    /// Daily margin trading summary of the exchanges with trade_date within range,
+    /// of every exchange if exchange is None. Sorted by trade_date and exchange_id ascending.
+    /// InvalidParams is returned for HKEX, which has no A share margin trading.
+    /// ```no_run
+    /// # fn main() -> Result<(), tushare::TushareError> {
+    /// use chrono::NaiveDate;
+    /// let tushare = tushare::Tushare::from_env()?;
+    /// let range = NaiveDate::from_ymd_opt(2024, 1, 1).unwrap()..=NaiveDate::from_ymd_opt(2024, 4, 30).unwrap();
+    /// let sse = tushare.margin(range, Some(tushare::Exchange::SSE))?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn margin(
+        &self,
+        range: RangeInclusive<NaiveDate>,
+        exchange: Option<Exchange>,
+    ) -> Result<DataFrame, TushareError> {
+        let mut query = self
+            .querybuilder(TushareApi::Margin)
+            .start_date(range.start())
+            .end_date(range.end())
+            .fields(MARGIN_FIELDS)
+            .dtypes(&float_dtypes(MARGIN_NUMERIC))
+            .parse_dates(true);
+        match exchange {
+            Some(Exchange::HKEX) => {
+                return Err(TushareError::InvalidParams("margin is not available for HKEX".to_string()))
+            }
+            Some(exchange) => query = query.addparam("exchange_id", exchange),
+            None => {}
+        }
+        Ok(query.query_all()?.sort(["trade_date", "exchange_id"], SortMultipleOptions::default())?)
+    }
+
+    /// Daily margin trading of a stock with trade_date within range, sorted by trade_date ascending.
+    /// Zero rows for a stock not in the margin trading list.
+    pub fn margin_detail(
+        &self,
+        ts_code: impl AsRef<str>,
+        range: RangeInclusive<NaiveDate>,
+    ) -> Result<DataFrame, TushareError> {
+        check_codes(ts_code.as_ref())?;
+        let df = self
+            .querybuilder(TushareApi::MarginDetail)
+            .addparam("ts_code", ts_code.as_ref())
+            .start_date(range.start())
+            .end_date(range.end())
+            .fields(MARGIN_DETAIL_FIELDS)
+            .dtypes(&float_dtypes(MARGIN_DETAIL_NUMERIC))
+            .parse_dates(true)
+            .allow_empty(true)
+            .query_all()?;
+        Ok(df.sort(["trade_date"], SortMultipleOptions::default())?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::transport::MockTransport;
+    use serde_json::{json, Value};
+    use std::sync::Arc;
+
+    fn day(d: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(2024, 1, d).unwrap()
+    }
+
+    /// A row of the fields with integer amounts, so the cast to Float64 is checked
+    fn row(fields: &str, numeric: &[&str], trade_date: &str, exchange_id: &str) -> Vec<Value> {
+        fields
+            .split(',')
+            .map(|f| match f {
+                "trade_date" => json!(trade_date),
+                "exchange_id" => json!(exchange_id),
+                "ts_code" => json!("600000.SH"),
+                f if numeric.contains(&f) => json!(100),
+                _ => json!("x"),
+            })
+            .collect()
+    }
+
+    fn assert_columns(df: &DataFrame, numeric: &[&str]) {
+        assert_eq!(df.column("trade_date").unwrap().dtype(), &DataType::Date);
+        for column in numeric {
+            assert_eq!(df.column(column).unwrap().dtype(), &DataType::Float64, "{column}");
+        }
+    }
+
+    #[test]
+    fn test_margin() {
+        let fields: Vec<&str> = MARGIN_FIELDS.split(',').collect();
+        let rows = vec![
+            row(MARGIN_FIELDS, MARGIN_NUMERIC, "20240103", "SSE"),
+            row(MARGIN_FIELDS, MARGIN_NUMERIC, "20240102", "SZSE"),
+            row(MARGIN_FIELDS, MARGIN_NUMERIC, "20240102", "SSE"),
+        ];
+        let mock = Arc::new(MockTransport::new().respond("margin", MockTransport::data(&fields, rows)));
+        let tushare = Tushare::new("<token here>").with_transport(mock.clone());
+        let df = tushare.margin(day(2)..=day(3), Some(Exchange::SZSE)).unwrap();
+        tushare.margin(day(2)..=day(3), None).unwrap();
+        let requests = mock.requests();
+        assert_eq!(requests[0]["params"]["start_date"], "20240102");
+        assert_eq!(requests[0]["params"]["end_date"], "20240103");
+        assert_eq!(requests[0]["params"]["exchange_id"], "SZSE");
+        assert!(requests[1]["params"].get("exchange_id").is_none());
+        assert_columns(&df, MARGIN_NUMERIC);
+        let dates: Vec<_> = df.column("trade_date").unwrap().date().unwrap().as_date_iter().flatten().collect();
+        assert_eq!(dates, [day(2), day(2), day(3)]);
+        let exchanges: Vec<_> = df.column("exchange_id").unwrap().str().unwrap().into_iter().flatten().collect();
+        assert_eq!(exchanges, ["SSE", "SZSE", "SSE"]);
+
+        let result = tushare.margin(day(2)..=day(3), Some(Exchange::HKEX));
+        assert!(matches!(result, Err(TushareError::InvalidParams(_))));
+        assert_eq!(mock.requests().len(), 2);
+    }
+
+    #[test]
+    fn test_margin_detail() {
+        let fields: Vec<&str> = MARGIN_DETAIL_FIELDS.split(',').collect();
+        let rows = vec![
+            row(MARGIN_DETAIL_FIELDS, MARGIN_DETAIL_NUMERIC, "20240103", ""),
+            row(MARGIN_DETAIL_FIELDS, MARGIN_DETAIL_NUMERIC, "20240102", ""),
+        ];
+        let mock = Arc::new(MockTransport::new().respond("margin_detail", MockTransport::data(&fields, rows)));
+        let tushare = Tushare::new("<token here>").with_transport(mock.clone());
+        let df = tushare.margin_detail("600000.SH", day(2)..=day(3)).unwrap();
+        let params = &mock.requests()[0]["params"];
+        assert_eq!(params["ts_code"], "600000.SH");
+        assert_eq!(params["start_date"], "20240102");
+        assert_eq!(params["end_date"], "20240103");
+        assert_columns(&df, MARGIN_DETAIL_NUMERIC);
+        let dates: Vec<_> = df.column("trade_date").unwrap().date().unwrap().as_date_iter().flatten().collect();
+        assert_eq!(dates, [day(2), day(3)]);
+        assert!(tushare.margin_detail("600000", day(2)..=day(3)).is_err());
+
+        let mock = MockTransport::new().respond("margin_detail", MockTransport::data(&fields, vec![]));
+        let df = Tushare::new("<token here>").with_transport(mock).margin_detail("000004.SZ", day(2)..=day(3)).unwrap();
+        assert_eq!(df.height(), 0);
+    }
+}
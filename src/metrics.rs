@@ -0,0 +1,149 @@
+//! Call statistics of every api, enabled by Tushare::with_metrics()
+use crate::middleware::{Middleware, Response};
+use std::collections::{BTreeMap, HashMap};
+use std::fmt::Write;
+use std::sync::Mutex;
+use std::time::Duration;
+
+/// Name, help text and value of a Prometheus counter
+type Counter = (&'static str, &'static str, fn(&ApiMetrics) -> String);
+
+/// Statistics of an api
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ApiMetrics {
+    /// Requests sent, including retries
+    pub calls: u64,
+    /// Failed requests, including tushare error codes
+    pub errors: u64,
+    /// Rows returned by the successful requests
+    pub rows: u64,
+    /// Total time of all requests
+    pub latency: Duration,
+    /// Estimated points consumed by the successful requests, see Metrics::cost()
+    pub points: u64,
+}
+
+impl ApiMetrics {
+    /// Share of the failed requests, 0 if there is no request
+    pub fn error_rate(&self) -> f64 {
+        if self.calls == 0 {
+            0.0
+        } else {
+            self.errors as f64 / self.calls as f64
+        }
+    }
+
+    /// Average time of a request
+    pub fn mean_latency(&self) -> Duration {
+        if self.calls == 0 {
+            Duration::ZERO
+        } else {
+            self.latency / self.calls as u32
+        }
+    }
+}
+
+/// Collect the statistics of every api, it is a [Middleware] registered by Tushare::with_metrics().
+/// Tushare doesn't report the points consumed, so they are estimated from a cost per successful call,
+/// 1 for every api unless set by cost().
+#[derive(Debug, Default)]
+pub struct Metrics {
+    apis: Mutex<BTreeMap<String, ApiMetrics>>,
+    costs: HashMap<String, u64>,
+}
+
+impl Metrics {
+    /// Create an empty collector
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the estimated points of a successful call of the api
+    pub fn cost(mut self, api_name: impl Into<String>, points: u64) -> Self {
+        self.costs.insert(api_name.into(), points);
+        self
+    }
+
+    /// A copy of the statistics of every api, sorted by api_name
+    pub fn snapshot(&self) -> BTreeMap<String, ApiMetrics> {
+        self.apis.lock().unwrap_or_else(|e| e.into_inner()).clone()
+    }
+
+    /// Statistics of all apis summed up
+    pub fn total(&self) -> ApiMetrics {
+        self.snapshot().values().fold(ApiMetrics::default(), |mut acc, m| {
+            acc.calls += m.calls;
+            acc.errors += m.errors;
+            acc.rows += m.rows;
+            acc.latency += m.latency;
+            acc.points += m.points;
+            acc
+        })
+    }
+
+    /// Clear all statistics
+    pub fn reset(&self) {
+        self.apis.lock().unwrap_or_else(|e| e.into_inner()).clear();
+    }
+
+    /// The statistics in Prometheus text exposition format
+    pub fn to_prometheus(&self) -> String {
+        let apis = self.snapshot();
+        let mut out = String::new();
+        let counters: [Counter; 5] = [
+            ("tushare_calls_total", "Requests sent to tushare", |m| m.calls.to_string()),
+            ("tushare_errors_total", "Failed requests", |m| m.errors.to_string()),
+            ("tushare_rows_total", "Rows returned", |m| m.rows.to_string()),
+            ("tushare_latency_seconds_total", "Total time of the requests", |m| {
+                m.latency.as_secs_f64().to_string()
+            }),
+            ("tushare_points_total", "Estimated points consumed", |m| m.points.to_string()),
+        ];
+        for (name, help, value) in counters {
+            let _ = writeln!(out, "# HELP {name} {help}");
+            let _ = writeln!(out, "# TYPE {name} counter");
+            for (api, m) in &apis {
+                let _ = writeln!(out, "{name}{{api=\"{api}\"}} {}", value(m));
+            }
+        }
+        out
+    }
+}
+
+impl Middleware for Metrics {
+    fn after(&self, response: &Response) {
+        let api_name = response.api_name();
+        let mut apis = self.apis.lock().unwrap_or_else(|e| e.into_inner());
+        let m = apis.entry(api_name.to_string()).or_default();
+        m.calls += 1;
+        m.latency += response.elapsed;
+        match response.result {
+            Ok((meta, _)) => {
+                m.rows += meta.rows as u64;
+                m.points += self.costs.get(api_name).copied().unwrap_or(1);
+            }
+            Err(_) => m.errors += 1,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::transport::MockTransport;
+    use crate::Tushare;
+    #[test]
+    fn test_metrics() {
+        let mock = MockTransport::new()
+            .respond("daily", MockTransport::data(&["close"], vec![vec![1.0.into()], vec![2.0.into()]]))
+            .respond("weekly", MockTransport::error(40101, "token invalid"));
+        let tushare = Tushare::new("").with_transport(mock).with_metrics(Metrics::new().cost("daily", 5));
+        tushare.querybuilder("daily").query_raw().unwrap();
+        assert!(tushare.querybuilder("weekly").query_raw().is_err());
+        let metrics = tushare.metrics().unwrap();
+        let daily = &metrics.snapshot()["daily"];
+        assert_eq!((daily.calls, daily.rows, daily.points), (1, 2, 5));
+        assert_eq!(metrics.snapshot()["weekly"].error_rate(), 1.0);
+        assert!(metrics.to_prometheus().contains("tushare_calls_total{api=\"daily\"} 1"));
+    }
+}
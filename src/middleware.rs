@@ -0,0 +1,76 @@
+//! Hooks around every request sent to tushare, registered by Tushare::with_middleware().
+//! Use them for custom logging, metrics or rewriting the requests without forking the crate.
+use crate::error::TushareError;
+use crate::table::{ResponseMeta, Table};
+use serde_json::Value;
+use std::time::Duration;
+
+/// The outcome of a request passed to Middleware::after()
+#[derive(Debug)]
+pub struct Response<'a> {
+    /// The request as sent, after all before() hooks
+    pub request: &'a Value,
+    /// The metadata and the table of a response with code 0, or the error of this attempt
+    pub result: &'a Result<(ResponseMeta, Table), TushareError>,
+    /// Time spent by the transport and the response parsing, excluding the rate limit wait
+    pub elapsed: Duration,
+}
+
+impl Response<'_> {
+    /// The api_name of the request
+    pub fn api_name(&self) -> &str {
+        self.request["api_name"].as_str().unwrap_or_default()
+    }
+}
+
+/// A hook called around every http request, including every retry attempt.
+/// Middlewares are called in the order of registration, both methods do nothing by default.
+/// ```
+/// use tushare::middleware::{Middleware, Response};
+/// struct PrintErrors;
+/// impl Middleware for PrintErrors {
+///     fn after(&self, response: &Response) {
+///         if let Err(e) = response.result {
+///             eprintln!("{} failed: {e}", response.api_name());
+///         }
+///     }
+/// }
+/// let tushare = tushare::Tushare::new("<your token>").with_middleware(PrintErrors);
+/// ```
+pub trait Middleware: Send + Sync {
+    /// Called before the request is sent, the request json can be modified, e.g. to replace the token
+    fn before(&self, _request: &mut Value) {}
+
+    /// Called after the response is received and its code is checked, or the request failed
+    fn after(&self, _response: &Response) {}
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::transport::MockTransport;
+    use crate::Tushare;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    struct Rewrite(Arc<AtomicUsize>);
+    impl Middleware for Rewrite {
+        fn before(&self, request: &mut Value) {
+            request["api_name"] = "trade_cal".into();
+        }
+        fn after(&self, response: &Response) {
+            assert_eq!(response.api_name(), "trade_cal");
+            self.0.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    #[test]
+    fn test_middleware() {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let mock = MockTransport::new().respond("trade_cal", MockTransport::data(&["cal_date"], vec![vec!["20240102".into()]]));
+        let tushare = Tushare::new("").with_transport(mock).with_middleware(Rewrite(calls.clone()));
+        let table = tushare.querybuilder("daily").query_raw().unwrap();
+        assert_eq!(table.len(), 1);
+        assert_eq!(calls.load(Ordering::Relaxed), 1);
+    }
+}
@@ -0,0 +1,221 @@
+//! Helpers of the money flow apis, by order size of a stock and through the Stock Connect.
+//! The amounts are in 10 thousand yuan unless noted, the volumes in lots.
+use crate::api::TushareApi;
+use crate::code::check_codes;
+use crate::date::IntoTushareDate;
+use crate::error::TushareError;
+use crate::params::{Northbound, Southbound};
+use crate::stock::float_dtypes;
+use crate::tushare::Tushare;
+use chrono::NaiveDate;
+use polars::prelude::*;
+use std::ops::RangeInclusive;
+
+/// Fields returned by Tushare::moneyflow(), small/medium/large/extra large orders of buy and sell
+pub const MONEYFLOW_FIELDS: &str = "ts_code,trade_date,buy_sm_vol,buy_sm_amount,sell_sm_vol,sell_sm_amount,\
+buy_md_vol,buy_md_amount,sell_md_vol,sell_md_amount,buy_lg_vol,buy_lg_amount,sell_lg_vol,sell_lg_amount,\
+buy_elg_vol,buy_elg_amount,sell_elg_vol,sell_elg_amount,net_mf_vol,net_mf_amount";
+
+/// Numeric columns of moneyflow
+const MONEYFLOW_NUMERIC: &[&str] = &[
+    "buy_sm_vol", "buy_sm_amount", "sell_sm_vol", "sell_sm_amount",
+    "buy_md_vol", "buy_md_amount", "sell_md_vol", "sell_md_amount",
+    "buy_lg_vol", "buy_lg_amount", "sell_lg_vol", "sell_lg_amount",
+    "buy_elg_vol", "buy_elg_amount", "sell_elg_vol", "sell_elg_amount",
+    "net_mf_vol", "net_mf_amount",
+];
+
+/// Fields returned by Tushare::moneyflow_hsgt(), in million yuan
+pub const MONEYFLOW_HSGT_FIELDS: &str = "trade_date,ggt_ss,ggt_sz,hgt,sgt,north_money,south_money";
+
+/// Numeric columns of moneyflow_hsgt
+const MONEYFLOW_HSGT_NUMERIC: &[&str] = &["ggt_ss", "ggt_sz", "hgt", "sgt", "north_money", "south_money"];
+
+/// Fields returned by Tushare::hsgt_top10(), the amounts in yuan
+pub const HSGT_TOP10_FIELDS: &str = "trade_date,ts_code,name,close,change,rank,market_type,amount,net_amount,buy,sell";
+
+/// Numeric columns of hsgt_top10
+const HSGT_TOP10_NUMERIC: &[&str] = &["close", "change", "amount", "net_amount", "buy", "sell"];
+
+/// Fields returned by Tushare::ggt_top10(), the amounts in Hong Kong dollars
+pub const GGT_TOP10_FIELDS: &str = "trade_date,ts_code,name,close,p_change,rank,market_type,amount,net_amount,\
+sh_amount,sh_net_amount,sh_buy,sh_sell,sz_amount,sz_net_amount,sz_buy,sz_sell";
+
+/// Numeric columns of ggt_top10
+const GGT_TOP10_NUMERIC: &[&str] = &[
+    "close", "p_change", "amount", "net_amount", "sh_amount", "sh_net_amount", "sh_buy", "sh_sell",
+    "sz_amount", "sz_net_amount", "sz_buy", "sz_sell",
+];
+
+impl Tushare {
+    /// Money flow of a stock by order size with trade_date within range, sorted by trade_date ascending.
+    /// The orders are split by amount: small below 50 thousand yuan, medium up to 200 thousand,
+    /// large up to 1 million and extra large above.
+    pub fn moneyflow(
+        &self,
+        ts_code: impl AsRef<str>,
+        range: RangeInclusive<NaiveDate>,
+    ) -> Result<DataFrame, TushareError> {
+        check_codes(ts_code.as_ref())?;
+        let df = self
+            .querybuilder(TushareApi::Moneyflow)
+            .addparam("ts_code", ts_code.as_ref())
+            .start_date(range.start())
+            .end_date(range.end())
+            .fields(MONEYFLOW_FIELDS)
+            .dtypes(&float_dtypes(MONEYFLOW_NUMERIC))
+            .parse_dates(true)
+            .query_all()?;
+        Ok(df.sort(["trade_date"], SortMultipleOptions::default())?)
+    }
+
+    /// Daily money flow of the Stock Connect within range, sorted by trade_date ascending.
+    /// hgt/sgt are the northbound flows through Shanghai/Shenzhen and north_money their sum,
+    /// ggt_ss/ggt_sz the southbound flows and south_money their sum.
+    pub fn moneyflow_hsgt(&self, range: RangeInclusive<NaiveDate>) -> Result<DataFrame, TushareError> {
+        let df = self
+            .querybuilder(TushareApi::MoneyflowHsgt)
+            .start_date(range.start())
+            .end_date(range.end())
+            .fields(MONEYFLOW_HSGT_FIELDS)
+            .dtypes(&float_dtypes(MONEYFLOW_HSGT_NUMERIC))
+            .parse_dates(true)
+            .query_all()?;
+        Ok(df.sort(["trade_date"], SortMultipleOptions::default())?)
+    }
+
+    /// Top 10 stocks traded through the northbound Stock Connect on trade_date, of both markets if market is None.
+    /// Sorted by market_type and rank, zero rows on a day the Connect is closed.
+    pub fn hsgt_top10(
+        &self,
+        trade_date: impl IntoTushareDate,
+        market: Option<Northbound>,
+    ) -> Result<DataFrame, TushareError> {
+        let mut query = self
+            .querybuilder(TushareApi::HsgtTop10)
+            .addparam_date("trade_date", trade_date)
+            .fields(HSGT_TOP10_FIELDS)
+            .dtypes(&float_dtypes(HSGT_TOP10_NUMERIC))
+            .parse_dates(true)
+            .allow_empty(true);
+        if let Some(market) = market {
+            query = query.addparam("market_type", market);
+        }
+        Ok(query.query_all()?.sort(["market_type", "rank"], SortMultipleOptions::default())?)
+    }
+
+    /// Top 10 Hong Kong stocks traded through the southbound Stock Connect on trade_date, of both markets if market is None.
+    /// The sh_/sz_ columns are the trades through Shanghai/Shenzhen, sorted by market_type and rank.
+    pub fn ggt_top10(
+        &self,
+        trade_date: impl IntoTushareDate,
+        market: Option<Southbound>,
+    ) -> Result<DataFrame, TushareError> {
+        let mut query = self
+            .querybuilder(TushareApi::GgtTop10)
+            .addparam_date("trade_date", trade_date)
+            .fields(GGT_TOP10_FIELDS)
+            .dtypes(&float_dtypes(GGT_TOP10_NUMERIC))
+            .parse_dates(true)
+            .allow_empty(true);
+        if let Some(market) = market {
+            query = query.addparam("market_type", market);
+        }
+        Ok(query.query_all()?.sort(["market_type", "rank"], SortMultipleOptions::default())?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::transport::MockTransport;
+    use serde_json::{json, Value};
+    use std::sync::Arc;
+
+    /// A row of the fields with 1 in the numeric columns, so the cast to Float64 is checked
+    fn row(fields: &str, numeric: &[&str], trade_date: &str) -> Vec<Value> {
+        fields
+            .split(',')
+            .map(|f| match f {
+                "trade_date" => json!(trade_date),
+                "ts_code" => json!("000001.SZ"),
+                "rank" | "market_type" => json!(1),
+                f if numeric.contains(&f) => json!(1),
+                _ => json!("x"),
+            })
+            .collect()
+    }
+
+    fn transport(api: &str, fields: &str, rows: Vec<Vec<Value>>) -> Arc<MockTransport> {
+        let fields: Vec<&str> = fields.split(',').collect();
+        Arc::new(MockTransport::new().respond(api, MockTransport::data(&fields, rows)))
+    }
+
+    fn assert_float(df: &DataFrame, numeric: &[&str]) {
+        for column in numeric {
+            assert_eq!(df.column(column).unwrap().dtype(), &DataType::Float64, "{column}");
+        }
+        assert_eq!(df.column("trade_date").unwrap().dtype(), &DataType::Date);
+    }
+
+    #[test]
+    fn test_moneyflow() {
+        let rows = vec![row(MONEYFLOW_FIELDS, MONEYFLOW_NUMERIC, "20240103"), row(MONEYFLOW_FIELDS, MONEYFLOW_NUMERIC, "20240102")];
+        let mock = transport("moneyflow", MONEYFLOW_FIELDS, rows);
+        let tushare = Tushare::new("<token here>").with_transport(mock.clone());
+        let day = |d| NaiveDate::from_ymd_opt(2024, 1, d).unwrap();
+        let df = tushare.moneyflow("000001.SZ", day(2)..=day(3)).unwrap();
+        let params = &mock.requests()[0]["params"];
+        assert_eq!(params["ts_code"], "000001.SZ");
+        assert_eq!(params["start_date"], "20240102");
+        assert_eq!(params["end_date"], "20240103");
+        assert_float(&df, MONEYFLOW_NUMERIC);
+        let dates: Vec<_> = df.column("trade_date").unwrap().date().unwrap().as_date_iter().flatten().collect();
+        assert_eq!(dates, [day(2), day(3)]);
+        assert!(tushare.moneyflow("000001", day(2)..=day(3)).is_err());
+    }
+
+    #[test]
+    fn test_moneyflow_hsgt() {
+        let rows = vec![row(MONEYFLOW_HSGT_FIELDS, MONEYFLOW_HSGT_NUMERIC, "20240102")];
+        let mock = transport("moneyflow_hsgt", MONEYFLOW_HSGT_FIELDS, rows);
+        let tushare = Tushare::new("<token here>").with_transport(mock.clone());
+        let day = NaiveDate::from_ymd_opt(2024, 1, 2).unwrap();
+        let df = tushare.moneyflow_hsgt(day..=day).unwrap();
+        assert_eq!(mock.requests()[0]["params"]["start_date"], "20240102");
+        assert_float(&df, MONEYFLOW_HSGT_NUMERIC);
+    }
+
+    #[test]
+    fn test_hsgt_top10() {
+        let day = NaiveDate::from_ymd_opt(2024, 1, 2).unwrap();
+        let mock = transport("hsgt_top10", HSGT_TOP10_FIELDS, vec![row(HSGT_TOP10_FIELDS, HSGT_TOP10_NUMERIC, "20240102")]);
+        let tushare = Tushare::new("<token here>").with_transport(mock.clone());
+        let df = tushare.hsgt_top10(day, Some(Northbound::Shenzhen)).unwrap();
+        tushare.hsgt_top10(day, Some(Northbound::Shanghai)).unwrap();
+        tushare.hsgt_top10(day, None).unwrap();
+        let requests = mock.requests();
+        assert_eq!(requests[0]["params"]["trade_date"], "20240102");
+        assert_eq!(requests[0]["params"]["market_type"], "3");
+        assert_eq!(requests[1]["params"]["market_type"], "1");
+        assert!(requests[2]["params"].get("market_type").is_none());
+        assert_float(&df, HSGT_TOP10_NUMERIC);
+    }
+
+    #[test]
+    fn test_ggt_top10() {
+        let day = NaiveDate::from_ymd_opt(2024, 1, 2).unwrap();
+        let mock = transport("ggt_top10", GGT_TOP10_FIELDS, vec![row(GGT_TOP10_FIELDS, GGT_TOP10_NUMERIC, "20240102")]);
+        let tushare = Tushare::new("<token here>").with_transport(mock.clone());
+        let df = tushare.ggt_top10(day, Some(Southbound::Shanghai)).unwrap();
+        tushare.ggt_top10(day, Some(Southbound::Shenzhen)).unwrap();
+        let requests = mock.requests();
+        assert_eq!(requests[0]["params"]["market_type"], "2");
+        assert_eq!(requests[1]["params"]["market_type"], "4");
+        assert_float(&df, GGT_TOP10_NUMERIC);
+
+        let mock = transport("ggt_top10", GGT_TOP10_FIELDS, vec![]);
+        let df = Tushare::new("<token here>").with_transport(mock).ggt_top10(day, None).unwrap();
+        assert_eq!(df.height(), 0);
+    }
+}
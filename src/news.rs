@@ -0,0 +1,118 @@
+//! Helpers of the announcement and news apis, for event driven strategies.
+//! The keywords are matched on client side, since tushare has no text search param.
+use crate::api::TushareApi;
+use crate::code::check_codes;
+use crate::date::TUSHARE_DATETIME_FORMAT;
+use crate::error::TushareError;
+use crate::params::NewsSource;
+use crate::transform;
+use crate::tushare::Tushare;
+use chrono::{NaiveDate, NaiveDateTime};
+use polars::prelude::*;
+use std::ops::RangeInclusive;
+
+/// Fields returned by Tushare::anns()
+pub const ANNS_FIELDS: &str = "ts_code,ann_date,ann_type,title,content,pub_time";
+
+/// Fields returned by Tushare::news()
+pub const NEWS_FIELDS: &str = "datetime,title,content,channels";
+
+/// Keep the rows whose columns contain any of the keywords, all rows if keywords is empty.
+/// Columns not present or not String are ignored, nulls never match.
+pub fn filter_keywords(df: DataFrame, columns: &[&str], keywords: &[&str]) -> Result<DataFrame, TushareError> {
+    if keywords.is_empty() {
+        return Ok(df);
+    }
+    let mut mask = BooleanChunked::full("mask", false, df.height());
+    for name in columns {
+        let Ok(column) = df.column(name) else {
+            continue;
+        };
+        let Ok(values) = column.str() else {
+            continue;
+        };
+        let matched: BooleanChunked = values
+            .into_iter()
+            .map(|v| v.is_some_and(|v| keywords.iter().any(|k| v.contains(k))))
+            .collect();
+        mask = &mask | &matched;
+    }
+    Ok(df.filter(&mask)?)
+}
+
+impl Tushare {
+    /// Announcements of a stock, or every stock if ts_code is None, with ann_date within range.
+    /// Only the ones with any of the keywords in title are kept, all of them if keywords is empty.
+    /// ann_date is a Date and pub_time a Datetime, sorted by pub_time ascending.
+    pub fn anns(
+        &self,
+        ts_code: Option<&str>,
+        range: RangeInclusive<NaiveDate>,
+        keywords: &[&str],
+    ) -> Result<DataFrame, TushareError> {
+        let mut query = self
+            .querybuilder(TushareApi::Anns)
+            .start_date(range.start())
+            .end_date(range.end())
+            .fields(ANNS_FIELDS)
+            .parse_dates(true)
+            .allow_empty(true);
+        if let Some(ts_code) = ts_code {
+            check_codes(ts_code)?;
+            query = query.addparam("ts_code", ts_code);
+        }
+        let df = transform::parse_datetimes(query.query_all()?, &["pub_time"])?;
+        let df = filter_keywords(df, &["title"], keywords)?;
+        Ok(df.sort(["pub_time"], SortMultipleOptions::default().with_maintain_order(true))?)
+    }
+
+    /// Flash news of the source between start and end, both inclusive.
+    /// Only the ones with any of the keywords in title or content are kept, all of them if keywords is empty.
+    /// datetime is a Datetime, sorted ascending.
+    /// ```no_run
+    /// # fn main() -> Result<(), tushare::TushareError> {
+    /// use chrono::NaiveDate;
+    /// let tushare = tushare::Tushare::from_env()?;
+    /// let day = NaiveDate::from_ymd_opt(2024, 4, 24).unwrap();
+    /// let start = day.and_hms_opt(9, 0, 0).unwrap();
+    /// let end = day.and_hms_opt(15, 0, 0).unwrap();
+    /// let news = tushare.news(tushare::NewsSource::Sina, start, end, &["降准", "降息"])?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn news(
+        &self,
+        src: NewsSource,
+        start: NaiveDateTime,
+        end: NaiveDateTime,
+        keywords: &[&str],
+    ) -> Result<DataFrame, TushareError> {
+        let df = self
+            .querybuilder(TushareApi::News)
+            .addparam("src", src)
+            .addparam("start_date", start.format(TUSHARE_DATETIME_FORMAT).to_string())
+            .addparam("end_date", end.format(TUSHARE_DATETIME_FORMAT).to_string())
+            .fields(NEWS_FIELDS)
+            .allow_empty(true)
+            .query_all()?;
+        let df = transform::parse_datetimes(df, &["datetime"])?;
+        let df = filter_keywords(df, &["title", "content"], keywords)?;
+        Ok(df.sort(["datetime"], SortMultipleOptions::default().with_maintain_order(true))?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[test]
+    fn test_filter_keywords() {
+        let df = df!(
+            "title" => [Some("央行宣布降准"), Some("公司年报"), None],
+            "content" => ["", "降息预期", "降准"]
+        )
+        .unwrap();
+        assert_eq!(filter_keywords(df.clone(), &["title"], &["降准"]).unwrap().height(), 1);
+        assert_eq!(filter_keywords(df.clone(), &["title", "content"], &["降准", "降息"]).unwrap().height(), 3);
+        assert_eq!(filter_keywords(df, &["missing"], &["降准"]).unwrap().height(), 0);
+    }
+}
@@ -0,0 +1,132 @@
+//! Helpers of the Hong Kong and US stock apis.
+//! The codes are checked before sending, as tushare returns empty data for a code of the wrong market,
+//! e.g. "700.HK" instead of "00700.HK", or "AAPL.US" instead of "AAPL".
+use crate::api::TushareApi;
+use crate::error::TushareError;
+use crate::params::ListStatus;
+use crate::stock::{float_dtypes, DAILY_FIELDS, DAILY_NUMERIC};
+use crate::tushare::Tushare;
+use chrono::NaiveDate;
+use polars::prelude::*;
+use std::ops::RangeInclusive;
+
+/// Fields returned by Tushare::hk_basic()
+pub const HK_BASIC_FIELDS: &str = "ts_code,name,fullname,enname,cn_spell,market,list_status,\
+list_date,delist_date,trade_unit,isin,curr_type";
+
+/// Fields returned by Tushare::us_basic()
+pub const US_BASIC_FIELDS: &str = "ts_code,name,enname,classify,list_date,delist_date";
+
+/// Fields returned by Tushare::us_daily(), note the change in percent is pct_change instead of pct_chg
+pub const US_DAILY_FIELDS: &str = "ts_code,trade_date,open,high,low,close,pre_close,change,\
+pct_change,vol,amount,vwap,turnover_ratio,total_mv,pe,pb";
+
+/// Numeric columns of us_daily
+const US_DAILY_NUMERIC: &[&str] = &[
+    "open", "high", "low", "close", "pre_close", "change", "pct_change", "vol", "amount", "vwap",
+    "turnover_ratio", "total_mv", "pe", "pb",
+];
+
+/// Check a Hong Kong stock code, 5 digits with the .HK suffix like "00700.HK"
+pub fn check_hk_code(ts_code: &str) -> Result<(), TushareError> {
+    match ts_code.strip_suffix(".HK") {
+        Some(symbol) if symbol.len() == 5 && symbol.bytes().all(|b| b.is_ascii_digit()) => Ok(()),
+        _ => Err(TushareError::InvalidCode(format!(
+            "{ts_code}, expected a Hong Kong code like 00700.HK"
+        ))),
+    }
+}
+
+/// Check a US stock code, the ticker without suffix like "AAPL" or "BRK.B"
+pub fn check_us_code(ts_code: &str) -> Result<(), TushareError> {
+    let valid_char = |b: u8| b.is_ascii_uppercase() || b.is_ascii_digit() || b == b'.' || b == b'-';
+    let has_suffix = [".HK", ".SH", ".SZ", ".BJ", ".US"]
+        .iter()
+        .any(|s| ts_code.ends_with(s));
+    if !ts_code.is_empty() && ts_code.len() <= 10 && ts_code.bytes().all(valid_char) && !has_suffix {
+        Ok(())
+    } else {
+        Err(TushareError::InvalidCode(format!(
+            "{ts_code}, expected an upper case US ticker like AAPL"
+        )))
+    }
+}
+
+impl Tushare {
+    /// Hong Kong stock list, with list_date/delist_date as Date.
+    /// Tushare returns the listed stocks only if list_status is None.
+    pub fn hk_basic(&self, list_status: Option<ListStatus>) -> Result<DataFrame, TushareError> {
+        let mut query = self
+            .querybuilder(TushareApi::HkBasic)
+            .fields(HK_BASIC_FIELDS)
+            .dtypes(&[("trade_unit", DataType::Float64)])
+            .parse_dates(true);
+        if let Some(list_status) = list_status {
+            query = query.addparam("list_status", list_status);
+        }
+        query.query_all()
+    }
+
+    /// Daily bars of a Hong Kong stock within range, same columns as Tushare::daily().
+    /// InvalidCode is returned if ts_code is not like "00700.HK".
+    pub fn hk_daily(
+        &self,
+        ts_code: &str,
+        range: RangeInclusive<NaiveDate>,
+    ) -> Result<DataFrame, TushareError> {
+        check_hk_code(ts_code)?;
+        let df = self
+            .querybuilder(TushareApi::HkDaily)
+            .addparam("ts_code", ts_code)
+            .start_date(range.start())
+            .end_date(range.end())
+            .fields(DAILY_FIELDS)
+            .dtypes(&float_dtypes(DAILY_NUMERIC))
+            .parse_dates(true)
+            .query_all()?;
+        Ok(df.sort(["trade_date"], SortMultipleOptions::default())?)
+    }
+
+    /// US stock list, with list_date/delist_date as Date
+    pub fn us_basic(&self) -> Result<DataFrame, TushareError> {
+        self.querybuilder(TushareApi::UsBasic)
+            .fields(US_BASIC_FIELDS)
+            .parse_dates(true)
+            .query_all()
+    }
+
+    /// Daily bars of a US stock within range, with valuation columns like pe/pb/total_mv.
+    /// InvalidCode is returned if ts_code is not a ticker like "AAPL".
+    pub fn us_daily(
+        &self,
+        ts_code: &str,
+        range: RangeInclusive<NaiveDate>,
+    ) -> Result<DataFrame, TushareError> {
+        check_us_code(ts_code)?;
+        let df = self
+            .querybuilder(TushareApi::UsDaily)
+            .addparam("ts_code", ts_code)
+            .start_date(range.start())
+            .end_date(range.end())
+            .fields(US_DAILY_FIELDS)
+            .dtypes(&float_dtypes(US_DAILY_NUMERIC))
+            .parse_dates(true)
+            .query_all()?;
+        Ok(df.sort(["trade_date"], SortMultipleOptions::default())?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[test]
+    fn test_check_code() {
+        assert!(check_hk_code("00700.HK").is_ok());
+        assert!(check_hk_code("700.HK").is_err());
+        assert!(check_hk_code("00700").is_err());
+        assert!(check_us_code("AAPL").is_ok());
+        assert!(check_us_code("BRK.B").is_ok());
+        assert!(check_us_code("aapl").is_err());
+        assert!(check_us_code("00700.HK").is_err());
+    }
+}
@@ -0,0 +1,186 @@
+//! Pivot a long DataFrame of many codes, e.g. daily bars with ts_code and trade_date,
+//! into wide panels of dates × codes per field, the input of most factor workflows.
+use crate::calendar::TradeCalendar;
+use crate::date::{parse_tushare_date, TUSHARE_DATE_FORMAT};
+use crate::error::TushareError;
+use chrono::NaiveDate;
+use polars::prelude::*;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+
+/// Describe how a long DataFrame is pivoted into panels.
+/// Every panel has the date column followed by a Float64 column per code in sorted order, one row per date ascending.
+/// ```
+/// use polars::prelude::*;
+/// use tushare::panel::PanelBuilder;
+/// let daily = df!(
+///     "ts_code" => ["000001.SZ", "600000.SH", "000001.SZ"],
+///     "trade_date" => ["20240102", "20240102", "20240103"],
+///     "close" => [10.5, 7.2, 10.6]
+/// ).unwrap();
+/// let close = PanelBuilder::new().forward_fill(true).pivot(&daily, "close").unwrap();
+/// assert_eq!(close.get_column_names(), ["trade_date", "000001.SZ", "600000.SH"]);
+/// assert_eq!(close.column("600000.SH").unwrap().f64().unwrap().get(1), Some(7.2));
+/// ```
+#[derive(Debug, Clone)]
+pub struct PanelBuilder {
+    date_column: String,
+    code_column: String,
+    calendar: Option<TradeCalendar>,
+    forward_fill: bool,
+}
+
+impl Default for PanelBuilder {
+    fn default() -> Self {
+        PanelBuilder::new()
+    }
+}
+
+impl PanelBuilder {
+    /// Pivot by trade_date and ts_code, on the dates of the DataFrame without filling
+    pub fn new() -> Self {
+        PanelBuilder {
+            date_column: "trade_date".to_string(),
+            code_column: "ts_code".to_string(),
+            calendar: None,
+            forward_fill: false,
+        }
+    }
+
+    /// The column of the rows, a YYYYMMDD string or a Date column, default trade_date
+    pub fn date_column(self, date_column: &str) -> Self {
+        PanelBuilder {
+            date_column: date_column.to_string(),
+            ..self
+        }
+    }
+
+    /// The column of the columns, default ts_code
+    pub fn code_column(self, code_column: &str) -> Self {
+        PanelBuilder {
+            code_column: code_column.to_string(),
+            ..self
+        }
+    }
+
+    /// Use the trading days of the calendar as the rows, so a suspended code has a null on the days it is missing,
+    /// and the rows out of the calendar are dropped
+    pub fn align_to(self, calendar: &TradeCalendar) -> Self {
+        PanelBuilder {
+            calendar: Some(calendar.clone()),
+            ..self
+        }
+    }
+
+    /// Fill the nulls of every code with its last value, the days before its first value stay null
+    pub fn forward_fill(self, forward_fill: bool) -> Self {
+        PanelBuilder { forward_fill, ..self }
+    }
+
+    /// The panel of a numeric field, e.g. close
+    pub fn pivot(&self, df: &DataFrame, field: &str) -> Result<DataFrame, TushareError> {
+        Ok(self.pivot_fields(df, &[field])?.remove(field).unwrap_or_default())
+    }
+
+    /// The panels of several numeric fields by field name, sharing their dates and codes
+    pub fn pivot_fields(&self, df: &DataFrame, fields: &[&str]) -> Result<BTreeMap<String, DataFrame>, TushareError> {
+        let date_column = df.column(&self.date_column)?;
+        let is_date = date_column.dtype() == &DataType::Date;
+        let dates = parse_dates(date_column)?;
+        let codes = df.column(&self.code_column)?.cast(&DataType::String)?;
+        let codes = codes.str()?;
+
+        let rows: Vec<NaiveDate> = match &self.calendar {
+            Some(calendar) => calendar.trading_days().collect(),
+            None => dates.iter().flatten().copied().collect::<BTreeSet<_>>().into_iter().collect(),
+        };
+        let columns: Vec<&str> = codes.into_iter().flatten().collect::<BTreeSet<_>>().into_iter().collect();
+        let row_of: HashMap<NaiveDate, usize> = rows.iter().enumerate().map(|(i, d)| (*d, i)).collect();
+        let column_of: HashMap<&str, usize> = columns.iter().enumerate().map(|(i, c)| (*c, i)).collect();
+        // the cell of every row of df, None if its date is not a row of the panel
+        let cells: Vec<Option<(usize, usize)>> = dates
+            .iter()
+            .zip(codes)
+            .map(|(date, code)| Some((*row_of.get(&(*date)?)?, column_of[code?])))
+            .collect();
+
+        let date_series = match is_date {
+            true => Series::new(&self.date_column, &rows),
+            false => {
+                let rows: Vec<String> = rows.iter().map(|d| d.format(TUSHARE_DATE_FORMAT).to_string()).collect();
+                Series::new(&self.date_column, rows)
+            }
+        };
+        let mut panels = BTreeMap::new();
+        for field in fields {
+            let values = df.column(field)?.cast(&DataType::Float64)?;
+            let mut grid = vec![vec![None; rows.len()]; columns.len()];
+            for (cell, value) in cells.iter().zip(values.f64()?) {
+                if let (Some((row, column)), Some(value)) = (cell, value) {
+                    grid[*column][*row] = Some(value);
+                }
+            }
+            let mut series = vec![date_series.clone()];
+            for (code, mut values) in columns.iter().zip(grid) {
+                if self.forward_fill {
+                    let mut last = None;
+                    for value in values.iter_mut() {
+                        match value {
+                            Some(v) => last = Some(*v),
+                            None => *value = last,
+                        }
+                    }
+                }
+                series.push(Series::new(code, values));
+            }
+            panels.insert(field.to_string(), DataFrame::new(series)?);
+        }
+        Ok(panels)
+    }
+}
+
+/// The dates of a YYYYMMDD string or Date column
+fn parse_dates(column: &Series) -> Result<Vec<Option<NaiveDate>>, TushareError> {
+    if column.dtype() == &DataType::Date {
+        return Ok(column.date()?.as_date_iter().collect());
+    }
+    let column = column.cast(&DataType::String)?;
+    column
+        .str()?
+        .into_iter()
+        .map(|s| match s {
+            Some(s) => parse_tushare_date(s)
+                .map(Some)
+                .ok_or_else(|| TushareError::InvalidParams(format!("{s} of {} is not a YYYYMMDD date", column.name()))),
+            None => Ok(None),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[test]
+    fn test_panel_align() {
+        let daily = df!(
+            "ts_code" => ["000001.SZ", "600000.SH", "000001.SZ", "600000.SH"],
+            "trade_date" => ["20240102", "20240102", "20240104", "20240105"],
+            "close" => [10.5, 7.2, 10.6, 7.3],
+            "vol" => [100, 200, 110, 210]
+        )
+        .unwrap();
+        let day = |d| NaiveDate::from_ymd_opt(2024, 1, d).unwrap();
+        let calendar = TradeCalendar::new(day(2)..=day(4), [day(2), day(3), day(4)]);
+        let panels = PanelBuilder::new()
+            .align_to(&calendar)
+            .forward_fill(true)
+            .pivot_fields(&daily, &["close", "vol"])
+            .unwrap();
+        let close = &panels["close"];
+        assert_eq!(close.height(), 3);
+        let sz: Vec<Option<f64>> = close.column("000001.SZ").unwrap().f64().unwrap().into_iter().collect();
+        assert_eq!(sz, [Some(10.5), Some(10.5), Some(10.6)]);
+        let sh: Vec<Option<f64>> = close.column("600000.SH").unwrap().f64().unwrap().into_iter().collect();
+        assert_eq!(sh, [Some(7.2), Some(7.2), Some(7.2)]);
+        assert_eq!(panels["vol"].column("000001.SZ").unwrap().f64().unwrap().get(1), Some(100.0));
+    }
+}
@@ -0,0 +1,622 @@
+//! Enums of the parameter values predefined by tushare.
+//! Tushare returns empty data instead of an error for an unknown value,
+//! so the enums save a lot of debugging of silently empty results.
+use crate::code::TsCode;
+use crate::date::IntoTushareDate;
+use chrono::{Datelike, NaiveDate};
+use std::cmp::Ordering;
+use std::fmt;
+
+/// Values accepted by QueryBuilder::addparam(), formatted the way tushare expects.
+/// Dates are formatted as *YYYYMMDD*, bools as "1"/"0", and the enums of this module as their as_str().
+/// ```
+/// # let tushare = tushare::Tushare::new("<token>");
+/// use chrono::NaiveDate;
+/// let query = tushare.querybuilder("daily")
+///     .addparam("ts_code", "000001.SZ")
+///     .addparam("trade_date", NaiveDate::from_ymd_opt(2024, 4, 24).unwrap())
+///     .addparam("limit", 100);
+/// ```
+pub trait IntoParamValue {
+    /// The string sent to tushare
+    fn to_param_value(&self) -> String;
+}
+
+impl<T: IntoParamValue + ?Sized> IntoParamValue for &T {
+    fn to_param_value(&self) -> String {
+        (**self).to_param_value()
+    }
+}
+
+impl IntoParamValue for bool {
+    fn to_param_value(&self) -> String {
+        if *self { "1" } else { "0" }.to_string()
+    }
+}
+
+impl IntoParamValue for NaiveDate {
+    fn to_param_value(&self) -> String {
+        self.to_tushare_date()
+    }
+}
+
+macro_rules! impl_param_value_to_string {
+    ($($t:ty),*) => {
+        $(impl IntoParamValue for $t {
+            fn to_param_value(&self) -> String {
+                self.to_string()
+            }
+        })*
+    };
+}
+
+impl_param_value_to_string!(str, String, TsCode, i8, i16, i32, i64, isize, u8, u16, u32, u64, usize, f32, f64);
+
+/// Stock exchanges, e.g. the exchange param of stock_basic and trade_cal
+/// ```
+/// use tushare::{Exchange, Market};
+/// # let tushare = tushare::Tushare::new("<token>");
+/// let query = tushare.querybuilder("stock_basic").addparam("exchange", Exchange::SZSE).addparam("market", Market::ChiNext);
+/// assert_eq!(query.preview()["params"]["exchange"], "SZSE");
+/// assert_eq!(query.preview()["params"]["market"], "创业板");
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum Exchange {
+    /// Shanghai Stock Exchange
+    SSE,
+    /// Shenzhen Stock Exchange
+    SZSE,
+    /// Beijing Stock Exchange
+    BSE,
+    /// Hong Kong Exchanges, only used by the stock codes as the trade calendar is not in trade_cal
+    HKEX,
+}
+
+impl Exchange {
+    /// The value sent to tushare
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Exchange::SSE => "SSE",
+            Exchange::SZSE => "SZSE",
+            Exchange::BSE => "BSE",
+            Exchange::HKEX => "HKEX",
+        }
+    }
+
+    /// The suffix of the stock codes, e.g. "SZ" of "000001.SZ"
+    pub fn code_suffix(&self) -> &'static str {
+        match self {
+            Exchange::SSE => "SH",
+            Exchange::SZSE => "SZ",
+            Exchange::BSE => "BJ",
+            Exchange::HKEX => "HK",
+        }
+    }
+}
+
+/// Market boards of A shares
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Market {
+    /// Main board (主板)
+    Main,
+    /// ChiNext board of SZSE (创业板)
+    ChiNext,
+    /// STAR market of SSE (科创板)
+    Star,
+    /// Chinese depositary receipts (CDR)
+    Cdr,
+    /// Beijing Stock Exchange (北交所)
+    Bse,
+}
+
+impl Market {
+    /// The value sent to tushare, tushare uses chinese names of the boards
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Market::Main => "主板",
+            Market::ChiNext => "创业板",
+            Market::Star => "科创板",
+            Market::Cdr => "CDR",
+            Market::Bse => "北交所",
+        }
+    }
+}
+
+/// Markets of the northbound Stock Connect, the market_type of hsgt_top10
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Northbound {
+    /// Shanghai Connect (沪股通)
+    Shanghai,
+    /// Shenzhen Connect (深股通)
+    Shenzhen,
+}
+
+impl Northbound {
+    /// The value sent to tushare
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Northbound::Shanghai => "1",
+            Northbound::Shenzhen => "3",
+        }
+    }
+}
+
+/// Markets of the southbound Stock Connect, the market_type of ggt_top10
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Southbound {
+    /// Hong Kong stocks bought through Shanghai (港股通(沪))
+    Shanghai,
+    /// Hong Kong stocks bought through Shenzhen (港股通(深))
+    Shenzhen,
+}
+
+impl Southbound {
+    /// The value sent to tushare
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Southbound::Shanghai => "2",
+            Southbound::Shenzhen => "4",
+        }
+    }
+}
+
+/// Limit status of a stock in limit_list_d
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum LimitType {
+    /// Closed at the limit up (涨停)
+    Up,
+    /// Closed at the limit down (跌停)
+    Down,
+    /// Hit the limit up but closed below it (炸板)
+    Broken,
+}
+
+impl LimitType {
+    /// The value sent to tushare
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            LimitType::Up => "U",
+            LimitType::Down => "D",
+            LimitType::Broken => "Z",
+        }
+    }
+}
+
+/// Direction of a holder trade, the trade_type of stk_holdertrade
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum HolderTradeType {
+    /// Shares increased (增持)
+    Increase,
+    /// Shares decreased (减持)
+    Decrease,
+}
+
+impl HolderTradeType {
+    /// The value sent to tushare
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            HolderTradeType::Increase => "IN",
+            HolderTradeType::Decrease => "DE",
+        }
+    }
+}
+
+/// Type of a holder, the holder_type of stk_holdertrade
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum HolderType {
+    /// Company (公司)
+    Company,
+    /// Individual (个人)
+    Person,
+    /// Executive (高管)
+    Executive,
+}
+
+impl HolderType {
+    /// The value sent to tushare
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            HolderType::Company => "C",
+            HolderType::Person => "P",
+            HolderType::Executive => "G",
+        }
+    }
+}
+
+/// Sources of the flash news, the src of the news api
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum NewsSource {
+    /// Sina Finance (新浪财经)
+    Sina,
+    /// Wall Street CN (华尔街见闻)
+    WallStreetCn,
+    /// 10jqka (同花顺)
+    Ths,
+    /// East Money (东方财富)
+    EastMoney,
+    /// Yuncaijing (云财经)
+    YunCaiJing,
+    /// Phoenix Finance (凤凰新闻)
+    FengHuang,
+    /// JRJ (金融界)
+    JinRongJie,
+}
+
+impl NewsSource {
+    /// The value sent to tushare
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            NewsSource::Sina => "sina",
+            NewsSource::WallStreetCn => "wallstreetcn",
+            NewsSource::Ths => "10jqka",
+            NewsSource::EastMoney => "eastmoney",
+            NewsSource::YunCaiJing => "yuncaijing",
+            NewsSource::FengHuang => "fenghuang",
+            NewsSource::JinRongJie => "jinrongjie",
+        }
+    }
+}
+
+/// Listing status of a stock
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ListStatus {
+    /// Listed
+    Listed,
+    /// Delisted
+    Delisted,
+    /// Listing suspended
+    Paused,
+}
+
+impl ListStatus {
+    /// The value sent to tushare
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ListStatus::Listed => "L",
+            ListStatus::Delisted => "D",
+            ListStatus::Paused => "P",
+        }
+    }
+}
+
+/// Report types of the financial statements
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum ReportType {
+    /// Consolidated statement (合并报表)
+    #[default]
+    Consolidated,
+    /// Consolidated statement of the single quarter (单季合并)
+    ConsolidatedQuarter,
+    /// Adjusted consolidated statement of the single quarter (调整单季合并表)
+    ConsolidatedQuarterAdjusted,
+    /// Adjusted consolidated statement (调整合并报表)
+    ConsolidatedAdjusted,
+    /// Consolidated statement before adjustment (调整前合并报表)
+    ConsolidatedBeforeAdjustment,
+    /// Statement of the parent company (母公司报表)
+    Parent,
+}
+
+impl ReportType {
+    /// The value sent to tushare
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ReportType::Consolidated => "1",
+            ReportType::ConsolidatedQuarter => "2",
+            ReportType::ConsolidatedQuarterAdjusted => "3",
+            ReportType::ConsolidatedAdjusted => "4",
+            ReportType::ConsolidatedBeforeAdjustment => "5",
+            ReportType::Parent => "6",
+        }
+    }
+}
+
+/// The report period of the financial statements, formatted as its end_date, e.g. "20240630" of H1(2024).
+/// Use it for the period/end_date params and the periods of the financial helpers instead of the date strings.
+/// ```
+/// use tushare::ReportPeriod;
+/// # let tushare = tushare::Tushare::new("<token>");
+/// let query = tushare.querybuilder("income_vip").addparam("period", ReportPeriod::Annual(2023));
+/// assert_eq!(query.preview()["params"]["period"], "20231231");
+/// assert_eq!(ReportPeriod::Q1(2024).previous(), ReportPeriod::Annual(2023));
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ReportPeriod {
+    /// The first quarter report (一季报), ending on March 31
+    Q1(i32),
+    /// The half year report (半年报), ending on June 30
+    H1(i32),
+    /// The third quarter report (三季报), ending on September 30
+    Q3(i32),
+    /// The annual report (年报), ending on December 31
+    Annual(i32),
+}
+
+impl ReportPeriod {
+    /// The year of the period
+    pub fn year(&self) -> i32 {
+        match *self {
+            ReportPeriod::Q1(year) | ReportPeriod::H1(year) | ReportPeriod::Q3(year) | ReportPeriod::Annual(year) => year,
+        }
+    }
+
+    /// Month and day of the last day of the period
+    fn month_day(&self) -> (u32, u32) {
+        match self {
+            ReportPeriod::Q1(_) => (3, 31),
+            ReportPeriod::H1(_) => (6, 30),
+            ReportPeriod::Q3(_) => (9, 30),
+            ReportPeriod::Annual(_) => (12, 31),
+        }
+    }
+
+    /// The last day of the period, the end_date of its statements.
+    /// None if the year is out of the range of NaiveDate.
+    pub fn end_date(&self) -> Option<NaiveDate> {
+        let (month, day) = self.month_day();
+        NaiveDate::from_ymd_opt(self.year(), month, day)
+    }
+
+    /// The period ending on the date, None if it is not the end of a quarter
+    pub fn from_end_date(date: NaiveDate) -> Option<Self> {
+        let period = match (date.month(), date.day()) {
+            (3, 31) => ReportPeriod::Q1,
+            (6, 30) => ReportPeriod::H1,
+            (9, 30) => ReportPeriod::Q3,
+            (12, 31) => ReportPeriod::Annual,
+            _ => return None,
+        };
+        Some(period(date.year()))
+    }
+
+    /// The latest period ended on or before the date, e.g. Q1 of the year on May 1
+    pub fn last_ended(date: NaiveDate) -> Self {
+        let year = date.year();
+        [ReportPeriod::Annual(year), ReportPeriod::Q3(year), ReportPeriod::H1(year), ReportPeriod::Q1(year)]
+            .into_iter()
+            .find(|p| p.end_date().is_some_and(|end| end <= date))
+            .unwrap_or(ReportPeriod::Annual(year - 1))
+    }
+
+    /// The period before, e.g. the annual report of the last year before Q1
+    pub fn previous(&self) -> Self {
+        match *self {
+            ReportPeriod::Q1(year) => ReportPeriod::Annual(year - 1),
+            ReportPeriod::H1(year) => ReportPeriod::Q1(year),
+            ReportPeriod::Q3(year) => ReportPeriod::H1(year),
+            ReportPeriod::Annual(year) => ReportPeriod::Q3(year),
+        }
+    }
+
+    /// The period after, e.g. Q1 of the next year after the annual report
+    pub fn next(&self) -> Self {
+        match *self {
+            ReportPeriod::Q1(year) => ReportPeriod::H1(year),
+            ReportPeriod::H1(year) => ReportPeriod::Q3(year),
+            ReportPeriod::Q3(year) => ReportPeriod::Annual(year),
+            ReportPeriod::Annual(year) => ReportPeriod::Q1(year + 1),
+        }
+    }
+}
+
+impl PartialOrd for ReportPeriod {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for ReportPeriod {
+    fn cmp(&self, other: &Self) -> Ordering {
+        (self.year(), self.month_day()).cmp(&(other.year(), other.month_day()))
+    }
+}
+
+/// The end_date of the period, saturated to NaiveDate::MIN/MAX if the year is out of the range of NaiveDate,
+/// so a range of periods still covers the same dates
+impl From<ReportPeriod> for NaiveDate {
+    fn from(period: ReportPeriod) -> Self {
+        period.end_date().unwrap_or(if period.year() < 0 { NaiveDate::MIN } else { NaiveDate::MAX })
+    }
+}
+
+impl IntoTushareDate for ReportPeriod {
+    fn to_tushare_date(&self) -> String {
+        let (month, day) = self.month_day();
+        format!("{:04}{month:02}{day:02}", self.year())
+    }
+}
+
+impl IntoParamValue for ReportPeriod {
+    fn to_param_value(&self) -> String {
+        self.to_tushare_date()
+    }
+}
+
+impl fmt::Display for ReportPeriod {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.to_tushare_date())
+    }
+}
+
+/// Common stock indexes
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Index {
+    /// SSE 50 (上证50)
+    SSE50,
+    /// CSI 300 (沪深300)
+    CSI300,
+    /// CSI 500 (中证500)
+    CSI500,
+    /// CSI 1000 (中证1000)
+    CSI1000,
+    /// ChiNext Index (创业板指)
+    ChiNext,
+    /// STAR 50 (科创50)
+    STAR50,
+}
+
+impl Index {
+    /// The index_code sent to tushare
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Index::SSE50 => "000016.SH",
+            Index::CSI300 => "000300.SH",
+            Index::CSI500 => "000905.SH",
+            Index::CSI1000 => "000852.SH",
+            Index::ChiNext => "399006.SZ",
+            Index::STAR50 => "000688.SH",
+        }
+    }
+}
+
+/// Levels of the Shenwan industry classification
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum SwLevel {
+    /// Level 1 industries (一级行业)
+    L1,
+    /// Level 2 industries (二级行业)
+    L2,
+    /// Level 3 industries (三级行业)
+    L3,
+}
+
+impl SwLevel {
+    /// The value sent to tushare
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SwLevel::L1 => "L1",
+            SwLevel::L2 => "L2",
+            SwLevel::L3 => "L3",
+        }
+    }
+}
+
+/// Where a fund is traded
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum FundMarket {
+    /// Exchange traded funds like ETF and LOF (场内)
+    Exchange,
+    /// Over-the-counter mutual funds (场外)
+    Otc,
+}
+
+impl FundMarket {
+    /// The value sent to tushare
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            FundMarket::Exchange => "E",
+            FundMarket::Otc => "O",
+        }
+    }
+}
+
+/// Status of a fund
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum FundStatus {
+    /// Issuing
+    Issuing,
+    /// Listed
+    Listed,
+    /// Delisted
+    Delisted,
+}
+
+impl FundStatus {
+    /// The value sent to tushare
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            FundStatus::Issuing => "I",
+            FundStatus::Listed => "L",
+            FundStatus::Delisted => "D",
+        }
+    }
+}
+
+/// Exchanges of futures and options
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum DerivativeExchange {
+    /// China Financial Futures Exchange (中金所)
+    CFFEX,
+    /// Dalian Commodity Exchange (大商所)
+    DCE,
+    /// Zhengzhou Commodity Exchange (郑商所)
+    CZCE,
+    /// Shanghai Futures Exchange (上期所)
+    SHFE,
+    /// Shanghai International Energy Exchange (上能源)
+    INE,
+    /// Guangzhou Futures Exchange (广期所)
+    GFEX,
+    /// Shanghai Stock Exchange, for ETF options
+    SSE,
+    /// Shenzhen Stock Exchange, for ETF options
+    SZSE,
+}
+
+impl DerivativeExchange {
+    /// The value sent to tushare
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            DerivativeExchange::CFFEX => "CFFEX",
+            DerivativeExchange::DCE => "DCE",
+            DerivativeExchange::CZCE => "CZCE",
+            DerivativeExchange::SHFE => "SHFE",
+            DerivativeExchange::INE => "INE",
+            DerivativeExchange::GFEX => "GFEX",
+            DerivativeExchange::SSE => "SSE",
+            DerivativeExchange::SZSE => "SZSE",
+        }
+    }
+}
+
+/// Display the enums and pass them to addparam() as the strings tushare expects
+macro_rules! impl_param_enum {
+    ($($t:ty),*) => {
+        $(impl fmt::Display for $t {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                f.write_str(self.as_str())
+            }
+        }
+
+        impl IntoParamValue for $t {
+            fn to_param_value(&self) -> String {
+                self.as_str().to_string()
+            }
+        })*
+    };
+}
+
+impl_param_enum!(
+    Exchange,
+    Market,
+    Northbound,
+    Southbound,
+    LimitType,
+    HolderTradeType,
+    HolderType,
+    NewsSource,
+    ListStatus,
+    ReportType,
+    Index,
+    SwLevel,
+    FundMarket,
+    FundStatus,
+    DerivativeExchange
+);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[test]
+    fn test_report_period_out_of_range() {
+        let period = ReportPeriod::Annual(i32::MAX);
+        assert_eq!(period.end_date(), None);
+        assert_eq!(NaiveDate::from(period), NaiveDate::MAX);
+        assert_eq!(NaiveDate::from(ReportPeriod::Q1(i32::MIN)), NaiveDate::MIN);
+        assert_eq!(period.to_tushare_date(), format!("{}1231", i32::MAX));
+        assert!(ReportPeriod::Q1(i32::MAX) < period);
+        assert!(ReportPeriod::Annual(2023) < ReportPeriod::Q1(2024));
+        assert_eq!(ReportPeriod::H1(2024).end_date(), NaiveDate::from_ymd_opt(2024, 6, 30));
+        assert_eq!(ReportPeriod::H1(2024).to_tushare_date(), "20240630");
+    }
+}
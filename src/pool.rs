@@ -0,0 +1,94 @@
+use crate::builder::QueryBuilder;
+use crate::error::TushareError;
+use crate::tushare::Tushare;
+use polars::prelude::*;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::thread;
+
+/// Call f for every item in up to workers threads, the results are in the order of items
+pub(crate) fn parallel_map<T: Sync, R: Send>(
+    items: &[T],
+    workers: usize,
+    f: impl Fn(&T) -> R + Sync,
+) -> Vec<R> {
+    let next = AtomicUsize::new(0);
+    let results: Vec<Mutex<Option<R>>> = items.iter().map(|_| Mutex::new(None)).collect();
+    thread::scope(|scope| {
+        for _ in 0..workers.clamp(1, items.len().max(1)) {
+            scope.spawn(|| loop {
+                let i = next.fetch_add(1, Ordering::Relaxed);
+                let Some(item) = items.get(i) else { break };
+                let result = f(item);
+                *results[i].lock().unwrap_or_else(|e| e.into_inner()) = Some(result);
+            });
+        }
+    });
+    results
+        .into_iter()
+        .map(|r| {
+            r.into_inner()
+                .unwrap_or_else(|e| e.into_inner())
+                .expect("every item is processed once the scope ends")
+        })
+        .collect()
+}
+
+/// Run many independent queries with a bounded number of worker threads.
+/// The queries share the rate limiter, retry policy and cache of their Tushare objects,
+/// so it is safe to submit thousands of them, e.g. daily bars of every stock.
+#[derive(Debug, Clone)]
+pub struct QueryPool {
+    workers: usize,
+    all_pages: bool,
+}
+
+impl QueryPool {
+    /// Create a pool running up to workers queries at the same time
+    pub fn new(workers: usize) -> Self {
+        QueryPool {
+            workers: workers.max(1),
+            all_pages: false,
+        }
+    }
+
+    /// Use query_all() instead of query() for every query, default false
+    pub fn all_pages(self, all_pages: bool) -> Self {
+        QueryPool { all_pages, ..self }
+    }
+
+    /// Run the queries, the results are in the same order of queries.
+    /// A failed query doesn't stop the others.
+    pub fn run(&self, queries: &[QueryBuilder]) -> Vec<Result<DataFrame, TushareError>> {
+        parallel_map(queries, self.workers, |query| {
+            if self.all_pages {
+                query.query_all()
+            } else {
+                query.query()
+            }
+        })
+    }
+}
+
+impl Tushare {
+    /// Run the queries with up to workers threads, see [QueryPool]
+    pub fn query_many(
+        &self,
+        queries: &[QueryBuilder],
+        workers: usize,
+    ) -> Vec<Result<DataFrame, TushareError>> {
+        QueryPool::new(workers).run(queries)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[test]
+    fn test_parallel_map() {
+        let items: Vec<usize> = (0..100).collect();
+        let results = parallel_map(&items, 8, |i| i * 2);
+        assert_eq!(results, items.iter().map(|i| i * 2).collect::<Vec<_>>());
+        assert!(parallel_map(&[] as &[usize], 8, |i| *i).is_empty());
+    }
+}
@@ -0,0 +1,108 @@
+//! Progress of long downloads, reported to the hook set by QueryBuilder::on_progress() or SyncJob::on_progress()
+use std::fmt;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// A snapshot of a running download, passed to the progress hook after every page or code.
+/// The unit of done/total depends on the call:
+/// pages in query_all()/query_chunks(), where the total is unknown as tushare doesn't return a row count,
+/// and codes in query_batch()/Tushare::sync().
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Progress {
+    /// Pages fetched so far, only counted by query_all()/query_chunks()
+    pub pages: usize,
+    /// Rows fetched so far
+    pub rows: usize,
+    /// Units of work done
+    pub done: usize,
+    /// Total units of work, None if unknown
+    pub total: Option<usize>,
+    /// Time since the download started
+    pub elapsed: Duration,
+}
+
+impl Progress {
+    /// Units of work not done yet, None if the total is unknown
+    pub fn remaining(&self) -> Option<usize> {
+        self.total.map(|total| total.saturating_sub(self.done))
+    }
+
+    /// Estimated time to finish, by the average time of the done units
+    pub fn eta(&self) -> Option<Duration> {
+        let remaining = self.remaining()?;
+        if self.done == 0 {
+            return None;
+        }
+        Some(self.elapsed.mul_f64(remaining as f64 / self.done as f64))
+    }
+}
+
+/// A callback receiving the progress, it may be called from multiple threads
+#[derive(Clone)]
+pub struct ProgressHook(Arc<dyn Fn(&Progress) + Send + Sync>);
+
+impl ProgressHook {
+    /// Wrap the callback
+    pub fn new(f: impl Fn(&Progress) + Send + Sync + 'static) -> Self {
+        ProgressHook(Arc::new(f))
+    }
+}
+
+impl fmt::Debug for ProgressHook {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("ProgressHook")
+    }
+}
+
+/// Accumulate the progress of a download and report it to the hook
+pub(crate) struct ProgressTracker {
+    hook: Option<ProgressHook>,
+    start: Instant,
+    state: Mutex<Progress>,
+}
+
+impl ProgressTracker {
+    pub(crate) fn new(hook: Option<ProgressHook>, total: Option<usize>) -> Self {
+        ProgressTracker {
+            hook,
+            start: Instant::now(),
+            state: Mutex::new(Progress {
+                total,
+                ..Default::default()
+            }),
+        }
+    }
+
+    /// Count a unit of work done with its pages and rows, and call the hook
+    pub(crate) fn advance(&self, pages: usize, rows: usize) {
+        let Some(hook) = &self.hook else { return };
+        let progress = {
+            let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+            state.pages += pages;
+            state.rows += rows;
+            state.done += 1;
+            state.elapsed = self.start.elapsed();
+            state.clone()
+        };
+        // call the hook without the lock, a slow hook doesn't block the other threads
+        (hook.0)(&progress);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[test]
+    fn test_progress() {
+        let reported = Arc::new(Mutex::new(vec![]));
+        let sink = reported.clone();
+        let tracker = ProgressTracker::new(Some(ProgressHook::new(move |p| sink.lock().unwrap().push(p.clone()))), Some(4));
+        tracker.advance(1, 100);
+        tracker.advance(1, 50);
+        let reported = reported.lock().unwrap();
+        assert_eq!(reported.len(), 2);
+        assert_eq!((reported[1].rows, reported[1].done, reported[1].remaining()), (150, 2, Some(2)));
+        let progress = Progress { done: 1, total: Some(3), elapsed: Duration::from_secs(2), ..Default::default() };
+        assert_eq!(progress.eta(), Some(Duration::from_secs(4)));
+    }
+}
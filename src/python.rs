@@ -0,0 +1,224 @@
+//! Python bindings of Tushare and QueryBuilder, enabled by the "python" feature.
+//! The results are returned as py-polars DataFrames, so Python research shares one client
+//! (with its rate limiter and cache) with the Rust data infrastructure.
+//! The http calls release the GIL, other Python threads keep running while waiting for tushare.
+//!
+//! Build a Python extension from a cdylib crate with [maturin](https://www.maturin.rs),
+//! depending on this crate with the "python" feature and `pyo3/extension-module`:
+//! ```ignore
+//! use pyo3::prelude::*;
+//! #[pymodule]
+//! fn tushare(m: &Bound<'_, PyModule>) -> PyResult<()> {
+//!     tushare::python::register(m)
+//! }
+//! ```
+//! Then in Python:
+//! ```python
+//! import tushare
+//! ts = tushare.Tushare(rate_limit=500, cache_dir="cache")
+//! df = ts.querybuilder("daily").addparam("trade_date", "20240424").query()
+//! ```
+use crate::builder::{Dict, QueryBuilder};
+use crate::cache::DiskCache;
+use crate::error::TushareError;
+use crate::tushare::Tushare;
+use polars::prelude::*;
+use pyo3::create_exception;
+use pyo3::exceptions::PyException;
+use pyo3::prelude::*;
+use pyo3::types::PyBytes;
+use std::sync::Arc;
+use std::time::Duration;
+
+create_exception!(tushare, PyTushareError, PyException, "Any error of the tushare client");
+
+impl From<TushareError> for PyErr {
+    fn from(e: TushareError) -> Self {
+        PyTushareError::new_err(e.to_string())
+    }
+}
+
+/// The in memory Arrow IPC file of a DataFrame
+fn to_ipc(mut df: DataFrame) -> Result<Vec<u8>, TushareError> {
+    let mut buf = vec![];
+    IpcWriter::new(&mut buf).finish(&mut df)?;
+    Ok(buf)
+}
+
+/// Convert a DataFrame to a py-polars DataFrame through an in memory Arrow IPC file
+fn to_python(py: Python<'_>, df: DataFrame) -> PyResult<PyObject> {
+    let buf = to_ipc(df)?;
+    let bytes = PyModule::import(py, "io")?
+        .getattr("BytesIO")?
+        .call1((PyBytes::new(py, &buf),))?;
+    Ok(PyModule::import(py, "polars")?
+        .getattr("read_ipc")?
+        .call1((bytes,))?
+        .unbind())
+}
+
+/// The tushare client, `Tushare(token=None, rate_limit=None, cache_dir=None, cache_ttl_secs=86400)`.
+/// The token is read from TUSHARE_TOKEN if not given.
+#[pyclass(name = "Tushare", module = "tushare")]
+#[derive(Clone)]
+pub struct PyTushare {
+    tushare: Arc<Tushare>,
+}
+
+#[pymethods]
+impl PyTushare {
+    #[new]
+    #[pyo3(signature = (token=None, rate_limit=None, cache_dir=None, cache_ttl_secs=86400))]
+    fn new(
+        token: Option<&str>,
+        rate_limit: Option<u32>,
+        cache_dir: Option<&str>,
+        cache_ttl_secs: u64,
+    ) -> PyResult<Self> {
+        let mut tushare = match token {
+            Some(token) => Tushare::new(token),
+            None => Tushare::from_env()?,
+        };
+        if let Some(calls_per_minute) = rate_limit {
+            tushare = tushare.with_rate_limit(calls_per_minute);
+        }
+        if let Some(dir) = cache_dir {
+            tushare = tushare.with_cache(DiskCache::new(dir, Duration::from_secs(cache_ttl_secs)));
+        }
+        Ok(PyTushare {
+            tushare: Arc::new(tushare),
+        })
+    }
+
+    /// Create a query of the api
+    fn querybuilder(&self, api_name: &str) -> PyQueryBuilder {
+        PyQueryBuilder {
+            tushare: self.tushare.clone(),
+            api_name: api_name.to_string(),
+            params: Dict::new(),
+            fields: None,
+        }
+    }
+
+    /// Query all rows of the api with the params, a shortcut of querybuilder(...).query_all()
+    #[pyo3(signature = (api_name, params=None, fields=None))]
+    fn query(&self, py: Python<'_>, api_name: &str, params: Option<Dict>, fields: Option<&str>) -> PyResult<PyObject> {
+        let query = PyQueryBuilder {
+            params: params.unwrap_or_default(),
+            fields: fields.map(|f| f.to_string()),
+            ..self.querybuilder(api_name)
+        };
+        query.query_all(py)
+    }
+}
+
+/// A query of an api, immutable like the Rust QueryBuilder: every setter returns a new query
+#[pyclass(name = "QueryBuilder", module = "tushare")]
+#[derive(Clone)]
+pub struct PyQueryBuilder {
+    tushare: Arc<Tushare>,
+    api_name: String,
+    params: Dict,
+    fields: Option<String>,
+}
+
+impl PyQueryBuilder {
+    /// The Rust QueryBuilder of the query
+    fn builder(&self) -> QueryBuilder<'_> {
+        let query = self.tushare.querybuilder(self.api_name.as_str()).params(self.params.clone());
+        match &self.fields {
+            Some(fields) => query.fields(fields),
+            None => query,
+        }
+    }
+
+    /// Run f with the Rust QueryBuilder, with the GIL released
+    fn run(&self, py: Python<'_>, f: fn(&QueryBuilder) -> Result<DataFrame, TushareError>) -> PyResult<PyObject> {
+        let df = py.allow_threads(|| f(&self.builder()))?;
+        to_python(py, df)
+    }
+}
+
+#[pymethods]
+impl PyQueryBuilder {
+    /// Set a param, the value is sent as str
+    fn addparam(&self, k: &str, v: &str) -> Self {
+        let mut query = self.clone();
+        query.params.insert(k.to_string(), v.to_string());
+        query
+    }
+
+    /// Set the comma separated fields returned
+    fn fields(&self, fields: &str) -> Self {
+        PyQueryBuilder {
+            fields: Some(fields.to_string()),
+            ..self.clone()
+        }
+    }
+
+    /// The json body posted to tushare with the token redacted, see QueryBuilder::preview()
+    fn preview(&self) -> String {
+        self.builder().preview().to_string()
+    }
+
+    /// A single call of the api as a polars DataFrame
+    fn query(&self, py: Python<'_>) -> PyResult<PyObject> {
+        self.run(py, |query| query.query())
+    }
+
+    /// All pages of the api as a polars DataFrame
+    fn query_all(&self, py: Python<'_>) -> PyResult<PyObject> {
+        self.run(py, |query| query.query_all())
+    }
+}
+
+/// Add the classes and the exception to a Python module
+pub fn register(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add_class::<PyTushare>()?;
+    m.add_class::<PyQueryBuilder>()?;
+    m.add("TushareError", m.py().get_type::<PyTushareError>())?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::transport::MockTransport;
+    use std::io::Cursor;
+
+    fn client(mock: MockTransport) -> PyTushare {
+        PyTushare {
+            tushare: Arc::new(Tushare::new("<token here>").with_transport(mock)),
+        }
+    }
+
+    #[test]
+    fn test_query_mapping() {
+        let query = client(MockTransport::new())
+            .querybuilder("daily")
+            .addparam("ts_code", "000001.SZ")
+            .fields("ts_code,close");
+        let body = query.addparam("trade_date", "20240102").preview();
+        let body: serde_json::Value = serde_json::from_str(&body).unwrap();
+        assert_eq!(body["api_name"], "daily");
+        assert_eq!(body["params"]["ts_code"], "000001.SZ");
+        assert_eq!(body["params"]["trade_date"], "20240102");
+        assert_eq!(body["fields"], "ts_code,close");
+        // the setters return a new query
+        assert!(!query.params.contains_key("trade_date"));
+    }
+
+    #[test]
+    fn test_to_ipc() {
+        let mock = MockTransport::new().respond(
+            "daily",
+            MockTransport::data(
+                &["ts_code", "trade_date", "close"],
+                vec![vec!["000001.SZ".into(), "20240102".into(), 10.5.into()]],
+            ),
+        );
+        let df = client(mock).querybuilder("daily").builder().query().unwrap();
+        let read = IpcReader::new(Cursor::new(to_ipc(df.clone()).unwrap())).finish().unwrap();
+        assert!(read.equals_missing(&df));
+    }
+}
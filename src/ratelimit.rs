@@ -0,0 +1,113 @@
+use crate::cancel::{self, CancellationToken};
+use crate::error::TushareError;
+use log::info;
+use std::sync::Mutex;
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// A token bucket limiting the calls sent to tushare server.
+/// Tushare throttles every account by points, e.g. 200 calls per minute for 2000 points,
+/// and returns error code 40203 once exceeded.
+/// The bucket is shared by all threads querying with the same Tushare object,
+/// a call blocks until a token is available instead of failing.
+#[derive(Debug)]
+pub struct RateLimiter {
+    capacity: f64,
+    refill_per_sec: f64,
+    state: Mutex<BucketState>,
+}
+
+#[derive(Debug)]
+struct BucketState {
+    tokens: f64,
+    last_refill: Instant,
+    paused_until: Option<Instant>,
+}
+
+impl RateLimiter {
+    /// Create a rate limiter allowing calls_per_minute calls per minute.
+    /// The bucket starts full, so up to calls_per_minute calls can be sent in a burst.
+    pub fn per_minute(calls_per_minute: u32) -> Self {
+        let capacity = calls_per_minute.max(1) as f64;
+        RateLimiter {
+            capacity,
+            refill_per_sec: capacity / 60.0,
+            state: Mutex::new(BucketState {
+                tokens: capacity,
+                last_refill: Instant::now(),
+                paused_until: None,
+            }),
+        }
+    }
+
+    /// Calls allowed per minute
+    pub fn calls_per_minute(&self) -> u32 {
+        self.capacity as u32
+    }
+
+    /// Take a token if available, otherwise returns how long to wait for the next one
+    pub fn try_acquire(&self) -> Result<(), Duration> {
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        let now = Instant::now();
+        if let Some(until) = state.paused_until {
+            if until > now {
+                return Err(until - now);
+            }
+            state.paused_until = None;
+        }
+        let elapsed = now.duration_since(state.last_refill).as_secs_f64();
+        state.tokens = (state.tokens + elapsed * self.refill_per_sec).min(self.capacity);
+        state.last_refill = now;
+        if state.tokens >= 1.0 {
+            state.tokens -= 1.0;
+            Ok(())
+        } else {
+            Err(Duration::from_secs_f64(
+                (1.0 - state.tokens) / self.refill_per_sec,
+            ))
+        }
+    }
+
+    /// Stop handing out tokens for the duration, e.g. when tushare asks to wait after a rate limit error.
+    /// A longer pause already in effect is kept.
+    pub fn pause(&self, duration: Duration) {
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        let until = Instant::now() + duration;
+        if state.paused_until.is_none_or(|current| current < until) {
+            state.paused_until = Some(until);
+        }
+    }
+
+    /// Block the current thread until a token is taken
+    pub fn acquire(&self) {
+        while let Err(wait) = self.try_acquire() {
+            info!("Rate limit reached, wait {wait:?}");
+            thread::sleep(wait);
+        }
+    }
+
+    /// Same as acquire(), Cancelled as soon as the token of the query is cancelled
+    pub(crate) fn acquire_cancellable(&self, cancellation: Option<&CancellationToken>) -> Result<(), TushareError> {
+        while let Err(wait) = self.try_acquire() {
+            info!("Rate limit reached, wait {wait:?}");
+            cancel::sleep(cancellation, wait)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[test]
+    fn test_try_acquire() {
+        let limiter = RateLimiter::per_minute(2);
+        assert!(limiter.try_acquire().is_ok());
+        assert!(limiter.try_acquire().is_ok());
+        let wait = limiter.try_acquire().unwrap_err();
+        assert!(wait > Duration::from_secs(29) && wait <= Duration::from_secs(30));
+        let limiter = RateLimiter::per_minute(100);
+        limiter.pause(Duration::from_secs(10));
+        assert!(limiter.try_acquire().unwrap_err() > Duration::from_secs(9));
+    }
+}
@@ -0,0 +1,218 @@
+//! Realtime quotes, either polled from the realtime apis by a [Poller],
+//! or pushed over websocket with the "realtime" feature, see Tushare::subscribe().
+//! Both require the realtime permission of your account, and deliver the same [Tick].
+//! ```no_run
+//! # fn main() -> Result<(), tushare::TushareError> {
+//! use std::sync::Arc;
+//! use tushare::realtime::Poller;
+//! let tushare = Arc::new(tushare::Tushare::from_env()?);
+//! let (ticks, _handle) = Poller::new(&["000001.SZ", "600000.SH"]).spawn(tushare);
+//! for tick in ticks {
+//!     println!("{} {:?}", tick.ts_code, tick.price);
+//! }
+//! # Ok(())
+//! # }
+//! ```
+use crate::api::TushareApi;
+use crate::error::TushareError;
+use crate::tushare::Tushare;
+use log::{info, warn};
+use serde_json::{Map, Value};
+use std::collections::HashMap;
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+/// A realtime quote, pushed by tushare over websocket or polled by a [Poller].
+/// The common fields are picked from the record by name and None if missing,
+/// the full record is kept in record.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Tick {
+    /// Topic of the subscription, e.g. HQ_STK_TICK, or the api_name of a Poller
+    pub topic: String,
+    /// Code of the quote, e.g. 000001.SZ
+    pub ts_code: String,
+    /// Name of the security
+    pub name: Option<String>,
+    /// Time of the quote as returned, e.g. "2024-04-24 10:30:00"
+    pub trade_time: Option<String>,
+    /// Latest price
+    pub price: Option<f64>,
+    /// Open price of the day
+    pub open: Option<f64>,
+    /// High price of the day
+    pub high: Option<f64>,
+    /// Low price of the day
+    pub low: Option<f64>,
+    /// Close price of the previous trade day
+    pub pre_close: Option<f64>,
+    /// Accumulated volume of the day
+    pub vol: Option<f64>,
+    /// Accumulated amount of the day
+    pub amount: Option<f64>,
+    /// All fields of the record
+    pub record: Map<String, Value>,
+}
+
+impl Tick {
+    /// Parse a record returned by tushare
+    pub(crate) fn from_record(topic: &str, ts_code: &str, record: Map<String, Value>) -> Self {
+        let number = |keys: &[&str]| {
+            keys.iter().find_map(|k| match record.get(*k)? {
+                Value::Number(n) => n.as_f64(),
+                Value::String(s) => s.parse().ok(),
+                _ => None,
+            })
+        };
+        let text = |keys: &[&str]| {
+            keys.iter()
+                .find_map(|k| record.get(*k)?.as_str().map(|s| s.to_string()))
+        };
+        Tick {
+            topic: topic.to_string(),
+            ts_code: ts_code.to_string(),
+            name: text(&["name"]),
+            trade_time: text(&["trade_time", "time"]),
+            price: number(&["price", "close"]),
+            open: number(&["open"]),
+            high: number(&["high"]),
+            low: number(&["low"]),
+            pre_close: number(&["pre_close", "pre_price"]),
+            vol: number(&["vol", "volume"]),
+            amount: number(&["amount"]),
+            record,
+        }
+    }
+}
+
+/// Convert a batch of ticks to a DataFrame, e.g. to append them to the daily history
+#[cfg(feature = "dataframe")]
+pub fn ticks_to_dataframe(ticks: &[Tick]) -> Result<polars::prelude::DataFrame, TushareError> {
+    use polars::prelude::*;
+    let f64_column = |f: fn(&Tick) -> Option<f64>| ticks.iter().map(f).collect::<Vec<_>>();
+    Ok(df!(
+        "ts_code" => ticks.iter().map(|t| t.ts_code.as_str()).collect::<Vec<_>>(),
+        "trade_time" => ticks.iter().map(|t| t.trade_time.as_deref()).collect::<Vec<_>>(),
+        "price" => f64_column(|t| t.price),
+        "open" => f64_column(|t| t.open),
+        "high" => f64_column(|t| t.high),
+        "low" => f64_column(|t| t.low),
+        "pre_close" => f64_column(|t| t.pre_close),
+        "vol" => f64_column(|t| t.vol),
+        "amount" => f64_column(|t| t.amount)
+    )?)
+}
+
+/// Poll a realtime quote api on an interval, for accounts without the websocket access.
+/// Only the quotes changed since the last poll are sent, so an idle market sends nothing.
+#[derive(Debug, Clone)]
+pub struct Poller {
+    api_name: String,
+    codes: Vec<String>,
+    interval: Duration,
+}
+
+impl Poller {
+    /// Poll the realtime daily bars (rt_k) of the codes every 3 seconds.
+    /// Wildcards like "6*.SH" are accepted by rt_k as well.
+    pub fn new(codes: &[&str]) -> Self {
+        Poller {
+            api_name: TushareApi::RtK.to_string(),
+            codes: codes.iter().map(|c| c.to_string()).collect(),
+            interval: Duration::from_secs(3),
+        }
+    }
+
+    /// The api polled with the ts_code parameter, default rt_k
+    pub fn api_name(self, api_name: impl Into<String>) -> Self {
+        Poller {
+            api_name: api_name.into(),
+            ..self
+        }
+    }
+
+    /// Time between the start of two polls, default 3 seconds.
+    /// Every poll is a call counted by the rate limit of your account.
+    pub fn interval(self, interval: Duration) -> Self {
+        Poller { interval, ..self }
+    }
+
+    /// Poll in a new thread and receive the ticks from the returned channel.
+    /// The thread stops with the first update after the receiver is dropped, or a non retryable error.
+    pub fn spawn(self, tushare: Arc<Tushare>) -> (Receiver<Tick>, JoinHandle<Result<(), TushareError>>) {
+        let (tx, rx) = mpsc::channel();
+        let handle = thread::spawn(move || tushare.poll(&self, &tx));
+        (rx, handle)
+    }
+}
+
+/// The ticks of the rows changed since last, and remember them in last
+fn changed_ticks(topic: &str, rows: Vec<Value>, last: &mut HashMap<String, Map<String, Value>>) -> Vec<Tick> {
+    rows.into_iter()
+        .filter_map(|row| match row {
+            Value::Object(record) => Some(record),
+            _ => None,
+        })
+        .filter_map(|record| {
+            let ts_code = record.get("ts_code")?.as_str()?.to_string();
+            if last.get(&ts_code) == Some(&record) {
+                return None;
+            }
+            last.insert(ts_code.clone(), record.clone());
+            Some(Tick::from_record(topic, &ts_code, record))
+        })
+        .collect()
+}
+
+impl Tushare {
+    /// Poll the quotes and send the changed ones to tx, blocks the current thread.
+    /// Retryable errors are logged and polled again next time, other errors are returned.
+    /// Returns Ok once the receiver of tx is dropped.
+    pub fn poll(&self, poller: &Poller, tx: &Sender<Tick>) -> Result<(), TushareError> {
+        let query = self
+            .querybuilder(poller.api_name.as_str())
+            .addparam("ts_code", poller.codes.join(","));
+        let mut last = HashMap::new();
+        info!("Polling {} of {} codes every {:?}", poller.api_name, poller.codes.len(), poller.interval);
+        loop {
+            let start = Instant::now();
+            match query.query_raw() {
+                Ok(table) => {
+                    for tick in changed_ticks(&poller.api_name, table.rows(), &mut last) {
+                        if tx.send(tick).is_err() {
+                            return Ok(());
+                        }
+                    }
+                }
+                Err(e) if e.is_retryable() => warn!("Polling {} failed: {e}", poller.api_name),
+                Err(e) => return Err(e),
+            }
+            thread::sleep(poller.interval.saturating_sub(start.elapsed()));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    #[test]
+    fn test_changed_ticks() {
+        let mut last = HashMap::new();
+        let rows = vec![
+            json!({"ts_code": "000001.SZ", "close": 10.5, "vol": 100}),
+            json!({"ts_code": "600000.SH", "close": "7.1", "vol": 200}),
+        ];
+        let ticks = changed_ticks("rt_k", rows.clone(), &mut last);
+        assert_eq!(ticks.len(), 2);
+        assert_eq!(ticks[0].price, Some(10.5));
+        assert_eq!(ticks[1].price, Some(7.1));
+        let mut changed = rows;
+        changed[1]["vol"] = json!(300);
+        let ticks = changed_ticks("rt_k", changed, &mut last);
+        assert_eq!(ticks.len(), 1);
+        assert_eq!(ticks[0].ts_code, "600000.SH");
+        assert_eq!(ticks[0].vol, Some(300.0));
+    }
+}
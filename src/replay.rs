@@ -0,0 +1,145 @@
+//! Record and replay the responses of tushare, for deterministic tests and offline development.
+//! The recording is a JSON lines file, one `{"request": .., "response": ..}` object per line.
+//! The token is removed from the recorded requests, so the file is safe to commit as a test fixture.
+use crate::error::TushareError;
+use crate::transport::Transport;
+use crate::tushare::Tushare;
+use log::info;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::fs::{self, File};
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+#[derive(Debug, Serialize, Deserialize)]
+struct Record {
+    request: Value,
+    response: String,
+}
+
+/// The key of a request, the request body without token.
+/// serde_json sorts the object keys, so the string is canonical.
+fn request_key(body: &str) -> Result<(Value, String), TushareError> {
+    let mut request: Value = serde_json::from_str(body)?;
+    if let Some(obj) = request.as_object_mut() {
+        obj.remove("token");
+    }
+    let key = request.to_string();
+    Ok((request, key))
+}
+
+/// Send the requests with the inner transport, and append every request/response pair to a file
+pub struct RecordingTransport {
+    inner: Arc<dyn Transport>,
+    writer: Mutex<BufWriter<File>>,
+}
+
+impl RecordingTransport {
+    /// Record the responses of inner to path, an existing file is truncated
+    pub fn new(inner: Arc<dyn Transport>, path: impl AsRef<Path>) -> Result<Self, TushareError> {
+        let path = path.as_ref();
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        Ok(RecordingTransport {
+            inner,
+            writer: Mutex::new(BufWriter::new(File::create(path)?)),
+        })
+    }
+}
+
+impl Transport for RecordingTransport {
+    fn post(&self, endpoint: &str, body: &str, timeout: Option<Duration>) -> Result<String, TushareError> {
+        let response = self.inner.post(endpoint, body, timeout)?;
+        let (request, _) = request_key(body)?;
+        let line = serde_json::to_string(&Record {
+            request,
+            response: response.clone(),
+        })?;
+        let mut writer = self.writer.lock().unwrap_or_else(|e| e.into_inner());
+        writeln!(writer, "{line}")?;
+        // flush every record, the recording is complete even if the process is killed
+        writer.flush()?;
+        Ok(response)
+    }
+}
+
+/// Serve the requests from a recording of RecordingTransport, without token and network.
+/// CacheMiss is returned for a request not in the recording.
+/// If the same request was recorded more than once, the last response is served.
+pub struct ReplayTransport {
+    path: PathBuf,
+    responses: HashMap<String, String>,
+}
+
+impl ReplayTransport {
+    /// Load the recording from path
+    pub fn new(path: impl AsRef<Path>) -> Result<Self, TushareError> {
+        let path = path.as_ref();
+        let mut responses = HashMap::new();
+        for line in fs::read_to_string(path)?.lines().filter(|l| !l.trim().is_empty()) {
+            let record: Record = serde_json::from_str(line)?;
+            responses.insert(record.request.to_string(), record.response);
+        }
+        info!("Loaded {} recorded responses from {path:?}", responses.len());
+        Ok(ReplayTransport {
+            path: path.to_path_buf(),
+            responses,
+        })
+    }
+}
+
+impl Transport for ReplayTransport {
+    fn post(&self, _endpoint: &str, body: &str, _timeout: Option<Duration>) -> Result<String, TushareError> {
+        let (_, key) = request_key(body)?;
+        self.responses.get(&key).cloned().ok_or_else(|| {
+            TushareError::CacheMiss(format!("request {key} is not recorded in {:?}", self.path))
+        })
+    }
+}
+
+impl Tushare {
+    /// Write every request/response pair to path while querying as usual, see [RecordingTransport]
+    /// ```no_run
+    /// # fn main() -> Result<(), tushare::TushareError> {
+    /// let tushare = tushare::Tushare::from_env()?.recording("tests/fixtures/daily.jsonl")?;
+    /// // the same queries later run offline with:
+    /// let tushare = tushare::Tushare::new("").replaying("tests/fixtures/daily.jsonl")?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn recording(self, path: impl AsRef<Path>) -> Result<Self, TushareError> {
+        let transport = RecordingTransport::new(self.transport.clone(), path)?;
+        Ok(self.with_transport(transport))
+    }
+
+    /// Serve identical requests from a recording of recording(), see [ReplayTransport]
+    pub fn replaying(self, path: impl AsRef<Path>) -> Result<Self, TushareError> {
+        Ok(self.with_transport(ReplayTransport::new(path)?))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::transport::MockTransport;
+    #[test]
+    fn test_record_replay() {
+        let path = std::env::temp_dir().join(format!("tushare_replay_{}.jsonl", std::process::id()));
+        let body = MockTransport::data(&["ts_code"], vec![vec!["000001.SZ".into()]]);
+        let mock = Arc::new(MockTransport::new().respond("daily", body.clone()));
+        let recording = RecordingTransport::new(mock, &path).unwrap();
+        let request = r#"{"api_name":"daily","token":"secret","params":{"ts_code":"000001.SZ"},"fields":null}"#;
+        assert_eq!(recording.post("", request, None).unwrap(), body);
+        assert!(!fs::read_to_string(&path).unwrap().contains("secret"));
+
+        let replay = ReplayTransport::new(&path).unwrap();
+        let other_token = r#"{"fields":null,"params":{"ts_code":"000001.SZ"},"token":"other","api_name":"daily"}"#;
+        assert_eq!(replay.post("", other_token, None).unwrap(), body);
+        assert!(replay.post("", r#"{"api_name":"weekly"}"#, None).is_err());
+        let _ = fs::remove_file(&path);
+    }
+}
@@ -0,0 +1,175 @@
+use crate::cancel::{self, CancellationToken};
+use crate::error::{TushareError, TushareServerError};
+use log::warn;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// Retry policy applied by every query() call of a Tushare object.
+/// Only transient errors are retried, see [TushareError::is_retryable],
+/// and a rate limit error of tushare only with wait_on_rate_limit(true).
+/// The delay before n-th retry is base_delay * 2^(n-1), capped by max_delay,
+/// then randomized by +/- jitter percent to avoid a burst of retries from many threads.
+#[derive(Debug, Clone)]
+pub struct RetryPolicy {
+    /// Total attempts including the first one. 1 means no retry.
+    pub max_attempts: u32,
+    /// Delay before the first retry
+    pub base_delay: Duration,
+    /// Upper bound of the delay between two attempts
+    pub max_delay: Duration,
+    /// Random factor in range 0.0..=1.0 applied to every delay
+    pub jitter: f64,
+    /// Wait as long as a rate limit error suggests before the retry, instead of the exponential delay.
+    /// The wait is up to an hour and not capped by max_delay, so it is opt-in.
+    pub wait_on_rate_limit: bool,
+}
+
+impl Default for RetryPolicy {
+    /// 3 attempts, starting from 500ms delay, with 20% jitter
+    fn default() -> Self {
+        RetryPolicy {
+            max_attempts: 3,
+            base_delay: Duration::from_millis(500),
+            max_delay: Duration::from_secs(30),
+            jitter: 0.2,
+            wait_on_rate_limit: false,
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// Create a retry policy with max attempts and the base delay, max delay and jitter are set to default.
+    pub fn new(max_attempts: u32, base_delay: Duration) -> Self {
+        RetryPolicy {
+            max_attempts,
+            base_delay,
+            ..Default::default()
+        }
+    }
+
+    /// A policy never retries, errors are returned to caller immediately
+    pub fn none() -> Self {
+        Self::new(1, Duration::ZERO)
+    }
+
+    /// Set the upper bound of the delay between two attempts
+    pub fn max_delay(self, max_delay: Duration) -> Self {
+        RetryPolicy { max_delay, ..self }
+    }
+
+    /// Set the jitter factor, it will be clamped into 0.0..=1.0
+    pub fn jitter(self, jitter: f64) -> Self {
+        RetryPolicy {
+            jitter: jitter.clamp(0.0, 1.0),
+            ..self
+        }
+    }
+
+    /// Retry a rate limited call after the wait parsed from the error, see [TushareServerError::retry_after].
+    /// The shared RateLimiter of the Tushare object is paused as well, so other threads wait too.
+    ///
+    /// [TushareServerError::retry_after]: crate::TushareServerError::retry_after
+    pub fn wait_on_rate_limit(self, wait_on_rate_limit: bool) -> Self {
+        RetryPolicy {
+            wait_on_rate_limit,
+            ..self
+        }
+    }
+
+    /// Delay before the given retry, retry starts from 1
+    pub fn delay(&self, retry: u32) -> Duration {
+        let exp = retry.saturating_sub(1).min(31);
+        let delay = self
+            .base_delay
+            .saturating_mul(1 << exp)
+            .min(self.max_delay);
+        if self.jitter <= 0.0 {
+            return delay;
+        }
+        // cheap pseudo random in -1.0..1.0, good enough to spread retries
+        let nanos = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.subsec_nanos())
+            .unwrap_or(0);
+        let rand = (nanos % 2001) as f64 / 1000.0 - 1.0;
+        delay.mul_f64((1.0 + rand * self.jitter).max(0.0))
+    }
+
+    /// Whether the error is retried: a transient error, but a tushare rate limit only with wait_on_rate_limit(),
+    /// since an immediate retry can't succeed and consumes the quota of the window
+    fn should_retry(&self, e: &TushareError) -> bool {
+        match e {
+            TushareError::RequestError(TushareServerError::RateLimited { .. }) => self.wait_on_rate_limit,
+            e => e.is_retryable(),
+        }
+    }
+
+    /// Call f until it succeeds, returns a non retryable error, or max attempts is reached.
+    /// The wait between the attempts ends with Cancelled once the token is cancelled.
+    pub(crate) fn run<T>(
+        &self,
+        cancellation: Option<&CancellationToken>,
+        mut f: impl FnMut() -> Result<T, TushareError>,
+    ) -> Result<T, TushareError> {
+        let mut attempt = 1;
+        loop {
+            match f() {
+                Err(e) if attempt < self.max_attempts && self.should_retry(&e) => {
+                    let delay = match e.retry_after() {
+                        Some(wait) if self.wait_on_rate_limit => wait,
+                        _ => self.delay(attempt),
+                    };
+                    warn!("Attempt {attempt} failed: {e}, retry in {delay:?}");
+                    cancel::sleep(cancellation, delay)?;
+                    attempt += 1;
+                }
+                result => return result,
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[test]
+    fn test_delay() {
+        let policy = RetryPolicy::new(5, Duration::from_millis(100))
+            .max_delay(Duration::from_millis(300))
+            .jitter(0.0);
+        assert_eq!(policy.delay(1), Duration::from_millis(100));
+        assert_eq!(policy.delay(2), Duration::from_millis(200));
+        assert_eq!(policy.delay(3), Duration::from_millis(300));
+        let jittered = policy.jitter(0.5).delay(1);
+        assert!(jittered >= Duration::from_millis(50) && jittered <= Duration::from_millis(150));
+    }
+
+    #[test]
+    fn test_wait_on_rate_limit() {
+        use std::time::Instant;
+        // the 0 seconds wait of the msg replaces the 10 seconds base delay
+        let policy = RetryPolicy::new(2, Duration::from_secs(10)).wait_on_rate_limit(true);
+        let mut calls = 0;
+        let start = Instant::now();
+        let result = policy.run(None, || {
+            calls += 1;
+            match calls {
+                1 => Err(TushareServerError::from_code(40203, "访问频率过高，请0秒后重试").into()),
+                _ => Ok(calls),
+            }
+        });
+        assert_eq!(result.unwrap(), 2);
+        assert!(start.elapsed() < Duration::from_secs(1));
+    }
+
+    #[test]
+    fn test_rate_limit_opt_in() {
+        use crate::transport::MockTransport;
+        use crate::Tushare;
+        use std::sync::Arc;
+        let mock = Arc::new(MockTransport::new().respond("daily", MockTransport::error(40203, "抱歉，您每分钟最多访问该接口500次")));
+        let tushare = Tushare::new("<token here>").with_transport(mock.clone());
+        let result = tushare.querybuilder("daily").query_raw();
+        assert!(matches!(result, Err(TushareError::RequestError(TushareServerError::RateLimited { .. }))));
+        assert_eq!(mock.requests().len(), 1);
+    }
+}
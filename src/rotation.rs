@@ -0,0 +1,100 @@
+//! Spread the calls over the tokens of multiple tushare accounts, set by Tushare::with_tokens()
+use crate::error::{TushareError, TushareServerError};
+use crate::middleware::{Middleware, Response};
+use log::warn;
+use serde_json::Value;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// How the token of every request is chosen
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Rotation {
+    /// Use the tokens in turn, every request uses the next one
+    RoundRobin,
+    /// Use the same token until tushare returns a rate limit or daily quota error (40203), then switch to the next one
+    #[default]
+    FallbackOnRateLimit,
+}
+
+/// A [Middleware] replacing the token of every request by the rotation.
+/// A rate limited request is retried with the next token if the retry policy waits on rate limits,
+/// see [RetryPolicy::wait_on_rate_limit](crate::RetryPolicy::wait_on_rate_limit).
+#[derive(Debug)]
+pub struct TokenRotation {
+    tokens: Vec<String>,
+    rotation: Rotation,
+    next: AtomicUsize,
+}
+
+impl TokenRotation {
+    /// Rotate over the tokens, ConfigError if there is none
+    pub fn new(tokens: &[&str], rotation: Rotation) -> Result<Self, TushareError> {
+        if tokens.is_empty() {
+            return Err(TushareError::ConfigError("no token to rotate".to_string()));
+        }
+        Ok(TokenRotation {
+            tokens: tokens.iter().map(|t| t.to_string()).collect(),
+            rotation,
+            next: AtomicUsize::new(0),
+        })
+    }
+
+    /// The tokens rotated over
+    pub fn tokens(&self) -> &[String] {
+        &self.tokens
+    }
+}
+
+impl Middleware for TokenRotation {
+    fn before(&self, request: &mut Value) {
+        let i = match self.rotation {
+            Rotation::RoundRobin => self.next.fetch_add(1, Ordering::Relaxed),
+            Rotation::FallbackOnRateLimit => self.next.load(Ordering::Relaxed),
+        };
+        request["token"] = self.tokens[i % self.tokens.len()].clone().into();
+    }
+
+    fn after(&self, response: &Response) {
+        if self.rotation != Rotation::FallbackOnRateLimit {
+            return;
+        }
+        if let Err(TushareError::RequestError(
+            TushareServerError::RateLimited { .. } | TushareServerError::DailyQuotaExceeded { .. },
+        )) = response.result
+        {
+            let token = response.request["token"].as_str().unwrap_or_default();
+            let current = self.next.load(Ordering::Relaxed);
+            // concurrent requests limited on the same token switch only once
+            if self.tokens[current % self.tokens.len()] == token {
+                let _ = self
+                    .next
+                    .compare_exchange(current, current + 1, Ordering::Relaxed, Ordering::Relaxed);
+                warn!("Token {} is rate limited, switch to the next one", current % self.tokens.len());
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::retry::RetryPolicy;
+    use crate::transport::MockTransport;
+    use crate::Tushare;
+    use std::sync::Arc;
+    use std::time::Duration;
+    #[test]
+    fn test_fallback_on_rate_limit() {
+        let mock = Arc::new(
+            MockTransport::new()
+                .respond("daily", MockTransport::error(40203, "访问频率过高，请0秒后重试"))
+                .respond("daily", MockTransport::data(&["close"], vec![vec![1.0.into()]])),
+        );
+        let tushare = Tushare::with_tokens(&["a", "b"], Rotation::FallbackOnRateLimit)
+            .unwrap()
+            .with_retry(RetryPolicy::new(2, Duration::ZERO).wait_on_rate_limit(true))
+            .with_transport(mock.clone());
+        assert_eq!(tushare.querybuilder("daily").query_raw().unwrap().len(), 1);
+        let tokens: Vec<Value> = mock.requests().iter().map(|r| r["token"].clone()).collect();
+        assert_eq!(tokens, ["a", "b"]);
+    }
+}
@@ -0,0 +1,172 @@
+//! Upsert query results and incremental syncs into SQLite tables, enabled by the "sqlite" feature.
+//! The table is created on the first write, with the primary key (ts_code, trade_date) if the columns exist,
+//! so writing the same rows again replaces them instead of duplicating.
+//! ```no_run
+//! # fn main() -> Result<(), tushare::TushareError> {
+//! let conn = rusqlite::Connection::open("tushare.db")?;
+//! let tushare = tushare::Tushare::from_env()?;
+//! tushare
+//!     .querybuilder("daily")
+//!     .addparam("trade_date", "20240424")
+//!     .to_sqlite(&conn, "daily")?;
+//! # Ok(())
+//! # }
+//! ```
+use crate::builder::QueryBuilder;
+use crate::date::parse_tushare_date;
+use crate::error::TushareError;
+use crate::sync::{SyncJob, SyncReport};
+use crate::tushare::Tushare;
+use log::info;
+use polars::prelude::*;
+use rusqlite::types::Value;
+use rusqlite::{params_from_iter, Connection, OptionalExtension};
+
+/// The primary key used by QueryBuilder::to_sqlite(), the columns missing in the result are left out
+pub const DEFAULT_KEY: &[&str] = &["ts_code", "trade_date"];
+
+/// Quote a table or column name
+fn quote(name: &str) -> String {
+    format!("\"{}\"", name.replace('"', "\"\""))
+}
+
+/// The SQLite column type of a polars type
+fn sql_type(dtype: &DataType) -> &'static str {
+    match dtype {
+        DataType::Boolean
+        | DataType::Int8
+        | DataType::Int16
+        | DataType::Int32
+        | DataType::Int64
+        | DataType::UInt8
+        | DataType::UInt16
+        | DataType::UInt32
+        | DataType::UInt64 => "INTEGER",
+        DataType::Float32 | DataType::Float64 => "REAL",
+        _ => "TEXT",
+    }
+}
+
+/// The SQLite value of a cell, dates are written as ISO 8601 text
+fn sql_value(value: AnyValue) -> Value {
+    match value {
+        AnyValue::Null => Value::Null,
+        AnyValue::Boolean(b) => Value::Integer(b.into()),
+        AnyValue::String(s) => Value::Text(s.to_string()),
+        AnyValue::StringOwned(s) => Value::Text(s.to_string()),
+        AnyValue::Float32(f) => Value::Real(f.into()),
+        AnyValue::Float64(f) => Value::Real(f),
+        value if value.dtype().is_integer() => value.extract::<i64>().map_or(Value::Null, Value::Integer),
+        value => Value::Text(value.to_string()),
+    }
+}
+
+/// Write the rows of df into the table in a transaction, the table is created if not exists.
+/// With a non empty key, the table has it as the primary key and rows of an existing key are replaced,
+/// otherwise the rows are appended.
+/// # return
+/// The number of rows written
+pub fn write_sqlite(conn: &Connection, table: &str, df: &DataFrame, key: &[&str]) -> Result<usize, TushareError> {
+    let columns = df.get_columns();
+    let mut definitions: Vec<String> = columns
+        .iter()
+        .map(|c| format!("{} {}", quote(c.name()), sql_type(c.dtype())))
+        .collect();
+    if !key.is_empty() {
+        let key: Vec<String> = key.iter().map(|k| quote(k)).collect();
+        definitions.push(format!("PRIMARY KEY ({})", key.join(", ")));
+    }
+    let tx = conn.unchecked_transaction()?;
+    tx.execute(
+        &format!("CREATE TABLE IF NOT EXISTS {} ({})", quote(table), definitions.join(", ")),
+        [],
+    )?;
+    let names: Vec<String> = columns.iter().map(|c| quote(c.name())).collect();
+    let placeholders = vec!["?"; columns.len()].join(", ");
+    let verb = if key.is_empty() { "INSERT" } else { "INSERT OR REPLACE" };
+    {
+        let mut insert = tx.prepare(&format!(
+            "{verb} INTO {} ({}) VALUES ({placeholders})",
+            quote(table),
+            names.join(", ")
+        ))?;
+        for i in 0..df.height() {
+            let row = columns.iter().map(|c| c.get(i).map(sql_value)).collect::<PolarsResult<Vec<_>>>()?;
+            insert.execute(params_from_iter(row))?;
+        }
+    }
+    tx.commit()?;
+    info!("Wrote {} rows to sqlite table {table}", df.height());
+    Ok(df.height())
+}
+
+/// The key columns of DEFAULT_KEY found in df
+fn default_key(df: &DataFrame) -> Vec<&'static str> {
+    DEFAULT_KEY.iter().copied().filter(|k| df.column(k).is_ok()).collect()
+}
+
+impl QueryBuilder<'_> {
+    /// Query all rows like query_all(), and upsert them into the SQLite table by ts_code and trade_date,
+    /// see [write_sqlite]. Use write_sqlite() for the other keys.
+    /// # return
+    /// The number of rows written
+    pub fn to_sqlite(&self, conn: &Connection, table: &str) -> Result<usize, TushareError> {
+        let df = self.query_all()?;
+        write_sqlite(conn, table, &df, &default_key(&df))
+    }
+}
+
+impl Tushare {
+    /// Same as Tushare::sync(), but the rows are upserted into a SQLite table keyed by ts_code and the date column.
+    /// The rows after the last date of every code in the table are fetched.
+    pub fn sync_sqlite(&self, job: &SyncJob, conn: &Connection, table: &str) -> Result<SyncReport, TushareError> {
+        let date_column = job.date_column_name();
+        let table_exists = conn
+            .query_row("SELECT 1 FROM sqlite_master WHERE type = 'table' AND name = ?", [table], |_| Ok(()))
+            .optional()?
+            .is_some();
+        let mut report = SyncReport::default();
+        for code in job.codes() {
+            let last = if table_exists {
+                conn.query_row(
+                    &format!("SELECT max({}) FROM {} WHERE ts_code = ?", quote(date_column), quote(table)),
+                    [code],
+                    |row| row.get::<_, Option<String>>(0),
+                )?
+                .as_deref()
+                .and_then(parse_tushare_date)
+            } else {
+                None
+            };
+            let rows = match job.fetch_since(self, code, last)? {
+                Some(df) => write_sqlite(conn, table, &df, &["ts_code", date_column])?,
+                None => 0,
+            };
+            report.rows.insert(code.clone(), rows);
+        }
+        Ok(report)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[test]
+    fn test_write_sqlite() {
+        let conn = Connection::open_in_memory().unwrap();
+        let df = df!(
+            "ts_code" => ["000001.SZ", "000001.SZ"],
+            "trade_date" => ["20240424", "20240425"],
+            "close" => [Some(10.5), None],
+            "vol" => [100i64, 200]
+        )
+        .unwrap();
+        write_sqlite(&conn, "daily", &df, &default_key(&df)).unwrap();
+        let update = df!("ts_code" => ["000001.SZ"], "trade_date" => ["20240425"], "close" => [11.0], "vol" => [300i64]).unwrap();
+        write_sqlite(&conn, "daily", &update, &default_key(&update)).unwrap();
+        let (count, close): (i64, f64) = conn
+            .query_row("SELECT count(*), max(close) FROM daily", [], |row| Ok((row.get(0)?, row.get(1)?)))
+            .unwrap();
+        assert_eq!((count, close), (2, 11.0));
+    }
+}
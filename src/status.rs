@@ -0,0 +1,182 @@
+//! Helpers of the trading status apis, suspensions and limit up/down,
+//! with boolean flag columns to exclude the untradable stocks from a backtest.
+use crate::api::TushareApi;
+use crate::code::check_codes;
+use crate::error::TushareError;
+use crate::fields;
+use crate::params::LimitType;
+use crate::stock::float_dtypes;
+use crate::tushare::Tushare;
+use chrono::NaiveDate;
+use polars::prelude::*;
+use std::ops::RangeInclusive;
+
+/// Fields returned by Tushare::suspend_d()
+pub const SUSPEND_FIELDS: &str = fields::suspend_d::ALL;
+
+/// Fields returned by Tushare::limit_list_d()
+pub const LIMIT_LIST_FIELDS: &str = "trade_date,ts_code,industry,name,close,pct_chg,amount,limit_amount,float_mv,\
+total_mv,turnover_ratio,fd_amount,first_time,last_time,open_times,up_stat,limit_times,limit";
+
+/// Numeric columns of limit_list_d, the amounts are in yuan
+const LIMIT_LIST_NUMERIC: &[&str] = &[
+    "close", "pct_chg", "amount", "limit_amount", "float_mv", "total_mv", "turnover_ratio", "fd_amount",
+];
+
+impl Tushare {
+    /// Suspensions and resumptions with trade_date within range, of every stock if ts_code is None.
+    /// Sorted by trade_date ascending, with the is_suspended and is_resumed flag columns from suspend_type.
+    /// ```no_run
+    /// # fn main() -> Result<(), tushare::TushareError> {
+    /// use chrono::NaiveDate;
+    /// use polars::prelude::*;
+    /// use std::collections::HashSet;
+    /// let tushare = tushare::Tushare::from_env()?;
+    /// let day = NaiveDate::from_ymd_opt(2024, 4, 24).unwrap();
+    /// let suspended = tushare.suspend_d(None, day..=day)?.lazy().filter(col("is_suspended")).collect()?;
+    /// let limit_up = tushare.limit_list_d(day..=day, None)?.lazy().filter(col("is_limit_up")).collect()?;
+    /// let mut excluded = HashSet::new();
+    /// for df in [&suspended, &limit_up] {
+    ///     excluded.extend(df.column("ts_code")?.str()?.into_iter().flatten().map(|c| c.to_string()));
+    /// }
+    /// // the stocks which can be bought at the close
+    /// let prices = tushare.querybuilder("daily").addparam("trade_date", "20240424").query()?;
+    /// let tradable: BooleanChunked = prices.column("ts_code")?.str()?.into_iter()
+    ///     .map(|c| c.is_some_and(|c| !excluded.contains(c)))
+    ///     .collect();
+    /// let prices = prices.filter(&tradable)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn suspend_d(
+        &self,
+        ts_code: Option<&str>,
+        range: RangeInclusive<NaiveDate>,
+    ) -> Result<DataFrame, TushareError> {
+        let mut query = self
+            .querybuilder(TushareApi::SuspendD)
+            .start_date(range.start())
+            .end_date(range.end())
+            .fields(SUSPEND_FIELDS)
+            .parse_dates(true)
+            .allow_empty(true);
+        if let Some(ts_code) = ts_code {
+            check_codes(ts_code)?;
+            query = query.addparam("ts_code", ts_code);
+        }
+        Ok(query
+            .query_all()?
+            .lazy()
+            .with_columns([
+                col("suspend_type").eq(lit("S")).alias("is_suspended"),
+                col("suspend_type").eq(lit("R")).alias("is_resumed"),
+            ])
+            .sort(["trade_date"], SortMultipleOptions::default().with_maintain_order(true))
+            .collect()?)
+    }
+
+    /// The stocks hitting the limit with trade_date within range, of every limit type if limit_type is None.
+    /// Sorted by trade_date ascending, with the is_limit_up, is_limit_down and is_broken flag columns from limit.
+    /// A limit up stock can't be bought at the close, and a limit down one can't be sold.
+    pub fn limit_list_d(
+        &self,
+        range: RangeInclusive<NaiveDate>,
+        limit_type: Option<LimitType>,
+    ) -> Result<DataFrame, TushareError> {
+        let mut query = self
+            .querybuilder(TushareApi::LimitListD)
+            .start_date(range.start())
+            .end_date(range.end())
+            .fields(LIMIT_LIST_FIELDS)
+            .dtypes(&float_dtypes(LIMIT_LIST_NUMERIC))
+            .parse_dates(true)
+            .allow_empty(true);
+        if let Some(limit_type) = limit_type {
+            query = query.addparam("limit_type", limit_type);
+        }
+        Ok(query
+            .query_all()?
+            .lazy()
+            .with_columns([
+                col("limit").eq(lit(LimitType::Up.as_str())).alias("is_limit_up"),
+                col("limit").eq(lit(LimitType::Down.as_str())).alias("is_limit_down"),
+                col("limit").eq(lit(LimitType::Broken.as_str())).alias("is_broken"),
+            ])
+            .sort(["trade_date"], SortMultipleOptions::default().with_maintain_order(true))
+            .collect()?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::transport::MockTransport;
+    use serde_json::json;
+    use std::sync::Arc;
+
+    fn day(d: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(2024, 4, d).unwrap()
+    }
+
+    #[test]
+    fn test_suspend_d() {
+        let fields: Vec<&str> = SUSPEND_FIELDS.split(',').collect();
+        let row = |code: &str, date: &str, suspend_type: &str| vec![json!(code), json!(date), json!(null), json!(suspend_type)];
+        let body = MockTransport::data(&fields, vec![row("600000.SH", "20240424", "R"), row("600000.SH", "20240423", "S")]);
+        let mock = Arc::new(MockTransport::new().respond("suspend_d", body));
+        let tushare = Tushare::new("<token here>").with_transport(mock.clone());
+        let df = tushare.suspend_d(Some("600000.SH"), day(23)..=day(24)).unwrap();
+        let params = &mock.requests()[0]["params"];
+        assert_eq!(params["start_date"], "20240423");
+        assert_eq!(params["end_date"], "20240424");
+        assert_eq!(params["ts_code"], "600000.SH");
+        let dates: Vec<_> = df.column("trade_date").unwrap().date().unwrap().as_date_iter().flatten().collect();
+        assert_eq!(dates, [day(23), day(24)]);
+        let flags = |name: &str| df.column(name).unwrap().bool().unwrap().into_iter().collect::<Vec<_>>();
+        assert_eq!(flags("is_suspended"), [Some(true), Some(false)]);
+        assert_eq!(flags("is_resumed"), [Some(false), Some(true)]);
+    }
+
+    #[test]
+    fn test_suspend_d_empty() {
+        let fields: Vec<&str> = SUSPEND_FIELDS.split(',').collect();
+        let mock = MockTransport::new().respond("suspend_d", MockTransport::data(&fields, vec![]));
+        let tushare = Tushare::new("<token here>").with_transport(mock);
+        let df = tushare.suspend_d(None, day(23)..=day(24)).unwrap();
+        assert_eq!(df.height(), 0);
+        assert!(tushare.suspend_d(Some("600000"), day(23)..=day(24)).is_err());
+    }
+
+    #[test]
+    fn test_limit_list_d() {
+        let fields: Vec<&str> = LIMIT_LIST_FIELDS.split(',').collect();
+        let row = |date: &str, code: &str, limit: &str| {
+            let mut row = vec![json!(date), json!(code), json!("银行"), json!("name")];
+            row.extend(LIMIT_LIST_NUMERIC.iter().map(|_| json!(10)));
+            row.extend([json!("09:30:00"), json!("14:57:00"), json!(1), json!("1/1"), json!(1), json!(limit)]);
+            row
+        };
+        let rows = vec![row("20240424", "000001.SZ", "U"), row("20240423", "000002.SZ", "D"), row("20240423", "000003.SZ", "Z")];
+        let mock = Arc::new(MockTransport::new().respond("limit_list_d", MockTransport::data(&fields, rows)));
+        let tushare = Tushare::new("<token here>").with_transport(mock.clone());
+        let df = tushare.limit_list_d(day(23)..=day(24), Some(LimitType::Up)).unwrap();
+        let params = &mock.requests()[0]["params"];
+        assert_eq!(params["start_date"], "20240423");
+        assert_eq!(params["end_date"], "20240424");
+        assert_eq!(params["limit_type"], "U");
+        let codes: Vec<_> = df.column("ts_code").unwrap().str().unwrap().into_iter().flatten().collect();
+        assert_eq!(codes, ["000002.SZ", "000003.SZ", "000001.SZ"]);
+        assert_eq!(df.column("trade_date").unwrap().dtype(), &DataType::Date);
+        for column in LIMIT_LIST_NUMERIC {
+            assert_eq!(df.column(column).unwrap().dtype(), &DataType::Float64, "{column}");
+        }
+        let flags = |name: &str| df.column(name).unwrap().bool().unwrap().into_iter().collect::<Vec<_>>();
+        assert_eq!(flags("is_limit_up"), [Some(false), Some(false), Some(true)]);
+        assert_eq!(flags("is_limit_down"), [Some(true), Some(false), Some(false)]);
+        assert_eq!(flags("is_broken"), [Some(false), Some(true), Some(false)]);
+
+        let mock = MockTransport::new().respond("limit_list_d", MockTransport::data(&fields, vec![]));
+        let df = Tushare::new("<token here>").with_transport(mock).limit_list_d(day(23)..=day(24), None).unwrap();
+        assert_eq!(df.height(), 0);
+    }
+}
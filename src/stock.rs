@@ -0,0 +1,322 @@
+//! High level helpers of the stock apis.
+//! They select the fields, parse the dates and cast the numeric columns,
+//! so the returned DataFrame is ready to use without learning the raw params.
+use crate::api::TushareApi;
+use crate::code::check_codes;
+use crate::error::TushareError;
+use crate::fields::{self, DailyField, StockBasicField};
+use crate::date::IntoTushareDate;
+use crate::params::{Exchange, ListStatus, Market};
+use crate::transform;
+use crate::tushare::Tushare;
+use chrono::NaiveDate;
+use polars::prelude::*;
+use std::collections::{HashMap, HashSet};
+
+/// Fields returned by Tushare::daily(), weekly() and monthly()
+pub const DAILY_FIELDS: &str = fields::daily::ALL;
+
+/// Numeric columns of daily bars.
+/// vol (in lots) and amount (in thousand yuan) are floats in tushare, so all of them are f64.
+pub(crate) const DAILY_NUMERIC: &[&str] = &[
+    "open", "high", "low", "close", "pre_close", "change", "pct_chg", "vol", "amount",
+];
+
+/// Fields returned by Tushare::stock_basic()
+pub const STOCK_BASIC_FIELDS: &str = fields::stock_basic::ALL;
+
+/// Whether the stock name marks special treatment, e.g. "ST康美" or "*ST海润"
+pub fn is_st_name(name: &str) -> bool {
+    name.contains("ST")
+}
+
+/// The stocks of stock_basic tradable on date, given the suspended codes and the namechange history.
+/// The name column is replaced by the name used on date, and an is_st column is added.
+fn tradable_on(
+    basic: DataFrame,
+    suspended: &HashSet<String>,
+    names: DataFrame,
+    date: NaiveDate,
+) -> Result<DataFrame, TushareError> {
+    let listed = basic
+        .lazy()
+        .filter(
+            col("list_date")
+                .lt_eq(lit(date))
+                .and(col("delist_date").is_null().or(col("delist_date").gt(lit(date)))),
+        )
+        .collect()?;
+    // the name history is sorted by start_date, so the last one wins
+    let names = names
+        .lazy()
+        .filter(
+            col("start_date")
+                .lt_eq(lit(date))
+                .and(col("end_date").is_null().or(col("end_date").gt_eq(lit(date)))),
+        )
+        .sort(["start_date"], SortMultipleOptions::default())
+        .collect()?;
+    let names: HashMap<&str, &str> = names
+        .column("ts_code")?
+        .str()?
+        .into_iter()
+        .zip(names.column("name")?.str()?)
+        .filter_map(|(code, name)| Some((code?, name?)))
+        .collect();
+    let codes = listed.column("ts_code")?.str()?;
+    let current_names: StringChunked = codes
+        .into_iter()
+        .zip(listed.column("name")?.str()?)
+        .map(|(code, name)| code.and_then(|c| names.get(c).copied()).or(name))
+        .collect();
+    let is_st: BooleanChunked = current_names
+        .into_iter()
+        .map(|name| name.map(is_st_name))
+        .collect();
+    let not_suspended: BooleanChunked = codes
+        .into_iter()
+        .map(|code| code.map(|c| !suspended.contains(c)))
+        .collect();
+    let mut df = listed.clone();
+    df.with_column(current_names.into_series().with_name("name"))?;
+    df.with_column(is_st.into_series().with_name("is_st"))?;
+    Ok(df.filter(&not_suspended)?)
+}
+
+pub(crate) fn float_dtypes<'a>(columns: &[&'a str]) -> Vec<(&'a str, DataType)> {
+    columns.iter().map(|c| (*c, DataType::Float64)).collect()
+}
+
+impl Tushare {
+    /// Daily bars (unadjusted) of a stock between start and end, both inclusive.
+    /// The result is sorted by trade_date ascending, with trade_date as Date and OHLCV as f64.
+    /// # ts_code/start/end
+    /// Stock code like "000001.SZ" or a [TsCode](crate::TsCode), and dates like chrono::NaiveDate.
+    /// InvalidCode is returned for a malformed code before sending the request.
+    pub fn daily(
+        &self,
+        ts_code: impl AsRef<str>,
+        start: impl IntoTushareDate,
+        end: impl IntoTushareDate,
+    ) -> Result<DataFrame, TushareError> {
+        self.stock_bars(TushareApi::Daily, ts_code.as_ref(), start, end, DAILY_FIELDS)
+    }
+
+    /// Weekly bars (unadjusted) of a stock between start and end, in the same columns as daily().
+    /// trade_date is the last trading day of every week.
+    pub fn weekly(
+        &self,
+        ts_code: impl AsRef<str>,
+        start: impl IntoTushareDate,
+        end: impl IntoTushareDate,
+    ) -> Result<DataFrame, TushareError> {
+        self.stock_bars(TushareApi::Weekly, ts_code.as_ref(), start, end, DAILY_FIELDS)
+    }
+
+    /// Monthly bars (unadjusted) of a stock between start and end, in the same columns as daily().
+    /// trade_date is the last trading day of every month.
+    pub fn monthly(
+        &self,
+        ts_code: impl AsRef<str>,
+        start: impl IntoTushareDate,
+        end: impl IntoTushareDate,
+    ) -> Result<DataFrame, TushareError> {
+        self.stock_bars(TushareApi::Monthly, ts_code.as_ref(), start, end, DAILY_FIELDS)
+    }
+
+    /// Same as daily() with only the selected fields, serialized into the fields param.
+    /// trade_date is always returned to sort the bars.
+    /// ```no_run
+    /// # fn main() -> Result<(), tushare::TushareError> {
+    /// use chrono::NaiveDate;
+    /// use tushare::fields::DailyField;
+    /// let tushare = tushare::Tushare::from_env()?;
+    /// let (start, end) = (NaiveDate::from_ymd_opt(2024, 1, 1).unwrap(), NaiveDate::from_ymd_opt(2024, 4, 30).unwrap());
+    /// let df = tushare.daily_select("000001.SZ", start, end, &[DailyField::Open, DailyField::Close])?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn daily_select(
+        &self,
+        ts_code: impl AsRef<str>,
+        start: impl IntoTushareDate,
+        end: impl IntoTushareDate,
+        fields: &[DailyField],
+    ) -> Result<DataFrame, TushareError> {
+        let mut fields = fields.to_vec();
+        if !fields.contains(&DailyField::TradeDate) {
+            fields.insert(0, DailyField::TradeDate);
+        }
+        self.stock_bars(TushareApi::Daily, ts_code.as_ref(), start, end, &fields::join(&fields))
+    }
+
+    /// Bars of daily, weekly or monthly, which share the fields
+    fn stock_bars(
+        &self,
+        api: TushareApi,
+        ts_code: &str,
+        start: impl IntoTushareDate,
+        end: impl IntoTushareDate,
+        fields: &str,
+    ) -> Result<DataFrame, TushareError> {
+        check_codes(ts_code)?;
+        let df = self
+            .querybuilder(api)
+            .addparam("ts_code", ts_code)
+            .start_date(start)
+            .end_date(end)
+            .fields(fields)
+            .dtypes(&float_dtypes(DAILY_NUMERIC))
+            .parse_dates(true)
+            .query_all()?;
+        Ok(df.sort(["trade_date"], SortMultipleOptions::default())?)
+    }
+
+    /// The listed-company table, one row per stock, with list_date/delist_date as Date.
+    /// Every filter is optional, note tushare returns the listed stocks only if list_status is None.
+    pub fn stock_basic(
+        &self,
+        exchange: Option<Exchange>,
+        list_status: Option<ListStatus>,
+        market: Option<Market>,
+    ) -> Result<DataFrame, TushareError> {
+        self.stock_basic_query(exchange, list_status, market, STOCK_BASIC_FIELDS)
+    }
+
+    /// Same as stock_basic() with only the selected fields, serialized into the fields param
+    pub fn stock_basic_select(
+        &self,
+        exchange: Option<Exchange>,
+        list_status: Option<ListStatus>,
+        market: Option<Market>,
+        fields: &[StockBasicField],
+    ) -> Result<DataFrame, TushareError> {
+        self.stock_basic_query(exchange, list_status, market, &fields::join(fields))
+    }
+
+    fn stock_basic_query(
+        &self,
+        exchange: Option<Exchange>,
+        list_status: Option<ListStatus>,
+        market: Option<Market>,
+        fields: &str,
+    ) -> Result<DataFrame, TushareError> {
+        let mut query = self
+            .querybuilder(TushareApi::StockBasic)
+            .fields(fields)
+            .parse_dates(true);
+        if let Some(exchange) = exchange {
+            query = query.addparam("exchange", exchange);
+        }
+        if let Some(list_status) = list_status {
+            query = query.addparam("list_status", list_status);
+        }
+        if let Some(market) = market {
+            query = query.addparam("market", market);
+        }
+        query.query_all()
+    }
+
+    /// The stocks tradable on date: listed on or before date, not delisted and not suspended on that day.
+    /// The name is the one used on date, and the is_st column marks the special treatment stocks,
+    /// filter them out with `col("is_st").not()` for a non-ST universe.
+    /// It combines stock_basic, suspend_d and namechange, 5 calls or so.
+    /// ```no_run
+    /// # fn main() -> Result<(), tushare::TushareError> {
+    /// use chrono::NaiveDate;
+    /// use polars::prelude::*;
+    /// let tushare = tushare::Tushare::from_env()?;
+    /// let universe = tushare.universe(NaiveDate::from_ymd_opt(2024, 4, 24).unwrap())?;
+    /// let non_st = universe.lazy().filter(col("is_st").not()).collect()?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn universe(&self, date: NaiveDate) -> Result<DataFrame, TushareError> {
+        let mut basic = self.stock_basic(None, Some(ListStatus::Listed), None)?;
+        for status in [ListStatus::Delisted, ListStatus::Paused] {
+            // there may be no paused stocks at all
+            let df = match self.stock_basic(None, Some(status), None) {
+                Ok(df) => df,
+                Err(TushareError::EmptyError) => continue,
+                Err(e) => return Err(e),
+            };
+            basic.vstack_mut(&transform::apply_schema(df, &basic.schema())?)?;
+        }
+        let suspended = self
+            .querybuilder(TushareApi::SuspendD)
+            .addparam_date("trade_date", date)
+            .addparam("suspend_type", "S")
+            .fields("ts_code,trade_date,suspend_type")
+            .allow_empty(true)
+            .query_all()?;
+        let suspended: HashSet<String> = suspended
+            .column("ts_code")?
+            .str()?
+            .into_iter()
+            .flatten()
+            .map(|c| c.to_string())
+            .collect();
+        let names = self
+            .querybuilder(TushareApi::NameChange)
+            .fields("ts_code,name,start_date,end_date")
+            .parse_date_columns(&["start_date", "end_date"])
+            .query_all()?;
+        tradable_on(basic, &suspended, names, date)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[test]
+    fn test_tradable_on() {
+        let date = |s: &str| NaiveDate::parse_from_str(s, "%Y%m%d").unwrap();
+        let basic = df!(
+            "ts_code" => ["000001.SZ", "600000.SH", "600001.SH", "688999.SH", "000004.SZ"],
+            "name" => ["平安银行", "浦发银行", "邯郸钢铁", "新股", "ST国华"],
+            "list_date" => [date("19910403"), date("19991110"), date("19980122"), date("20240601"), date("19910114")],
+            "delist_date" => [None, None, Some(date("20091229")), None, None]
+        )
+        .unwrap();
+        let names = df!(
+            "ts_code" => ["000004.SZ", "000004.SZ"],
+            "name" => ["国农科技", "ST国华"],
+            "start_date" => [date("20140101"), date("20240501")],
+            "end_date" => [Some(date("20240430")), None]
+        )
+        .unwrap();
+        let suspended = HashSet::from(["600000.SH".to_string()]);
+        let df = tradable_on(basic, &suspended, names, date("20240424")).unwrap();
+        let codes: Vec<_> = df.column("ts_code").unwrap().str().unwrap().into_iter().flatten().collect();
+        assert_eq!(codes, ["000001.SZ", "000004.SZ"]);
+        assert_eq!(df.column("name").unwrap().str().unwrap().get(1), Some("国农科技"));
+        assert_eq!(df.column("is_st").unwrap().bool().unwrap().get(1), Some(false));
+    }
+
+    #[test]
+    fn test_daily() {
+        use crate::transport::MockTransport;
+        use serde_json::json;
+        let row = |date: &str, close: f64, vol: i64| {
+            let prices = [json!(10), json!(11), json!(9.5), json!(close), json!(10), json!(0.5), json!(5.0)];
+            let mut row = vec![json!("000001.SZ"), json!(date)];
+            row.extend(prices);
+            row.extend([json!(vol), json!(1.2e6)]);
+            row
+        };
+        let fields: Vec<&str> = DAILY_FIELDS.split(',').collect();
+        // tushare returns the latest bar first
+        let body = MockTransport::data(&fields, vec![row("20240103", 10.6, 1200), row("20240102", 10.5, 1000)]);
+        let mock = std::sync::Arc::new(MockTransport::new().respond("daily", body));
+        let tushare = Tushare::new("<token here>").with_transport(mock.clone());
+        let day = |d| NaiveDate::from_ymd_opt(2024, 1, d).unwrap();
+        let df = tushare.daily("000001.SZ", day(2), day(3)).unwrap();
+        assert_eq!(mock.requests()[0]["fields"], DAILY_FIELDS);
+        assert_eq!(df.column("trade_date").unwrap().dtype(), &DataType::Date);
+        for column in DAILY_NUMERIC {
+            assert_eq!(df.column(column).unwrap().dtype(), &DataType::Float64, "{column}");
+        }
+        let close: Vec<Option<f64>> = df.column("close").unwrap().f64().unwrap().into_iter().collect();
+        assert_eq!(close, [Some(10.5), Some(10.6)]);
+    }
+}
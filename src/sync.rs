@@ -0,0 +1,254 @@
+use crate::builder::Dict;
+use crate::checkpoint::Checkpoint;
+use crate::error::TushareError;
+use crate::date::{parse_tushare_date, IntoTushareDate};
+use crate::progress::{Progress, ProgressHook, ProgressTracker};
+use crate::transform;
+use crate::tushare::Tushare;
+use chrono::{Local, NaiveDate};
+use log::info;
+use polars::prelude::*;
+use std::collections::BTreeMap;
+use std::fs::{self, File};
+use std::path::{Path, PathBuf};
+
+/// Describe what Tushare::sync() keeps up to date in a local directory.
+/// Every code is stored in its own parquet file `<dir>/<api_name>/<ts_code>.parquet`,
+/// and only the rows after the last stored date are fetched by the next sync.
+#[derive(Debug, Clone)]
+pub struct SyncJob {
+    api_name: String,
+    codes: Vec<String>,
+    params: Dict,
+    fields: Option<String>,
+    date_column: String,
+    start: NaiveDate,
+    progress: Option<ProgressHook>,
+    checkpoint: Option<PathBuf>,
+}
+
+impl SyncJob {
+    /// Sync the api for every code, e.g. SyncJob::new(TushareApi::Daily, &["000001.SZ"])
+    pub fn new(api_name: impl Into<String>, codes: &[&str]) -> Self {
+        SyncJob {
+            api_name: api_name.into(),
+            codes: codes.iter().map(|c| c.to_string()).collect(),
+            params: Dict::new(),
+            fields: None,
+            date_column: "trade_date".to_string(),
+            start: NaiveDate::from_ymd_opt(1990, 1, 1).expect("valid date"),
+            progress: None,
+            checkpoint: None,
+        }
+    }
+
+    /// Extra parameters sent with every query, ts_code/start_date are set by the sync
+    pub fn params(self, params: Dict) -> Self {
+        SyncJob { params, ..self }
+    }
+
+    /// Return fields of every query, must include the date column
+    pub fn fields(self, fields: &str) -> Self {
+        SyncJob {
+            fields: Some(fields.to_string()),
+            ..self
+        }
+    }
+
+    /// The *YYYYMMDD* column used to find the last stored row, default "trade_date"
+    pub fn date_column(self, date_column: &str) -> Self {
+        SyncJob {
+            date_column: date_column.to_string(),
+            ..self
+        }
+    }
+
+    /// The first date fetched for a code not stored yet, default 1990-01-01
+    pub fn start(self, start: NaiveDate) -> Self {
+        SyncJob { start, ..self }
+    }
+
+    /// Call f with the progress after every code is synced
+    pub fn on_progress(self, f: impl Fn(&Progress) + Send + Sync + 'static) -> Self {
+        SyncJob {
+            progress: Some(ProgressHook::new(f)),
+            ..self
+        }
+    }
+
+    /// Save the codes synced today to a [Checkpoint] file, so a sync interrupted
+    /// after thousands of codes resumes without calling the api again for them.
+    /// The file is removed once every code is synced.
+    pub fn checkpoint(self, path: impl Into<PathBuf>) -> Self {
+        SyncJob {
+            checkpoint: Some(path.into()),
+            ..self
+        }
+    }
+
+    /// The api name of the job
+    pub fn api_name(&self) -> &str {
+        &self.api_name
+    }
+
+    /// The codes of the job
+    pub fn codes(&self) -> &[String] {
+        &self.codes
+    }
+
+    /// The date column of the job
+    #[cfg(feature = "sqlite")]
+    pub(crate) fn date_column_name(&self) -> &str {
+        &self.date_column
+    }
+
+    /// Fetch the rows of the code after the last stored date, or from the start of the job.
+    /// None if the code is up to date.
+    pub(crate) fn fetch_since(
+        &self,
+        tushare: &Tushare,
+        code: &str,
+        last: Option<NaiveDate>,
+    ) -> Result<Option<DataFrame>, TushareError> {
+        let start = match last {
+            Some(last) => last.succ_opt().unwrap_or(last),
+            None => self.start,
+        };
+        let mut query = tushare
+            .querybuilder(self.api_name.as_str())
+            .params(self.params.clone())
+            .addparam("ts_code", code)
+            .start_date(start);
+        if let Some(fields) = &self.fields {
+            query = query.fields(fields);
+        }
+        match query.query_all() {
+            Ok(df) => Ok(Some(df)),
+            Err(TushareError::EmptyError) => {
+                info!("{} {code} is up to date", self.api_name);
+                Ok(None)
+            }
+            Err(e) => Err(e),
+        }
+    }
+
+    /// The parquet file of the code under dir
+    pub fn path(&self, dir: &Path, code: &str) -> PathBuf {
+        dir.join(&self.api_name).join(format!("{code}.parquet"))
+    }
+}
+
+/// Rows appended by Tushare::sync() per code
+#[derive(Debug, Clone, Default)]
+pub struct SyncReport {
+    /// Rows appended of every code, 0 if the code is already up to date
+    pub rows: BTreeMap<String, usize>,
+}
+
+impl SyncReport {
+    /// Rows appended of all codes
+    pub fn total_rows(&self) -> usize {
+        self.rows.values().sum()
+    }
+}
+
+/// Read a parquet file, None if it does not exist
+pub fn read_parquet(path: &Path) -> Result<Option<DataFrame>, TushareError> {
+    if !path.exists() {
+        return Ok(None);
+    }
+    Ok(Some(ParquetReader::new(File::open(path)?).finish()?))
+}
+
+/// Write a parquet file through a temp file, so a crash never leaves a partial file
+pub fn write_parquet(path: &Path, df: &mut DataFrame) -> Result<(), TushareError> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let tmp = path.with_extension("parquet.tmp");
+    ParquetWriter::new(File::create(&tmp)?).finish(df)?;
+    fs::rename(&tmp, path)?;
+    Ok(())
+}
+
+/// The max *YYYYMMDD* value of the column
+fn last_date(df: &DataFrame, column: &str) -> Result<Option<NaiveDate>, TushareError> {
+    let dates = df.column(column)?.str()?;
+    Ok(dates.into_iter().flatten().filter_map(parse_tushare_date).max())
+}
+
+impl Tushare {
+    /// Fetch the rows newer than the local parquet files of the job, and append them to the files.
+    /// The dates are kept as *YYYYMMDD* strings in the files, same as tushare returns.
+    pub fn sync(&self, job: &SyncJob, dir: impl AsRef<Path>) -> Result<SyncReport, TushareError> {
+        let dir = dir.as_ref();
+        let mut report = SyncReport::default();
+        // a checkpoint of another day is stale, the codes may have new rows since then
+        let checkpoint = match &job.checkpoint {
+            Some(path) => {
+                let today = Local::now().date_naive().to_tushare_date();
+                Some(Checkpoint::open(path, &format!("sync {} {today}", job.api_name))?)
+            }
+            None => None,
+        };
+        let tracker = ProgressTracker::new(job.progress.clone(), Some(job.codes.len()));
+        for code in &job.codes {
+            let rows = match checkpoint.as_ref().and_then(|c| c.done(code)) {
+                Some(rows) => rows,
+                None => {
+                    let rows = self.sync_code(job, dir, code)?;
+                    if let Some(checkpoint) = &checkpoint {
+                        checkpoint.mark_done(code, rows)?;
+                    }
+                    rows
+                }
+            };
+            tracker.advance(0, rows);
+            report.rows.insert(code.clone(), rows);
+        }
+        if let Some(checkpoint) = checkpoint {
+            checkpoint.finish()?;
+        }
+        Ok(report)
+    }
+
+    /// Sync a single code of the job, returns the rows appended
+    pub(crate) fn sync_code(&self, job: &SyncJob, dir: &Path, code: &str) -> Result<usize, TushareError> {
+        let path = job.path(dir, code);
+        let stored = read_parquet(&path)?;
+        let last = match &stored {
+            Some(df) => last_date(df, &job.date_column)?,
+            None => None,
+        };
+        let Some(fetched) = job.fetch_since(self, code, last)? else {
+            return Ok(0);
+        };
+        let rows = fetched.height();
+        let mut df = match stored {
+            Some(mut stored) => {
+                // keep the stored types, inferred types of a small delta may differ
+                let fetched = transform::apply_schema(fetched, &stored.schema())?;
+                stored.vstack_mut(&fetched.select(stored.get_column_names())?)?;
+                stored
+            }
+            None => fetched,
+        };
+        df.sort_in_place([job.date_column.as_str()], SortMultipleOptions::default())?;
+        write_parquet(&path, &mut df)?;
+        info!("{} {code} appended {rows} rows to {path:?}", job.api_name);
+        Ok(rows)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[test]
+    fn test_last_date() {
+        let df = df!("trade_date" => [Some("20240424"), None, Some("20240426"), Some("20240425")]).unwrap();
+        let last = last_date(&df, "trade_date").unwrap();
+        assert_eq!(last, NaiveDate::from_ymd_opt(2024, 4, 26));
+        let job = SyncJob::new("daily", &["000001.SZ"]);
+        assert_eq!(job.path(Path::new("data"), "000001.SZ"), Path::new("data/daily/000001.SZ.parquet"));
+    }
+}
@@ -0,0 +1,575 @@
+use crate::error::TushareError;
+use log::warn;
+use serde::de::{self, DeserializeSeed, Deserializer, IgnoredAny, MapAccess, SeqAccess, Visitor};
+use serde_json::{Map, Value};
+use std::fmt;
+use std::time::Duration;
+
+/// The raw data returned by tushare, without converting to a polars DataFrame.
+/// Tushare returns the data in a column names + row arrays layout:
+/// `{"data": {"fields": ["ts_code", ...], "items": [["000001.SZ", ...], ...]}}`,
+/// the rows are read into a buffer per column while the body is deserialized.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Table {
+    /// Column names
+    pub fields: Vec<String>,
+    /// Values of every column in the order of fields, all of the same length
+    pub columns: Vec<Vec<Value>>,
+    /// Whether tushare has more rows than returned, e.g. the 6,000 rows cap is reached
+    pub has_more: bool,
+}
+
+/// Metadata of a tushare response besides the data
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ResponseMeta {
+    /// Request id generated by tushare, useful when reporting issues to tushare
+    pub request_id: Option<String>,
+    /// Whether tushare has more rows than returned, fetch the rest with offset or query_all()
+    pub has_more: bool,
+    /// Rows returned
+    pub rows: usize,
+    /// Http status of the response, None if it is not from the transport, e.g. parsed from a cached body
+    pub http_status: Option<u16>,
+}
+
+/// Details of a query returned by QueryBuilder::query_detailed(), to debug production data pipelines
+#[derive(Debug, Clone, PartialEq)]
+pub struct QueryReport {
+    /// The request json as built by the QueryBuilder, without the token
+    pub request: Value,
+    /// Request id generated by tushare, useful when reporting issues to tushare
+    pub request_id: Option<String>,
+    /// Http status of the successful response
+    pub http_status: Option<u16>,
+    /// Time spent by the whole query, including the retries and the rate limit wait
+    pub elapsed: Duration,
+    /// Rows of the result
+    pub rows: usize,
+    /// Columns of the result
+    pub columns: usize,
+    /// Whether tushare has more rows than returned, fetch the rest with query_all()
+    pub has_more: bool,
+    /// Pages fetched, 1 for a single call
+    pub pages: usize,
+    /// Anomalies found at the page boundaries by QueryBuilder::query_all_detailed() with stable_order() set
+    pub anomalies: Vec<PageAnomaly>,
+}
+
+/// An anomaly at the boundary of two pages, usually caused by the data changing on tushare during the paging
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PageAnomaly {
+    /// Rows of the page repeat the keys of the previous page, e.g. rows were inserted before the offset
+    Overlap {
+        /// Index of the page, from 0
+        page: usize,
+        /// Rows repeating the previous page
+        rows: usize,
+    },
+    /// The page doesn't continue the order of the previous page, e.g. rows were deleted before the offset
+    /// and some rows are skipped
+    OrderBreak {
+        /// Index of the page, from 0
+        page: usize,
+    },
+}
+
+impl QueryReport {
+    /// Build the report of a request from the response metadata
+    #[cfg(feature = "dataframe")]
+    pub(crate) fn new(request: &Value, meta: ResponseMeta, columns: usize, elapsed: Duration) -> Self {
+        let mut request = request.clone();
+        if let Some(obj) = request.as_object_mut() {
+            obj.remove("token");
+        }
+        QueryReport {
+            request,
+            request_id: meta.request_id,
+            http_status: meta.http_status,
+            elapsed,
+            rows: meta.rows,
+            columns,
+            has_more: meta.has_more,
+            pages: 1,
+            anomalies: vec![],
+        }
+    }
+}
+
+impl ResponseMeta {
+    /// Extract the metadata from the tushare response body
+    pub fn from_response(resp_json: &Value) -> Self {
+        ResponseMeta {
+            request_id: resp_json["request_id"].as_str().map(|id| id.to_string()),
+            has_more: resp_json["data"]["has_more"].as_bool().unwrap_or(false),
+            rows: resp_json["data"]["items"].as_array().map_or(0, |items| items.len()),
+            http_status: None,
+        }
+    }
+}
+
+/// How strictly the tushare response body is parsed, set by QueryBuilder::parse_mode()
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ParseMode {
+    /// Fail with the json path of the first node not as documented, e.g. a row of less values than the fields
+    #[default]
+    Strict,
+    /// Parse what is possible and log a warning for the rest: missing items become zero rows,
+    /// short rows are filled with nulls, extra values and nested values are dropped.
+    /// Only a missing data/fields is still an error, since no column is known
+    Lenient,
+}
+
+impl Table {
+    /// Extract the table from the tushare response body.
+    /// DataError is returned with the json path if the body is not as documented,
+    /// and RowShapeError if a row has more or less values than the fields.
+    pub fn from_response(resp_json: &Value) -> Result<Self, TushareError> {
+        Self::parse(resp_json, ParseMode::Strict, false)
+    }
+
+    /// Same as from_response() in the parse mode, the malformed rows are skipped with a warning if skip_malformed_rows is set.
+    /// The body is walked by reference, only the values are copied into the columns.
+    pub(crate) fn parse(resp_json: &Value, mode: ParseMode, skip_malformed_rows: bool) -> Result<Self, TushareError> {
+        parse_with(resp_json, mode, skip_malformed_rows)?.table
+    }
+
+    /// Convert the numeric strings of the columns to json numbers, e.g. "10.23" to 10.23,
+    /// for the apis returning some numbers as strings, which breaks the type inference of the DataFrame.
+    /// The listed columns are coerced whole, a string not a number becomes null.
+    /// With no column listed, only the columns mixing numbers and numeric strings are coerced,
+    /// so codes and dates returned as strings like "20240424" are kept.
+    pub fn coerce_numeric(&mut self, columns: &[&str]) {
+        for (field, values) in self.fields.iter().zip(self.columns.iter_mut()) {
+            let listed = columns.contains(&field.as_str());
+            if !listed {
+                if !columns.is_empty() {
+                    continue;
+                }
+                let has_number = values.iter().any(|v| v.is_number());
+                if !has_number || !values.iter().all(|v| !v.is_string() || v.as_str().and_then(parse_number).is_some()) {
+                    continue;
+                }
+            }
+            for value in values.iter_mut() {
+                if let Value::String(s) = value {
+                    *value = parse_number(s).unwrap_or(Value::Null);
+                }
+            }
+        }
+    }
+
+    /// Number of rows
+    pub fn len(&self) -> usize {
+        self.columns.first().map_or(0, Vec::len)
+    }
+
+    /// Whether there is no row
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Convert every row to a json object keyed by field names
+    pub fn rows(&self) -> Vec<Value> {
+        (0..self.len())
+            .map(|i| {
+                let row: Map<String, Value> = self
+                    .fields
+                    .iter()
+                    .cloned()
+                    .zip(self.columns.iter().map(|column| column.get(i).cloned().unwrap_or_default()))
+                    .collect();
+                Value::Object(row)
+            })
+            .collect()
+    }
+}
+
+/// Parse a numeric string as an integer or a finite float json number
+fn parse_number(s: &str) -> Option<Value> {
+    let s = s.trim();
+    if let Ok(i) = s.parse::<i64>() {
+        return Some(i.into());
+    }
+    s.parse::<f64>()
+        .ok()
+        .and_then(serde_json::Number::from_f64)
+        .map(Value::Number)
+}
+
+/// A tushare response body deserialized by [parse_response]
+#[derive(Debug)]
+pub(crate) struct ParsedResponse {
+    /// The return code, 0 on success
+    pub code: Option<i64>,
+    /// The error message of a non-zero code
+    pub msg: Option<String>,
+    /// Request id generated by tushare
+    pub request_id: Option<String>,
+    /// The table of the data, an error if the data is missing or not as documented
+    pub table: Result<Table, TushareError>,
+}
+
+/// Deserialize a response body in one pass: data/items are read row by row into the columns of the table,
+/// so the json tree of the whole body is never built.
+pub(crate) fn parse_response(
+    body: &str,
+    mode: ParseMode,
+    skip_malformed_rows: bool,
+) -> Result<ParsedResponse, TushareError> {
+    let mut deserializer = serde_json::Deserializer::from_str(body);
+    let response = parse_with(&mut deserializer, mode, skip_malformed_rows)?;
+    deserializer.end()?;
+    Ok(response)
+}
+
+/// Deserialize a response body from any json deserializer, e.g. a str or a Value
+fn parse_with<'de, D>(deserializer: D, mode: ParseMode, skip_malformed_rows: bool) -> Result<ParsedResponse, TushareError>
+where
+    D: Deserializer<'de, Error = serde_json::Error>,
+{
+    let mut builder = TableBuilder {
+        mode,
+        skip_malformed_rows,
+        fields: None,
+        columns: vec![],
+        pending: vec![],
+        items: 0,
+        has_items: false,
+        has_more: false,
+        error: None,
+    };
+    let envelope = deserializer.deserialize_map(ResponseVisitor(&mut builder))?;
+    Ok(ParsedResponse {
+        code: envelope.code,
+        msg: envelope.msg,
+        request_id: envelope.request_id,
+        table: match envelope.has_data {
+            true => builder.finish(),
+            false => Err(TushareError::DataError("data/fields".to_string())),
+        },
+    })
+}
+
+/// The columns of the table filled while data is deserialized.
+/// The first error is kept and the rest of the body is still read, so the code of the body is always known.
+struct TableBuilder {
+    mode: ParseMode,
+    skip_malformed_rows: bool,
+    fields: Option<Vec<String>>,
+    columns: Vec<Vec<Value>>,
+    /// The items read before the fields, added once the fields are known
+    pending: Vec<Value>,
+    /// Items read so far, including the skipped ones
+    items: usize,
+    has_items: bool,
+    has_more: bool,
+    error: Option<TushareError>,
+}
+
+impl TableBuilder {
+    fn record(&mut self, result: Result<(), TushareError>) {
+        if let Err(e) = result {
+            self.error.get_or_insert(e);
+        }
+    }
+
+    fn set_fields(&mut self, fields: Value) -> Result<(), TushareError> {
+        let Value::Array(fields) = fields else {
+            return Err(TushareError::DataError("data/fields".to_string()));
+        };
+        let mut names = Vec::with_capacity(fields.len());
+        for (i, field) in fields.into_iter().enumerate() {
+            let name = match field {
+                Value::String(field) => field,
+                field if self.mode == ParseMode::Lenient => {
+                    warn!("data/fields/{i} is not a string: {field}");
+                    field.to_string()
+                }
+                _ => return Err(TushareError::DataError(format!("data/fields/{i}"))),
+            };
+            names.push(name);
+        }
+        self.columns = vec![Vec::new(); names.len()];
+        self.fields = Some(names);
+        for (i, item) in std::mem::take(&mut self.pending).into_iter().enumerate() {
+            self.push_item(i, item)?;
+        }
+        Ok(())
+    }
+
+    fn add_item(&mut self, item: Value) {
+        let i = self.items;
+        self.items += 1;
+        if self.error.is_some() {
+            return;
+        }
+        match self.fields {
+            Some(_) => {
+                let result = self.push_item(i, item);
+                self.record(result);
+            }
+            None => self.pending.push(item),
+        }
+    }
+
+    /// Append the values of the i-th item to the columns
+    fn push_item(&mut self, i: usize, item: Value) -> Result<(), TushareError> {
+        let lenient = self.mode == ParseMode::Lenient;
+        let Value::Array(values) = item else {
+            if lenient {
+                warn!("data/items/{i} is not an array, skipped");
+                return Ok(());
+            }
+            return Err(TushareError::DataError(format!("data/items/{i} is expected to be an array")));
+        };
+        if values.len() != self.columns.len() {
+            let (expected, got) = (self.columns.len(), values.len());
+            if self.skip_malformed_rows {
+                warn!("data/items/{i} has {got} values but {expected} fields, skipped");
+                return Ok(());
+            }
+            if !lenient {
+                return Err(TushareError::RowShapeError { row: i, expected, got });
+            }
+            warn!("data/items/{i} has {got} values but {expected} fields, filled with nulls");
+        }
+        let mut values = values.into_iter();
+        for (j, column) in self.columns.iter_mut().enumerate() {
+            let value = match values.next() {
+                Some(Value::Array(_) | Value::Object(_)) if lenient => {
+                    warn!("data/items/{i}/{j} is not a scalar, replaced with null");
+                    Value::Null
+                }
+                Some(Value::Array(_) | Value::Object(_)) => {
+                    return Err(TushareError::DataError(format!("data/items/{i}/{j} is expected to be a scalar")))
+                }
+                Some(value) => value,
+                None => Value::Null,
+            };
+            column.push(value);
+        }
+        Ok(())
+    }
+
+    fn set_has_more(&mut self, has_more: Value) -> Result<(), TushareError> {
+        self.has_more = match has_more {
+            Value::Bool(has_more) => has_more,
+            Value::Null => false,
+            _ if self.mode == ParseMode::Lenient => {
+                warn!("data/has_more is not a bool, taken as false");
+                false
+            }
+            _ => return Err(TushareError::DataError("data/has_more is expected to be a bool".to_string())),
+        };
+        Ok(())
+    }
+
+    fn finish(self) -> Result<Table, TushareError> {
+        if let Some(e) = self.error {
+            return Err(e);
+        }
+        let Some(fields) = self.fields else {
+            return Err(TushareError::DataError("data/fields".to_string()));
+        };
+        if !self.has_items {
+            if self.mode == ParseMode::Strict {
+                return Err(TushareError::DataError("data/items".to_string()));
+            }
+            warn!("data/items is missing, zero rows returned");
+        }
+        Ok(Table {
+            fields,
+            columns: self.columns,
+            has_more: self.has_more,
+        })
+    }
+}
+
+/// The fields of a response body besides data
+#[derive(Default)]
+struct Envelope {
+    code: Option<i64>,
+    msg: Option<String>,
+    request_id: Option<String>,
+    has_data: bool,
+}
+
+/// Visit the response object, data is read into the builder
+struct ResponseVisitor<'a>(&'a mut TableBuilder);
+
+impl<'de> Visitor<'de> for ResponseVisitor<'_> {
+    type Value = Envelope;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("a tushare response object")
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Envelope, A::Error> {
+        let mut envelope = Envelope::default();
+        while let Some(key) = map.next_key::<String>()? {
+            match key.as_str() {
+                "code" => envelope.code = map.next_value::<Value>()?.as_i64(),
+                "msg" => envelope.msg = map.next_value::<Value>()?.as_str().map(String::from),
+                "request_id" => envelope.request_id = map.next_value::<Value>()?.as_str().map(String::from),
+                "data" => envelope.has_data = map.next_value_seed(DataVisitor(&mut *self.0))?,
+                _ => {
+                    map.next_value::<IgnoredAny>()?;
+                }
+            }
+        }
+        Ok(envelope)
+    }
+}
+
+/// Visit the data object, false if it is null
+struct DataVisitor<'a>(&'a mut TableBuilder);
+
+impl<'de> DeserializeSeed<'de> for DataVisitor<'_> {
+    type Value = bool;
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<bool, D::Error> {
+        deserializer.deserialize_any(self)
+    }
+}
+
+impl<'de> Visitor<'de> for DataVisitor<'_> {
+    type Value = bool;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("the data object")
+    }
+
+    fn visit_unit<E: de::Error>(self) -> Result<bool, E> {
+        Ok(false)
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<bool, A::Error> {
+        while let Some(key) = map.next_key::<String>()? {
+            match key.as_str() {
+                "fields" => {
+                    let result = self.0.set_fields(map.next_value()?);
+                    self.0.record(result);
+                }
+                "items" => map.next_value_seed(ItemsVisitor(&mut *self.0))?,
+                "has_more" => {
+                    let result = self.0.set_has_more(map.next_value()?);
+                    self.0.record(result);
+                }
+                _ => {
+                    map.next_value::<IgnoredAny>()?;
+                }
+            }
+        }
+        Ok(true)
+    }
+}
+
+/// Visit data/items one row at a time, null is taken as missing
+struct ItemsVisitor<'a>(&'a mut TableBuilder);
+
+impl<'de> DeserializeSeed<'de> for ItemsVisitor<'_> {
+    type Value = ();
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<(), D::Error> {
+        deserializer.deserialize_any(self)
+    }
+}
+
+impl<'de> Visitor<'de> for ItemsVisitor<'_> {
+    type Value = ();
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("the data/items array")
+    }
+
+    fn visit_unit<E: de::Error>(self) -> Result<(), E> {
+        Ok(())
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<(), A::Error> {
+        self.0.has_items = true;
+        while let Some(item) = seq.next_element::<Value>()? {
+            self.0.add_item(item);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    #[test]
+    fn test_from_response() {
+        let resp = json!({"request_id": "abc", "code": 0, "data": {"fields": ["ts_code", "close"], "items": [["000001.SZ", 10.5]], "has_more": true}});
+        let table = Table::from_response(&resp).unwrap();
+        assert_eq!(table.len(), 1);
+        assert!(table.has_more);
+        let meta = ResponseMeta::from_response(&resp);
+        assert_eq!(meta.request_id.as_deref(), Some("abc"));
+        assert_eq!(meta.rows, 1);
+        assert_eq!(table.rows(), vec![json!({"ts_code": "000001.SZ", "close": 10.5})]);
+        assert!(Table::from_response(&json!({"data": {"fields": []}})).is_err());
+        let malformed = json!({"data": {"fields": ["ts_code", "close"], "items": [["000001.SZ", 10.5], ["600000.SH"]]}});
+        assert!(matches!(
+            Table::from_response(&malformed),
+            Err(TushareError::RowShapeError { row: 1, expected: 2, got: 1 })
+        ));
+        assert_eq!(Table::parse(&malformed, ParseMode::Strict, true).unwrap().len(), 1);
+        let lenient = Table::parse(&malformed, ParseMode::Lenient, false).unwrap();
+        assert_eq!(lenient.rows()[1], json!({"ts_code": "600000.SH", "close": null}));
+        let nested = json!({"data": {"fields": ["ts_code"], "items": [[["000001.SZ"]]]}});
+        assert!(matches!(Table::from_response(&nested), Err(TushareError::DataError(path)) if path.starts_with("data/items/0/0")));
+        assert_eq!(Table::parse(&nested, ParseMode::Lenient, false).unwrap().columns, vec![vec![Value::Null]]);
+        assert!(Table::parse(&json!({"data": {"fields": ["ts_code"]}}), ParseMode::Lenient, false).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_coerce_numeric() {
+        let mut table = Table {
+            fields: vec!["trade_date".to_string(), "close".to_string(), "vol".to_string()],
+            columns: vec![
+                vec![json!("20240424"), json!("20240423")],
+                vec![json!(10.5), json!("10.23")],
+                vec![json!("100"), json!("n/a")],
+            ],
+            has_more: false,
+        };
+        table.coerce_numeric(&[]);
+        assert_eq!(table.rows()[1], json!({"trade_date": "20240423", "close": 10.23, "vol": "n/a"}));
+        table.coerce_numeric(&["vol"]);
+        assert_eq!(table.columns[2], vec![json!(100), Value::Null]);
+    }
+
+    #[test]
+    fn test_parse_response() {
+        // a 6,000 rows x 30 columns body written as text, parsed without a json tree
+        let (rows, width) = (6000, 30);
+        let fields: Vec<String> = (0..width).map(|j| format!("\"f{j}\"")).collect();
+        let mut body = format!("{{\"request_id\": \"abc\", \"code\": 0, \"msg\": \"\", \"data\": {{\"fields\": [{}], \"items\": [", fields.join(","));
+        for i in 0..rows {
+            let values: Vec<String> = (0..width).map(|j| format!("{}.5", i * width + j)).collect();
+            body.push_str(&format!("{}[{}]", if i == 0 { "" } else { "," }, values.join(",")));
+        }
+        body.push_str("], \"has_more\": true}}");
+        let response = parse_response(&body, ParseMode::Strict, false).unwrap();
+        assert_eq!((response.code, response.request_id.as_deref()), (Some(0), Some("abc")));
+        let table = response.table.unwrap();
+        assert_eq!((table.len(), table.fields.len(), table.has_more), (rows, width, true));
+        assert!(table.columns.iter().all(|column| column.len() == rows));
+        assert_eq!(table.columns[29][5999], json!(179999.5));
+
+        // the items may come before the fields
+        let body = r#"{"data": {"items": [["000001.SZ", 1], ["600000.SH"]], "fields": ["ts_code", "vol"]}}"#;
+        let table = parse_response(body, ParseMode::Lenient, false).unwrap().table.unwrap();
+        assert_eq!(table.columns[1], vec![json!(1), Value::Null]);
+        assert!(matches!(
+            parse_response(body, ParseMode::Strict, false).unwrap().table,
+            Err(TushareError::RowShapeError { row: 1, expected: 2, got: 1 })
+        ));
+        // the code of an error is read even if data is null
+        let response = parse_response(r#"{"data": null, "code": 40101, "msg": "token不对"}"#, ParseMode::Strict, false).unwrap();
+        assert_eq!((response.code, response.msg.as_deref()), (Some(40101), Some("token不对")));
+        assert!(parse_response(r#"{"code": 0} trailing"#, ParseMode::Strict, false).is_err());
+    }
+}
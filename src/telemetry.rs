@@ -0,0 +1,88 @@
+//! The tracing span of a query, enabled by the "tracing" feature and a no-op without it.
+//! The span `tushare_query` has the fields api_name, params, attempts, elapsed_ms and rows,
+//! every failed attempt is recorded as a warn event and the final error as an error event.
+use crate::builder::Dict;
+use crate::error::TushareError;
+#[cfg(feature = "tracing")]
+use std::cell::Cell;
+#[cfg(feature = "tracing")]
+use std::time::Instant;
+
+/// The span of a query, entered until it is dropped
+pub(crate) struct QuerySpan {
+    #[cfg(feature = "tracing")]
+    span: tracing::span::EnteredSpan,
+    #[cfg(feature = "tracing")]
+    start: Instant,
+    #[cfg(feature = "tracing")]
+    attempts: Cell<u32>,
+}
+
+impl QuerySpan {
+    /// Create and enter the span of a query
+    #[cfg(feature = "tracing")]
+    pub(crate) fn enter(api_name: &str, params: Option<&Dict>) -> Self {
+        use tracing::field::Empty;
+        let span = tracing::info_span!(
+            "tushare_query",
+            api_name,
+            params = %param_summary(params),
+            attempts = Empty,
+            elapsed_ms = Empty,
+            rows = Empty,
+        );
+        QuerySpan {
+            span: span.entered(),
+            start: Instant::now(),
+            attempts: Cell::new(0),
+        }
+    }
+
+    #[cfg(not(feature = "tracing"))]
+    pub(crate) fn enter(_api_name: &str, _params: Option<&Dict>) -> Self {
+        QuerySpan {}
+    }
+
+    /// Record an attempt of the request, with an event if it failed
+    pub(crate) fn attempt<T>(&self, result: &Result<T, TushareError>) {
+        #[cfg(feature = "tracing")]
+        {
+            let attempt = self.attempts.get() + 1;
+            self.attempts.set(attempt);
+            self.span.record("attempts", attempt);
+            if let Err(e) = result {
+                tracing::warn!(attempt, error = %e, "attempt failed");
+            }
+        }
+        #[cfg(not(feature = "tracing"))]
+        let _ = result;
+    }
+
+    /// Record the elapsed time and the rows of the query, or an error event if it failed
+    pub(crate) fn finish<T>(&self, result: &Result<T, TushareError>, rows: impl FnOnce(&T) -> usize) {
+        #[cfg(feature = "tracing")]
+        {
+            self.span.record("elapsed_ms", self.start.elapsed().as_millis() as u64);
+            match result {
+                Ok(value) => {
+                    self.span.record("rows", rows(value));
+                }
+                Err(e) => tracing::error!(error = %e, "query failed"),
+            }
+        }
+        #[cfg(not(feature = "tracing"))]
+        let _ = (result, rows);
+    }
+}
+
+/// The params sorted by name like `end_date=20240430,ts_code=000001.SZ`
+#[cfg(feature = "tracing")]
+fn param_summary(params: Option<&Dict>) -> String {
+    let mut params: Vec<String> = params
+        .into_iter()
+        .flatten()
+        .map(|(k, v)| format!("{k}={v}"))
+        .collect();
+    params.sort();
+    params.join(",")
+}
@@ -0,0 +1,155 @@
+//! Fetch the token of every request from a vault or secret manager, set by Tushare::with_token_provider().
+//! The provider is asked when a query builds its request, so a rotated secret is picked up by the next query
+//! without recreating the Tushare object.
+//! ```no_run
+//! # fn main() -> Result<(), tushare::TushareError> {
+//! use std::time::Duration;
+//! use tushare::token::CachedToken;
+//! use tushare::TushareError;
+//! let read_secret = || std::fs::read_to_string("/run/secrets/tushare_token")
+//!     .map(|token| token.trim().to_string())
+//!     .map_err(|e| TushareError::ConfigError(e.to_string()));
+//! let tushare = tushare::Tushare::new("")
+//!     .with_token_provider(CachedToken::new(read_secret, Duration::from_secs(300)));
+//! # Ok(())
+//! # }
+//! ```
+use crate::error::TushareError;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll, Wake, Waker};
+use std::thread::{self, Thread};
+use std::time::{Duration, Instant};
+
+/// The source of the token, asked once per query before the request is sent.
+/// Implemented for closures returning `Result<String, TushareError>`.
+pub trait TokenProvider: Send + Sync {
+    /// The token of the next request, the error fails the query before anything is sent
+    fn token(&self) -> Result<String, TushareError>;
+}
+
+impl<F> TokenProvider for F
+where
+    F: Fn() -> Result<String, TushareError> + Send + Sync,
+{
+    fn token(&self) -> Result<String, TushareError> {
+        self()
+    }
+}
+
+/// The future returned by an [AsyncTokenProvider]
+pub type TokenFuture<'a> = Pin<Box<dyn Future<Output = Result<String, TushareError>> + Send + 'a>>;
+
+/// An async source of the token, e.g. the async client of a secret manager.
+/// Wrap it in [Blocking] to use it with Tushare::with_token_provider().
+pub trait AsyncTokenProvider: Send + Sync {
+    /// The token of the next request
+    fn token(&self) -> TokenFuture<'_>;
+}
+
+/// A [TokenProvider] polling an [AsyncTokenProvider] to completion on the calling thread.
+/// The future must not rely on the reactor of an async runtime, e.g. tokio io or timers,
+/// spawn such work on the runtime and await its result through a channel instead.
+pub struct Blocking<P>(pub P);
+
+impl<P: AsyncTokenProvider> TokenProvider for Blocking<P> {
+    fn token(&self) -> Result<String, TushareError> {
+        block_on(self.0.token())
+    }
+}
+
+/// Wakes the thread blocked in block_on()
+struct ThreadWaker(Thread);
+
+impl Wake for ThreadWaker {
+    fn wake(self: Arc<Self>) {
+        self.0.unpark();
+    }
+}
+
+fn block_on<T>(mut future: Pin<Box<dyn Future<Output = T> + Send + '_>>) -> T {
+    let waker = Waker::from(Arc::new(ThreadWaker(thread::current())));
+    let mut cx = Context::from_waker(&waker);
+    loop {
+        match future.as_mut().poll(&mut cx) {
+            Poll::Ready(value) => return value,
+            Poll::Pending => thread::park(),
+        }
+    }
+}
+
+/// A [TokenProvider] keeping the token of another provider for ttl, so the vault is not asked for every query.
+/// An error is not cached, the next query asks again.
+pub struct CachedToken<P> {
+    provider: P,
+    ttl: Duration,
+    cached: Mutex<Option<(String, Instant)>>,
+}
+
+impl<P: TokenProvider> CachedToken<P> {
+    /// Keep the tokens of provider for ttl
+    pub fn new(provider: P, ttl: Duration) -> Self {
+        CachedToken {
+            provider,
+            ttl,
+            cached: Mutex::new(None),
+        }
+    }
+}
+
+impl<P: TokenProvider> TokenProvider for CachedToken<P> {
+    fn token(&self) -> Result<String, TushareError> {
+        let mut cached = self.cached.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some((token, fetched)) = cached.as_ref() {
+            if fetched.elapsed() < self.ttl {
+                return Ok(token.clone());
+            }
+        }
+        let token = self.provider.token()?;
+        *cached = Some((token.clone(), Instant::now()));
+        Ok(token)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::transport::MockTransport;
+    use crate::Tushare;
+    use serde_json::Value;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    #[test]
+    fn test_token_provider() {
+        let mock = Arc::new(
+            MockTransport::new()
+                .respond("daily", MockTransport::data(&["close"], vec![vec![1.0.into()]]))
+                .respond("daily", MockTransport::data(&["close"], vec![vec![2.0.into()]])),
+        );
+        let calls = AtomicUsize::new(0);
+        let provider = move || Ok(format!("token{}", calls.fetch_add(1, Ordering::Relaxed)));
+        let tushare = Tushare::new("unused").with_token_provider(provider).with_transport(mock.clone());
+        tushare.querybuilder("daily").query_raw().unwrap();
+        tushare.querybuilder("daily").query_raw().unwrap();
+        let tokens: Vec<Value> = mock.requests().iter().map(|r| r["token"].clone()).collect();
+        assert_eq!(tokens, ["token0", "token1"]);
+    }
+
+    #[test]
+    fn test_blocking_and_cached() {
+        struct Vault(AtomicUsize);
+        impl AsyncTokenProvider for Vault {
+            fn token(&self) -> TokenFuture<'_> {
+                let n = self.0.fetch_add(1, Ordering::Relaxed);
+                Box::pin(async move { Ok(format!("token{n}")) })
+            }
+        }
+        let provider = CachedToken::new(Blocking(Vault(AtomicUsize::new(0))), Duration::from_secs(60));
+        assert_eq!(provider.token().unwrap(), "token0");
+        assert_eq!(provider.token().unwrap(), "token0");
+        let provider = CachedToken::new(Blocking(Vault(AtomicUsize::new(0))), Duration::ZERO);
+        assert_eq!(provider.token().unwrap(), "token0");
+        assert_eq!(provider.token().unwrap(), "token1");
+    }
+}
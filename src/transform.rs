@@ -0,0 +1,246 @@
+use crate::date::{TUSHARE_DATETIME_FORMAT, TUSHARE_DATE_FORMAT};
+use polars::prelude::*;
+use std::collections::HashMap;
+
+/// Date columns commonly returned by tushare apis in *YYYYMMDD* format.
+/// Used by QueryBuilder::parse_dates(true).
+pub const KNOWN_DATE_COLUMNS: &[&str] = &[
+    "trade_date",
+    "cal_date",
+    "pretrade_date",
+    "ann_date",
+    "f_ann_date",
+    "end_date",
+    "list_date",
+    "delist_date",
+    "ex_date",
+    "record_date",
+    "pay_date",
+    "div_listdate",
+    "imp_ann_date",
+    "base_date",
+    "nav_date",
+    "in_date",
+    "out_date",
+];
+
+fn date_expr(column: &str) -> Expr {
+    col(column).str().to_date(StrptimeOptions {
+        format: Some(TUSHARE_DATE_FORMAT.to_string()),
+        strict: false,
+        ..Default::default()
+    })
+}
+
+/// Convert the given String columns in *YYYYMMDD* format to polars Date.
+/// Columns not present or not String are ignored, unparsable values become null.
+pub fn parse_dates(df: DataFrame, columns: &[String]) -> PolarsResult<DataFrame> {
+    let exprs: Vec<Expr> = columns
+        .iter()
+        .filter(|c| matches!(df.column(c).map(|s| s.dtype()), Ok(DataType::String)))
+        .map(|c| date_expr(c))
+        .collect();
+    if exprs.is_empty() {
+        return Ok(df);
+    }
+    df.lazy().with_columns(exprs).collect()
+}
+
+/// Convert the given String columns in *YYYY-MM-DD HH:MM:SS* format, e.g. trade_time of minute bars, to polars Datetime.
+/// Columns not present or not String are ignored, unparsable values become null.
+pub fn parse_datetimes(df: DataFrame, columns: &[&str]) -> PolarsResult<DataFrame> {
+    let exprs: Vec<Expr> = columns
+        .iter()
+        .filter(|c| matches!(df.column(c).map(|s| s.dtype()), Ok(DataType::String)))
+        .map(|c| {
+            col(c).str().to_datetime(
+                Some(TimeUnit::Milliseconds),
+                None,
+                StrptimeOptions {
+                    format: Some(TUSHARE_DATETIME_FORMAT.to_string()),
+                    strict: false,
+                    ..Default::default()
+                },
+                lit("raise"),
+            )
+        })
+        .collect();
+    if exprs.is_empty() {
+        return Ok(df);
+    }
+    df.lazy().with_columns(exprs).collect()
+}
+
+/// Cast the columns to the dtypes of the schema.
+/// Columns not present in the DataFrame are ignored, values failed to cast become null.
+/// String columns cast to Date are parsed in *YYYYMMDD* format.
+pub fn apply_schema(df: DataFrame, schema: &Schema) -> PolarsResult<DataFrame> {
+    let exprs: Vec<Expr> = schema
+        .iter()
+        .filter_map(|(name, dtype)| {
+            let current = df.column(name).ok()?.dtype();
+            match (current, dtype) {
+                (current, dtype) if current == dtype => None,
+                (DataType::String, DataType::Date) => Some(date_expr(name)),
+                _ => Some(col(name).cast(dtype.clone())),
+            }
+        })
+        .collect();
+    if exprs.is_empty() {
+        return Ok(df);
+    }
+    df.lazy().with_columns(exprs).collect()
+}
+
+/// What to do with the nulls of numeric columns, set by QueryBuilder::null_policy().
+/// Financial apis return null for the items a company doesn't report, e.g. inventories of a bank.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum NullPolicy {
+    /// Keep the nulls as returned by tushare
+    #[default]
+    KeepNull,
+    /// Replace the nulls with 0
+    FillZero,
+    /// Drop the rows having a null
+    DropRows,
+}
+
+/// Apply the policy to every numeric column, or the policy in overrides for a column named there.
+/// Non numeric columns are never changed.
+pub fn apply_null_policy(
+    df: DataFrame,
+    policy: NullPolicy,
+    overrides: &HashMap<String, NullPolicy>,
+) -> PolarsResult<DataFrame> {
+    let columns: Vec<(String, NullPolicy)> = df
+        .get_columns()
+        .iter()
+        .filter(|s| s.dtype().is_numeric() && s.null_count() > 0)
+        .map(|s| {
+            let name = s.name().to_string();
+            let policy = overrides.get(&name).copied().unwrap_or(policy);
+            (name, policy)
+        })
+        .collect();
+    let fill: Vec<Expr> = columns
+        .iter()
+        .filter(|(_, policy)| *policy == NullPolicy::FillZero)
+        .map(|(name, _)| col(name).fill_null(lit(0)))
+        .collect();
+    let drop: Vec<&str> = columns
+        .iter()
+        .filter(|(_, policy)| *policy == NullPolicy::DropRows)
+        .map(|(name, _)| name.as_str())
+        .collect();
+    let df = if drop.is_empty() { df } else { df.drop_nulls(Some(&drop))? };
+    if fill.is_empty() {
+        return Ok(df);
+    }
+    df.lazy().with_columns(fill).collect()
+}
+
+/// Convert a field name to snake_case, e.g. "TotalRevenue" to "total_revenue" and "pct-chg" to "pct_chg".
+/// Other characters than letters and digits become "_".
+pub fn snake_case(name: &str) -> String {
+    let mut result = String::with_capacity(name.len());
+    let mut prev: Option<char> = None;
+    for c in name.chars() {
+        if c.is_uppercase() {
+            if prev.is_some_and(|p| p.is_lowercase() || p.is_ascii_digit()) {
+                result.push('_');
+            }
+            result.extend(c.to_lowercase());
+        } else if c.is_alphanumeric() {
+            result.push(c);
+        } else if !result.is_empty() && !result.ends_with('_') {
+            result.push('_');
+        }
+        prev = Some(c);
+    }
+    result.trim_end_matches('_').to_string()
+}
+
+/// Rename the columns in renames, then convert the other ones to snake_case if normalize is set.
+/// An error is returned if two columns end up with the same name.
+pub fn rename_columns(
+    mut df: DataFrame,
+    renames: &HashMap<String, String>,
+    normalize: bool,
+) -> PolarsResult<DataFrame> {
+    if renames.is_empty() && !normalize {
+        return Ok(df);
+    }
+    let names: Vec<String> = df
+        .get_column_names()
+        .into_iter()
+        .map(|name| match renames.get(name) {
+            Some(rename) => rename.clone(),
+            None if normalize => snake_case(name),
+            None => name.to_string(),
+        })
+        .collect();
+    df.set_column_names(&names)?;
+    Ok(df)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[test]
+    fn test_parse_dates() {
+        let df = df!("trade_date" => ["20240424", "bad"], "close" => [1.0, 2.0]).unwrap();
+        let df = parse_dates(df, &["trade_date".to_string(), "close".to_string()]).unwrap();
+        assert_eq!(df.column("trade_date").unwrap().dtype(), &DataType::Date);
+        assert_eq!(df.column("trade_date").unwrap().null_count(), 1);
+        assert_eq!(df.column("close").unwrap().dtype(), &DataType::Float64);
+    }
+
+    #[test]
+    fn test_apply_schema() {
+        let df = df!("close" => ["10.23", "x"], "vol" => [1i64, 2]).unwrap();
+        let schema = Schema::from_iter([
+            Field::new("close", DataType::Float64),
+            Field::new("vol", DataType::Float64),
+            Field::new("missing", DataType::Int32),
+        ]);
+        let df = apply_schema(df, &schema).unwrap();
+        assert_eq!(df.column("close").unwrap().f64().unwrap().get(0), Some(10.23));
+        assert_eq!(df.column("close").unwrap().null_count(), 1);
+        assert_eq!(df.column("vol").unwrap().dtype(), &DataType::Float64);
+        assert_eq!(df.width(), 2);
+    }
+
+    #[test]
+    fn test_apply_null_policy() {
+        let df = df!(
+            "ts_code" => [Some("000001.SZ"), None, Some("000002.SZ")],
+            "inventories" => [None, Some(1.0), Some(2.0)],
+            "vol" => [Some(1i64), Some(2), None]
+        )
+        .unwrap();
+        let kept = apply_null_policy(df.clone(), NullPolicy::KeepNull, &HashMap::new()).unwrap();
+        assert!(kept.equals_missing(&df));
+        let filled = apply_null_policy(df.clone(), NullPolicy::FillZero, &HashMap::new()).unwrap();
+        assert_eq!(filled.column("inventories").unwrap().f64().unwrap().get(0), Some(0.0));
+        assert_eq!(filled.column("vol").unwrap().i64().unwrap().get(2), Some(0));
+        assert_eq!(filled.column("ts_code").unwrap().null_count(), 1);
+        let overrides = HashMap::from([("vol".to_string(), NullPolicy::DropRows)]);
+        let dropped = apply_null_policy(df, NullPolicy::FillZero, &overrides).unwrap();
+        assert_eq!(dropped.height(), 2);
+        assert_eq!(dropped.column("inventories").unwrap().null_count(), 0);
+    }
+
+    #[test]
+    fn test_rename_columns() {
+        assert_eq!(snake_case("TotalRevenue"), "total_revenue");
+        assert_eq!(snake_case("pct-chg"), "pct_chg");
+        assert_eq!(snake_case("q_ops_gr"), "q_ops_gr");
+        assert_eq!(snake_case("EPS (TTM)"), "eps_ttm");
+        let df = df!("ts_code" => ["000001.SZ"], "q_ops_gr" => [1.0], "NetProfit" => [2.0]).unwrap();
+        let renames = HashMap::from([("q_ops_gr".to_string(), "op_income_growth".to_string())]);
+        let df = rename_columns(df, &renames, true).unwrap();
+        assert_eq!(df.get_column_names(), &["ts_code", "op_income_growth", "net_profit"]);
+        let renames = HashMap::from([("ts_code".to_string(), "net_profit".to_string())]);
+        assert!(rename_columns(df, &renames, false).is_err());
+    }
+}
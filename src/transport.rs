@@ -0,0 +1,178 @@
+use crate::error::{HttpStatusError, TushareError};
+use log::debug;
+use reqwest::blocking::Client;
+use reqwest::header::RETRY_AFTER;
+use serde_json::{json, Value};
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+/// The http layer of all queries, set by Tushare::with_transport().
+/// The default is [ReqwestTransport], replace it with [MockTransport] to test without network.
+pub trait Transport: Send + Sync {
+    /// Post the json body to the endpoint and return the response body.
+    /// Errors of the transport itself (connection, http status) should be returned as TushareError,
+    /// while the tushare error code in the body is checked by the caller.
+    fn post(&self, endpoint: &str, body: &str, timeout: Option<Duration>) -> Result<String, TushareError>;
+
+    /// Same as post(), together with the http status of the response, reported by QueryReport.
+    /// The default implementation reports 200 for every successful post().
+    fn post_with_status(
+        &self,
+        endpoint: &str,
+        body: &str,
+        timeout: Option<Duration>,
+    ) -> Result<(u16, String), TushareError> {
+        Ok((200, self.post(endpoint, body, timeout)?))
+    }
+}
+
+impl<T: Transport + ?Sized> Transport for Arc<T> {
+    fn post(&self, endpoint: &str, body: &str, timeout: Option<Duration>) -> Result<String, TushareError> {
+        (**self).post(endpoint, body, timeout)
+    }
+
+    fn post_with_status(
+        &self,
+        endpoint: &str,
+        body: &str,
+        timeout: Option<Duration>,
+    ) -> Result<(u16, String), TushareError> {
+        (**self).post_with_status(endpoint, body, timeout)
+    }
+}
+
+/// Send the requests with a reqwest blocking client
+#[derive(Debug, Clone)]
+pub struct ReqwestTransport {
+    client: Client,
+}
+
+impl ReqwestTransport {
+    /// Create a transport with the client.
+    /// Cloning a reqwest client is cheap, it is a reference to the same connection pool.
+    pub fn new(client: Client) -> Self {
+        ReqwestTransport { client }
+    }
+}
+
+impl Transport for ReqwestTransport {
+    fn post(&self, endpoint: &str, body: &str, timeout: Option<Duration>) -> Result<String, TushareError> {
+        Ok(self.post_with_status(endpoint, body, timeout)?.1)
+    }
+
+    fn post_with_status(
+        &self,
+        endpoint: &str,
+        body: &str,
+        timeout: Option<Duration>,
+    ) -> Result<(u16, String), TushareError> {
+        let mut request = self.client.post(endpoint).body(body.to_string());
+        if let Some(timeout) = timeout {
+            request = request.timeout(timeout);
+        }
+        let response = request.send()?; // sending network error
+        let status = response.status();
+        if status.is_client_error() || status.is_server_error() {
+            let retry_after = response.headers().get(RETRY_AFTER).and_then(|v| v.to_str().ok());
+            let e = HttpStatusError::from_status(status.as_u16(), retry_after);
+            // the body of a gateway error is an html page at best, only worth a debug log
+            debug!("{e}: {}", response.text().unwrap_or_default());
+            return Err(e.into());
+        }
+        Ok((status.as_u16(), response.text()?))
+    }
+}
+
+/// A transport replaying canned responses by api_name, for tests without token and network.
+/// Responses of an api are returned in order, and the last one is repeated.
+/// ```
+/// # #[cfg(feature = "dataframe")]
+/// # fn main() -> Result<(), tushare::TushareError> {
+/// use tushare::transport::MockTransport;
+/// use serde_json::json;
+/// let mock = MockTransport::new()
+///     .respond("daily", MockTransport::data(&["ts_code", "close"], vec![vec![json!("000001.SZ"), json!(10.5)]]));
+/// let tushare = tushare::Tushare::new("<any token>").with_transport(mock);
+/// let df = tushare.querybuilder("daily").query()?;
+/// assert_eq!(df.height(), 1);
+/// # Ok(())
+/// # }
+/// # #[cfg(not(feature = "dataframe"))] fn main() {}
+/// ```
+#[derive(Debug, Default)]
+pub struct MockTransport {
+    responses: Mutex<HashMap<String, VecDeque<String>>>,
+    requests: Mutex<Vec<Value>>,
+}
+
+impl MockTransport {
+    /// Create a mock without any response
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Queue a response body of the api
+    pub fn respond(self, api_name: impl Into<String>, body: impl Into<String>) -> Self {
+        self.responses
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .entry(api_name.into())
+            .or_default()
+            .push_back(body.into());
+        self
+    }
+
+    /// A successful response body with the fields and items
+    pub fn data(fields: &[&str], items: Vec<Vec<Value>>) -> String {
+        json!({
+            "request_id": "mock",
+            "code": 0,
+            "msg": "",
+            "data": {"fields": fields, "items": items, "has_more": false}
+        })
+        .to_string()
+    }
+
+    /// An error response body with the tushare code and msg
+    pub fn error(code: i64, msg: &str) -> String {
+        json!({"request_id": "mock", "code": code, "msg": msg, "data": null}).to_string()
+    }
+
+    /// All request bodies received, in order
+    pub fn requests(&self) -> Vec<Value> {
+        self.requests.lock().unwrap_or_else(|e| e.into_inner()).clone()
+    }
+}
+
+impl Transport for MockTransport {
+    fn post(&self, _endpoint: &str, body: &str, _timeout: Option<Duration>) -> Result<String, TushareError> {
+        let request: Value = serde_json::from_str(body)?;
+        let api_name = request["api_name"].as_str().unwrap_or_default().to_string();
+        self.requests
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .push(request);
+        let mut responses = self.responses.lock().unwrap_or_else(|e| e.into_inner());
+        let response = match responses.get_mut(&api_name) {
+            Some(queue) if queue.len() > 1 => queue.pop_front(),
+            Some(queue) => queue.front().cloned(),
+            None => None,
+        };
+        Ok(response.unwrap_or_else(|| Self::error(-1, &format!("no mock response of {api_name}"))))
+    }
+}
+
+/// Fail every request with CacheMiss without touching the network, the transport of Tushare::offline()
+#[derive(Debug, Clone, Copy, Default)]
+pub struct OfflineTransport;
+
+impl Transport for OfflineTransport {
+    fn post(&self, _endpoint: &str, body: &str, _timeout: Option<Duration>) -> Result<String, TushareError> {
+        let mut request: Value = serde_json::from_str(body)?;
+        if let Some(obj) = request.as_object_mut() {
+            obj.remove("token");
+        }
+        Err(TushareError::CacheMiss(format!("request {request} is not cached and the network is off")))
+    }
+}
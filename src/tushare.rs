@@ -1,12 +1,57 @@
 use crate::builder::*;
+#[cfg(feature = "dataframe")]
+use crate::cache::Cache;
+#[cfg(feature = "dataframe")]
+use crate::coalesce::Coalescer;
+use crate::client::ClientConfig;
+use crate::config::{self, TushareConfig};
+use crate::metrics::Metrics;
+use crate::middleware::Middleware;
+use crate::ratelimit::RateLimiter;
+use crate::retry::RetryPolicy;
+use crate::rotation::{Rotation, TokenRotation};
+use crate::token::TokenProvider;
+use crate::transport::{ReqwestTransport, Transport};
+use reqwest::blocking::Client;
+use std::path::Path;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// The default endpoint of tushare pro api
+pub const TUSHARE_ENDPOINT: &str = "https://api.tushare.pro";
+/// The official mirror of tushare pro api, for networks where api.tushare.pro is slow or blocked
+pub const WADITU_ENDPOINT: &str = "http://api.waditu.com";
 
 /// A struct to hold all tushare calls
 pub struct Tushare {
     /// Internal string holds tushare webapi access token.
     /// Used in every call as a hidden parameter.
     pub token: String,
-    /// This is actually a constant of "http://api.tushare.pro"
+    /// The url requests are posted to, [TUSHARE_ENDPOINT] by default.
+    /// Set it with with_endpoint() to use a mirror like [WADITU_ENDPOINT] or a corporate gateway.
     pub api_endpoint: String,
+    /// Retry policy of transient errors in query(), see [RetryPolicy::default]
+    pub retry_policy: RetryPolicy,
+    /// Optional client side throttling shared by all queries, see [RateLimiter]
+    pub rate_limiter: Option<RateLimiter>,
+    /// Optional cache of query results, see [Cache]
+    #[cfg(feature = "dataframe")]
+    pub cache: Option<Box<dyn Cache>>,
+    /// Settings of the underlying http client, see [TushareBuilder]
+    pub(crate) client_config: ClientConfig,
+    /// Sends the requests, shared by all queries for connection pooling and keep-alive
+    pub(crate) transport: Arc<dyn Transport>,
+    /// Hooks around every request, see [Middleware]
+    pub(crate) middlewares: Vec<Arc<dyn Middleware>>,
+    /// Call statistics, see Tushare::with_metrics()
+    pub(crate) metrics: Option<Arc<Metrics>>,
+    /// Whether the request and response bodies are logged, see Tushare::with_log_bodies()
+    pub(crate) log_bodies: bool,
+    /// Asked for the token of every query instead of the fixed token, see Tushare::with_token_provider()
+    pub(crate) token_provider: Option<Arc<dyn TokenProvider>>,
+    /// Shares the result of identical queries in flight, see Tushare::with_coalescing()
+    #[cfg(feature = "dataframe")]
+    pub(crate) coalescer: Option<Coalescer>,
 }
 
 /// Tushare struct methods implementation
@@ -17,13 +62,416 @@ impl Tushare{
     /// Apply it before you do any access 
     pub fn new(token: &str) -> Self {
         Tushare{ token : token.to_string(),
-                 api_endpoint: "http://api.tushare.pro".to_string()}
+                 api_endpoint: TUSHARE_ENDPOINT.to_string(),
+                 retry_policy: RetryPolicy::default(),
+                 rate_limiter: None,
+                 #[cfg(feature = "dataframe")]
+                 cache: None,
+                 client_config: ClientConfig::default(),
+                 transport: Arc::new(ReqwestTransport::new(Client::new())),
+                 middlewares: vec![],
+                 metrics: None,
+                 log_bodies: false,
+                 token_provider: None,
+                 #[cfg(feature = "dataframe")]
+                 coalescer: None}
+    }
+
+    /// Create a TushareBuilder to configure the http client, retry, rate limit and cache
+    pub fn builder(token: &str) -> TushareBuilder {
+        TushareBuilder::new(token)
+    }
+
+    /// Create a tushare object with the token from TUSHARE_TOKEN environment variable.
+    /// ConfigError is returned if the variable is not set.
+    pub fn from_env() -> Result<Self, TushareError> {
+        Ok(Self::new(&config::token_from_env()?))
+    }
+
+    /// Create a tushare object from a TOML config file, see [TushareConfig] for the keys.
+    /// The token falls back to TUSHARE_TOKEN environment variable if it is not in the file.
+    pub fn from_config(path: impl AsRef<Path>) -> Result<Self, TushareError> {
+        Self::with_config(TushareConfig::load(path)?)
+    }
+
+    /// Create a tushare object from a loaded config, see from_config()
+    pub fn with_config(config: TushareConfig) -> Result<Self, TushareError> {
+        let token = match config.token {
+            Some(token) => token,
+            None => config::token_from_env()?,
+        };
+        let mut tushare = Self::new(&token);
+        if let Some(endpoint) = config.endpoint {
+            tushare = tushare.with_endpoint(&endpoint);
+        }
+        if let Some(secs) = config.timeout_secs {
+            tushare = tushare.with_timeout(Duration::from_secs(secs));
+        }
+        if let Some(calls_per_minute) = config.rate_limit {
+            tushare = tushare.with_rate_limit(calls_per_minute);
+        }
+        Ok(tushare)
+    }
+
+    /// Create a tushare object rotating the tokens of multiple accounts, see [Rotation].
+    /// ConfigError is returned if tokens is empty.
+    /// ```no_run
+    /// # fn main() -> Result<(), tushare::TushareError> {
+    /// use tushare::rotation::Rotation;
+    /// let tushare = tushare::Tushare::with_tokens(&["<token 1>", "<token 2>"], Rotation::FallbackOnRateLimit)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn with_tokens(tokens: &[&str], rotation: Rotation) -> Result<Self, TushareError> {
+        let rotation = TokenRotation::new(tokens, rotation)?;
+        Ok(Self::new(tokens[0]).with_middleware(rotation))
+    }
+
+    /// Ask the provider for the token when every query builds its request, instead of using the token of Tushare::new(),
+    /// e.g. to read it from a vault or a secret file rotated at runtime, see the [token](crate::token) module.
+    /// Middlewares like the token rotation of with_tokens() still see and may replace the provided token.
+    pub fn with_token_provider(self, provider: impl TokenProvider + 'static) -> Self {
+        Tushare {
+            token_provider: Some(Arc::new(provider)),
+            ..self
+        }
+    }
+
+    /// Post the requests to endpoint instead of [TUSHARE_ENDPOINT], e.g. WADITU_ENDPOINT
+    pub fn with_endpoint(self, endpoint: &str) -> Self {
+        Tushare {
+            api_endpoint: endpoint.to_string(),
+            ..self
+        }
+    }
+
+    /// Set the timeout of a single http request, a timed out request fails with NetworkError
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.client_config.timeout = Some(timeout);
+        self
+    }
+
+    /// Settings of the underlying http client
+    pub fn client_config(&self) -> &ClientConfig {
+        &self.client_config
+    }
+
+    /// Replace the default retry policy.
+    /// Use RetryPolicy::none() to disable retry and handle NetworkError yourself.
+    pub fn with_retry(self, retry_policy: RetryPolicy) -> Self {
+        Tushare { retry_policy, ..self }
+    }
+
+    /// Throttle all queries of this object to calls_per_minute.
+    /// Check your account points on tushare.pro for the allowed frequency, e.g. 200 for 2000 points.
+    /// Queries from multiple threads wait for each other instead of getting the 40203 error.
+    pub fn with_rate_limit(self, calls_per_minute: u32) -> Self {
+        Tushare {
+            rate_limiter: Some(RateLimiter::per_minute(calls_per_minute)),
+            ..self
+        }
+    }
+
+    /// Serve identical queries from the cache, e.g. MemoryCache or DiskCache.
+    /// Cached results don't consume api points, but may be stale within the cache ttl.
+    #[cfg(feature = "dataframe")]
+    pub fn with_cache(self, cache: impl Cache + 'static) -> Self {
+        Tushare {
+            cache: Some(Box::new(cache)),
+            ..self
+        }
+    }
+
+    /// Create a tushare object serving only from the cache, e.g. a DiskCache filled by earlier runs,
+    /// for reproducible research and CI tests without token and quota.
+    /// No http call is ever made, a query not in the cache fails with TushareError::CacheMiss.
+    /// Only query() and query_all() read the cache, the other queries like query_raw() always miss.
+    /// Give the cache a ttl long enough for the run, e.g. Duration::MAX, or the expired results miss as well.
+    /// ```no_run
+    /// # #[cfg(feature = "dataframe")]
+    /// # fn main() -> Result<(), tushare::TushareError> {
+    /// use std::time::Duration;
+    /// use tushare::{DiskCache, Tushare, TushareError};
+    /// let tushare = Tushare::offline(DiskCache::new("cache", Duration::MAX));
+    /// match tushare.querybuilder("daily").addparam("ts_code", "000001.SZ").query() {
+    ///     Ok(df) => println!("{df}"),
+    ///     Err(TushareError::CacheMiss(e)) => eprintln!("run online once to fill the cache: {e}"),
+    ///     Err(e) => return Err(e),
+    /// }
+    /// # Ok(())
+    /// # }
+    /// # #[cfg(not(feature = "dataframe"))] fn main() {}
+    /// ```
+    #[cfg(feature = "dataframe")]
+    pub fn offline(cache: impl Cache + 'static) -> Self {
+        Tushare::new("").with_cache(cache).with_transport(crate::transport::OfflineTransport)
+    }
+
+    /// Remove the cached results of an api from the cache set by with_cache(), e.g. after tushare corrected its data
+    #[cfg(feature = "dataframe")]
+    pub fn invalidate_cache(&self, api_name: &str) {
+        if let Some(cache) = &self.cache {
+            cache.invalidate(api_name);
+        }
+    }
+
+    /// Remove every result from the cache set by with_cache()
+    #[cfg(feature = "dataframe")]
+    pub fn clear_cache(&self) {
+        if let Some(cache) = &self.cache {
+            cache.clear();
+        }
+    }
+
+    /// Make a single http call for identical queries running at the same time, e.g. from the threads of a fan-out pipeline,
+    /// and share its DataFrame with every caller. A failed call is not shared, the other callers then query themselves.
+    /// Only query() and the pages of query_all() are coalesced, a finished query is not kept, see with_cache() for that.
+    #[cfg(feature = "dataframe")]
+    pub fn with_coalescing(self, enable: bool) -> Self {
+        Tushare {
+            coalescer: enable.then(Coalescer::default),
+            ..self
+        }
+    }
+
+    /// Replace the http transport, e.g. with a MockTransport to test without token and network.
+    /// The timeout, proxy and other client settings only apply to the default transport.
+    pub fn with_transport(self, transport: impl Transport + 'static) -> Self {
+        Tushare {
+            transport: Arc::new(transport),
+            ..self
+        }
+    }
+
+    /// Log the request and response bodies at info level, default false since the responses may be large.
+    /// The token is always redacted from the logged request, a line of the api, status and elapsed time
+    /// is logged for every request anyway.
+    pub fn with_log_bodies(self, log_bodies: bool) -> Self {
+        Tushare { log_bodies, ..self }
+    }
+
+    /// Add a middleware called around every request, after the ones already added
+    pub fn with_middleware(mut self, middleware: impl Middleware + 'static) -> Self {
+        self.middlewares.push(Arc::new(middleware));
+        self
+    }
+
+    /// Collect the call statistics of every api, read them with metrics()
+    /// ```no_run
+    /// # fn main() -> Result<(), tushare::TushareError> {
+    /// use tushare::metrics::Metrics;
+    /// let tushare = tushare::Tushare::from_env()?.with_metrics(Metrics::new());
+    /// // ... queries
+    /// if let Some(metrics) = tushare.metrics() {
+    ///     print!("{}", metrics.to_prometheus());
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn with_metrics(mut self, metrics: Metrics) -> Self {
+        let metrics = Arc::new(metrics);
+        self.middlewares.push(metrics.clone());
+        self.metrics = Some(metrics);
+        self
+    }
+
+    /// The call statistics if with_metrics() is set
+    pub fn metrics(&self) -> Option<&Metrics> {
+        self.metrics.as_deref()
     }
 
     /// Create a QueryBuilder to actually build and process the query
     /// # api_name: 
-    pub fn querybuilder(self: &Self, api_name: &str) -> QueryBuilder{
+    /// A [TushareApi](crate::TushareApi) like TushareApi::Daily, or the raw api_name string like "daily"
+    pub fn querybuilder(&self, api_name: impl Into<String>) -> QueryBuilder<'_>{
         QueryBuilder::new(self, api_name)
     }
 
 }
+
+/// Builder of a Tushare object, for settings not covered by Tushare::new()
+/// ```no_run
+/// # fn main() -> Result<(), tushare::TushareError> {
+/// use std::time::Duration;
+/// let tushare = tushare::Tushare::builder("<your token>")
+///     .connect_timeout(Duration::from_secs(5))
+///     .timeout(Duration::from_secs(60))
+///     .proxy("http://127.0.0.1:8080")
+///     .pool_max_idle_per_host(16)
+///     .build()?;
+/// # Ok(())
+/// # }
+/// ```
+pub struct TushareBuilder {
+    token: String,
+    endpoint: Option<String>,
+    client_config: ClientConfig,
+    retry_policy: RetryPolicy,
+    rate_limit: Option<u32>,
+    #[cfg(feature = "dataframe")]
+    cache: Option<Box<dyn Cache>>,
+    middlewares: Vec<Arc<dyn Middleware>>,
+    metrics: Option<Metrics>,
+    log_bodies: bool,
+    token_provider: Option<Arc<dyn TokenProvider>>,
+    #[cfg(feature = "dataframe")]
+    coalescing: bool,
+}
+
+impl TushareBuilder {
+    /// Create a builder with the token, other settings are the same as Tushare::new()
+    pub fn new(token: &str) -> Self {
+        TushareBuilder {
+            token: token.to_string(),
+            endpoint: None,
+            client_config: ClientConfig::default(),
+            retry_policy: RetryPolicy::default(),
+            rate_limit: None,
+            #[cfg(feature = "dataframe")]
+            cache: None,
+            middlewares: vec![],
+            metrics: None,
+            log_bodies: false,
+            token_provider: None,
+            #[cfg(feature = "dataframe")]
+            coalescing: false,
+        }
+    }
+
+    /// See Tushare::with_endpoint()
+    pub fn endpoint(mut self, endpoint: &str) -> Self {
+        self.endpoint = Some(endpoint.to_string());
+        self
+    }
+
+    /// Timeout of establishing the connection
+    pub fn connect_timeout(mut self, timeout: Duration) -> Self {
+        self.client_config.connect_timeout = Some(timeout);
+        self
+    }
+
+    /// Timeout of a whole request, from connecting until the body is read
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.client_config.timeout = Some(timeout);
+        self
+    }
+
+    /// Send all requests through the proxy, e.g. "http://127.0.0.1:8080"
+    pub fn proxy(mut self, proxy: &str) -> Self {
+        self.client_config.proxy = Some(proxy.to_string());
+        self
+    }
+
+    /// Set the User-Agent header of all requests
+    pub fn user_agent(mut self, user_agent: &str) -> Self {
+        self.client_config.user_agent = Some(user_agent.to_string());
+        self
+    }
+
+    /// Accept gzip/deflate compressed responses (the Accept-Encoding header), default true.
+    /// Tushare::new() has it on as well.
+    pub fn compression(mut self, compression: bool) -> Self {
+        self.client_config.compression = Some(compression);
+        self
+    }
+
+    /// Keep at most max_idle idle connections per host for reuse, e.g. the threads of a downloader
+    pub fn pool_max_idle_per_host(mut self, max_idle: usize) -> Self {
+        self.client_config.pool_max_idle_per_host = Some(max_idle);
+        self
+    }
+
+    /// Close the connections idle for longer than timeout
+    pub fn pool_idle_timeout(mut self, timeout: Duration) -> Self {
+        self.client_config.pool_idle_timeout = Some(timeout);
+        self
+    }
+
+    /// Send TCP keep-alive probes at the interval on every connection
+    pub fn tcp_keepalive(mut self, interval: Duration) -> Self {
+        self.client_config.tcp_keepalive = Some(interval);
+        self
+    }
+
+    /// Talk HTTP/2 without negotiating it, only for an endpoint known to support it
+    pub fn http2_prior_knowledge(mut self) -> Self {
+        self.client_config.http2_prior_knowledge = true;
+        self
+    }
+
+    /// See Tushare::with_retry()
+    pub fn retry(mut self, retry_policy: RetryPolicy) -> Self {
+        self.retry_policy = retry_policy;
+        self
+    }
+
+    /// See Tushare::with_rate_limit()
+    pub fn rate_limit(mut self, calls_per_minute: u32) -> Self {
+        self.rate_limit = Some(calls_per_minute);
+        self
+    }
+
+    /// See Tushare::with_cache()
+    #[cfg(feature = "dataframe")]
+    pub fn cache(mut self, cache: impl Cache + 'static) -> Self {
+        self.cache = Some(Box::new(cache));
+        self
+    }
+
+    /// See Tushare::with_coalescing()
+    #[cfg(feature = "dataframe")]
+    pub fn coalescing(mut self, enable: bool) -> Self {
+        self.coalescing = enable;
+        self
+    }
+
+    /// See Tushare::with_middleware()
+    pub fn middleware(mut self, middleware: impl Middleware + 'static) -> Self {
+        self.middlewares.push(Arc::new(middleware));
+        self
+    }
+
+    /// See Tushare::with_metrics()
+    pub fn metrics(mut self, metrics: Metrics) -> Self {
+        self.metrics = Some(metrics);
+        self
+    }
+
+    /// See Tushare::with_log_bodies()
+    pub fn log_bodies(mut self, log_bodies: bool) -> Self {
+        self.log_bodies = log_bodies;
+        self
+    }
+
+    /// See Tushare::with_token_provider()
+    pub fn token_provider(mut self, provider: impl TokenProvider + 'static) -> Self {
+        self.token_provider = Some(Arc::new(provider));
+        self
+    }
+
+    /// Create the Tushare object.
+    /// NetworkError is returned if the http client can't be built, e.g. a malformed proxy url.
+    pub fn build(self) -> Result<Tushare, TushareError> {
+        let mut tushare = Tushare::new(&self.token).with_retry(self.retry_policy);
+        tushare.transport = Arc::new(ReqwestTransport::new(self.client_config.build_client()?));
+        tushare.client_config = self.client_config;
+        tushare.middlewares = self.middlewares;
+        tushare.log_bodies = self.log_bodies;
+        tushare.token_provider = self.token_provider;
+        if let Some(endpoint) = self.endpoint {
+            tushare.api_endpoint = endpoint;
+        }
+        if let Some(metrics) = self.metrics {
+            tushare = tushare.with_metrics(metrics);
+        }
+        #[cfg(feature = "dataframe")]
+        {
+            tushare.cache = self.cache;
+            tushare = tushare.with_coalescing(self.coalescing);
+        }
+        if let Some(calls_per_minute) = self.rate_limit {
+            tushare = tushare.with_rate_limit(calls_per_minute);
+        }
+        Ok(tushare)
+    }
+}
@@ -0,0 +1,234 @@
+//! Realtime quotes pushed by tushare over websocket, enabled by the "realtime" feature.
+//! The subscription requires the realtime permission of your account.
+//! ```no_run
+//! # fn main() -> Result<(), tushare::TushareError> {
+//! use std::ops::ControlFlow;
+//! let tushare = tushare::Tushare::from_env()?;
+//! tushare.subscribe(&["000001.SZ", "600000.SH"], |tick| {
+//!     println!("{} {:?} {:?}", tick.ts_code, tick.trade_time, tick.price);
+//!     ControlFlow::Continue(())
+//! })?;
+//! # Ok(())
+//! # }
+//! ```
+use crate::error::{TushareError, TushareServerError};
+use crate::realtime::Tick;
+use crate::tushare::Tushare;
+use log::{info, warn};
+use serde_json::{json, Value};
+use std::io::ErrorKind;
+use std::net::TcpStream;
+use std::ops::ControlFlow;
+use std::thread;
+use std::time::Duration;
+use tungstenite::stream::MaybeTlsStream;
+use tungstenite::{Message, WebSocket};
+
+/// The websocket endpoint of tushare realtime quotes
+pub const REALTIME_ENDPOINT: &str = "wss://ws.tushare.pro/listening";
+/// Topic of stock ticks
+pub const STOCK_TICK_TOPIC: &str = "HQ_STK_TICK";
+/// Tushare closes a connection without any message for a minute, so a ping is sent in between
+const PING_INTERVAL: Duration = Duration::from_secs(30);
+
+/// Describe what Tushare::subscribe_with() listens to
+#[derive(Debug, Clone)]
+pub struct Subscription {
+    topic: String,
+    codes: Vec<String>,
+    endpoint: String,
+    max_reconnects: u32,
+}
+
+impl Subscription {
+    /// Subscribe the stock ticks of the codes
+    pub fn new(codes: &[&str]) -> Self {
+        Subscription {
+            topic: STOCK_TICK_TOPIC.to_string(),
+            codes: codes.iter().map(|c| c.to_string()).collect(),
+            endpoint: REALTIME_ENDPOINT.to_string(),
+            max_reconnects: 10,
+        }
+    }
+
+    /// The topic to subscribe, default [STOCK_TICK_TOPIC]
+    pub fn topic(self, topic: &str) -> Self {
+        Subscription {
+            topic: topic.to_string(),
+            ..self
+        }
+    }
+
+    /// The websocket url, default [REALTIME_ENDPOINT]
+    pub fn endpoint(self, endpoint: &str) -> Self {
+        Subscription {
+            endpoint: endpoint.to_string(),
+            ..self
+        }
+    }
+
+    /// Give up after the connection fails this many times in a row, default 10.
+    /// The wait between reconnects follows the retry policy of the Tushare object.
+    pub fn max_reconnects(self, max_reconnects: u32) -> Self {
+        Subscription {
+            max_reconnects,
+            ..self
+        }
+    }
+}
+
+/// Parse a message pushed by tushare, None for the pong and other messages without a record
+fn parse_message(text: &str) -> Result<Option<Tick>, TushareError> {
+    let message: Value = serde_json::from_str(text)?;
+    if !message["status"].as_bool().unwrap_or(false) {
+        let msg = message["message"].as_str().or(message["msg"].as_str()).unwrap_or(text);
+        return Err(TushareServerError::from_code(message["code"].as_i64().unwrap_or(-1), msg).into());
+    }
+    let data = &message["data"];
+    let (Some(topic), Some(code), Some(record)) = (
+        data["topic"].as_str(),
+        data["code"].as_str(),
+        data["record"].as_object(),
+    ) else {
+        return Ok(None);
+    };
+    Ok(Some(Tick::from_record(topic, code, record.clone())))
+}
+
+/// Whether the read returned because of the read timeout, not a broken connection
+fn is_timeout(e: &tungstenite::Error) -> bool {
+    matches!(e, tungstenite::Error::Io(e) if matches!(e.kind(), ErrorKind::WouldBlock | ErrorKind::TimedOut))
+}
+
+fn set_read_timeout(socket: &WebSocket<MaybeTlsStream<TcpStream>>, timeout: Duration) -> std::io::Result<()> {
+    match socket.get_ref() {
+        MaybeTlsStream::Plain(stream) => stream.set_read_timeout(Some(timeout)),
+        MaybeTlsStream::NativeTls(stream) => stream.get_ref().set_read_timeout(Some(timeout)),
+        _ => Ok(()),
+    }
+}
+
+impl Tushare {
+    /// Listen to the realtime stock ticks of the codes, and call f with every tick.
+    /// Blocks the current thread until f returns ControlFlow::Break, see subscribe_with().
+    pub fn subscribe(
+        &self,
+        codes: &[&str],
+        f: impl FnMut(Tick) -> ControlFlow<()>,
+    ) -> Result<(), TushareError> {
+        self.subscribe_with(&Subscription::new(codes), f)
+    }
+
+    /// Listen to the subscription and call f with every tick, until f returns ControlFlow::Break.
+    /// A broken or closed connection is reconnected, an error is returned once it fails or is closed
+    /// without any tick max_reconnects times in a row, or tushare rejects the subscription,
+    /// e.g. without the realtime permission.
+    pub fn subscribe_with(
+        &self,
+        subscription: &Subscription,
+        mut f: impl FnMut(Tick) -> ControlFlow<()>,
+    ) -> Result<(), TushareError> {
+        let mut failures = 0;
+        loop {
+            let e = match self.listen(subscription, &mut f, &mut failures) {
+                Ok(ControlFlow::Break(())) => return Ok(()),
+                Ok(ControlFlow::Continue(())) => TushareError::from(tungstenite::Error::ConnectionClosed),
+                Err(e @ (TushareError::WebSocketError(_) | TushareError::IoError(_))) => e,
+                Err(e) => return Err(e),
+            };
+            if failures >= subscription.max_reconnects {
+                return Err(e);
+            }
+            failures += 1;
+            let delay = self.retry_policy.delay(failures);
+            warn!("Realtime connection failed: {e}, reconnect in {delay:?}");
+            thread::sleep(delay);
+        }
+    }
+
+    /// Connect and read the ticks until f breaks or the server closes the connection
+    fn listen(
+        &self,
+        subscription: &Subscription,
+        f: &mut impl FnMut(Tick) -> ControlFlow<()>,
+        failures: &mut u32,
+    ) -> Result<ControlFlow<()>, TushareError> {
+        let (mut socket, _) = tungstenite::connect(subscription.endpoint.as_str())?;
+        set_read_timeout(&socket, PING_INTERVAL)?;
+        let request = json!({
+            "action": "listening",
+            "token": self.token,
+            "data": {subscription.topic.as_str(): subscription.codes},
+        });
+        socket.send(Message::Text(request.to_string()))?;
+        info!("Subscribed {} of {} codes", subscription.topic, subscription.codes.len());
+        loop {
+            let text = match socket.read() {
+                Ok(Message::Text(text)) => text,
+                Ok(Message::Close(_)) => return Ok(ControlFlow::Continue(())),
+                Ok(_) => continue,
+                Err(e) if is_timeout(&e) => {
+                    socket.send(Message::Text(json!({"action": "ping"}).to_string()))?;
+                    continue;
+                }
+                Err(tungstenite::Error::ConnectionClosed) => return Ok(ControlFlow::Continue(())),
+                Err(e) => return Err(e.into()),
+            };
+            if let Some(tick) = parse_message(&text)? {
+                *failures = 0;
+                if f(tick).is_break() {
+                    let _ = socket.close(None);
+                    return Ok(ControlFlow::Break(()));
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[test]
+    fn test_parse_message() {
+        let text = r#"{"status": true, "data": {"topic": "HQ_STK_TICK", "code": "000001.SZ",
+            "record": {"name": "平安银行", "trade_time": "2024-04-24 10:30:00", "price": 10.5, "pre_price": "10.4", "volume": 1200}}}"#;
+        let tick = parse_message(text).unwrap().unwrap();
+        assert_eq!(tick.ts_code, "000001.SZ");
+        assert_eq!(tick.price, Some(10.5));
+        assert_eq!(tick.pre_close, Some(10.4));
+        assert_eq!(tick.vol, Some(1200.0));
+        assert_eq!(tick.high, None);
+        assert!(parse_message(r#"{"status": true, "data": "pong"}"#).unwrap().is_none());
+        assert!(parse_message(r#"{"status": false, "message": "token不对"}"#).is_err());
+    }
+
+    #[test]
+    fn test_reconnect_on_close() {
+        use crate::retry::RetryPolicy;
+        use std::net::TcpListener;
+        use std::time::Instant;
+        // a server closing every connection right after the subscription
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let endpoint = format!("ws://{}", listener.local_addr().unwrap());
+        let server = thread::spawn(move || {
+            let mut connections = 0;
+            for stream in listener.incoming().take(3) {
+                let mut socket = tungstenite::accept(stream.unwrap()).unwrap();
+                let _ = socket.read();
+                let _ = socket.close(None);
+                while socket.read().is_ok() {}
+                connections += 1;
+            }
+            connections
+        });
+        let policy = RetryPolicy::new(3, Duration::from_millis(50)).jitter(0.0);
+        let tushare = Tushare::new("<token here>").with_retry(policy);
+        let subscription = Subscription::new(&["000001.SZ"]).endpoint(&endpoint).max_reconnects(2);
+        let start = Instant::now();
+        let result = tushare.subscribe_with(&subscription, |_| ControlFlow::Continue(()));
+        assert!(matches!(result, Err(TushareError::WebSocketError(_))));
+        // the delays of the two reconnects, 50ms then 100ms
+        assert!(start.elapsed() >= Duration::from_millis(150));
+        assert_eq!(server.join().unwrap(), 3);
+    }
+}